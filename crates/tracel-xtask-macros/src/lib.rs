@@ -68,6 +68,18 @@ pub fn extend_targets(args: TokenStream, input: TokenStream) -> TokenStream {
 // Commands
 // ========
 
+// Convert a command name to its module name, i.e. 'ApiSnapshot' to 'api_snapshot'
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
 fn generate_dispatch_function(
     enum_ident: &syn::Ident,
     args: &Punctuated<Meta, Comma>,
@@ -75,13 +87,24 @@ fn generate_dispatch_function(
     let arms: Vec<proc_macro2::TokenStream> = args.iter().map(|meta| {
         let cmd_ident = meta.path().get_ident().unwrap();
         let cmd_ident_string = cmd_ident.to_string();
-        let module_ident = syn::Ident::new(cmd_ident_string.to_lowercase().as_str(), cmd_ident.span());
+        let module_ident = syn::Ident::new(to_snake_case(&cmd_ident_string).as_str(), cmd_ident.span());
+        // the arguments not passed on the command line default to the ones of the xtask.toml file
+        let command_name = to_snake_case(&cmd_ident_string).replace('_', "-");
+        let apply_defaults = quote! {
+            cmd_args.apply_defaults(|key| get_config().get_default_args(#command_name, key));
+        };
         match cmd_ident_string.as_str() {
             "Fix" => quote! {
-                #enum_ident::#cmd_ident(cmd_args) => base_commands::#module_ident::handle_command(cmd_args, args.environment, args.context, None),
+                #enum_ident::#cmd_ident(mut cmd_args) => {
+                    #apply_defaults
+                    base_commands::#module_ident::handle_command(cmd_args, args.environment, args.context, None)
+                }
             },
             _ => quote! {
-                #enum_ident::#cmd_ident(cmd_args) => base_commands::#module_ident::handle_command(cmd_args, args.environment, args.context),
+                #enum_ident::#cmd_ident(mut cmd_args) => {
+                    #apply_defaults
+                    base_commands::#module_ident::handle_command(cmd_args, args.environment, args.context)
+                }
             }
         }
     }).collect();
@@ -104,6 +127,27 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Supported commands and their quoted expansions
     let mut variant_map: HashMap<&str, proc_macro2::TokenStream> = HashMap::new();
+    variant_map.insert(
+        "ApiSnapshot",
+        quote! {
+            #[doc = r"Write or check the public API snapshot of the workspace crates."]
+            ApiSnapshot(tracel_xtask::commands::api_snapshot::ApiSnapshotCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Bench",
+        quote! {
+            #[doc = r"Run the benchmarks."]
+            Bench(tracel_xtask::commands::bench::BenchCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "BenchIds",
+        quote! {
+            #[doc = r"Check that the benchmark IDs of the workspace crates match their snapshot."]
+            BenchIds(tracel_xtask::commands::bench_ids::BenchIdsCmdArgs)
+        },
+    );
     variant_map.insert(
         "Build",
         quote! {
@@ -125,6 +169,27 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Check(tracel_xtask::commands::check::CheckCmdArgs)
         },
     );
+    variant_map.insert(
+        "ChangelogLint",
+        quote! {
+            #[doc = r"Check that the CHANGELOG.md file follows the keepachangelog format."]
+            ChangelogLint(tracel_xtask::commands::changelog_lint::ChangelogLintCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Clippy",
+        quote! {
+            #[doc = r"Run clippy on the workspace members, warnings are denied."]
+            Clippy(tracel_xtask::commands::clippy::ClippyCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "Compare",
+        quote! {
+            #[doc = r"Run a test or bench command on two git refs and compare their results."]
+            Compare(tracel_xtask::commands::compare::CompareCmdArgs)
+        },
+    );
     variant_map.insert(
         "Compile",
         quote! {
@@ -139,6 +204,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Coverage(tracel_xtask::commands::coverage::CoverageCmdArgs)
         },
     );
+    variant_map.insert(
+        "CriticalPath",
+        quote! {
+            #[doc = r"Report the longest chain of sequentially dependent compilations of the workspace build. This command must be called with 'cargo +nightly'."]
+            CriticalPath(tracel_xtask::commands::critical_path::CriticalPathCmdArgs)
+        },
+    );
     variant_map.insert(
         "Dependencies",
         quote! {
@@ -153,6 +225,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Doc(tracel_xtask::commands::doc::DocCmdArgs)
         },
     );
+    variant_map.insert(
+        "DocsrsCheck",
+        quote! {
+            #[doc = r"Build the documentation of each crate with its docs.rs configuration. This command must be called with 'cargo +nightly'."]
+            DocsrsCheck(tracel_xtask::commands::docsrs_check::DocsrsCheckCmdArgs)
+        },
+    );
     variant_map.insert(
         "Docker",
         quote! {
@@ -167,6 +246,20 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Fix(tracel_xtask::commands::fix::FixCmdArgs)
         },
     );
+    variant_map.insert(
+        "Fmt",
+        quote! {
+            #[doc = r"Format the workspace or check that it is formatted."]
+            Fmt(tracel_xtask::commands::fmt::FmtCmdArgs)
+        },
+    );
+    variant_map.insert(
+        "IncrementalTiming",
+        quote! {
+            #[doc = r"Measure the incremental rebuild time of the workspace after touching a source file of each crate."]
+            IncrementalTiming(tracel_xtask::commands::incremental_timing::IncrementalTimingCmdArgs)
+        },
+    );
     variant_map.insert(
         "Publish",
         quote! {
@@ -174,6 +267,13 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
             Publish(tracel_xtask::commands::publish::PublishCmdArgs)
         },
     );
+    variant_map.insert(
+        "ReadmeSync",
+        quote! {
+            #[doc = r"Check or fix the version references in the README of each crate."]
+            ReadmeSync(tracel_xtask::commands::readme_sync::ReadmeSyncCmdArgs)
+        },
+    );
     variant_map.insert(
         "Test",
         quote! {
@@ -238,12 +338,67 @@ pub fn base_commands(args: TokenStream, input: TokenStream) -> TokenStream {
 
 fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStream> {
     HashMap::from([
+        (
+            "ApiSnapshotCmdArgs",
+            quote! {
+                #[doc = r"Write the current public API of each crate to its snapshot file."]
+                #[arg(long, required = false, conflicts_with = "check")]
+                pub write: bool,
+                #[doc = r"Fail if the public API of a crate differs from its snapshot file. [default]"]
+                #[arg(long, required = false)]
+                pub check: bool,
+            },
+        ),
+        (
+            "BenchCmdArgs",
+            quote! {
+                #[doc = r"Run only the benchmark target with the passed name."]
+                #[arg(long = "bench", value_name = "NAME", required = false)]
+                pub bench: Option<String>,
+                #[doc = r"Compile the benchmarks but do not run them."]
+                #[arg(long = "no-run", required = false)]
+                pub no_run: bool,
+            },
+        ),
+        (
+            "BenchIdsCmdArgs",
+            quote! {
+                #[doc = r"Write the current benchmark IDs of each crate to its snapshot file to accept them."]
+                #[arg(long = "update", required = false)]
+                pub update: bool,
+                #[doc = r"Fail when the benchmark IDs of a crate differ from its snapshot instead of only warning about it."]
+                #[arg(long = "deny", required = false, conflicts_with = "update")]
+                pub deny: bool,
+            },
+        ),
         (
             "BuildCmdArgs",
             quote! {
                 #[doc = r"Build artifacs in release mode."]
                 #[arg(short, long, required = false)]
                 pub release: bool,
+                #[doc = r"Maximum number of parallel compilation jobs."]
+                #[arg(short = 'j', long = "jobs", value_name = "N", required = false)]
+                pub jobs: Option<u16>,
+                #[doc = r"Comma-separated list of features to enable."]
+                #[arg(
+                    long,
+                    value_name = "FEATURE,FEATURE,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub features: Option<Vec<String>>,
+                #[doc = r"If set, ignore default features."]
+                #[arg(long = "no-default-features", required = false)]
+                pub no_default_features: bool,
+            },
+        ),
+        (
+            "ChangelogLintCmdArgs",
+            quote! {
+                #[doc = r"Also check that the changelog has a section for the passed release version."]
+                #[arg(long = "check-version", value_name = "x.y.z", required = false)]
+                pub check_version: Option<String>,
             },
         ),
         (
@@ -252,6 +407,56 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                 #[doc = r"Ignore audit errors."]
                 #[arg(long = "ignore-audit", required = false)]
                 pub ignore_audit: bool,
+                #[doc = r"Fail when the workspace convention checks report issues instead of only warning about them."]
+                #[arg(long = "deny", required = false)]
+                pub deny: bool,
+                #[doc = r"Comma-separated list of lints which are only reported by the lint check, all the other warnings are denied. Reported warnings are summarized with their counts."]
+                #[arg(
+                    long = "warn-only",
+                    value_name = "LINT,LINT,...",
+                    value_delimiter = ',',
+                    required = false
+                )]
+                pub warn_only: Vec<String>,
+                #[doc = r"Keep checking the remaining cells of the feature matrix after a failure, the failed cells are reported at the end."]
+                #[arg(long = "keep-going", required = false)]
+                pub keep_going: bool,
+            },
+        ),
+        (
+            "ClippyCmdArgs",
+            quote! {
+                #[doc = r"Deny the passed lint in addition to the warnings, can be repeated."]
+                #[arg(long = "deny", value_name = "LINT", required = false)]
+                pub deny: Vec<String>,
+                #[doc = r"Allow the passed lint, it overrides the denied lints and can be repeated."]
+                #[arg(long = "allow", value_name = "LINT", required = false)]
+                pub allow: Vec<String>,
+            },
+        ),
+        (
+            "CompareCmdArgs",
+            quote! {
+                #[doc = r"Base git ref, usually the target branch of the changes."]
+                #[arg(long = "base", value_name = "REF")]
+                pub base: String,
+                #[doc = r"Head git ref, usually the branch with the changes."]
+                #[arg(long = "head", value_name = "REF", default_value = "HEAD")]
+                pub head: String,
+                #[doc = r"Test or bench command to run on each ref."]
+                #[arg(long = "command", value_name = "COMMAND", default_value = "cargo test --workspace")]
+                pub command: String,
+            },
+        ),
+        (
+            "CompileCmdArgs",
+            quote! {
+                #[doc = r"Compile the examples against the published version of their path dependencies instead of the local sources."]
+                #[arg(long = "published", required = false)]
+                pub published: bool,
+                #[doc = r"Published version to compile the examples against. Defaults to the version requirement of each path dependency, or to the version of the workspace crate."]
+                #[arg(long = "version", value_name = "VERSION", requires = "published", required = false)]
+                pub version: Option<String>,
             },
         ),
         (
@@ -268,6 +473,55 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                 pub services: Vec<String>,
             },
         ),
+        (
+            "CriticalPathCmdArgs",
+            quote! {
+                #[doc = r"Build in release mode."]
+                #[arg(short = 'r', long = "release", required = false)]
+                pub release: bool,
+            },
+        ),
+        (
+            "DependenciesCmdArgs",
+            quote! {
+                #[doc = r"Fail when the outdated or duplicates checks report dependencies instead of only warning about them."]
+                #[arg(long = "deny", required = false)]
+                pub deny: bool,
+            },
+        ),
+        (
+            "FmtCmdArgs",
+            quote! {
+                #[doc = r"Shorthand for the check subcommand."]
+                #[arg(long = "check", required = false)]
+                pub check: bool,
+            },
+        ),
+        (
+            "IncrementalTimingCmdArgs",
+            quote! {
+                #[doc = r"Comma-separated list of the crates to touch, all the crates of the workspace by default."]
+                #[arg(long = "crates", value_name = "CRATE,CRATE,...", value_delimiter = ',', required = false)]
+                pub crates: Vec<String>,
+                #[doc = r"Fail if an incremental rebuild takes longer than the passed duration (e.g. 30s, 2m)."]
+                #[arg(long = "threshold", value_name = "DURATION", required = false)]
+                pub threshold: Option<String>,
+                #[doc = r"Build in release mode."]
+                #[arg(short = 'r', long = "release", required = false)]
+                pub release: bool,
+            },
+        ),
+        (
+            "ReadmeSyncCmdArgs",
+            quote! {
+                #[doc = r"Fail if a version reference in a README differs from the crate version. [default]"]
+                #[arg(long, required = false, conflicts_with = "fix")]
+                pub check: bool,
+                #[doc = r"Update the version references in the READMEs to the crate version."]
+                #[arg(long, required = false)]
+                pub fix: bool,
+            },
+        ),
         (
             "TestCmdArgs",
             quote! {
@@ -292,7 +546,7 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                     required = false
                 )]
                 pub threads: Option<u16>,
-                #[doc = r"Comma-separated list of features to enable during tests."]
+                #[doc = r"Comma-separated list of features to enable during tests. Glob patterns like 'gpu-*' are expanded against the features declared by each crate."]
                 #[arg(
                     long,
                     value_name = "FEATURE,FEATURE,...",
@@ -300,12 +554,25 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                     required = false
                 )]
                 pub features: Option<Vec<String>>,
+                #[doc = r"Enable all the features of the tested crates."]
+                #[arg(
+                    long = "all-features",
+                    conflicts_with = "features",
+                    required = false
+                )]
+                pub all_features: bool,
                 #[doc = r"If set, ignore default features."]
                 #[arg(
                     long = "no-default-features",
                     required = false
                 )]
                 pub no_default_features: bool,
+                #[doc = r"Target triple to compile the tests for, i.e. 'aarch64-unknown-linux-gnu'. The tests of a foreign target run with the runner configured for it in the cargo configuration, i.e. qemu."]
+                #[arg(long = "target-triple", value_name = "TRIPLE", required = false)]
+                pub target_triple: Option<String>,
+                #[doc = r"Set a custom cfg for the test run, i.e. '--cfg loom' or '--cfg backend=cuda'. Can be repeated. The cfgs are appended to RUSTFLAGS so they also apply to the dependencies."]
+                #[arg(long = "cfg", value_name = "NAME[=VALUE]", required = false)]
+                pub cfgs: Vec<String>,
                 #[doc = r"Force execution of tests no matter the environment (i.e. authorize to execute tests in prod)."]
                 #[arg(
                     short = 'f',
@@ -319,6 +586,81 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                 #[doc = r"Build test in release mode."]
                 #[arg(short = 'r', long = "release", required = false)]
                 pub release: bool,
+                #[doc = r"Detect memory leaks with the counting allocator of 'tracel_xtask::leak_check' installed with the 'xtask_leak_check' cfg."]
+                #[arg(long = "leak-check", required = false)]
+                pub leak_check: bool,
+                #[doc = r"Number of bytes a test process can leave allocated at exit before the leak check fails, the Rust runtime keeps a few hundred bytes allocated until exit."]
+                #[arg(
+                    long = "leak-threshold",
+                    value_name = "BYTES",
+                    default_value_t = 1024,
+                    requires = "leak_check",
+                    required = false
+                )]
+                pub leak_threshold: u64,
+                #[doc = r"Report the files created or modified by the tests outside of the target directory."]
+                #[arg(long = "check-writes", required = false)]
+                pub check_writes: bool,
+                #[doc = r"Fail when the tests create or modify files outside of the target directory, implies '--check-writes'."]
+                #[arg(long = "deny-writes", required = false)]
+                pub deny_writes: bool,
+                #[doc = r"Run everything serially for test suites contending over a shared resource: one compilation job, one test thread and one package at a time."]
+                #[arg(
+                    long = "serial",
+                    conflicts_with_all = ["jobs", "threads"],
+                    required = false
+                )]
+                pub serial: bool,
+                #[doc = r"Run the tests in a random order with a random seed which is logged to reproduce the order (requires nightly)."]
+                #[arg(long = "shuffle", required = false)]
+                pub shuffle: bool,
+                #[doc = r"Run the tests in the random order given by the passed seed (requires nightly)."]
+                #[arg(long = "shuffle-seed", value_name = "SEED", required = false)]
+                pub shuffle_seed: Option<u64>,
+                #[doc = r"Run the tests of each member twice with different shuffle seeds and report the tests whose result depends on the order (requires nightly). The first seed can be set with '--shuffle-seed'."]
+                #[arg(long = "order-check", conflicts_with = "shuffle", required = false)]
+                pub order_check: bool,
+                #[doc = r"Raw argument passed to the libtest test harness after '--', i.e. '--test-runner-arg=--report-time'. Can be repeated."]
+                #[arg(
+                    long = "test-runner-arg",
+                    value_name = "ARG",
+                    allow_hyphen_values = true,
+                    required = false
+                )]
+                pub test_runner_args: Vec<String>,
+                #[doc = r"Number of times failed integration tests are retried. Defaults to 0 locally and to the 'test.retries' value of the xtask.toml file (or 2) in CI and production."]
+                #[arg(long = "retries", value_name = "NUMBER OF RETRIES", required = false)]
+                pub retries: Option<u32>,
+                #[doc = r"Write the test results to a report in the given format, i.e. 'tap:target/tests.tap'. Can be repeated."]
+                #[arg(long = "report", value_name = "FORMAT:PATH", required = false)]
+                pub reports: Vec<String>,
+                #[doc = r"Terminate the tests of a package, or of the workspace, running for longer than the passed number of seconds and report them as failed."]
+                #[arg(long = "timeout", value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..), required = false)]
+                pub timeout: Option<u64>,
+                #[doc = r"Run the tests in a scrubbed environment where only PATH, HOME, the Rust toolchain variables and the variables set by xtask are passed through."]
+                #[arg(long = "clean-env", required = false)]
+                pub clean_env: bool,
+                #[doc = r"Comma-separated list of additional environment variables passed through with --clean-env."]
+                #[arg(
+                    long = "env-passthrough",
+                    value_name = "VAR,VAR,...",
+                    value_delimiter = ',',
+                    requires = "clean_env",
+                    required = false
+                )]
+                pub env_passthrough: Vec<String>,
+                #[doc = r"Test only the passed workspace package with the crates and examples targets. Can be repeated."]
+                #[arg(long = "package", value_name = "CRATE", required = false)]
+                pub packages: Vec<String>,
+                #[doc = r"List the members and the cargo commands the run would execute without executing them."]
+                #[arg(long = "list", required = false)]
+                pub list: bool,
+                #[doc = r"Keep testing the remaining members after a failed member, the failed members are reported at the end."]
+                #[arg(long = "keep-going", required = false)]
+                pub keep_going: bool,
+                #[doc = r"Test only the members with files changed since the passed git reference and the members depending on them, with the crates and examples targets."]
+                #[arg(long = "changed-since", value_name = "GITREF", required = false)]
+                pub changed_since: Option<String>,
             },
         ),
         (
@@ -330,11 +672,93 @@ fn get_additional_cmd_args_map() -> HashMap<&'static str, proc_macro2::TokenStre
                 #[doc = r"Build in release mode."]
                 #[arg(short = 'r', long = "release", required = false)]
                 pub release: bool,
+                #[doc = r"Keep running the remaining checks and tests after a failure, the failed steps are reported at the end."]
+                #[arg(long = "keep-going", required = false)]
+                pub keep_going: bool,
+            },
+        ),
+        (
+            "VulnerabilitiesCmdArgs",
+            quote! {
+                #[doc = r"Enable debug assertions in the optimized sanitizer builds (appends -Cdebug-assertions=on)."]
+                #[arg(long = "debug-assertions", required = false)]
+                pub debug_assertions: bool,
+                #[doc = r"Run the sanitizers in a scrubbed environment where only PATH, HOME and the Rust toolchain variables are passed through."]
+                #[arg(long = "clean-env", required = false)]
+                pub clean_env: bool,
+                #[doc = r"Comma-separated list of additional environment variables passed through with --clean-env."]
+                #[arg(
+                    long = "env-passthrough",
+                    value_name = "VAR,VAR,...",
+                    value_delimiter = ',',
+                    requires = "clean_env",
+                    required = false
+                )]
+                pub env_passthrough: Vec<String>,
+                #[doc = r"Do not append the sanitizer flags to the RUSTFLAGS and RUSTDOCFLAGS of the environment, replace them instead."]
+                #[arg(long = "no-inherit-rustflags", required = false)]
+                pub no_inherit_rustflags: bool,
+                #[doc = r"Build the sanitizer and careful runs with the passed cargo profile instead of the test profile, the profile settings replace the default -Copt-level=3."]
+                #[arg(long = "cargo-profile", value_name = "NAME", required = false)]
+                pub cargo_profile: Option<String>,
+                #[doc = r"Run the sanitizers of the 'all' subcommand concurrently, at most N at a time, each one in its own target directory. All the sanitizers run before reporting the failures."]
+                #[arg(long = "max-parallel", value_name = "N", value_parser = clap::value_parser!(usize), required = false)]
+                pub max_parallel: Option<usize>,
+                #[doc = r"Fail when a sanitizer runs no test instead of only warning about it."]
+                #[arg(long = "require-tests", required = false)]
+                pub require_tests: bool,
+                #[doc = r"Arguments appended to 'cargo careful test' by the nightly checks, split like a shell does, i.e. '--release -- my_test'."]
+                #[arg(long = "careful-args", value_name = "ARGS", allow_hyphen_values = true, required = false)]
+                pub careful_args: Option<String>,
+                #[doc = r"Run 'cargo careful setup' even if the careful sysroot has already been prepared for the toolchain."]
+                #[arg(long = "rebuild-sysroot", required = false)]
+                pub rebuild_sysroot: bool,
+                #[doc = r"Remove the cached sanitizer builds and careful stamps of the other toolchains under 'target/vulnerabilities'."]
+                #[arg(long = "clean-cache", required = false)]
+                pub clean_cache: bool,
+                #[doc = r"Terminate 'cargo careful setup' and 'cargo careful test' if one of them runs longer than the passed duration, i.e. '30m' or '1h30m'."]
+                #[arg(long = "careful-timeout", value_name = "DURATION", required = false)]
+                pub careful_timeout: Option<String>,
+                #[doc = r"Write the status and duration of each check as a JSON array to the passed path."]
+                #[arg(long = "report-json", value_name = "PATH", required = false)]
+                pub report_json: Option<std::path::PathBuf>,
+                #[doc = r"Print the commands of the checks with the variables they set, like RUSTFLAGS, instead of executing them."]
+                #[arg(long = "dry-run", required = false)]
+                pub dry_run: bool,
+                #[doc = r"Optimization level of the sanitizer builds replacing the default '-Copt-level=3'."]
+                #[arg(long = "opt-level", value_name = "LEVEL", value_parser = ["0", "1", "2", "3", "s", "z"], required = false)]
+                pub opt_level: Option<String>,
+                #[doc = r"Flags appended to the RUSTFLAGS of the sanitizer builds after the sanitizer flags, i.e. '-Cdebuginfo=2'."]
+                #[arg(long = "extra-rustflags", value_name = "FLAGS", allow_hyphen_values = true, required = false)]
+                pub extra_rustflags: Option<String>,
+                #[doc = r"Nightly toolchain of the sanitizer and careful runs, i.e. 'nightly-2024-01-01', instead of the current toolchain. Cargo is invoked with '+<TOOLCHAIN>'."]
+                #[arg(long = "toolchain", value_name = "TOOLCHAIN", required = false)]
+                pub toolchain: Option<String>,
+                #[doc = r"Build each sanitizer in the directory named after it under the passed directory, instead of the sanitizers directory of the target directory, i.e. target/sanitizers/<sanitizer>."]
+                #[arg(long = "target-dir", value_name = "PATH", required = false)]
+                pub target_dir: Option<std::path::PathBuf>,
             },
         ),
     ])
 }
 
+// Returns the names of the additional fields declared for the passed base command arguments struct
+fn get_additional_cmd_args_field_names(cmd_args: &str) -> Vec<String> {
+    let additional_cmd_args_map = get_additional_cmd_args_map();
+    let Some(fields) = additional_cmd_args_map.get(cmd_args) else {
+        return vec![];
+    };
+    let named_fields: syn::FieldsNamed = match syn::parse2(quote! { { #fields } }) {
+        Ok(data) => data,
+        Err(_) => return vec![],
+    };
+    named_fields
+        .named
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(|ident| ident.to_string()))
+        .collect()
+}
+
 // Returns a tuple where 0 is the actual struct and 1 is additional implementations
 fn generate_command_args_struct(
     args: TokenStream,
@@ -400,6 +824,7 @@ fn generate_command_args_struct(
             );
         };
 
+        let has_target = target_type.is_some();
         let target_fields = if let Some(target) = target_type {
             quote! {
                 #[doc = r"The target on which executing the command."]
@@ -438,6 +863,64 @@ fn generate_command_args_struct(
             None => quote! {},
         };
 
+        // defaults of the xtask.toml file for the arguments shared by the commands
+        let additional_names: Vec<String> =
+            syn::parse2::<syn::FieldsNamed>(quote! { { #additional_fields } })
+                .map(|fields| {
+                    fields
+                        .named
+                        .iter()
+                        .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+        let target_defaults = if has_target {
+            quote! {
+                if self.exclude.is_empty() {
+                    if let Some(exclude) = defaults("exclude") {
+                        self.exclude = exclude;
+                    }
+                }
+                if self.only.is_empty() {
+                    if let Some(only) = defaults("only") {
+                        self.only = only;
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+        let features_defaults = if additional_names.iter().any(|n| n == "features") {
+            let all_features = if additional_names.iter().any(|n| n == "all_features") {
+                quote! { && !self.all_features }
+            } else {
+                quote! {}
+            };
+            quote! {
+                if self.features.is_none() #all_features {
+                    self.features = defaults("features");
+                }
+            }
+        } else {
+            quote! {}
+        };
+        // commands without any of these arguments do not read the defaults
+        let allow_unused = if target_defaults.is_empty() && features_defaults.is_empty() {
+            quote! { #[allow(unused_variables)] }
+        } else {
+            quote! {}
+        };
+        let defaults_impl = quote! {
+            impl #struct_name {
+                #[doc = r"Set the exclude, only and features arguments which are not passed on the command line to the values returned by the passed function for their name."]
+                #allow_unused
+                pub fn apply_defaults(&mut self, defaults: impl Fn(&str) -> Option<Vec<String>>) {
+                    #target_defaults
+                    #features_defaults
+                }
+            }
+        };
+
         let (subcommand_field, subcommand_impl) = if let Some(subcommand) = subcommand_type.clone()
         {
             (
@@ -468,6 +951,7 @@ fn generate_command_args_struct(
         });
         let mut additional_output = TokenStream::from(quote! {
             #subcommand_impl
+            #defaults_impl
         });
         // generate the subcommand enum only when it is declared
         if args.len() == 2 {
@@ -531,26 +1015,15 @@ fn generate_command_args_tryinto(args: TokenStream, input: TokenStream) -> Token
     } else {
         (quote! {}, quote! {})
     };
+    // the fields to forward are the target fields and the additional fields of the base command
+    let mut base_fields = vec!["exclude".to_string(), "only".to_string()];
+    base_fields.extend(get_additional_cmd_args_field_names(&base_type_string));
     let fields: Vec<_> = item
         .fields
         .iter()
         .filter_map(|f| {
             f.ident.as_ref().map(|ident| {
-                let ident_str = ident.to_string();
-                // TODO this hardcoded predicate is awful, find a way to make this better
-                if ident_str != "target"
-                    && (ident_str == "exclude"
-                        || ident_str == "features"
-                        || ident_str == "force"
-                        || ident_str == "ignore_audit"
-                        || ident_str == "jobs"
-                        || ident_str == "no_default_features"
-                        || ident_str == "no_capture"
-                        || ident_str == "only"
-                        || ident_str == "release"
-                        || ident_str == "test"
-                        || ident_str == "threads")
-                {
+                if base_fields.contains(&ident.to_string()) {
                     quote! { #ident: self.#ident, }
                 } else {
                     quote! {}
@@ -640,12 +1113,32 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 All,
                 #[doc = r"Run audit command."]
                 Audit,
+                #[doc = r"Check that the public error types implement 'std::error::Error', 'Send' and 'Sync'."]
+                ErrorTypes,
+                #[doc = r"Report features without a '##' doc comment in the crate manifests (document-features convention)."]
+                FeatureDocs,
+                #[doc = r"Run 'cargo check' for each crate with its default features, without its default features and with each of its features alone. It is not run by 'all'."]
+                FeatureMatrix,
+                #[doc = r"Report dependencies whose features differ when a member is built alone and when the whole workspace is built (feature unification)."]
+                FeatureUnification,
                 #[doc = r"Run format command."]
                 Format,
                 #[doc = r"Run lint command."]
                 Lint,
+                #[doc = r"Report publishable members whose 'authors', 'homepage' or 'repository' differ from the expected values."]
+                PackageMetadata,
+                #[doc = r"Check that the 'pub use' re-exports of the crate roots resolve from outside of the crates."]
+                Reexports,
+                #[doc = r"Report integration test files which do not follow the naming convention."]
+                TestFiles,
+                #[doc = r"Report test names defined in more than one workspace member."]
+                TestNames,
+                #[doc = r"Report crates whose tests do not compile with '--no-default-features' and their configured minimal features while the crate does."]
+                TestsNoDefaultFeatures,
                 #[doc = r"Report typos in source code."]
                 Typos,
+                #[doc = r"Report member dependencies defined in '[workspace.dependencies]' which are not inherited with 'workspace = true'."]
+                WorkspaceDeps,
             },
         ),
         (
@@ -656,6 +1149,10 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Install,
                 #[doc = r"Generate lcov.info file. [default with default debug profile]"]
                 Generate(GenerateCmdArgs),
+                #[doc = r"Report the changed lines which are not covered by the tests of an lcov.info file (patch coverage)."]
+                Patch(PatchCmdArgs),
+                #[doc = r"Generate a coverage report of the workspace with cargo-llvm-cov."]
+                LlvmCov(LlvmCovCmdArgs),
             },
         ),
         (
@@ -664,9 +1161,17 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 #[doc = r"Run all dependency checks."]
                 #[default]
                 All,
+                #[doc = r"Detect dependency cycles between the workspace crates, including the ones created by dev-dependencies."]
+                Cycles,
                 #[doc = r"Run cargo-deny Lint dependency graph to ensure all dependencies meet requirements `<https://crates.io/crates/cargo-deny>`. [default]"]
                 Deny,
-                #[doc = r"Run cargo-machete to find unused dependencies `<https://crates.io/crates/cargo-machete>`"]
+                #[doc = r"Report the crates built in several versions using 'cargo tree --duplicates'."]
+                Duplicates,
+                #[doc = r"Run cargo-outdated to find the dependencies with a newer version `<https://crates.io/crates/cargo-outdated>`"]
+                Outdated,
+                #[doc = r"Check that publishable crates have no git dependencies nor path dependencies outside of the workspace."]
+                Publishable,
+                #[doc = r"Run cargo-udeps to find unused dependencies `<https://crates.io/crates/cargo-udeps>`, or cargo-machete `<https://crates.io/crates/cargo-machete>` without a nightly toolchain."]
                 Unused,
             },
         ),
@@ -680,6 +1185,16 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Tests,
             },
         ),
+        (
+            "FmtSubCommand",
+            quote! {
+                #[doc = r"Fail if a file of the workspace is not formatted."]
+                Check,
+                #[default]
+                #[doc = r"Format the files of the workspace."]
+                Write,
+            },
+        ),
         (
             "DockerSubCommand",
             quote! {
@@ -702,8 +1217,12 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Format,
                 #[doc = r"Run lint command and fix issues."]
                 Lint,
+                #[doc = r"Align the 'authors', 'homepage' and 'repository' of publishable members with the expected values."]
+                PackageMetadata,
                 #[doc = r"Find typos in source code and fix them."]
                 Typos,
+                #[doc = r"Make member dependencies defined in '[workspace.dependencies]' inherit them with 'workspace = true'."]
+                WorkspaceDeps,
             },
         ),
         (
@@ -716,6 +1235,9 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 Unit,
                 #[doc = r"Run integration tests."]
                 Integration,
+                #[doc = r"Run documentation tests."]
+                #[command(name = "doctest")]
+                DocTest,
             },
         ),
         (
@@ -728,6 +1250,9 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 AddressSanitizer,
                 #[doc = r"Run LLVM Control Flow Integrity (CFI) (provides forward-edge control flow protection)"]
                 ControlFlowIntegrity,
+                #[doc = r"Run DataFlow sanitizer (generic dynamic data flow analysis framework, used for taint analysis)"]
+                #[command(alias = "dataflow")]
+                DataFlowSanitizer,
                 #[doc = r"Run newer variant of Address sanitizer (memory error detector similar to AddressSanitizer, but based on partial hardware assistance)"]
                 HWAddressSanitizer,
                 #[doc = r"Run Kernel LLVM Control Flow Integrity (KCFI) (provides forward-edge control flow protection for operating systems kernels)"]
@@ -738,6 +1263,8 @@ fn get_subcommand_variant_map() -> HashMap<&'static str, proc_macro2::TokenStrea
                 MemorySanitizer,
                 #[doc = r"Run another address sanitizer (like AddressSanitizer and HardwareAddressSanitizer but with lower overhead suitable for use as hardening for production binaries)"]
                 MemTagSanitizer,
+                #[doc = r"Run the tests in the Miri interpreter to detect undefined behavior `<https://github.com/rust-lang/miri>`"]
+                Miri,
                 #[doc = r"Run nightly-only checks through cargo-careful `<https://crates.io/crates/cargo-careful>`"]
                 NightlyChecks,
                 #[doc = r"Run SafeStack check (provides backward-edge control flow protection by separating stack into safe and unsafe regions"]
@@ -0,0 +1,173 @@
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
+use strum::IntoEnumIterator;
+
+use crate::{
+    endgroup, group, group_info,
+    prelude::{Context, Environment},
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        process::{spawn_child, wait_child_output},
+        rustup::is_nightly_toolchain_installed,
+        script::ensure_not_planned,
+        workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
+    },
+};
+
+use super::Target;
+
+/// Name of the public API snapshot file stored at the root of each crate.
+pub const API_SNAPSHOT_FILENAME: &str = "public-api.txt";
+const NIGHTLY_REQUIRED_MSG: &str =
+    "cargo-public-api needs a nightly toolchain to build rustdoc JSON.
+Install a nightly toolchain with 'rustup toolchain install nightly'.";
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct ApiSnapshotCmdArgs {}
+
+pub fn handle_command(
+    args: ApiSnapshotCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    ensure_not_planned("api-snapshot")?;
+    // cargo-public-api invokes the nightly toolchain itself so it does not need to be the current one
+    if !is_nightly_toolchain_installed() {
+        return Err(anyhow!(NIGHTLY_REQUIRED_MSG));
+    }
+    ensure_cargo_crate_is_installed("cargo-public-api", None, None, false)?;
+    run_api_snapshot(&args.target, &args)
+}
+
+fn run_api_snapshot(target: &Target, args: &ApiSnapshotCmdArgs) -> anyhow::Result<()> {
+    match target {
+        // a public API is always defined per crate so the workspace target checks all the crates
+        Target::Workspace | Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => get_workspace_members(WorkspaceMemberType::AllCrates),
+            };
+            let mut mismatches = vec![];
+            for member in members {
+                if args.exclude.contains(&member.name)
+                    || (!args.only.is_empty() && !args.only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                    continue;
+                }
+                if !Path::new(&member.path).join("src").join("lib.rs").exists() {
+                    group_info!("Skip '{}' because it has no library target.", member.name);
+                    continue;
+                }
+                group!("API Snapshot: {}", member.name);
+                if !snapshot_member(&member, args.write)? {
+                    mismatches.push(member.name.clone());
+                }
+                endgroup!();
+            }
+            if !mismatches.is_empty() {
+                return Err(anyhow!(
+                    "Public API differs from the snapshot for: {}. Use --write to update the snapshots.",
+                    mismatches.join(", ")
+                ));
+            }
+        }
+        Target::AllPackages => {
+            Target::iter()
+                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .try_for_each(|t| run_api_snapshot(&t, args))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write or check the snapshot of the passed member.
+/// Returns false if the snapshot does not match the current public API.
+fn snapshot_member(member: &WorkspaceMember, write: bool) -> anyhow::Result<bool> {
+    let current = get_public_api(&member.name)?;
+    let snapshot_path = Path::new(&member.path).join(API_SNAPSHOT_FILENAME);
+    if write {
+        std::fs::write(&snapshot_path, &current)?;
+        info!("Public API snapshot written to {}", snapshot_path.display());
+        return Ok(true);
+    }
+    let snapshot = std::fs::read_to_string(&snapshot_path).map_err(|e| {
+        anyhow!(
+            "Cannot read the public API snapshot {} ({e}). Use --write to create it.",
+            snapshot_path.display()
+        )
+    })?;
+    let (added, removed) = diff_public_api(&snapshot, &current);
+    if added.is_empty() && removed.is_empty() {
+        info!("Public API of '{}' matches its snapshot.", member.name);
+        return Ok(true);
+    }
+    error!("Public API of '{}' differs from its snapshot:", member.name);
+    removed.iter().for_each(|item| info!("- {item}"));
+    added.iter().for_each(|item| info!("+ {item}"));
+    Ok(false)
+}
+
+fn get_public_api(crate_name: &str) -> anyhow::Result<String> {
+    let output = spawn_child(
+        Command::new("cargo")
+            .args(["public-api", "-p", crate_name])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .and_then(wait_child_output)
+    .map_err(|e| anyhow!("Failed to execute cargo public-api: {}", e))?;
+    if !output.status.success() {
+        error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(anyhow!(
+            "Failed to retrieve the public API of '{}'.",
+            crate_name
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the items added and removed in the current public API compared to the snapshot.
+fn diff_public_api(snapshot: &str, current: &str) -> (Vec<String>, Vec<String>) {
+    let to_set = |api: &str| -> BTreeSet<String> {
+        api.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let snapshot = to_set(snapshot);
+    let current = to_set(current);
+    let added = current.difference(&snapshot).cloned().collect();
+    let removed = snapshot.difference(&current).cloned().collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::identical("pub fn a()\npub fn b()", "pub fn a()\npub fn b()", vec![], vec![])]
+    #[case::reordered("pub fn a()\npub fn b()", "pub fn b()\npub fn a()", vec![], vec![])]
+    #[case::added("pub fn a()", "pub fn a()\npub fn b()", vec!["pub fn b()"], vec![])]
+    #[case::removed("pub fn a()\npub fn b()", "pub fn a()", vec![], vec!["pub fn b()"])]
+    #[case::changed("pub fn a(x: u8)", "pub fn a(x: u16)", vec!["pub fn a(x: u16)"], vec!["pub fn a(x: u8)"])]
+    #[case::blank_lines_ignored("pub fn a()\n\n", "\npub fn a()", vec![], vec![])]
+    fn test_diff_public_api(
+        #[case] snapshot: &str,
+        #[case] current: &str,
+        #[case] expected_added: Vec<&str>,
+        #[case] expected_removed: Vec<&str>,
+    ) {
+        let (added, removed) = diff_public_api(snapshot, current);
+        assert_eq!(added, expected_added);
+        assert_eq!(removed, expected_removed);
+    }
+}
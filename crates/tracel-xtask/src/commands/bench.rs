@@ -0,0 +1,119 @@
+use anyhow::Ok;
+use strum::IntoEnumIterator;
+
+use crate::{
+    endgroup, group,
+    prelude::{Context, Environment},
+    utils::{
+        process::{run_process_for_package, run_process_for_workspace},
+        workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
+    },
+};
+
+use super::{Target, WARN_IGNORED_ONLY_ARGS};
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct BenchCmdArgs {}
+
+pub fn handle_command(args: BenchCmdArgs, _env: Environment, _ctx: Context) -> anyhow::Result<()> {
+    if args.target == Target::Workspace && !args.only.is_empty() {
+        warn!("{WARN_IGNORED_ONLY_ARGS}");
+    }
+    run_bench(&args.target, &args)
+}
+
+fn run_bench(target: &Target, args: &BenchCmdArgs) -> anyhow::Result<()> {
+    match target {
+        Target::Workspace => {
+            info!("Workspace Benchmarks");
+            let cmd_args = get_bench_args(None, args);
+            run_process_for_workspace(
+                "cargo",
+                &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+                &args.exclude,
+                Some(r".*target/[^/]+/deps/([^-\s]+)"),
+                Some("Benchmarks"),
+                "Workspace Benchmarks failed",
+                None,
+                None,
+            )?;
+        }
+        Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => unreachable!(),
+            };
+
+            for member in members {
+                run_bench_for_member(&member, args)?;
+            }
+        }
+        Target::AllPackages => {
+            Target::iter()
+                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .try_for_each(|t| run_bench(&t, args))?;
+        }
+    }
+    Ok(())
+}
+
+fn run_bench_for_member(member: &WorkspaceMember, args: &BenchCmdArgs) -> anyhow::Result<()> {
+    group!("Benchmarks: {}", member.name);
+    let cmd_args = get_bench_args(Some(&member.name), args);
+    run_process_for_package(
+        "cargo",
+        &member.name,
+        &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        &args.exclude,
+        &args.only,
+        &format!("Failed to execute benchmarks for '{}'", &member.name),
+        Some("no bench target named"),
+        Some(&format!(
+            "No benchmark named '{}' in the crate '{}'.",
+            args.bench.as_deref().unwrap_or_default(),
+            &member.name
+        )),
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the cargo bench arguments for the passed package, or for the whole workspace if there
+/// is no package.
+fn get_bench_args(package: Option<&str>, args: &BenchCmdArgs) -> Vec<String> {
+    let mut cmd_args = vec!["bench".to_string()];
+    match package {
+        Some(package) => cmd_args.extend(["-p".to_string(), package.to_string()]),
+        None => cmd_args.push("--workspace".to_string()),
+    }
+    if let Some(bench) = &args.bench {
+        cmd_args.extend(["--bench".to_string(), bench.clone()]);
+    }
+    if args.no_run {
+        cmd_args.push("--no-run".to_string());
+    }
+    cmd_args.extend(["--color".to_string(), "always".to_string()]);
+    cmd_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::try_parse_cmd_args;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::workspace(None, &[], &["bench", "--workspace", "--color", "always"])]
+    #[case::package(Some("my-crate"), &[], &["bench", "-p", "my-crate", "--color", "always"])]
+    #[case::named_bench(Some("my-crate"), &["--bench", "matmul"], &["bench", "-p", "my-crate", "--bench", "matmul", "--color", "always"])]
+    #[case::no_run(None, &["--no-run"], &["bench", "--workspace", "--no-run", "--color", "always"])]
+    fn test_get_bench_args(
+        #[case] package: Option<&str>,
+        #[case] cli_args: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let args: BenchCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        assert_eq!(get_bench_args(package, &args), expected);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
+use strum::IntoEnumIterator;
+
+use crate::{
+    endgroup, group, group_info,
+    prelude::{Context, Environment},
+    utils::{
+        process::{spawn_child, wait_child_output},
+        script::ensure_not_planned,
+        workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
+    },
+};
+
+use super::Target;
+
+/// Name of the benchmark IDs snapshot file stored at the root of each crate with benchmarks.
+pub const BENCH_IDS_SNAPSHOT_FILENAME: &str = "bench-ids.txt";
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct BenchIdsCmdArgs {}
+
+/// Changes of the benchmark IDs of a member compared to its snapshot.
+#[derive(Debug, Default, PartialEq)]
+struct BenchIdsDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    /// Pairs of the snapshot ID and the current ID which differ by a single segment.
+    renamed: Vec<(String, String)>,
+}
+
+impl BenchIdsDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+pub fn handle_command(
+    args: BenchIdsCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    ensure_not_planned("bench-ids")?;
+    let changed = run_bench_ids(&args.target, &args)?;
+    if changed.is_empty() {
+        info!("All benchmark IDs match their snapshots.");
+        return Ok(());
+    }
+    let msg = format!(
+        "Benchmark IDs differ from the snapshot for: {}. Use --update to accept the new IDs.",
+        changed.join(", ")
+    );
+    if args.deny {
+        return Err(anyhow!(msg));
+    }
+    warn!("{msg}");
+    Ok(())
+}
+
+/// Returns the names of the members whose benchmark IDs differ from their snapshot.
+fn run_bench_ids(target: &Target, args: &BenchIdsCmdArgs) -> anyhow::Result<Vec<String>> {
+    let mut changed = vec![];
+    match target {
+        // benchmarks are always defined per package so the workspace target checks all the crates
+        Target::Workspace | Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => get_workspace_members(WorkspaceMemberType::AllCrates),
+            };
+            for member in members {
+                if args.exclude.contains(&member.name)
+                    || (!args.only.is_empty() && !args.only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                    continue;
+                }
+                group!("Bench IDs: {}", member.name);
+                if !check_member(&member, args.update)? {
+                    changed.push(member.name.clone());
+                }
+                endgroup!();
+            }
+        }
+        Target::AllPackages => {
+            for t in Target::iter().filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+            {
+                changed.extend(run_bench_ids(&t, args)?);
+            }
+        }
+    }
+    Ok(changed)
+}
+
+/// Check or update the benchmark IDs snapshot of the passed member.
+/// Returns false if the current benchmark IDs differ from the snapshot.
+fn check_member(member: &WorkspaceMember, update: bool) -> anyhow::Result<bool> {
+    let snapshot_path = Path::new(&member.path).join(BENCH_IDS_SNAPSHOT_FILENAME);
+    let current = list_bench_ids(&member.name)?;
+    if current.is_empty() && !snapshot_path.exists() {
+        info!("'{}' has no benchmark.", member.name);
+        return Ok(true);
+    }
+    if update {
+        let mut contents = current.join("\n");
+        contents.push('\n');
+        std::fs::write(&snapshot_path, contents)?;
+        info!(
+            "Benchmark IDs snapshot written to {}",
+            snapshot_path.display()
+        );
+        return Ok(true);
+    }
+    let snapshot = std::fs::read_to_string(&snapshot_path).unwrap_or_default();
+    let diff = diff_bench_ids(&snapshot, &current);
+    if diff.is_empty() {
+        info!("Benchmark IDs of '{}' match their snapshot.", member.name);
+        return Ok(true);
+    }
+    warn!(
+        "Benchmark IDs of '{}' differ from their snapshot {}:",
+        member.name,
+        snapshot_path.display()
+    );
+    diff.removed.iter().for_each(|id| info!("- {id}"));
+    diff.added.iter().for_each(|id| info!("+ {id}"));
+    diff.renamed
+        .iter()
+        .for_each(|(from, to)| info!("~ {from} -> {to}"));
+    Ok(false)
+}
+
+fn list_bench_ids(package: &str) -> anyhow::Result<Vec<String>> {
+    info!("Listing the benchmarks of {package}...");
+    let output = spawn_child(
+        Command::new("cargo")
+            .args(["bench", "-p", package, "--benches", "--", "--list"])
+            .stdout(Stdio::piped()),
+    )
+    .and_then(wait_child_output)
+    .map_err(|e| anyhow!("Failed to execute cargo bench: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Cannot list the benchmarks of '{}'.", package));
+    }
+    Ok(parse_bench_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Returns the sorted benchmark IDs of the passed list output, both the Criterion `<ID>: benchmark`
+/// and the libtest `<ID>: bench` lines are supported.
+fn parse_bench_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            line.strip_suffix(": benchmark")
+                .or_else(|| line.strip_suffix(": bench"))
+        })
+        .map(str::to_string)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Returns the benchmark IDs added, removed and renamed compared to the snapshot. A removed ID and
+/// an added ID with the same number of `/` separated segments which differ by a single segment
+/// are reported as a rename.
+fn diff_bench_ids(snapshot: &str, current: &[String]) -> BenchIdsDiff {
+    let snapshot: BTreeSet<&str> = snapshot
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let current: BTreeSet<&str> = current.iter().map(String::as_str).collect();
+    let mut added: Vec<&str> = current.difference(&snapshot).copied().collect();
+    let mut diff = BenchIdsDiff::default();
+    for removed in snapshot.difference(&current) {
+        match added.iter().position(|id| is_renamed(removed, id)) {
+            Some(index) => {
+                let id = added.remove(index);
+                diff.renamed.push((removed.to_string(), id.to_string()));
+            }
+            None => diff.removed.push(removed.to_string()),
+        }
+    }
+    diff.added = added.into_iter().map(str::to_string).collect();
+    diff
+}
+
+fn is_renamed(from: &str, to: &str) -> bool {
+    let from: Vec<&str> = from.split('/').collect();
+    let to: Vec<&str> = to.split('/').collect();
+    from.len() == to.len() && from.iter().zip(&to).filter(|(a, b)| a != b).count() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_bench_list() {
+        let output =
+            "matmul/naive/64: benchmark\nconv2d/128: benchmark\nbench_sum: bench\n\n2 benchmarks\n";
+        assert_eq!(
+            parse_bench_list(output),
+            ids(&["bench_sum", "conv2d/128", "matmul/naive/64"])
+        );
+    }
+
+    #[rstest]
+    #[case::identical("a/1\nb/1\n", &["a/1", "b/1"], &[], &[], &[])]
+    #[case::added("a/1\n", &["a/1", "b/1"], &["b/1"], &[], &[])]
+    #[case::removed("a/1\nb/1\n", &["a/1"], &[], &["b/1"], &[])]
+    #[case::renamed("matmul/naive/64\n", &["matmul/tiled/64"], &[], &[], &[("matmul/naive/64", "matmul/tiled/64")])]
+    #[case::regrouped("matmul/naive/64\n", &["gemm/tiled/64"], &["gemm/tiled/64"], &["matmul/naive/64"], &[])]
+    #[case::no_snapshot("", &["a/1"], &["a/1"], &[], &[])]
+    fn test_diff_bench_ids(
+        #[case] snapshot: &str,
+        #[case] current: &[&str],
+        #[case] added: &[&str],
+        #[case] removed: &[&str],
+        #[case] renamed: &[(&str, &str)],
+    ) {
+        assert_eq!(
+            diff_bench_ids(snapshot, &ids(current)),
+            BenchIdsDiff {
+                added: ids(added),
+                removed: ids(removed),
+                renamed: renamed
+                    .iter()
+                    .map(|(from, to)| (from.to_string(), to.to_string()))
+                    .collect(),
+            }
+        );
+    }
+}
@@ -27,13 +27,10 @@ pub(crate) fn run_build(target: &Target, args: &BuildCmdArgs) -> anyhow::Result<
     match target {
         Target::Workspace => {
             group!("Build Workspace");
-            let mut cmd_args = vec!["build", "--workspace", "--color", "always"];
-            if args.release {
-                cmd_args.push("--release");
-            }
+            let cmd_args = get_build_args(None, args);
             run_process_for_workspace(
                 "cargo",
-                &cmd_args,
+                &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
                 &args.exclude,
                 None,
                 None,
@@ -44,21 +41,19 @@ pub(crate) fn run_build(target: &Target, args: &BuildCmdArgs) -> anyhow::Result<
             endgroup!();
         }
         Target::Crates | Target::Examples => {
-            let members = match args.target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+            let members = match target {
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
 
             for member in members {
-                let mut cmd_args = vec!["build", "-p", &member.name, "--color", "always"];
-                if args.release {
-                    cmd_args.push("--release");
-                }
+                group!("Build: {}", member.name);
+                let cmd_args = get_build_args(Some(&member.name), args);
                 run_process_for_package(
                     "cargo",
                     &member.name,
-                    &cmd_args,
+                    &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
                     &args.exclude,
                     &args.only,
                     &format!("Build command failed for {}", &member.name),
@@ -76,3 +71,52 @@ pub(crate) fn run_build(target: &Target, args: &BuildCmdArgs) -> anyhow::Result<
     }
     Ok(())
 }
+
+/// Returns the cargo build arguments for the passed package, or for the whole workspace if there
+/// is no package.
+fn get_build_args(package: Option<&str>, args: &BuildCmdArgs) -> Vec<String> {
+    let mut cmd_args = vec!["build".to_string()];
+    match package {
+        Some(package) => cmd_args.extend(["-p".to_string(), package.to_string()]),
+        None => cmd_args.push("--workspace".to_string()),
+    }
+    cmd_args.extend(["--color".to_string(), "always".to_string()]);
+    if args.release {
+        cmd_args.push("--release".to_string());
+    }
+    if let Some(jobs) = args.jobs {
+        cmd_args.extend(["--jobs".to_string(), jobs.to_string()]);
+    }
+    if let Some(features) = args.features.as_ref().filter(|f| !f.is_empty()) {
+        cmd_args.extend(["--features".to_string(), features.join(",")]);
+    }
+    if args.no_default_features {
+        cmd_args.push("--no-default-features".to_string());
+    }
+    cmd_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::try_parse_cmd_args;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::workspace(None, &[], &["build", "--workspace", "--color", "always"])]
+    #[case::package(Some("my-crate"), &["--release"], &["build", "-p", "my-crate", "--color", "always", "--release"])]
+    #[case::jobs(None, &["-j", "4"], &["build", "--workspace", "--color", "always", "--jobs", "4"])]
+    #[case::features(
+        Some("my-crate"),
+        &["--features", "std,gpu", "--no-default-features"],
+        &["build", "-p", "my-crate", "--color", "always", "--features", "std,gpu", "--no-default-features"]
+    )]
+    fn test_get_build_args(
+        #[case] package: Option<&str>,
+        #[case] cli_args: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let args: BuildCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        assert_eq!(get_build_args(package, &args), expected);
+    }
+}
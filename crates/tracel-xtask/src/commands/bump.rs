@@ -22,6 +22,7 @@ fn bump(command: &BumpSubCommand) -> anyhow::Result<()> {
         None,
         None,
         &format!("Error trying to bump {command} version"),
+        None,
     )?;
     endgroup!();
     Ok(())
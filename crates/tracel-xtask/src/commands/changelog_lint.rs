@@ -0,0 +1,315 @@
+use anyhow::{anyhow, Ok};
+use regex::Regex;
+use semver::Version;
+
+use crate::{
+    endgroup, group,
+    prelude::{Context, Environment},
+};
+
+/// Changelog file at the root of the workspace.
+pub const CHANGELOG_FILENAME: &str = "CHANGELOG.md";
+/// Categories of changes defined by the keepachangelog format.
+const CATEGORIES: [&str; 6] = [
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+];
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct ChangelogLintCmdArgs {}
+
+/// A structural error of the changelog.
+#[derive(Debug, PartialEq)]
+struct ChangelogError {
+    line: usize,
+    message: String,
+}
+
+/// A version section of the changelog.
+#[derive(Debug, PartialEq)]
+struct Release {
+    line: usize,
+    /// None for the Unreleased section.
+    version: Option<Version>,
+    name: String,
+}
+
+pub fn handle_command(
+    args: ChangelogLintCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    group!("Changelog Lint");
+    let contents = std::fs::read_to_string(CHANGELOG_FILENAME)
+        .map_err(|e| anyhow!("Cannot read {CHANGELOG_FILENAME} ({e})."))?;
+    let (releases, errors) = lint_changelog(&contents);
+    for e in &errors {
+        error!("{CHANGELOG_FILENAME}:{}: {}", e.line, e.message);
+    }
+    let missing_version = args
+        .check_version
+        .as_ref()
+        .filter(|version| !releases.iter().any(|r| &r.name == *version));
+    if let Some(version) = missing_version {
+        error!("{CHANGELOG_FILENAME}: no section for version {version}");
+    }
+    endgroup!();
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "{CHANGELOG_FILENAME} has {} error(s).",
+            errors.len()
+        ));
+    }
+    if let Some(version) = missing_version {
+        return Err(anyhow!(
+            "{CHANGELOG_FILENAME} has no section for version {version}."
+        ));
+    }
+    info!("{CHANGELOG_FILENAME} follows the keepachangelog format.");
+    Ok(())
+}
+
+/// Returns the version sections of the passed keepachangelog contents and its structural errors,
+/// see `<https://keepachangelog.com/en/1.1.0/>`.
+fn lint_changelog(contents: &str) -> (Vec<Release>, Vec<ChangelogError>) {
+    let release_re = Regex::new(
+        r"^## \[(?<name>[^\]]+)\](?: - (?<date>\d{4}-\d{2}-\d{2}))?(?: \[YANKED\])?\s*$",
+    )
+    .expect("should compile release regex");
+    let mut releases: Vec<Release> = vec![];
+    let mut errors = vec![];
+    let mut error = |line: usize, message: String| errors.push(ChangelogError { line, message });
+    let mut has_title = false;
+    let mut categories: Vec<&str> = vec![];
+    for (index, text) in contents.lines().enumerate() {
+        let line = index + 1;
+        if text.starts_with("# ") {
+            if has_title || !releases.is_empty() {
+                error(line, "the changelog must have a single title".to_string());
+            }
+            has_title = true;
+        } else if text.starts_with("## ") {
+            if !has_title {
+                error(
+                    line,
+                    "the changelog must start with a '# ' title".to_string(),
+                );
+                has_title = true;
+            }
+            categories.clear();
+            let Some(caps) = release_re.captures(text) else {
+                error(
+                    line,
+                    format!("invalid version heading '{text}', expected '## [x.y.z] - YYYY-MM-DD' or '## [Unreleased]'"),
+                );
+                continue;
+            };
+            let name = caps["name"].to_string();
+            let date = caps.name("date").map(|d| d.as_str());
+            let version = if name == "Unreleased" {
+                if !releases.is_empty() {
+                    error(
+                        line,
+                        "the Unreleased section must be the first section".to_string(),
+                    );
+                }
+                if date.is_some() {
+                    error(
+                        line,
+                        "the Unreleased section must not have a date".to_string(),
+                    );
+                }
+                None
+            } else {
+                let Result::Ok(version) = Version::parse(&name) else {
+                    error(line, format!("invalid version '{name}'"));
+                    continue;
+                };
+                match date {
+                    Some(date) if !is_valid_date(date) => {
+                        error(line, format!("invalid date '{date}'"))
+                    }
+                    Some(_) => {}
+                    None => error(line, format!("version {name} has no release date")),
+                }
+                Some(version)
+            };
+            if let Some(previous) = releases.iter().find(|r| r.name == name) {
+                error(
+                    line,
+                    format!(
+                        "duplicate section {name}, first defined at line {}",
+                        previous.line
+                    ),
+                );
+            } else if let (Some(version), Some(previous)) = (
+                &version,
+                releases
+                    .iter()
+                    .rev()
+                    .find_map(|r| r.version.as_ref().map(|v| (v, &r.name))),
+            ) {
+                // pre-releases precede their release, i.e. 1.0.0-rc.1 < 1.0.0
+                if version >= previous.0 {
+                    error(
+                        line,
+                        format!(
+                            "version {name} must be listed after the older version {}",
+                            previous.1
+                        ),
+                    );
+                }
+            }
+            releases.push(Release {
+                line,
+                version,
+                name,
+            });
+        } else if let Some(category) = text.strip_prefix("### ") {
+            let category = category.trim();
+            if releases.is_empty() {
+                error(
+                    line,
+                    format!("category '{category}' is outside of a version section"),
+                );
+            } else if !CATEGORIES.contains(&category) {
+                error(
+                    line,
+                    format!(
+                        "unknown category '{category}', expected one of {}",
+                        CATEGORIES.join(", ")
+                    ),
+                );
+            } else if categories.contains(&category) {
+                error(
+                    line,
+                    format!("duplicate category '{category}' in the section"),
+                );
+            } else {
+                categories.push(category);
+            }
+        }
+    }
+    if !has_title {
+        error(1, "the changelog must start with a '# ' title".to_string());
+    }
+    (releases, errors)
+}
+
+fn is_valid_date(date: &str) -> bool {
+    let parts: Vec<u32> = date.split('-').filter_map(|p| p.parse().ok()).collect();
+    matches!(parts.as_slice(), [_, month, day] if (1..=12).contains(month) && (1..=31).contains(day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const CHANGELOG: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+## [Unreleased]
+
+### Added
+
+- New command.
+
+## [1.1.0] - 2024-02-01 [YANKED]
+
+### Fixed
+
+- A bug.
+
+## [1.0.0] - 2024-01-01
+
+### Added
+
+- First release.
+
+## [1.0.0-rc.1] - 2023-12-01
+
+### Added
+
+- Release candidate.
+
+[unreleased]: https://example.com/compare/v1.1.0...HEAD
+";
+
+    #[test]
+    fn test_lint_changelog_valid() {
+        let (releases, errors) = lint_changelog(CHANGELOG);
+        assert_eq!(errors, vec![]);
+        let names: Vec<&str> = releases.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Unreleased", "1.1.0", "1.0.0", "1.0.0-rc.1"]);
+    }
+
+    #[rstest]
+    #[case::missing_title(
+        "## [1.0.0] - 2024-01-01\n",
+        1,
+        "the changelog must start with a '# ' title"
+    )]
+    #[case::invalid_heading("# Changelog\n## 1.0.0\n", 2, "invalid version heading '## 1.0.0', expected '## [x.y.z] - YYYY-MM-DD' or '## [Unreleased]'")]
+    #[case::invalid_version("# Changelog\n## [1.0] - 2024-01-01\n", 2, "invalid version '1.0'")]
+    #[case::missing_date("# Changelog\n## [1.0.0]\n", 2, "version 1.0.0 has no release date")]
+    #[case::invalid_date(
+        "# Changelog\n## [1.0.0] - 2024-13-01\n",
+        2,
+        "invalid date '2024-13-01'"
+    )]
+    #[case::unreleased_not_first(
+        "# Changelog\n## [1.0.0] - 2024-01-01\n## [Unreleased]\n",
+        3,
+        "the Unreleased section must be the first section"
+    )]
+    #[case::unordered(
+        "# Changelog\n## [1.0.0] - 2024-01-01\n## [1.1.0] - 2024-02-01\n",
+        3,
+        "version 1.1.0 must be listed after the older version 1.0.0"
+    )]
+    #[case::unordered_pre_release(
+        "# Changelog\n## [1.0.0-rc.1] - 2024-01-01\n## [1.0.0] - 2024-02-01\n",
+        3,
+        "version 1.0.0 must be listed after the older version 1.0.0-rc.1"
+    )]
+    #[case::duplicate_version(
+        "# Changelog\n## [1.0.0] - 2024-01-01\n## [1.0.0] - 2024-01-01\n",
+        3,
+        "duplicate section 1.0.0, first defined at line 2"
+    )]
+    #[case::unknown_category(
+        "# Changelog\n## [1.0.0] - 2024-01-01\n### Misc\n",
+        3,
+        "unknown category 'Misc', expected one of Added, Changed, Deprecated, Removed, Fixed, Security"
+    )]
+    #[case::duplicate_category(
+        "# Changelog\n## [1.0.0] - 2024-01-01\n### Added\n- a\n### Added\n",
+        5,
+        "duplicate category 'Added' in the section"
+    )]
+    #[case::category_outside_section(
+        "# Changelog\n### Added\n",
+        2,
+        "category 'Added' is outside of a version section"
+    )]
+    fn test_lint_changelog_errors(
+        #[case] contents: &str,
+        #[case] expected_line: usize,
+        #[case] expected_message: &str,
+    ) {
+        let (_, errors) = lint_changelog(contents);
+        assert_eq!(
+            errors,
+            vec![ChangelogError {
+                line: expected_line,
+                message: expected_message.to_string(),
+            }]
+        );
+    }
+}
@@ -1,20 +1,47 @@
-use anyhow::Ok;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::{BufRead, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
 use strum::IntoEnumIterator;
+use toml_edit::Item;
 
 use crate::{
-    commands::WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS,
-    endgroup, group,
+    commands::{run_step, WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS},
+    config::{get_config, Config},
+    endgroup, group, group_info,
     prelude::{Context, Environment},
     utils::{
-        cargo::ensure_cargo_crate_is_installed,
-        process::{run_process, run_process_for_package, run_process_for_workspace},
-        workspace::{get_workspace_members, WorkspaceMemberType},
+        cargo::{ensure_cargo_crate_is_installed, get_dependency_tables},
+        process::{
+            run_process, run_process_for_package, run_process_for_workspace, spawn_child,
+            wait_child, wait_child_output,
+        },
+        script::{ensure_not_planned, plan_command},
+        workspace::{
+            get_workspace_members, get_workspace_metadata, get_workspace_resolved_metadata,
+            WorkspaceMember, WorkspaceMemberType,
+        },
     },
     versions::TYPOS_VERSION,
 };
 
 use super::Target;
 
+/// Default naming convention of integration test files, see `check.integration-test-pattern` config.
+pub const DEFAULT_INTEGRATION_TEST_PATTERN: &str = "*_test.rs";
+
+/// Name of the crate generated in the target directory to check the re-exports of a crate.
+const REEXPORTS_CHECK_CRATE: &str = "xtask_reexports_check";
+/// Name of the crate generated in the target directory to check the error types of a crate.
+const ERROR_TYPES_CHECK_CRATE: &str = "xtask_error_types_check";
+/// Trait bounds that the public error types must satisfy.
+const ERROR_TYPE_BOUNDS: [&str; 3] = ["std::error::Error", "Send", "Sync"];
+
 #[tracel_xtask_macros::declare_command_args(Target, CheckSubCommand)]
 pub struct CheckCmdArgs {}
 
@@ -31,11 +58,64 @@ pub fn handle_command(args: CheckCmdArgs, _env: Environment, _ctx: Context) -> a
             Ok(())
         }
         CheckSubCommand::Audit => run_audit(),
+        CheckSubCommand::ErrorTypes => run_error_types(&args.target, &args.exclude, &args.only),
+        CheckSubCommand::FeatureDocs => {
+            run_feature_docs(&args.target, &args.exclude, &args.only, args.deny)
+        }
+        CheckSubCommand::FeatureMatrix => {
+            run_feature_matrix(&args.target, &args.exclude, &args.only, args.keep_going)
+        }
+        CheckSubCommand::FeatureUnification => {
+            run_feature_unification(&args.target, &args.exclude, &args.only, args.deny)
+        }
         CheckSubCommand::Format => run_format(&args.target, &args.exclude, &args.only),
-        CheckSubCommand::Lint => run_lint(&args.target, &args.exclude, &args.only),
+        CheckSubCommand::Lint => {
+            let mut warnings = BTreeMap::new();
+            let result = run_lint(
+                &args.target,
+                &args.exclude,
+                &args.only,
+                &args.warn_only,
+                &mut warnings,
+            );
+            if !args.warn_only.is_empty() {
+                report_lint_warnings(&warnings);
+            }
+            result
+        }
+        CheckSubCommand::PackageMetadata => {
+            run_package_metadata(&args.target, &args.exclude, &args.only, args.deny, false)
+        }
+        CheckSubCommand::Reexports => run_reexports(&args.target, &args.exclude, &args.only),
+        CheckSubCommand::TestFiles => {
+            run_test_files(&args.target, &args.exclude, &args.only, args.deny)
+        }
+        CheckSubCommand::TestNames => {
+            run_test_names(&args.target, &args.exclude, &args.only, args.deny)
+        }
+        CheckSubCommand::TestsNoDefaultFeatures => {
+            run_tests_no_default_features(&args.target, &args.exclude, &args.only, args.deny)
+        }
         CheckSubCommand::Typos => run_typos(),
+        CheckSubCommand::WorkspaceDeps => {
+            run_workspace_deps(&args.target, &args.exclude, &args.only, args.deny, false)
+        }
         CheckSubCommand::All => CheckSubCommand::iter()
-            .filter(|c| *c != CheckSubCommand::All)
+            // the feature matrix compiles each crate many times, the re-exports and error types
+            // checks compile a generated crate for each member, the test names check builds all
+            // the tests and the no-default-features check builds them once more, so they are only
+            // run explicitly
+            .filter(|c| {
+                !matches!(
+                    c,
+                    CheckSubCommand::All
+                        | CheckSubCommand::ErrorTypes
+                        | CheckSubCommand::FeatureMatrix
+                        | CheckSubCommand::Reexports
+                        | CheckSubCommand::TestNames
+                        | CheckSubCommand::TestsNoDefaultFeatures
+                )
+            })
             .try_for_each(|c| {
                 handle_command(
                     CheckCmdArgs {
@@ -44,6 +124,9 @@ pub fn handle_command(args: CheckCmdArgs, _env: Environment, _ctx: Context) -> a
                         exclude: args.exclude.clone(),
                         only: args.only.clone(),
                         ignore_audit: args.ignore_audit,
+                        deny: args.deny,
+                        warn_only: args.warn_only.clone(),
+                        keep_going: args.keep_going,
                     },
                     _env.clone(),
                     _ctx.clone(),
@@ -61,6 +144,7 @@ fn run_audit() -> anyhow::Result<()> {
         None,
         None,
         "Audit check execution failed",
+        None,
     )?;
     endgroup!();
     Ok(())
@@ -84,7 +168,7 @@ fn run_format(target: &Target, excluded: &[String], only: &[String]) -> anyhow::
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
@@ -113,69 +197,1739 @@ fn run_format(target: &Target, excluded: &[String], only: &[String]) -> anyhow::
     Ok(())
 }
 
-fn run_lint(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+/// Run clippy denying all the warnings but the ones of the `warn_only` lints which are counted
+/// per lint in `warnings`.
+fn run_lint(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    warn_only: &[String],
+    warnings: &mut BTreeMap<String, usize>,
+) -> anyhow::Result<()> {
     match target {
         Target::Workspace => {
             group!("Lint Workspace");
-            run_process_for_workspace(
-                "cargo",
-                &[
-                    "clippy",
-                    "--no-deps",
-                    "--color=always",
-                    "--",
-                    "--deny",
-                    "warnings",
-                ],
-                &[],
-                None,
-                None,
-                "Workspace lint failed",
-                None,
-                None,
-            )?;
+            let clippy_args = get_clippy_args(None, warn_only);
+            let clippy_args: Vec<&str> = clippy_args.iter().map(String::as_str).collect();
+            if warn_only.is_empty() {
+                run_process_for_workspace(
+                    "cargo",
+                    &clippy_args,
+                    &[],
+                    None,
+                    None,
+                    "Workspace lint failed",
+                    None,
+                    None,
+                )?;
+            } else {
+                run_clippy_counting_warnings(&clippy_args, "Workspace lint failed", warnings)?;
+            }
             endgroup!();
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
 
             for member in members {
                 group!("Lint: {}", member.name);
-                run_process_for_package(
-                    "cargo",
-                    &member.name,
-                    &[
-                        "clippy",
-                        "--no-deps",
-                        "--color=always",
-                        "-p",
+                let clippy_args = get_clippy_args(Some(&member.name), warn_only);
+                let clippy_args: Vec<&str> = clippy_args.iter().map(String::as_str).collect();
+                let error_msg = format!("Lint fix execution failed for {}", &member.name);
+                if warn_only.is_empty() {
+                    run_process_for_package(
+                        "cargo",
                         &member.name,
-                        "--",
-                        "--deny",
-                        "warnings",
-                    ],
-                    excluded,
-                    only,
-                    &format!("Lint fix execution failed for {}", &member.name),
-                    None,
-                    None,
-                )?;
+                        &clippy_args,
+                        excluded,
+                        only,
+                        &error_msg,
+                        None,
+                        None,
+                    )?;
+                } else if excluded.contains(&member.name)
+                    || (!only.is_empty() && !only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                } else {
+                    run_clippy_counting_warnings(&clippy_args, &error_msg, warnings)?;
+                }
                 endgroup!();
             }
         }
         Target::AllPackages => {
             Target::iter()
                 .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_lint(&t, excluded, only))?;
+                .try_for_each(|t| run_lint(&t, excluded, only, warn_only, warnings))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the clippy arguments for the workspace or the passed package.
+/// The diagnostics are emitted as JSON when some lints are only reported so that they can be counted.
+fn get_clippy_args(package: Option<&str>, warn_only: &[String]) -> Vec<String> {
+    let mut args = vec!["clippy", "--no-deps", "--color=always"];
+    if let Some(package) = package {
+        args.extend(["-p", package]);
+    }
+    if !warn_only.is_empty() {
+        args.push("--message-format=json-diagnostic-rendered-ansi");
+    }
+    args.extend(["--", "--deny", "warnings"]);
+    let mut args: Vec<String> = args.into_iter().map(str::to_string).collect();
+    // `--deny warnings` promotes the lints passed with `--warn` to errors, only a forced warning
+    // level is kept, including over the lint attributes of the sources
+    for lint in warn_only {
+        args.extend(["--force-warn".to_string(), lint.clone()]);
+    }
+    args
+}
+
+/// Run clippy with JSON diagnostics, print the rendered diagnostics and count the warnings per lint.
+fn run_clippy_counting_warnings(
+    args: &[&str],
+    error_msg: &str,
+    warnings: &mut BTreeMap<String, usize>,
+) -> anyhow::Result<()> {
+    group_info!("Command line: cargo {}", args.join(" "));
+    let mut command = Command::new("cargo");
+    command.args(args);
+    if plan_command(&command) {
+        return Ok(());
+    }
+    let mut child = spawn_child(command.stdout(Stdio::piped()))
+        .map_err(|e| anyhow!("Failed to execute cargo clippy: {}", e))?;
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            let Result::Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+                println!("{line}");
+                continue;
+            };
+            count_lint_warning(&message, warnings);
+            if let Some(rendered) = message["message"]["rendered"].as_str() {
+                eprint!("{rendered}");
+            }
+        }
+    }
+    let status = wait_child(child);
+    if !status.success() {
+        return Err(anyhow!("{error_msg}"));
+    }
+    Ok(())
+}
+
+/// Count the passed cargo message if it is a lint warning.
+fn count_lint_warning(message: &serde_json::Value, warnings: &mut BTreeMap<String, usize>) {
+    if message["reason"] != "compiler-message" || message["message"]["level"] != "warning" {
+        return;
+    }
+    if let Some(lint) = message["message"]["code"]["code"].as_str() {
+        *warnings.entry(lint.to_string()).or_default() += 1;
+    }
+}
+
+fn report_lint_warnings(warnings: &BTreeMap<String, usize>) {
+    if warnings.is_empty() {
+        info!("No warnings reported for the report-only lints.");
+        return;
+    }
+    let total: usize = warnings.values().sum();
+    warn!("{total} warning(s) reported for the report-only lints:");
+    for (lint, count) in warnings {
+        warn!("{count:>6}  {lint}");
+    }
+}
+
+/// Returns the members checked by the manifest and files checks, the exclude and only arguments
+/// are ignored by the workspace target.
+fn get_checked_members(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+) -> Vec<WorkspaceMember> {
+    let members = match target {
+        Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
+        Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+        Target::Workspace | Target::AllPackages => {
+            get_workspace_members(WorkspaceMemberType::AllCrates)
+                .into_iter()
+                .chain(get_workspace_members(WorkspaceMemberType::Example))
+                .collect()
+        }
+    };
+    members
+        .into_iter()
+        .filter(|member| {
+            let skip = *target != Target::Workspace
+                && (excluded.contains(&member.name)
+                    || (!only.is_empty() && !only.contains(&member.name)));
+            if skip {
+                group_info!("Skip '{}' because it has been excluded!", member.name);
+            }
+            !skip
+        })
+        .collect()
+}
+
+fn run_feature_docs(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    group!("Feature Docs");
+    let mut offenders_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        let manifest_path = Path::new(&member.path).join("Cargo.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Cannot read {} ({})", manifest_path.display(), e))?;
+        let offenders = find_undocumented_features(&manifest)
+            .map_err(|e| anyhow!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+        if !offenders.is_empty() {
+            warn!(
+                "{}: features without a '##' doc comment: {}",
+                member.name,
+                offenders.join(", ")
+            );
+            offenders_count += offenders.len();
+        }
+    }
+    endgroup!();
+
+    if offenders_count > 0 {
+        let msg = format!("{offenders_count} feature(s) have no '##' doc comment.");
+        if deny {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("All features have a '##' doc comment.");
+    }
+    Ok(())
+}
+
+/// Features of a dependency which are enabled when the whole workspace is built but not when a
+/// member depending on it is built alone.
+#[derive(Debug, PartialEq)]
+struct UnificationHazard {
+    member: String,
+    /// Name and version of the dependency.
+    dependency: String,
+    /// Features enabled only by the other members.
+    features: Vec<String>,
+    /// Other members enabling these features.
+    enabled_by: Vec<String>,
+}
+
+/// Run `cargo check` for each cell of the feature matrix of the checked members, fails at the
+/// first failed cell unless `keep_going` is set.
+fn run_feature_matrix(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    keep_going: bool,
+) -> anyhow::Result<()> {
+    let mut failures = vec![];
+    for member in get_checked_members(target, excluded, only) {
+        for (cell, cell_args) in get_feature_matrix_cells(&member.features) {
+            let mut cmd_args = vec!["check", "-p", &member.name];
+            cmd_args.extend(cell_args.iter().map(String::as_str));
+            cmd_args.extend(["--color", "always"]);
+            let name = format!("{} ({})", member.name, cell);
+            run_step(&name, keep_going, &mut failures, || {
+                group!("Feature Matrix: {name}");
+                run_process(
+                    "cargo",
+                    &cmd_args,
+                    None,
+                    None,
+                    &format!("'{}' does not compile with {}", member.name, cell),
+                    None,
+                )?;
+                endgroup!();
+                Ok(())
+            })?;
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} feature matrix cell(s) do not compile: {}",
+            failures.len(),
+            failures.join(", ")
+        ));
+    }
+    info!("All the feature matrix cells compile.");
+    Ok(())
+}
+
+/// Returns the name and the cargo arguments of the cells of the feature matrix of a member with
+/// the passed features: the default features, no default features and each feature alone.
+fn get_feature_matrix_cells(features: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut cells = vec![
+        ("default features".to_string(), vec![]),
+        (
+            "no default features".to_string(),
+            vec!["--no-default-features".to_string()],
+        ),
+    ];
+    cells.extend(
+        features
+            .iter()
+            .filter(|feature| *feature != "default")
+            .map(|feature| {
+                (
+                    format!("feature '{feature}'"),
+                    vec![
+                        "--no-default-features".to_string(),
+                        "--features".to_string(),
+                        feature.clone(),
+                    ],
+                )
+            }),
+    );
+    cells
+}
+
+fn run_feature_unification(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    group!("Feature Unification");
+    let checked: Vec<String> = get_checked_members(target, excluded, only)
+        .into_iter()
+        .map(|member| member.name)
+        .collect();
+    let hazards: Vec<UnificationHazard> =
+        find_unification_hazards(&get_workspace_resolved_metadata()?)
+            .into_iter()
+            .filter(|hazard| checked.contains(&hazard.member))
+            .collect();
+    for hazard in &hazards {
+        warn!(
+            "{}: '{}' is built with features [{}] only when built along with {}.",
+            hazard.member,
+            hazard.dependency,
+            hazard.features.join(", "),
+            hazard.enabled_by.join(", ")
+        );
+    }
+    endgroup!();
+    if !hazards.is_empty() {
+        let msg = format!(
+            "{} dependency feature set(s) depend on feature unification.",
+            hazards.len()
+        );
+        if deny {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("No dependency feature set depends on feature unification.");
+    }
+    Ok(())
+}
+
+/// Returns the features of the normal dependencies that each workspace member would not enable if
+/// it were built alone but that are enabled by the other members depending on them when the whole
+/// workspace is built. A member is only compared with the members built in the same build graph,
+/// that is when one depends on the other or when another member depends on both. Only the
+/// features requested by the members manifests are compared, the features enabled by the
+/// dependencies of the dependencies are not.
+fn find_unification_hazards(metadata: &serde_json::Value) -> Vec<UnificationHazard> {
+    let empty = vec![];
+    let packages: HashMap<&str, &serde_json::Value> = metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|package| Some((package["id"].as_str()?, package)))
+        .collect();
+    let members: Vec<&str> = metadata["workspace_members"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|id| id.as_str())
+        .collect();
+    // features enabled by each member, indexed by dependency package id
+    let mut requests: BTreeMap<&str, BTreeMap<&str, BTreeSet<String>>> = BTreeMap::new();
+    for node in metadata["resolve"]["nodes"].as_array().unwrap_or(&empty) {
+        let Some(member) = node["id"]
+            .as_str()
+            .filter(|id| members.contains(id))
+            .and_then(|id| packages.get(id))
+        else {
+            continue;
+        };
+        let member_name = member["name"].as_str().unwrap_or_default();
+        for dep in node["deps"].as_array().unwrap_or(&empty) {
+            let is_normal = dep["dep_kinds"]
+                .as_array()
+                .is_some_and(|kinds| kinds.iter().any(|k| k["kind"].is_null()));
+            let (Some(dep_id), Some(dep_name)) = (dep["pkg"].as_str(), dep["name"].as_str()) else {
+                continue;
+            };
+            let Some(dep_package) = packages.get(dep_id).filter(|_| is_normal) else {
+                continue;
+            };
+            // the resolved name is the crate name of the possibly renamed dependency
+            let declarations = member["dependencies"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .filter(|declaration| {
+                    declaration["kind"].is_null()
+                        && declaration["rename"]
+                            .as_str()
+                            .or(declaration["name"].as_str())
+                            .is_some_and(|name| name.replace('-', "_") == dep_name)
+                });
+            let mut requested = BTreeSet::new();
+            for declaration in declarations {
+                if declaration["uses_default_features"]
+                    .as_bool()
+                    .unwrap_or(true)
+                {
+                    requested.insert("default".to_string());
+                }
+                requested.extend(
+                    declaration["features"]
+                        .as_array()
+                        .unwrap_or(&empty)
+                        .iter()
+                        .filter_map(|f| f.as_str().map(String::from)),
+                );
+            }
+            requests.entry(dep_id).or_default().insert(
+                member_name,
+                expand_dependency_features(requested, &dep_package["features"]),
+            );
+        }
+    }
+    let built_together = find_members_built_together(metadata, &members, &packages);
+    let mut hazards = vec![];
+    for (dep_id, by_member) in requests {
+        let package = packages[dep_id];
+        for (member, features) in &by_member {
+            let mut enabled_by = vec![];
+            let mut missing = BTreeSet::new();
+            for (other, other_features) in by_member.iter().filter(|(other, _)| {
+                *other != member && built_together.contains(&(*member, **other))
+            }) {
+                let extra: Vec<&String> = other_features.difference(features).collect();
+                if !extra.is_empty() {
+                    enabled_by.push(other.to_string());
+                    missing.extend(extra.into_iter().cloned());
+                }
+            }
+            if !missing.is_empty() {
+                hazards.push(UnificationHazard {
+                    member: member.to_string(),
+                    dependency: format!(
+                        "{} {}",
+                        package["name"].as_str().unwrap_or_default(),
+                        package["version"].as_str().unwrap_or_default()
+                    ),
+                    features: missing.into_iter().collect(),
+                    enabled_by,
+                });
+            }
+        }
+    }
+    hazards.sort_by(|a, b| (&a.member, &a.dependency).cmp(&(&b.member, &b.dependency)));
+    hazards
+}
+
+/// Returns the pairs of names of the workspace members which are built in the same build graph,
+/// that is the pairs of members which are both in the normal dependency closure of a member.
+fn find_members_built_together<'a>(
+    metadata: &'a serde_json::Value,
+    members: &[&'a str],
+    packages: &HashMap<&'a str, &'a serde_json::Value>,
+) -> BTreeSet<(&'a str, &'a str)> {
+    let empty = vec![];
+    let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+    for node in metadata["resolve"]["nodes"].as_array().unwrap_or(&empty) {
+        let Some(id) = node["id"].as_str() else {
+            continue;
+        };
+        let deps = node["deps"]
+            .as_array()
+            .unwrap_or(&empty)
+            .iter()
+            .filter(|dep| {
+                dep["dep_kinds"]
+                    .as_array()
+                    .is_some_and(|kinds| kinds.iter().any(|k| k["kind"].is_null()))
+            })
+            .filter_map(|dep| dep["pkg"].as_str());
+        graph.entry(id).or_default().extend(deps);
+    }
+    let name = |id: &str| {
+        packages
+            .get(id)
+            .and_then(|package| package["name"].as_str())
+            .unwrap_or_default()
+    };
+    let mut together = BTreeSet::new();
+    for &root in members {
+        let mut visited = HashSet::from([root]);
+        let mut pending = vec![root];
+        while let Some(id) = pending.pop() {
+            for &dep in graph.get(id).into_iter().flatten() {
+                if visited.insert(dep) {
+                    pending.push(dep);
+                }
+            }
+        }
+        let closure: Vec<&str> = members
+            .iter()
+            .filter(|member| visited.contains(*member))
+            .map(|member| name(member))
+            .collect();
+        for &a in &closure {
+            for &b in &closure {
+                together.insert((a, b));
+            }
+        }
+    }
+    together
+}
+
+/// Returns the passed features of a dependency along with the features they enable according to
+/// the `features` table of the dependency.
+fn expand_dependency_features(
+    mut features: BTreeSet<String>,
+    table: &serde_json::Value,
+) -> BTreeSet<String> {
+    let mut pending: Vec<String> = features.iter().cloned().collect();
+    while let Some(feature) = pending.pop() {
+        for enabled in table[&feature].as_array().into_iter().flatten() {
+            let Some(enabled) = enabled.as_str() else {
+                continue;
+            };
+            // 'dep/feature' also enables the implicit feature of an optional dependency
+            let name = enabled.split('/').next().unwrap_or_default();
+            if !name.starts_with("dep:")
+                && !name.ends_with('?')
+                && table.get(name).is_some()
+                && features.insert(name.to_string())
+            {
+                pending.push(name.to_string());
+            }
+        }
+    }
+    // the default feature is only a request, it is not reported when missing
+    features.retain(|f| table.get(f).is_some() && f != "default");
+    features
+}
+
+/// A member dependency defined in `[workspace.dependencies]` which is not inherited.
+#[derive(Debug, PartialEq)]
+struct UninheritedDependency {
+    name: String,
+    /// Dotted name of the dependency table, i.e. `dev-dependencies`.
+    table: String,
+    version: Option<String>,
+    /// Replacement of the declaration inheriting the dependency, None if it cannot be fixed
+    /// automatically because it sets keys which cannot be inherited.
+    fix: Option<(Range<usize>, String)>,
+}
+
+/// Report, or fix with `fix`, the member dependencies which are not inherited from the workspace.
+pub(crate) fn run_workspace_deps(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+    fix: bool,
+) -> anyhow::Result<()> {
+    group!("Workspace Dependencies");
+    let workspace_dependencies = get_workspace_dependency_names()?;
+    let mut drifts_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        let manifest_path = Path::new(&member.path).join("Cargo.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Cannot read {} ({})", manifest_path.display(), e))?;
+        let drifts = find_uninherited_dependencies(&manifest, &workspace_dependencies)
+            .map_err(|e| anyhow!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+        let mut fixes = vec![];
+        for drift in drifts {
+            match drift.fix {
+                Some(replacement) if fix => {
+                    info!(
+                        "{}: '{}' in [{}] now inherits the workspace dependency.",
+                        member.name, drift.name, drift.table
+                    );
+                    fixes.push(replacement);
+                }
+                _ => {
+                    let declared = drift
+                        .version
+                        .map(|v| format!("version '{v}'"))
+                        .unwrap_or("its own source".to_string());
+                    warn!(
+                        "{}: '{}' in [{}] declares {} instead of inheriting the workspace dependency.",
+                        member.name, drift.name, drift.table, declared
+                    );
+                    drifts_count += 1;
+                }
+            }
+        }
+        if !fixes.is_empty() {
+            let mut fixed = manifest;
+            fixes.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+            for (range, replacement) in fixes {
+                fixed.replace_range(range, &replacement);
+            }
+            std::fs::write(&manifest_path, fixed)?;
         }
     }
+    endgroup!();
+
+    if drifts_count > 0 {
+        let msg = if fix {
+            format!("{drifts_count} dependencies set keys which cannot be inherited and must be fixed manually.")
+        } else {
+            format!("{drifts_count} dependencies do not inherit the workspace dependencies.")
+        };
+        if deny || fix {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("All the workspace dependencies are inherited.");
+    }
     Ok(())
 }
 
+/// Returns the names of the `[workspace.dependencies]` of the workspace manifest.
+fn get_workspace_dependency_names() -> anyhow::Result<Vec<String>> {
+    let manifest = std::fs::read_to_string("Cargo.toml")
+        .map_err(|e| anyhow!("Cannot read the workspace manifest ({})", e))?;
+    let document = manifest.parse::<toml_edit::DocumentMut>()?;
+    Ok(document
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table_like())
+        .map(|d| d.iter().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default())
+}
+
+/// Returns the dependencies of the passed manifest which are defined in the passed workspace
+/// dependencies but do not inherit them.
+fn find_uninherited_dependencies(
+    manifest: &str,
+    workspace_dependencies: &[String],
+) -> anyhow::Result<Vec<UninheritedDependency>> {
+    // keys dropped when inheriting the dependency, the features and optional keys are kept
+    const DROPPED_KEYS: [&str; 3] = ["version", "path", "registry"];
+    const KEPT_KEYS: [&str; 2] = ["features", "optional"];
+    let document = toml_edit::ImDocument::parse(manifest)?;
+    let span = |span: Option<Range<usize>>| span.ok_or_else(|| anyhow!("missing span"));
+    let mut drifts = vec![];
+    for (table_name, table) in get_dependency_tables(document.as_table()) {
+        for (name, item) in table.iter() {
+            if !workspace_dependencies.iter().any(|d| d == name) {
+                continue;
+            }
+            let (entries, inline) = match item {
+                Item::Value(toml_edit::Value::String(version)) => {
+                    drifts.push(UninheritedDependency {
+                        name: name.to_string(),
+                        table: table_name.clone(),
+                        version: Some(version.value().clone()),
+                        fix: Some((span(item.span())?, "{ workspace = true }".to_string())),
+                    });
+                    continue;
+                }
+                Item::Value(toml_edit::Value::InlineTable(dependency)) => {
+                    (dependency.get_values(), true)
+                }
+                Item::Table(dependency) => (dependency.get_values(), false),
+                _ => continue,
+            };
+            let get = |key: &str| {
+                entries
+                    .iter()
+                    .find(|(keys, _)| keys.len() == 1 && keys[0].get() == key)
+                    .map(|(_, value)| *value)
+            };
+            // renamed dependencies are not checked as the key is not the workspace dependency name
+            if get("workspace").is_some() || get("package").is_some() {
+                continue;
+            }
+            let fixable = entries.iter().all(|(keys, _)| {
+                keys.len() == 1
+                    && (DROPPED_KEYS.contains(&keys[0].get()) || KEPT_KEYS.contains(&keys[0].get()))
+            });
+            let fix = if fixable {
+                let mut kept = vec!["workspace = true".to_string()];
+                let mut range: Option<Range<usize>> = None;
+                for (keys, value) in &entries {
+                    let entry = span(keys[0].span())?.start..span(value.span())?.end;
+                    if KEPT_KEYS.contains(&keys[0].get()) {
+                        kept.push(manifest[entry.clone()].to_string());
+                    }
+                    range = Some(match range {
+                        Some(range) => range.start.min(entry.start)..range.end.max(entry.end),
+                        None => entry,
+                    });
+                }
+                match (inline, range) {
+                    (true, _) => Some((span(item.span())?, format!("{{ {} }}", kept.join(", ")))),
+                    (false, Some(range)) => Some((range, kept.join("\n"))),
+                    (false, None) => None,
+                }
+            } else {
+                None
+            };
+            drifts.push(UninheritedDependency {
+                name: name.to_string(),
+                table: table_name.clone(),
+                version: get("version").and_then(|v| v.as_str()).map(str::to_string),
+                fix,
+            });
+        }
+    }
+    Ok(drifts)
+}
+
+/// Keys of the package metadata which must be the same for all the publishable members.
+const PACKAGE_METADATA_KEYS: [&str; 3] = ["authors", "homepage", "repository"];
+
+/// Value of a package metadata key.
+#[derive(Debug, Clone, PartialEq)]
+enum MetadataValue {
+    String(String),
+    Array(Vec<String>),
+}
+
+impl MetadataValue {
+    fn from_toml(value: &toml_edit::Value) -> Option<Self> {
+        match value {
+            toml_edit::Value::String(s) => Some(Self::String(s.value().clone())),
+            toml_edit::Value::Array(array) => Some(Self::Array(
+                array
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn to_toml(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        match self {
+            Self::String(s) => quote(s),
+            Self::Array(values) => format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(|v| quote(v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// A package metadata key of a member manifest which differs from the expected value.
+#[derive(Debug, PartialEq)]
+struct MetadataDeviation {
+    key: String,
+    /// None when the key is missing.
+    found: Option<MetadataValue>,
+    expected: MetadataValue,
+    /// True when the value is inherited from `[workspace.package]`.
+    inherited: bool,
+    /// Span of the manifest to replace and its replacement, None when the deviation comes from
+    /// the workspace manifest.
+    fix: Option<(Range<usize>, String)>,
+}
+
+/// Report, or fix with `fix`, the publishable members whose package metadata differ from the
+/// expected values.
+pub(crate) fn run_package_metadata(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+    fix: bool,
+) -> anyhow::Result<()> {
+    group!("Package Metadata");
+    let manifest = std::fs::read_to_string("Cargo.toml")
+        .map_err(|e| anyhow!("Cannot read the workspace manifest ({})", e))?;
+    let workspace_package = parse_workspace_package_metadata(&manifest)?;
+    let expected = get_expected_package_metadata(get_config(), &workspace_package);
+    if expected.is_empty() {
+        endgroup!();
+        warn!("No expected package metadata, define them in [workspace.package] or in the check.package-metadata table of xtask.toml.");
+        return Ok(());
+    }
+    let metadata = get_workspace_metadata()?;
+    // 'publish = false' is reported as an empty list of registries
+    let unpublished: Vec<&str> = metadata["packages"]
+        .as_array()
+        .map(|packages| {
+            packages
+                .iter()
+                .filter(|p| matches!(p["publish"].as_array(), Some(r) if r.is_empty()))
+                .filter_map(|p| p["name"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut deviations_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        if unpublished.contains(&member.name.as_str()) {
+            group_info!("Skip '{}' because it is not published.", member.name);
+            continue;
+        }
+        let manifest_path = Path::new(&member.path).join("Cargo.toml");
+        let manifest = std::fs::read_to_string(&manifest_path)
+            .map_err(|e| anyhow!("Cannot read {} ({})", manifest_path.display(), e))?;
+        let deviations = find_metadata_deviations(&manifest, &expected, &workspace_package)
+            .map_err(|e| anyhow!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+        let mut fixes = vec![];
+        for deviation in deviations {
+            match deviation.fix {
+                Some(replacement) if fix => {
+                    if workspace_package.get(&deviation.key) == Some(&deviation.expected) {
+                        info!(
+                            "{}: '{}' is now inherited from the workspace.",
+                            member.name, deviation.key
+                        );
+                    } else {
+                        info!(
+                            "{}: '{}' is now {}.",
+                            member.name,
+                            deviation.key,
+                            deviation.expected.to_toml()
+                        );
+                    }
+                    fixes.push(replacement);
+                }
+                _ => {
+                    let found = match (&deviation.found, deviation.inherited) {
+                        (Some(value), true) => {
+                            format!("inherits {} from the workspace", value.to_toml())
+                        }
+                        (None, true) => {
+                            "inherits a key missing from [workspace.package]".to_string()
+                        }
+                        (Some(value), false) => format!("is {}", value.to_toml()),
+                        (None, false) => "is missing".to_string(),
+                    };
+                    warn!(
+                        "{}: '{}' {}, expected {}.",
+                        member.name,
+                        deviation.key,
+                        found,
+                        deviation.expected.to_toml()
+                    );
+                    deviations_count += 1;
+                }
+            }
+        }
+        if !fixes.is_empty() {
+            let mut fixed = manifest;
+            fixes.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+            for (range, replacement) in fixes {
+                fixed.replace_range(range, &replacement);
+            }
+            std::fs::write(&manifest_path, fixed)?;
+        }
+    }
+    endgroup!();
+
+    if deviations_count > 0 {
+        let msg = if fix {
+            format!("{deviations_count} package metadata are inherited from [workspace.package] and must be fixed manually.")
+        } else {
+            format!("{deviations_count} package metadata differ from the expected values.")
+        };
+        if deny || fix {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("All the publishable members have the expected package metadata.");
+    }
+    Ok(())
+}
+
+/// Returns the package metadata keys defined in the `[workspace.package]` table of the passed
+/// workspace manifest.
+fn parse_workspace_package_metadata(
+    manifest: &str,
+) -> anyhow::Result<BTreeMap<String, MetadataValue>> {
+    let document = toml_edit::ImDocument::parse(manifest)?;
+    let Some(package) = document
+        .get("workspace")
+        .and_then(|w| w.get("package"))
+        .and_then(|p| p.as_table_like())
+    else {
+        return Ok(BTreeMap::new());
+    };
+    Ok(PACKAGE_METADATA_KEYS
+        .iter()
+        .filter_map(|key| {
+            let value = MetadataValue::from_toml(package.get(key)?.as_value()?)?;
+            Some((key.to_string(), value))
+        })
+        .collect())
+}
+
+/// Returns the expected package metadata, the `check.package-metadata` table of the `xtask.toml`
+/// file takes precedence over the `[workspace.package]` table of the workspace manifest.
+fn get_expected_package_metadata(
+    config: &Config,
+    workspace_package: &BTreeMap<String, MetadataValue>,
+) -> BTreeMap<String, MetadataValue> {
+    PACKAGE_METADATA_KEYS
+        .iter()
+        .filter_map(|key| {
+            let path = format!("check.package-metadata.{key}");
+            let configured = if *key == "authors" {
+                config.get_string_array(&path).map(MetadataValue::Array)
+            } else {
+                config
+                    .get_str(&path)
+                    .map(|v| MetadataValue::String(v.to_string()))
+            };
+            let value = configured.or_else(|| workspace_package.get(*key).cloned())?;
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Returns the package metadata of the passed member manifest which differ from the expected
+/// values. Fixes inherit the value when `[workspace.package]` defines the expected value.
+fn find_metadata_deviations(
+    manifest: &str,
+    expected: &BTreeMap<String, MetadataValue>,
+    workspace_package: &BTreeMap<String, MetadataValue>,
+) -> anyhow::Result<Vec<MetadataDeviation>> {
+    let document = toml_edit::ImDocument::parse(manifest)?;
+    let package = document
+        .get("package")
+        .and_then(|p| p.as_table())
+        .ok_or_else(|| anyhow!("missing [package] table"))?;
+    let span = |span: Option<Range<usize>>| span.ok_or_else(|| anyhow!("missing span"));
+    let mut deviations = vec![];
+    for (key, expected) in expected {
+        let replacement = if workspace_package.get(key) == Some(expected) {
+            format!("{key}.workspace = true")
+        } else {
+            format!("{key} = {}", expected.to_toml())
+        };
+        let deviation = match package.get_key_value(key) {
+            Some((_, item)) if item.get("workspace").and_then(|w| w.as_bool()) == Some(true) => {
+                let found = workspace_package.get(key);
+                if found == Some(expected) {
+                    continue;
+                }
+                MetadataDeviation {
+                    key: key.clone(),
+                    found: found.cloned(),
+                    expected: expected.clone(),
+                    inherited: true,
+                    fix: None,
+                }
+            }
+            Some((k, Item::Value(value))) => {
+                let found = MetadataValue::from_toml(value);
+                if found.as_ref() == Some(expected) {
+                    continue;
+                }
+                MetadataDeviation {
+                    key: key.clone(),
+                    found,
+                    expected: expected.clone(),
+                    inherited: false,
+                    fix: Some((span(k.span())?.start..span(value.span())?.end, replacement)),
+                }
+            }
+            Some(_) => continue,
+            None => {
+                // append the key after the last entry of the package table
+                let mut end = None;
+                for (_, value) in package.get_values() {
+                    end = end.max(Some(span(value.span())?.end));
+                }
+                MetadataDeviation {
+                    key: key.clone(),
+                    found: None,
+                    expected: expected.clone(),
+                    inherited: false,
+                    fix: end.map(|end| (end..end, format!("\n{replacement}"))),
+                }
+            }
+        };
+        deviations.push(deviation);
+    }
+    Ok(deviations)
+}
+
+/// Returns the features of the passed manifest which are not preceded by a `##` doc comment
+/// line as expected by the document-features crate.
+fn find_undocumented_features(manifest: &str) -> anyhow::Result<Vec<String>> {
+    let document = manifest.parse::<toml_edit::DocumentMut>()?;
+    let Some(features) = document.get("features").and_then(|f| f.as_table_like()) else {
+        return Ok(vec![]);
+    };
+    Ok(features
+        .iter()
+        .filter(|(name, _)| {
+            let prefix = features
+                .key(name)
+                .and_then(|key| key.leaf_decor().prefix())
+                .and_then(|prefix| prefix.as_str())
+                .unwrap_or_default();
+            // the last comment line above the feature must be a doc comment
+            !prefix
+                .lines()
+                .map(str::trim)
+                .rev()
+                .find(|line| !line.is_empty())
+                .is_some_and(|line| line.starts_with("##"))
+        })
+        .map(|(name, _)| name.to_string())
+        .collect())
+}
+
+/// A re-exported item of the crate root or of its public inline modules.
+#[derive(Debug, PartialEq)]
+struct Reexport {
+    /// Path of the item from the crate root, i.e. `prelude::Target`.
+    path: String,
+    /// `cfg` attributes of the `pub use` declaration.
+    attributes: Vec<String>,
+    /// Line of the `pub use` declaration in the crate root file.
+    line: usize,
+}
+
+fn run_reexports(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+    ensure_not_planned("check reexports")?;
+    let libs = get_lib_targets()?;
+    let target_dir = get_target_directory()?;
+    let mut unresolved_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        let Some(lib) = libs.get(&member.name) else {
+            continue;
+        };
+        let Result::Ok(source) =
+            std::fs::read_to_string(Path::new(&member.path).join("src").join("lib.rs"))
+        else {
+            continue;
+        };
+        let reexports = parse_reexports(&source);
+        if reexports.is_empty() {
+            continue;
+        }
+        group!("Re-exports: {}", member.name);
+        let unresolved = check_reexports(&member, lib, &reexports, &target_dir)?;
+        for reexport in &unresolved {
+            error!(
+                "{}: re-export '{}' declared at src/lib.rs:{} does not resolve.",
+                member.name, reexport.path, reexport.line
+            );
+        }
+        unresolved_count += unresolved.len();
+        endgroup!();
+    }
+    if unresolved_count > 0 {
+        return Err(anyhow!("{unresolved_count} re-export(s) do not resolve."));
+    }
+    info!("All re-exports resolve.");
+    Ok(())
+}
+
+/// Library target of a workspace package.
+struct LibTarget {
+    name: String,
+    /// Features of the package with the features or dependencies they enable.
+    features: BTreeMap<String, Vec<String>>,
+}
+
+/// Returns the library target of each workspace package which has a library.
+fn get_lib_targets() -> anyhow::Result<HashMap<String, LibTarget>> {
+    let metadata = get_workspace_metadata()?;
+    let empty = vec![];
+    Ok(metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|package| {
+            let lib = package["targets"].as_array()?.iter().find(|target| {
+                target["kind"]
+                    .as_array()
+                    .is_some_and(|kinds| kinds.iter().any(|k| k == "lib" || k == "rlib"))
+            })?;
+            let features = package["features"]
+                .as_object()
+                .map(|features| {
+                    features
+                        .iter()
+                        .map(|(name, enabled)| {
+                            let enabled = enabled
+                                .as_array()
+                                .map(|enabled| {
+                                    enabled
+                                        .iter()
+                                        .filter_map(|e| e.as_str().map(str::to_string))
+                                        .collect()
+                                })
+                                .unwrap_or_default();
+                            (name.clone(), enabled)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some((
+                package["name"].as_str()?.to_string(),
+                LibTarget {
+                    name: lib["name"].as_str()?.to_string(),
+                    features,
+                },
+            ))
+        })
+        .collect())
+}
+
+/// Compile a crate referencing all the re-exports from outside of the crate and returns the
+/// re-exports which do not resolve.
+fn check_reexports<'a>(
+    member: &WorkspaceMember,
+    lib: &LibTarget,
+    reexports: &'a [Reexport],
+    target_dir: &Path,
+) -> anyhow::Result<Vec<&'a Reexport>> {
+    let (contents, lines) = generate_reexports_test(&lib.name, reexports);
+    let mut unresolved: Vec<&Reexport> =
+        compile_generated_crate(member, lib, REEXPORTS_CHECK_CRATE, &contents, target_dir)?
+            .iter()
+            .filter_map(|line_number| lines.get(line_number).map(|index| &reexports[*index]))
+            .collect();
+    unresolved.dedup_by_key(|reexport| reexport.line);
+    Ok(unresolved)
+}
+
+/// Returns the target directory of the workspace.
+fn get_target_directory() -> anyhow::Result<PathBuf> {
+    get_workspace_metadata()?["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("Cannot retrieve the target directory of the workspace."))
+}
+
+/// Compile the passed contents as the library of a scratch crate depending on the member and
+/// returns the lines of the library with errors. The scratch crate is generated in the passed
+/// target directory so that the member itself is never modified.
+fn compile_generated_crate(
+    member: &WorkspaceMember,
+    lib: &LibTarget,
+    crate_name: &str,
+    contents: &str,
+    target_dir: &Path,
+) -> anyhow::Result<Vec<usize>> {
+    let crate_dir = target_dir
+        .join("xtask-generated")
+        .join(crate_name)
+        .join(&member.name);
+    std::fs::create_dir_all(crate_dir.join("src"))?;
+    let member_dir = std::path::absolute(&member.path)?;
+    std::fs::write(
+        crate_dir.join("Cargo.toml"),
+        generate_scratch_manifest(&member.name, lib, &member_dir, crate_name),
+    )?;
+    std::fs::write(
+        crate_dir.join("src").join(format!("{crate_name}.rs")),
+        contents,
+    )?;
+    // resolve the same versions of the dependencies as the workspace
+    if let Result::Ok(lockfile) = std::fs::read("Cargo.lock") {
+        std::fs::write(crate_dir.join("Cargo.lock"), lockfile)?;
+    }
+    let output = spawn_child(
+        Command::new("cargo")
+            .arg("check")
+            .arg("--manifest-path")
+            .arg(crate_dir.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(target_dir)
+            .args(["--message-format=short", "--color=never"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .and_then(wait_child_output)
+    .map_err(|e| anyhow!("Failed to execute cargo check: {}", e))?;
+    if output.status.success() {
+        return Ok(vec![]);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let file_prefix = format!("{crate_name}.rs:");
+    let lines: Vec<usize> = stderr
+        .lines()
+        .filter(|line| line.contains(": error"))
+        .filter_map(|line| {
+            let location = &line[line.find(&file_prefix)? + file_prefix.len()..];
+            location.split(':').next()?.parse().ok()
+        })
+        .collect();
+    if lines.is_empty() {
+        error!("{stderr}");
+        return Err(anyhow!(
+            "Failed to compile the generated '{crate_name}' crate of '{}'.",
+            member.name
+        ));
+    }
+    Ok(lines)
+}
+
+/// Returns the manifest of the scratch crate compiling the generated library, it is its own
+/// workspace so that it is not mistaken for a member of the workspace of the target directory.
+/// The features of the member are mirrored so that the `cfg(feature)` attributes of the generated
+/// library match the features enabled in the member.
+fn generate_scratch_manifest(
+    package: &str,
+    lib: &LibTarget,
+    member_dir: &Path,
+    crate_name: &str,
+) -> String {
+    let lib_name = &lib.name;
+    let mut manifest = vec![
+        "[package]".to_string(),
+        format!("name = {:?}", crate_name.replace('_', "-")),
+        "version = \"0.0.0\"".to_string(),
+        "edition = \"2021\"".to_string(),
+        "publish = false".to_string(),
+        String::new(),
+        "[lib]".to_string(),
+        format!("path = {:?}", format!("src/{crate_name}.rs")),
+        String::new(),
+        "[dependencies]".to_string(),
+        format!(
+            "{lib_name} = {{ package = {package:?}, path = {:?} }}",
+            member_dir.to_string_lossy()
+        ),
+        String::new(),
+        "[features]".to_string(),
+    ];
+    for (feature, enabled) in &lib.features {
+        // the default features of the member are enabled by the dependency itself
+        let forwarded = (feature != "default").then(|| format!("{lib_name}/{feature}"));
+        let enabled: Vec<String> = forwarded
+            .into_iter()
+            .chain(enabled.iter().filter(|e| !e.contains(['/', ':'])).cloned())
+            .map(|e| format!("{e:?}"))
+            .collect();
+        manifest.push(format!("{feature:?} = [{}]", enabled.join(", ")));
+    }
+    manifest.extend([String::new(), "[workspace]".to_string(), String::new()]);
+    manifest.join("\n")
+}
+
+/// Returns the contents of a library importing all the passed re-exports and the index of
+/// the re-export imported at each line.
+fn generate_reexports_test(
+    lib_name: &str,
+    reexports: &[Reexport],
+) -> (String, HashMap<usize, usize>) {
+    let mut contents = vec![
+        "//! Generated by xtask to check the re-exports of the crate.".to_string(),
+        "#![allow(unused_imports)]".to_string(),
+    ];
+    let mut lines = HashMap::new();
+    for (index, reexport) in reexports.iter().enumerate() {
+        contents.extend(reexport.attributes.iter().cloned());
+        contents.push(format!("use {lib_name}::{};", reexport.path));
+        lines.insert(contents.len(), index);
+    }
+    (contents.join("\n") + "\n", lines)
+}
+
+/// Returns the items re-exported with `pub use` by the crate root and its public inline modules.
+/// Declarations in functions and in modules declared in their own file are ignored.
+fn parse_reexports(source: &str) -> Vec<Reexport> {
+    let mut reexports = vec![];
+    // inline modules as (name, is public, brace depth before the module)
+    let mut modules: Vec<(String, bool, usize)> = vec![];
+    let mut depth = 0;
+    let mut attributes: Vec<String> = vec![];
+    // pending multi-line declaration, its first line and whether it is recorded, the braces of
+    // the declarations which are not recorded are skipped too so that they do not close modules
+    let mut declaration: Option<(String, usize, bool)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if let Some((text, _, _)) = &mut declaration {
+            text.push(' ');
+            text.push_str(line);
+        } else if let Some(tree) = line.strip_prefix("pub use ") {
+            let recorded = depth == modules.len() && modules.iter().all(|(_, public, _)| *public);
+            declaration = Some((tree.to_string(), index + 1, recorded));
+        } else if line.starts_with("#[cfg(") {
+            attributes.push(line.to_string());
+            continue;
+        } else {
+            let module = line
+                .strip_prefix("pub mod ")
+                .map(|rest| (rest, true))
+                .or_else(|| line.strip_prefix("mod ").map(|rest| (rest, false)));
+            if let Some((rest, public)) = module {
+                if let Some(name) = rest.strip_suffix('{') {
+                    modules.push((name.trim().to_string(), public, depth));
+                }
+            }
+            depth += line.matches('{').count();
+            depth = depth.saturating_sub(line.matches('}').count());
+            while modules.last().is_some_and(|(_, _, open)| depth <= *open) {
+                modules.pop();
+            }
+        }
+        if let Some((text, first_line, recorded)) = &declaration {
+            let Some((tree, _)) = text.split_once(';') else {
+                continue;
+            };
+            if *recorded {
+                let module_path: String = modules
+                    .iter()
+                    .map(|(name, _, _)| format!("{name}::"))
+                    .collect();
+                reexports.extend(expand_use_tree(tree).into_iter().map(|name| Reexport {
+                    path: format!("{module_path}{name}"),
+                    attributes: attributes.clone(),
+                    line: *first_line,
+                }));
+            }
+            declaration = None;
+        }
+        if !line.is_empty() {
+            attributes.clear();
+        }
+    }
+    reexports
+}
+
+/// Returns the names under which a use tree exports its items, glob imports are ignored.
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let tree = tree.trim();
+    if let (Some(open), Some(close)) = (tree.find('{'), tree.rfind('}')) {
+        let prefix = tree[..open].trim().trim_end_matches("::");
+        let mut items = vec![];
+        let mut item_depth = 0;
+        let mut item_start = open + 1;
+        for (i, c) in tree.char_indices().take(close).skip(open + 1) {
+            match c {
+                '{' => item_depth += 1,
+                '}' => item_depth -= 1,
+                ',' if item_depth == 0 => {
+                    items.push(&tree[item_start..i]);
+                    item_start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        items.push(&tree[item_start..close]);
+        return items
+            .into_iter()
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .flat_map(|item| match item {
+                "self" => expand_use_tree(prefix),
+                _ => expand_use_tree(item),
+            })
+            .collect();
+    }
+    match tree.split_once(" as ") {
+        Some((_, "_")) => vec![],
+        Some((_, alias)) => vec![alias.trim().to_string()],
+        None if tree.ends_with('*') => vec![],
+        None => tree
+            .rsplit("::")
+            .next()
+            .filter(|name| !name.is_empty())
+            .map(|name| vec![name.to_string()])
+            .unwrap_or_default(),
+    }
+}
+
+fn run_error_types(target: &Target, excluded: &[String], only: &[String]) -> anyhow::Result<()> {
+    ensure_not_planned("check error-types")?;
+    let libs = get_lib_targets()?;
+    let target_dir = get_target_directory()?;
+    let mut failure_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        let Some(lib) = libs.get(&member.name) else {
+            continue;
+        };
+        let error_types =
+            match get_config().get_string_array(&format!("check.error-types.{}", member.name)) {
+                Some(error_types) => error_types,
+                None => find_crate_error_types(&Path::new(&member.path).join("src")),
+            };
+        if error_types.is_empty() {
+            continue;
+        }
+        group!("Error types: {}", member.name);
+        let (contents, lines) = generate_error_types_test(&lib.name, &error_types);
+        let mut failures: Vec<&(String, &str)> = compile_generated_crate(
+            &member,
+            lib,
+            ERROR_TYPES_CHECK_CRATE,
+            &contents,
+            &target_dir,
+        )?
+        .iter()
+        .filter_map(|line_number| lines.get(line_number))
+        .collect();
+        failures.dedup();
+        for (error_type, bound) in &failures {
+            error!(
+                "{}: error type '{error_type}' does not implement '{bound}'.",
+                member.name
+            );
+        }
+        failure_count += failures.len();
+        endgroup!();
+    }
+    if failure_count > 0 {
+        return Err(anyhow!(
+            "{failure_count} error type bound(s) are not satisfied."
+        ));
+    }
+    info!(
+        "All error types implement {}.",
+        ERROR_TYPE_BOUNDS.join(", ")
+    );
+    Ok(())
+}
+
+/// Returns the public error types of the crate of the passed source directory, walking the public
+/// file modules declared from `lib.rs`. The types are returned with their path from the crate root.
+fn find_crate_error_types(src_dir: &Path) -> Vec<String> {
+    let mut error_types = vec![];
+    // modules to visit as (file, directory of its submodules, path from the crate root)
+    let mut modules = vec![(src_dir.join("lib.rs"), src_dir.to_path_buf(), String::new())];
+    while let Some((file, dir, path)) = modules.pop() {
+        let Result::Ok(source) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        error_types.extend(
+            find_error_types(&source)
+                .into_iter()
+                .map(|error_type| format!("{path}{error_type}")),
+        );
+        for module in find_public_file_modules(&source) {
+            let module_dir = dir.join(&module);
+            let module_file = [dir.join(format!("{module}.rs")), module_dir.join("mod.rs")]
+                .into_iter()
+                .find(|file| file.is_file());
+            if let Some(module_file) = module_file {
+                modules.push((module_file, module_dir, format!("{path}{module}::")));
+            }
+        }
+    }
+    error_types.sort();
+    error_types
+}
+
+/// Returns the names of the public modules declared in their own file at the top level of the
+/// passed source, like `pub mod error;`.
+fn find_public_file_modules(source: &str) -> Vec<String> {
+    let mut modules = vec![];
+    let mut depth = 0;
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if depth == 0 {
+            if let Some(name) = line
+                .strip_prefix("pub mod ")
+                .and_then(|rest| rest.strip_suffix(';'))
+            {
+                modules.push(name.trim().to_string());
+            }
+        }
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+    }
+    modules
+}
+
+/// Returns the public error types of a module following the `*Error` naming convention, either
+/// declared at the top level of the module or re-exported by it. Generic types are ignored as
+/// their bounds depend on their parameters.
+fn find_error_types(source: &str) -> Vec<String> {
+    let mut error_types: Vec<String> = parse_reexports(source)
+        .into_iter()
+        .filter(|reexport| reexport.path.ends_with("Error"))
+        .map(|reexport| reexport.path)
+        .collect();
+    let mut depth = 0;
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if depth == 0 {
+            let declaration = line
+                .strip_prefix("pub struct ")
+                .or_else(|| line.strip_prefix("pub enum "));
+            if let Some(declaration) = declaration {
+                let end = declaration
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(declaration.len());
+                let (name, rest) = declaration.split_at(end);
+                if name.ends_with("Error") && !rest.trim_start().starts_with('<') {
+                    error_types.push(name.to_string());
+                }
+            }
+        }
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+    }
+    error_types.sort();
+    error_types.dedup();
+    error_types
+}
+
+/// Returns the contents of a library asserting the bounds of the passed error types and the
+/// error type and bound asserted at each line.
+fn generate_error_types_test(
+    lib_name: &str,
+    error_types: &[String],
+) -> (String, HashMap<usize, (String, &'static str)>) {
+    let mut contents = vec![
+        "//! Generated by xtask to check the error types of the crate.".to_string(),
+        "fn assert_bound<T: ?Sized + std::error::Error>() {}".to_string(),
+        "fn assert_send<T: ?Sized + Send>() {}".to_string(),
+        "fn assert_sync<T: ?Sized + Sync>() {}".to_string(),
+        "#[allow(dead_code)]".to_string(),
+        "fn error_types() {".to_string(),
+    ];
+    let mut lines = HashMap::new();
+    for error_type in error_types {
+        for (assertion, bound) in ["assert_bound", "assert_send", "assert_sync"]
+            .into_iter()
+            .zip(ERROR_TYPE_BOUNDS)
+        {
+            contents.push(format!("    {assertion}::<{lib_name}::{error_type}>();"));
+            lines.insert(contents.len(), (error_type.clone(), bound));
+        }
+    }
+    contents.push("}".to_string());
+    (contents.join("\n") + "\n", lines)
+}
+
+fn run_test_files(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    let pattern = get_config()
+        .get_str("check.integration-test-pattern")
+        .unwrap_or(DEFAULT_INTEGRATION_TEST_PATTERN);
+    let glob_pattern = glob::Pattern::new(pattern)
+        .map_err(|e| anyhow!("Invalid integration test pattern '{}': {}", pattern, e))?;
+    group!("Integration Test Files");
+    let mut offenders_count = 0;
+    for member in get_checked_members(target, excluded, only) {
+        let offenders =
+            find_misnamed_test_files(&Path::new(&member.path).join("tests"), &glob_pattern);
+        if !offenders.is_empty() {
+            warn!(
+                "{}: integration test files not matching '{}': {}",
+                member.name,
+                pattern,
+                offenders.join(", ")
+            );
+            offenders_count += offenders.len();
+        }
+    }
+    endgroup!();
+
+    if offenders_count > 0 {
+        let msg = format!(
+            "{offenders_count} integration test file(s) do not match the naming convention '{pattern}'."
+        );
+        if deny {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("All integration test files match the naming convention '{pattern}'.");
+    }
+    Ok(())
+}
+
+/// Returns the sorted names of the test files in the passed directory which do not match the pattern.
+/// Only the files at the root of the directory are integration test targets, subdirectories are
+/// considered to be shared modules.
+fn find_misnamed_test_files(tests_dir: &Path, pattern: &glob::Pattern) -> Vec<String> {
+    let Result::Ok(entries) = std::fs::read_dir(tests_dir) else {
+        return vec![];
+    };
+    let mut offenders: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+        .filter(|name| !pattern.matches(name))
+        .collect();
+    offenders.sort();
+    offenders
+}
+
+fn run_test_names(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    group!("Test Names");
+    let mut tests = BTreeMap::new();
+    for member in get_checked_members(target, excluded, only) {
+        info!("Listing the tests of {}...", member.name);
+        // doc tests are not listed as their names are made of file paths and line numbers
+        let mut command = Command::new("cargo");
+        command
+            .args(["test", "-p", &member.name, "--tests", "--"])
+            .args(["--list", "--format", "terse"]);
+        if plan_command(&command) {
+            continue;
+        }
+        let output = spawn_child(command.stdout(Stdio::piped()))
+            .and_then(wait_child_output)
+            .map_err(|e| anyhow!("Failed to execute cargo test: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!("Cannot list the tests of {}.", member.name));
+        }
+        tests.insert(
+            member.name,
+            parse_test_list(&String::from_utf8_lossy(&output.stdout)),
+        );
+    }
+    let duplicates = find_duplicate_test_names(&tests);
+    for (name, members) in &duplicates {
+        warn!("Test '{}' is defined in {}.", name, members.join(", "));
+    }
+    endgroup!();
+
+    if !duplicates.is_empty() {
+        let msg = format!(
+            "{} test name(s) are defined in more than one member.",
+            duplicates.len()
+        );
+        if deny {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("All test names are unique across the members.");
+    }
+    Ok(())
+}
+
+/// Returns the test names of the passed `--list --format terse` libtest output.
+fn parse_test_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the unqualified test names, without their module path, listed by more than one member
+/// with the names of these members.
+fn find_duplicate_test_names(
+    tests: &BTreeMap<String, Vec<String>>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut members_by_test: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (member, names) in tests {
+        let names: BTreeSet<&str> = names
+            .iter()
+            .map(|name| name.rsplit("::").next().unwrap_or(name))
+            .collect();
+        for name in names {
+            members_by_test
+                .entry(name.to_string())
+                .or_default()
+                .push(member.clone());
+        }
+    }
+    members_by_test.retain(|_, members| members.len() > 1);
+    members_by_test
+}
+
+fn run_tests_no_default_features(
+    target: &Target,
+    excluded: &[String],
+    only: &[String],
+    deny: bool,
+) -> anyhow::Result<()> {
+    group!("Tests without default features");
+    let mut offenders = vec![];
+    // examples have no default features to disable
+    let crates: Vec<String> = get_workspace_members(WorkspaceMemberType::AllCrates)
+        .into_iter()
+        .map(|member| member.name)
+        .collect();
+    let members: Vec<WorkspaceMember> = get_checked_members(target, excluded, only)
+        .into_iter()
+        .filter(|member| crates.contains(&member.name))
+        .collect();
+    for member in members {
+        let features = get_minimal_features_args(get_config(), &member.name);
+        let mut lib_args = vec!["check", "-p", &member.name, "--no-default-features"];
+        lib_args.extend(features.iter().map(String::as_str));
+        if run_process("cargo", &lib_args, None, None, "", None).is_err() {
+            warn!(
+                "{}: the crate does not compile without its default features, skipping its tests.",
+                member.name
+            );
+            continue;
+        }
+        let mut tests_args = lib_args.clone();
+        tests_args.push("--tests");
+        if run_process("cargo", &tests_args, None, None, "", None).is_err() {
+            warn!(
+                "{}: the tests do not compile without the default features while the crate does.",
+                member.name
+            );
+            offenders.push(member.name);
+        }
+    }
+    endgroup!();
+
+    if !offenders.is_empty() {
+        let msg = format!(
+            "The tests of {} crate(s) do not compile without the default features: {}",
+            offenders.len(),
+            offenders.join(", ")
+        );
+        if deny {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
+    } else {
+        info!("The tests of all the crates compile without the default features.");
+    }
+    Ok(())
+}
+
+/// Returns the cargo arguments enabling the minimal features of the passed crate, they are read
+/// from the `check.minimal-features` table of the `xtask.toml` file.
+fn get_minimal_features_args(config: &Config, crate_name: &str) -> Vec<String> {
+    match config.get_string_array(&format!("check.minimal-features.{crate_name}")) {
+        Some(features) if !features.is_empty() => {
+            vec!["--features".to_string(), features.join(",")]
+        }
+        _ => vec![],
+    }
+}
+
 fn run_typos() -> anyhow::Result<()> {
     if std::env::var("CI").is_err() {
         ensure_cargo_crate_is_installed("typos-cli", None, Some(TYPOS_VERSION), false)?;
@@ -187,7 +1941,677 @@ fn run_typos() -> anyhow::Result<()> {
         None,
         None,
         "Typos check execution failed",
+        None,
     )?;
     endgroup!();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_feature_matrix_cells() {
+        let features = vec!["default".to_string(), "gpu".to_string(), "std".to_string()];
+        let cells: Vec<(String, String)> = get_feature_matrix_cells(&features)
+            .into_iter()
+            .map(|(cell, args)| (cell, args.join(" ")))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                ("default features".to_string(), "".to_string()),
+                (
+                    "no default features".to_string(),
+                    "--no-default-features".to_string()
+                ),
+                (
+                    "feature 'gpu'".to_string(),
+                    "--no-default-features --features gpu".to_string()
+                ),
+                (
+                    "feature 'std'".to_string(),
+                    "--no-default-features --features std".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::version(
+        "[dependencies]\nanyhow = \"1.0\"\n",
+        Some("1.0"),
+        "[dependencies]\nanyhow = { workspace = true }\n"
+    )]
+    #[case::inline_table(
+        "[dependencies]\nanyhow = { version = \"1.0\", features = [\"backtrace\"], optional = true }\n",
+        Some("1.0"),
+        "[dependencies]\nanyhow = { workspace = true, features = [\"backtrace\"], optional = true }\n"
+    )]
+    #[case::path(
+        "[dev-dependencies]\nanyhow = { path = \"../anyhow\" }\n",
+        None,
+        "[dev-dependencies]\nanyhow = { workspace = true }\n"
+    )]
+    #[case::table(
+        "[dependencies.anyhow]\nversion = \"1.0\"\nfeatures = [\"backtrace\"]\n",
+        Some("1.0"),
+        "[dependencies.anyhow]\nworkspace = true\nfeatures = [\"backtrace\"]\n"
+    )]
+    #[case::target_table(
+        "[target.'cfg(unix)'.dependencies]\nanyhow = \"1.0\"\n",
+        Some("1.0"),
+        "[target.'cfg(unix)'.dependencies]\nanyhow = { workspace = true }\n"
+    )]
+    fn test_find_uninherited_dependencies(
+        #[case] manifest: &str,
+        #[case] expected_version: Option<&str>,
+        #[case] expected_fixed: &str,
+    ) {
+        let drifts = find_uninherited_dependencies(manifest, &["anyhow".to_string()])
+            .expect("manifest should be valid");
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].name, "anyhow");
+        assert_eq!(drifts[0].version.as_deref(), expected_version);
+        let (range, replacement) = drifts[0].fix.clone().expect("drift should be fixable");
+        let mut fixed = manifest.to_string();
+        fixed.replace_range(range, &replacement);
+        assert_eq!(fixed, expected_fixed);
+    }
+
+    #[rstest]
+    #[case::inherited("[dependencies]\nanyhow = { workspace = true, features = [\"std\"] }\n")]
+    #[case::not_in_workspace("[dependencies]\nlog = \"0.4\"\n")]
+    #[case::renamed("[dependencies]\nanyhow = { package = \"other\", version = \"1.0\" }\n")]
+    fn test_find_uninherited_dependencies_none(#[case] manifest: &str) {
+        let drifts = find_uninherited_dependencies(manifest, &["anyhow".to_string()])
+            .expect("manifest should be valid");
+        assert_eq!(drifts, vec![]);
+    }
+
+    #[test]
+    fn test_find_uninherited_dependencies_not_fixable() {
+        let manifest = "[dependencies]\nanyhow = { version = \"1.0\", default-features = false }\n";
+        let drifts = find_uninherited_dependencies(manifest, &["anyhow".to_string()])
+            .expect("manifest should be valid");
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].fix, None);
+    }
+
+    fn package_metadata(entries: &[(&str, MetadataValue)]) -> BTreeMap<String, MetadataValue> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_get_expected_package_metadata() {
+        let config = Config::parse(
+            "[check.package-metadata]\nrepository = \"https://example.com/repo\"\nauthors = [\"Alice\"]\n",
+        )
+        .expect("config should be valid");
+        let workspace_package = package_metadata(&[
+            (
+                "homepage",
+                MetadataValue::String("https://example.com".to_string()),
+            ),
+            (
+                "repository",
+                MetadataValue::String("https://example.com/old".to_string()),
+            ),
+        ]);
+        assert_eq!(
+            get_expected_package_metadata(&config, &workspace_package),
+            package_metadata(&[
+                ("authors", MetadataValue::Array(vec!["Alice".to_string()])),
+                (
+                    "homepage",
+                    MetadataValue::String("https://example.com".to_string())
+                ),
+                (
+                    "repository",
+                    MetadataValue::String("https://example.com/repo".to_string())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_workspace_package_metadata() {
+        let manifest = "[workspace.package]\nedition = \"2021\"\nauthors = [\"Alice\", \"Bob\"]\nhomepage = \"https://example.com\"\n";
+        assert_eq!(
+            parse_workspace_package_metadata(manifest).expect("manifest should be valid"),
+            package_metadata(&[
+                (
+                    "authors",
+                    MetadataValue::Array(vec!["Alice".to_string(), "Bob".to_string()])
+                ),
+                (
+                    "homepage",
+                    MetadataValue::String("https://example.com".to_string())
+                ),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case::different_value(
+        "[package]\nname = \"my-crate\"\nrepository = \"https://example.com/old\"\n",
+        &[],
+        "[package]\nname = \"my-crate\"\nrepository = \"https://example.com/repo\"\n"
+    )]
+    #[case::missing(
+        "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\n\n[dependencies]\n",
+        &[],
+        "[package]\nname = \"my-crate\"\nversion = \"0.1.0\"\nrepository = \"https://example.com/repo\"\n\n[dependencies]\n"
+    )]
+    #[case::inherit_different_value(
+        "[package]\nname = \"my-crate\"\nrepository = \"https://example.com/old\"\n",
+        &[("repository", "https://example.com/repo")],
+        "[package]\nname = \"my-crate\"\nrepository.workspace = true\n"
+    )]
+    #[case::inherit_missing(
+        "[package]\nname = \"my-crate\"\n",
+        &[("repository", "https://example.com/repo")],
+        "[package]\nname = \"my-crate\"\nrepository.workspace = true\n"
+    )]
+    fn test_find_metadata_deviations(
+        #[case] manifest: &str,
+        #[case] workspace_package: &[(&str, &str)],
+        #[case] expected_fixed: &str,
+    ) {
+        let expected = package_metadata(&[(
+            "repository",
+            MetadataValue::String("https://example.com/repo".to_string()),
+        )]);
+        let workspace_package: BTreeMap<String, MetadataValue> = workspace_package
+            .iter()
+            .map(|(key, value)| (key.to_string(), MetadataValue::String(value.to_string())))
+            .collect();
+        let deviations = find_metadata_deviations(manifest, &expected, &workspace_package)
+            .expect("manifest should be valid");
+        assert_eq!(deviations.len(), 1);
+        let (range, replacement) = deviations[0]
+            .fix
+            .clone()
+            .expect("deviation should be fixable");
+        let mut fixed = manifest.to_string();
+        fixed.replace_range(range, &replacement);
+        assert_eq!(fixed, expected_fixed);
+    }
+
+    #[rstest]
+    #[case::same_value("[package]\nname = \"my-crate\"\nauthors = [\"Alice\"]\n")]
+    #[case::inherited("[package]\nname = \"my-crate\"\nauthors.workspace = true\n")]
+    #[case::inherited_inline("[package]\nname = \"my-crate\"\nauthors = { workspace = true }\n")]
+    fn test_find_metadata_deviations_none(#[case] manifest: &str) {
+        let expected =
+            package_metadata(&[("authors", MetadataValue::Array(vec!["Alice".to_string()]))]);
+        let deviations = find_metadata_deviations(manifest, &expected, &expected)
+            .expect("manifest should be valid");
+        assert_eq!(deviations, vec![]);
+    }
+
+    #[test]
+    fn test_find_metadata_deviations_inherited_value() {
+        let manifest = "[package]\nname = \"my-crate\"\nauthors.workspace = true\n";
+        let expected =
+            package_metadata(&[("authors", MetadataValue::Array(vec!["Alice".to_string()]))]);
+        let workspace_package =
+            package_metadata(&[("authors", MetadataValue::Array(vec!["Bob".to_string()]))]);
+        let deviations = find_metadata_deviations(manifest, &expected, &workspace_package)
+            .expect("manifest should be valid");
+        assert_eq!(
+            deviations,
+            vec![MetadataDeviation {
+                key: "authors".to_string(),
+                found: Some(MetadataValue::Array(vec!["Bob".to_string()])),
+                expected: MetadataValue::Array(vec!["Alice".to_string()]),
+                inherited: true,
+                fix: None,
+            }]
+        );
+    }
+
+    #[rstest]
+    #[case::workspace(None, &[], "clippy --no-deps --color=always -- --deny warnings")]
+    #[case::package(
+        Some("my-crate"),
+        &[],
+        "clippy --no-deps --color=always -p my-crate -- --deny warnings"
+    )]
+    #[case::warn_only(
+        None,
+        &["dead_code", "clippy::needless_return"],
+        "clippy --no-deps --color=always --message-format=json-diagnostic-rendered-ansi -- --deny warnings --force-warn dead_code --force-warn clippy::needless_return"
+    )]
+    fn test_get_clippy_args(
+        #[case] package: Option<&str>,
+        #[case] warn_only: &[&str],
+        #[case] expected: &str,
+    ) {
+        let warn_only: Vec<String> = warn_only.iter().map(|s| s.to_string()).collect();
+        assert_eq!(get_clippy_args(package, &warn_only).join(" "), expected);
+    }
+
+    #[test]
+    fn test_get_clippy_args_warn_only_passes_with_lint_warnings() {
+        let dir = std::env::temp_dir().join(format!(
+            "xtask-test-clippy-warn-only-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("src")).expect("crate dir should be created");
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"warn-only\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .expect("manifest should be written");
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "#![deny(dead_code)]\nfn unused() {}\n",
+        )
+        .expect("lib should be written");
+        let status = Command::new("cargo")
+            .args(get_clippy_args(None, &["dead_code".to_string()]))
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", dir.join("target"))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .expect("clippy should run");
+        std::fs::remove_dir_all(&dir).expect("crate dir should be removed");
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_count_lint_warning() {
+        let messages = [
+            json!({"reason": "compiler-message", "message": {"level": "warning", "code": {"code": "dead_code"}}}),
+            json!({"reason": "compiler-message", "message": {"level": "warning", "code": {"code": "dead_code"}}}),
+            json!({"reason": "compiler-message", "message": {"level": "error", "code": {"code": "unused_variables"}}}),
+            // summary warnings have no lint code
+            json!({"reason": "compiler-message", "message": {"level": "warning", "code": null}}),
+            json!({"reason": "compiler-artifact"}),
+        ];
+        let mut warnings = BTreeMap::new();
+        for message in &messages {
+            count_lint_warning(message, &mut warnings);
+        }
+        assert_eq!(warnings, BTreeMap::from([("dead_code".to_string(), 2)]));
+    }
+
+    #[rstest]
+    #[case::simple("crate::a::B", vec!["B"])]
+    #[case::alias("crate::a::B as C", vec!["C"])]
+    #[case::underscore_alias("crate::a::Trait as _", vec![])]
+    #[case::glob("crate::a::*", vec![])]
+    #[case::external_crate("anyhow", vec!["anyhow"])]
+    #[case::group("crate::a::{B, c::D as E, self, f::{G, H}}", vec!["B", "E", "a", "G", "H"])]
+    fn test_expand_use_tree(#[case] tree: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(expand_use_tree(tree), expected);
+    }
+
+    #[test]
+    fn test_parse_reexports() {
+        let source = r#"
+pub mod commands;
+mod private {
+    pub use crate::Hidden;
+}
+
+pub use crate::commands::Target;
+#[cfg(feature = "std")]
+pub use crate::std_only::{
+    StdItem,
+    OtherItem, // comment
+};
+
+pub mod prelude {
+    pub use anyhow;
+
+    pub mod macros {
+        pub use my_macros::my_macro;
+    }
+    pub use crate::commands::Target as PreludeTarget;
+}
+
+fn function() {
+    pub use crate::InFunction;
+}
+"#;
+        let reexports = parse_reexports(source);
+        let expected = vec![
+            ("Target", vec![], 7),
+            ("StdItem", vec!["#[cfg(feature = \"std\")]"], 9),
+            ("OtherItem", vec!["#[cfg(feature = \"std\")]"], 9),
+            ("prelude::anyhow", vec![], 15),
+            ("prelude::macros::my_macro", vec![], 18),
+            ("prelude::PreludeTarget", vec![], 20),
+        ];
+        let reexports: Vec<(&str, Vec<&str>, usize)> = reexports
+            .iter()
+            .map(|r| {
+                (
+                    r.path.as_str(),
+                    r.attributes.iter().map(String::as_str).collect(),
+                    r.line,
+                )
+            })
+            .collect();
+        assert_eq!(reexports, expected);
+    }
+
+    #[test]
+    fn test_parse_reexports_skips_multi_line_private_reexport() {
+        let source = r#"
+pub mod public {
+    mod private {
+        pub use crate::hidden::{
+            Hidden,
+            OtherHidden,
+        };
+    }
+    pub use crate::Visible;
+}
+pub use crate::Root;
+"#;
+        let reexports: Vec<(String, usize)> = parse_reexports(source)
+            .into_iter()
+            .map(|r| (r.path, r.line))
+            .collect();
+        let expected = vec![("public::Visible".to_string(), 9), ("Root".to_string(), 11)];
+        assert_eq!(reexports, expected);
+    }
+
+    #[test]
+    fn test_find_unification_hazards() {
+        let dependency = |name: &str, features: &[&str], default: bool| json!({"name": name, "rename": null, "kind": null, "features": features, "uses_default_features": default});
+        let node = |id: &str, deps: &[&str]| {
+            let deps: Vec<serde_json::Value> = deps
+                .iter()
+                .map(|dep| json!({"name": dep.replace('-', "_"), "pkg": dep, "dep_kinds": [{"kind": null}]}))
+                .collect();
+            json!({"id": id, "deps": deps})
+        };
+        let metadata = json!({
+            "workspace_members": ["app", "core", "cli", "web", "server"],
+            "packages": [
+                {"id": "app", "name": "app", "version": "0.1.0", "features": {},
+                 "dependencies": [dependency("serde", &[], false)]},
+                {"id": "web", "name": "web", "version": "0.1.0", "features": {},
+                 "dependencies": [dependency("serde", &["std"], false)]},
+                {"id": "core", "name": "core", "version": "0.1.0", "features": {},
+                 "dependencies": [dependency("serde", &["derive"], true), dependency("my-log", &[], true)]},
+                {"id": "cli", "name": "cli", "version": "0.1.0", "features": {},
+                 "dependencies": [dependency("serde", &["derive"], true), dependency("my-log", &[], true)]},
+                {"id": "serde", "name": "serde", "version": "1.0.0",
+                 "features": {"default": ["std"], "std": [], "derive": ["serde_derive"], "serde_derive": ["dep:serde_derive"]},
+                 "dependencies": []},
+                {"id": "my-log", "name": "my-log", "version": "0.2.0", "features": {"default": []}, "dependencies": []},
+                {"id": "server", "name": "server", "version": "0.1.0", "features": {}, "dependencies": []},
+            ],
+            "resolve": {"nodes": [
+                node("app", &["serde"]),
+                node("web", &["serde"]),
+                node("core", &["serde", "my-log"]),
+                node("cli", &["serde", "my-log", "app"]),
+                node("server", &["core", "app"]),
+            ]},
+        });
+        assert_eq!(
+            find_unification_hazards(&metadata),
+            vec![UnificationHazard {
+                member: "app".to_string(),
+                dependency: "serde 1.0.0".to_string(),
+                features: vec![
+                    "derive".to_string(),
+                    "serde_derive".to_string(),
+                    "std".to_string()
+                ],
+                enabled_by: vec!["cli".to_string(), "core".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_error_types() {
+        let source = r#"
+pub use errors::{ParseError, Parser};
+pub use io::Error as IoError;
+
+pub struct ConfigError {
+    message: String,
+}
+
+pub enum LoadError { Missing }
+
+pub struct GenericError<T>(T);
+
+struct PrivateError;
+
+mod inner {
+    pub struct InnerError;
+}
+"#;
+        assert_eq!(
+            find_error_types(source),
+            vec!["ConfigError", "IoError", "LoadError", "ParseError"]
+        );
+    }
+
+    #[test]
+    fn test_find_public_file_modules() {
+        let source = "pub mod error;\nmod private;\npub mod inline {\n    pub mod nested;\n}\npub(crate) mod internal;\n";
+        assert_eq!(find_public_file_modules(source), vec!["error"]);
+    }
+
+    #[test]
+    fn test_find_crate_error_types() {
+        let src_dir = std::env::temp_dir().join(format!(
+            "xtask-test-find-crate-error-types-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(src_dir.join("io")).expect("src dir should be created");
+        for (file, source) in [
+            (
+                "lib.rs",
+                "pub mod error;\npub mod io;\nmod private;\npub use io::ReadError;\n",
+            ),
+            ("error.rs", "pub enum ParseError {}\n"),
+            ("io/mod.rs", "pub mod write;\npub struct ReadError;\n"),
+            ("io/write.rs", "pub struct WriteError;\n"),
+            ("private.rs", "pub struct HiddenError;\n"),
+        ] {
+            std::fs::write(src_dir.join(file), source).expect("source should be written");
+        }
+        let error_types = find_crate_error_types(&src_dir);
+        std::fs::remove_dir_all(&src_dir).expect("src dir should be removed");
+        assert_eq!(
+            error_types,
+            vec![
+                "ReadError",
+                "error::ParseError",
+                "io::ReadError",
+                "io::write::WriteError"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_error_types_test() {
+        let (contents, lines) = generate_error_types_test("my_crate", &["MyError".to_string()]);
+        assert_eq!(
+            contents,
+            "//! Generated by xtask to check the error types of the crate.
+fn assert_bound<T: ?Sized + std::error::Error>() {}
+fn assert_send<T: ?Sized + Send>() {}
+fn assert_sync<T: ?Sized + Sync>() {}
+#[allow(dead_code)]
+fn error_types() {
+    assert_bound::<my_crate::MyError>();
+    assert_send::<my_crate::MyError>();
+    assert_sync::<my_crate::MyError>();
+}
+"
+        );
+        assert_eq!(lines[&7], ("MyError".to_string(), "std::error::Error"));
+        assert_eq!(lines[&9], ("MyError".to_string(), "Sync"));
+    }
+
+    #[test]
+    fn test_generate_scratch_manifest() {
+        let lib = LibTarget {
+            name: "my_crate".to_string(),
+            features: BTreeMap::from([
+                ("default".to_string(), vec!["std".to_string()]),
+                (
+                    "std".to_string(),
+                    vec!["alloc".to_string(), "serde?/std".to_string()],
+                ),
+                ("alloc".to_string(), vec![]),
+            ]),
+        };
+        let manifest = generate_scratch_manifest(
+            "my-crate",
+            &lib,
+            Path::new("/workspace/crates/my-crate"),
+            REEXPORTS_CHECK_CRATE,
+        );
+        assert_eq!(
+            manifest,
+            "[package]\nname = \"xtask-reexports-check\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n[lib]\npath = \"src/xtask_reexports_check.rs\"\n\n[dependencies]\nmy_crate = { package = \"my-crate\", path = \"/workspace/crates/my-crate\" }\n\n[features]\n\"alloc\" = [\"my_crate/alloc\"]\n\"default\" = [\"std\"]\n\"std\" = [\"my_crate/std\", \"alloc\"]\n\n[workspace]\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_reexports_test() {
+        let reexports = vec![
+            Reexport {
+                path: "Target".to_string(),
+                attributes: vec![],
+                line: 3,
+            },
+            Reexport {
+                path: "prelude::StdItem".to_string(),
+                attributes: vec!["#[cfg(feature = \"std\")]".to_string()],
+                line: 5,
+            },
+        ];
+        let (contents, lines) = generate_reexports_test("my_crate", &reexports);
+        assert_eq!(
+            contents,
+            "//! Generated by xtask to check the re-exports of the crate.\n#![allow(unused_imports)]\nuse my_crate::Target;\n#[cfg(feature = \"std\")]\nuse my_crate::prelude::StdItem;\n"
+        );
+        assert_eq!(lines, HashMap::from([(3, 0), (5, 1)]));
+    }
+
+    #[rstest]
+    #[case::no_features("[package]\nname = \"a\"\n", vec![])]
+    #[case::documented(
+        "[features]\n## Default features.\ndefault = [\"std\"]\n## Enable std.\n## On two lines.\nstd = []\n",
+        vec![]
+    )]
+    #[case::undocumented(
+        "[features]\n## Default features.\ndefault = [\"std\"]\nstd = []\n# regular comment\ngpu = []\n",
+        vec!["std", "gpu"]
+    )]
+    #[case::section_doc_only("[features]\n#! ### Section\nstd = []\n", vec!["std"])]
+    #[case::blank_line_after_doc("[features]\n## Enable std.\n\nstd = []\n", vec![])]
+    fn test_find_undocumented_features(#[case] manifest: &str, #[case] expected: Vec<&str>) {
+        let offenders = find_undocumented_features(manifest).expect("manifest should be parsed");
+        assert_eq!(offenders, expected);
+    }
+
+    #[rstest]
+    #[case::configured("my-crate", vec!["--features", "std,alloc"])]
+    #[case::empty("empty-crate", vec![])]
+    #[case::not_configured("other-crate", vec![])]
+    fn test_get_minimal_features_args(#[case] crate_name: &str, #[case] expected: Vec<&str>) {
+        let config = Config::parse(
+            "[check.minimal-features]\nmy-crate = [\"std\", \"alloc\"]\nempty-crate = []\n",
+        )
+        .expect("config should be valid");
+        assert_eq!(get_minimal_features_args(&config, crate_name), expected);
+    }
+
+    #[test]
+    fn test_parse_test_list() {
+        let output = "tests::test_a: test\ntests::bench_b: benchmark\ntest_c: test\n\n2 tests, 1 benchmark\n";
+        assert_eq!(parse_test_list(output), vec!["tests::test_a", "test_c"]);
+    }
+
+    #[test]
+    fn test_find_duplicate_test_names() {
+        let tests = BTreeMap::from([
+            (
+                "core".to_string(),
+                vec!["tests::test_parse".to_string(), "test_core".to_string()],
+            ),
+            (
+                "cli".to_string(),
+                vec![
+                    "tests::test_parse".to_string(),
+                    "tests::test_parse".to_string(),
+                ],
+            ),
+            (
+                "server".to_string(),
+                vec![
+                    "test_core".to_string(),
+                    "api::tests::test_serve".to_string(),
+                ],
+            ),
+            ("web".to_string(), vec!["routes::test_serve".to_string()]),
+        ]);
+        assert_eq!(
+            find_duplicate_test_names(&tests),
+            BTreeMap::from([
+                (
+                    "test_core".to_string(),
+                    vec!["core".to_string(), "server".to_string()]
+                ),
+                (
+                    "test_parse".to_string(),
+                    vec!["cli".to_string(), "core".to_string()]
+                ),
+                (
+                    "test_serve".to_string(),
+                    vec!["server".to_string(), "web".to_string()]
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_find_misnamed_test_files() {
+        let tests_dir = std::env::temp_dir().join(format!(
+            "xtask-test-find-misnamed-test-files-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(tests_dir.join("common")).expect("tests dir should be created");
+        for file in [
+            "valid_test.rs",
+            "invalid.rs",
+            "test_also_invalid.rs",
+            "README.md",
+            "common/mod.rs",
+        ] {
+            std::fs::write(tests_dir.join(file), "").expect("test file should be written");
+        }
+        let pattern = glob::Pattern::new(DEFAULT_INTEGRATION_TEST_PATTERN).unwrap();
+        let offenders = find_misnamed_test_files(&tests_dir, &pattern);
+        std::fs::remove_dir_all(&tests_dir).expect("tests dir should be removed");
+        assert_eq!(offenders, vec!["invalid.rs", "test_also_invalid.rs"]);
+    }
+
+    #[test]
+    fn test_find_misnamed_test_files_without_tests_dir() {
+        let pattern = glob::Pattern::new(DEFAULT_INTEGRATION_TEST_PATTERN).unwrap();
+        let offenders = find_misnamed_test_files(Path::new("this-dir-does-not-exist"), &pattern);
+        assert!(offenders.is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Ok;
+use strum::IntoEnumIterator;
+
+use crate::{
+    endgroup, group,
+    prelude::{Context, Environment},
+    utils::{
+        process::{run_process_for_package, run_process_for_workspace},
+        workspace::{get_workspace_members, WorkspaceMemberType},
+    },
+};
+
+use super::{Target, WARN_IGNORED_ONLY_ARGS};
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct ClippyCmdArgs {}
+
+pub fn handle_command(args: ClippyCmdArgs, _env: Environment, _ctx: Context) -> anyhow::Result<()> {
+    if args.target == Target::Workspace && !args.only.is_empty() {
+        warn!("{WARN_IGNORED_ONLY_ARGS}");
+    }
+    run_clippy(&args.target, &args)
+}
+
+fn run_clippy(target: &Target, args: &ClippyCmdArgs) -> anyhow::Result<()> {
+    match target {
+        Target::Workspace => {
+            group!("Clippy Workspace");
+            let cmd_args = get_clippy_args(None, args);
+            run_process_for_workspace(
+                "cargo",
+                &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+                &args.exclude,
+                None,
+                None,
+                "Workspace clippy failed",
+                None,
+                None,
+            )?;
+            endgroup!();
+        }
+        Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => unreachable!(),
+            };
+
+            for member in members {
+                group!("Clippy: {}", member.name);
+                let cmd_args = get_clippy_args(Some(&member.name), args);
+                run_process_for_package(
+                    "cargo",
+                    &member.name,
+                    &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+                    &args.exclude,
+                    &args.only,
+                    &format!("Clippy failed for {}", &member.name),
+                    None,
+                    None,
+                )?;
+                endgroup!();
+            }
+        }
+        Target::AllPackages => {
+            Target::iter()
+                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .try_for_each(|t| run_clippy(&t, args))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the clippy arguments for the passed package, or for the whole workspace if there is no
+/// package. The warnings are denied, then the passed lints are denied and finally the passed lints
+/// are allowed as the last level passed for a lint wins.
+fn get_clippy_args(package: Option<&str>, args: &ClippyCmdArgs) -> Vec<String> {
+    let mut cmd_args = vec!["clippy".to_string()];
+    match package {
+        Some(package) => cmd_args.extend(["-p".to_string(), package.to_string()]),
+        None => cmd_args.push("--workspace".to_string()),
+    }
+    cmd_args
+        .extend(["--no-deps", "--color=always", "--", "--deny", "warnings"].map(str::to_string));
+    for lint in &args.deny {
+        cmd_args.extend(["--deny".to_string(), lint.clone()]);
+    }
+    for lint in &args.allow {
+        cmd_args.extend(["--allow".to_string(), lint.clone()]);
+    }
+    cmd_args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::try_parse_cmd_args;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::workspace(None, &[], &["clippy", "--workspace", "--no-deps", "--color=always", "--", "--deny", "warnings"])]
+    #[case::package(Some("my-crate"), &[], &["clippy", "-p", "my-crate", "--no-deps", "--color=always", "--", "--deny", "warnings"])]
+    #[case::deny(
+        None,
+        &["--deny", "clippy::unwrap_used", "--deny", "clippy::pedantic"],
+        &["clippy", "--workspace", "--no-deps", "--color=always", "--", "--deny", "warnings", "--deny", "clippy::unwrap_used", "--deny", "clippy::pedantic"]
+    )]
+    #[case::allow_overrides_deny(
+        None,
+        &["--allow", "clippy::too_many_arguments", "--deny", "clippy::pedantic"],
+        &["clippy", "--workspace", "--no-deps", "--color=always", "--", "--deny", "warnings", "--deny", "clippy::pedantic", "--allow", "clippy::too_many_arguments"]
+    )]
+    fn test_get_clippy_args(
+        #[case] package: Option<&str>,
+        #[case] cli_args: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let args: ClippyCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        assert_eq!(get_clippy_args(package, &args), expected);
+    }
+}
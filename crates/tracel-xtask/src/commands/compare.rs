@@ -0,0 +1,328 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    endgroup, group,
+    prelude::{Context, Environment},
+    report::{parse_test_results, TestOutcome},
+    utils::{
+        process::{spawn_child, wait_child},
+        script::ensure_not_planned,
+        time::format_duration,
+    },
+};
+use anyhow::{anyhow, Ok};
+
+/// Directory of the git worktrees and of the shared target directory.
+const COMPARE_DIR: &str = "target/compare";
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct CompareCmdArgs {}
+
+/// Identifier of a test or benchmark, its name is only unique within its test binary.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct TestKey {
+    binary: String,
+    name: String,
+}
+
+impl std::fmt::Display for TestKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.binary, self.name)
+    }
+}
+
+/// Results of the command for a git ref.
+struct RefResults {
+    git_ref: String,
+    success: bool,
+    duration: Duration,
+    outcomes: BTreeMap<TestKey, TestOutcome>,
+}
+
+pub fn handle_command(
+    args: CompareCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    ensure_not_planned("compare")?;
+    let command: Vec<&str> = args.command.split_whitespace().collect();
+    if command.is_empty() {
+        return Err(anyhow!("The command to compare cannot be empty."));
+    }
+    let compare_dir = std::env::current_dir()?.join(COMPARE_DIR);
+    let base = run_for_ref(&args.base, "base", &command, &compare_dir)?;
+    let head = run_for_ref(&args.head, "head", &command, &compare_dir)?;
+    group!("Compare {} and {}", base.git_ref, head.git_ref);
+    for line in compare_results(&base, &head) {
+        info!("{line}");
+    }
+    endgroup!();
+    Ok(())
+}
+
+/// Run the command in a temporary git worktree of the passed ref and collect its results.
+fn run_for_ref(
+    git_ref: &str,
+    name: &str,
+    command: &[&str],
+    compare_dir: &Path,
+) -> anyhow::Result<RefResults> {
+    group!("Run '{}' on {}", command.join(" "), git_ref);
+    let worktree = compare_dir.join(name);
+    remove_worktree(&worktree);
+    run_git(&[
+        "worktree",
+        "add",
+        "--detach",
+        &worktree.to_string_lossy(),
+        git_ref,
+    ])?;
+    let result = run_command_in(command, &worktree, &compare_dir.join("target"));
+    remove_worktree(&worktree);
+    let (success, duration, output) = result?;
+    endgroup!();
+    Ok(RefResults {
+        git_ref: git_ref.to_string(),
+        success,
+        duration,
+        outcomes: parse_outcomes(&output),
+    })
+}
+
+/// Run the command in the passed directory, returns its success, duration and output.
+fn run_command_in(
+    command: &[&str],
+    dir: &Path,
+    target_dir: &Path,
+) -> anyhow::Result<(bool, Duration, String)> {
+    info!("Command line: {}", command.join(" "));
+    let start = Instant::now();
+    let mut child = spawn_child(
+        Command::new(command[0])
+            .args(&command[1..])
+            .current_dir(dir)
+            // share the build artifacts of the dependencies between the refs
+            .env("CARGO_TARGET_DIR", target_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .map_err(|e| anyhow!("Failed to start {}: {e}", command[0]))?;
+    // cargo prints the names of the test binaries on stderr and libtest the results on stdout
+    let (tx, rx) = mpsc::channel();
+    let forward = |stream: Option<Box<dyn Read + Send>>, is_stderr: bool| {
+        let tx = tx.clone();
+        stream.map(|stream| {
+            thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    let _ = tx.send((line, is_stderr));
+                }
+            })
+        })
+    };
+    let readers = [
+        forward(child.stdout.take().map(|s| Box::new(s) as _), false),
+        forward(child.stderr.take().map(|s| Box::new(s) as _), true),
+    ];
+    drop(tx);
+    let mut output = String::new();
+    for (line, is_stderr) in rx {
+        if is_stderr {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+        output.push_str(&line);
+        output.push('\n');
+    }
+    readers.into_iter().flatten().for_each(|reader| {
+        let _ = reader.join();
+    });
+    let status = wait_child(child);
+    Ok((status.success(), start.elapsed(), output))
+}
+
+fn run_git(args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("Failed to execute git: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to execute git {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+fn remove_worktree(worktree: &Path) {
+    if worktree.exists() {
+        let _ = run_git(&["worktree", "remove", "--force", &worktree.to_string_lossy()]);
+    }
+    let _ = run_git(&["worktree", "prune"]);
+}
+
+/// Returns the outcome of each test and benchmark of the passed cargo output, keyed by the test
+/// binary announced by cargo before the libtest output.
+fn parse_outcomes(output: &str) -> BTreeMap<TestKey, TestOutcome> {
+    parse_test_results(None, output)
+        .into_iter()
+        .map(|result| {
+            let key = TestKey {
+                binary: result.binary,
+                name: result.name,
+            };
+            (key, result.outcome)
+        })
+        .collect()
+}
+
+/// Returns the report lines of the differences between the base and head results.
+fn compare_results(base: &RefResults, head: &RefResults) -> Vec<String> {
+    let mut lines = vec![];
+    let status = |success: bool| if success { "succeeded" } else { "failed" };
+    for results in [base, head] {
+        lines.push(format!(
+            "{}: command {} in {}",
+            results.git_ref,
+            status(results.success),
+            format_duration(&results.duration)
+        ));
+    }
+    let count = |results: &RefResults, outcome: &TestOutcome| {
+        results.outcomes.values().filter(|o| *o == outcome).count()
+    };
+    for (label, outcome) in [
+        ("passed", TestOutcome::Passed),
+        ("failed", TestOutcome::Failed),
+    ] {
+        let (before, after) = (count(base, &outcome), count(head, &outcome));
+        lines.push(format!(
+            "{label}: {before} -> {after} ({:+})",
+            after as i64 - before as i64
+        ));
+    }
+    let names: std::collections::BTreeSet<&TestKey> =
+        base.outcomes.keys().chain(head.outcomes.keys()).collect();
+    for name in names {
+        match (base.outcomes.get(name), head.outcomes.get(name)) {
+            (Some(TestOutcome::Bench(before)), Some(TestOutcome::Bench(after))) => {
+                let delta = (after - before) / before.max(f64::MIN_POSITIVE) * 100.0;
+                lines.push(format!(
+                    "{name}: {before} -> {after} ns/iter ({delta:+.2}%)"
+                ));
+            }
+            (Some(before), Some(after)) if before != after => {
+                lines.push(format!("{name}: {before} -> {after}"));
+            }
+            (Some(before), None) => lines.push(format!("{name}: removed (was {before})")),
+            (None, Some(after)) => lines.push(format!("{name}: added ({after})")),
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "
+     Running unittests src/lib.rs (target/debug/deps/my_crate-0123456789abcdef)
+
+running 4 tests
+test tests::test_a ... ok
+test tests::test_b ... FAILED
+test tests::test_c ... ignored
+test tests::test_d - should panic ... ok
+test benches::bench_sum ... bench:       1,234 ns/iter (+/- 56)
+test benches::bench_fast ... bench:           0.25 ns/iter (+/- 0.01)
+
+test result: FAILED. 2 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+     Running tests/test_api.rs (target/debug/deps/test_api-0123456789abcdef)
+
+running 1 test
+test tests::test_a ... FAILED
+";
+
+    fn key(binary: &str, name: &str) -> TestKey {
+        TestKey {
+            binary: binary.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn results(git_ref: &str, outcomes: &[(&str, TestOutcome)]) -> RefResults {
+        RefResults {
+            git_ref: git_ref.to_string(),
+            success: true,
+            duration: Duration::from_secs(65),
+            outcomes: outcomes
+                .iter()
+                .map(|(name, outcome)| (key("my_crate", name), outcome.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_outcomes() {
+        let outcomes = parse_outcomes(OUTPUT);
+        let expected: BTreeMap<TestKey, TestOutcome> = [
+            ("my_crate", "tests::test_a", TestOutcome::Passed),
+            ("my_crate", "tests::test_b", TestOutcome::Failed),
+            ("my_crate", "tests::test_c", TestOutcome::Ignored),
+            ("my_crate", "tests::test_d", TestOutcome::Passed),
+            ("my_crate", "benches::bench_sum", TestOutcome::Bench(1234.0)),
+            ("my_crate", "benches::bench_fast", TestOutcome::Bench(0.25)),
+            ("test_api", "tests::test_a", TestOutcome::Failed),
+        ]
+        .into_iter()
+        .map(|(binary, name, outcome)| (key(binary, name), outcome))
+        .collect();
+        assert_eq!(outcomes, expected);
+    }
+
+    #[test]
+    fn test_compare_results() {
+        let base = results(
+            "main",
+            &[
+                ("bench", TestOutcome::Bench(200.0)),
+                ("fixed", TestOutcome::Failed),
+                ("removed", TestOutcome::Passed),
+                ("same", TestOutcome::Passed),
+            ],
+        );
+        let head = results(
+            "feature",
+            &[
+                ("added", TestOutcome::Passed),
+                ("bench", TestOutcome::Bench(150.0)),
+                ("fixed", TestOutcome::Passed),
+                ("same", TestOutcome::Passed),
+            ],
+        );
+        assert_eq!(
+            compare_results(&base, &head),
+            vec![
+                "main: command succeeded in 00:01:05",
+                "feature: command succeeded in 00:01:05",
+                "passed: 2 -> 3 (+1)",
+                "failed: 1 -> 0 (-1)",
+                "my_crate added: added (ok)",
+                "my_crate bench: 200 -> 150 ns/iter (-25.00%)",
+                "my_crate fixed: FAILED -> ok",
+                "my_crate removed: removed (was ok)",
+            ]
+        );
+    }
+}
@@ -1,13 +1,20 @@
-use anyhow::Ok;
+use std::{collections::HashMap, ops::Range, path::Path};
+
+use anyhow::{anyhow, Ok};
 use strum::IntoEnumIterator;
+use toml_edit::{ImDocument, Item, Value};
 
 use crate::{
     commands::WARN_IGNORED_ONLY_ARGS,
-    endgroup, group,
+    endgroup, group, group_info,
     prelude::{Context, Environment},
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
-        workspace::{get_workspace_members, WorkspaceMemberType},
+        cargo::get_dependency_tables,
+        cleanup::CLEANUP_HANDLER,
+        process::{run_process, run_process_for_package, run_process_for_workspace},
+        workspace::{
+            get_workspace_members, get_workspace_metadata, WorkspaceMember, WorkspaceMemberType,
+        },
     },
 };
 
@@ -24,6 +31,9 @@ pub fn handle_command(
     if args.target == Target::Workspace && !args.only.is_empty() {
         warn!("{WARN_IGNORED_ONLY_ARGS}");
     }
+    if args.published {
+        return run_published_compile(&args);
+    }
     run_compile(&args.target, &args.exclude, &args.only)
 }
 
@@ -49,7 +59,7 @@ pub(crate) fn run_compile(
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
@@ -77,3 +87,394 @@ pub(crate) fn run_compile(
     }
     Ok(())
 }
+
+/// Compile each example against the published version of its path dependencies.
+/// The manifest of the example and the lock file are restored after each compilation.
+fn run_published_compile(args: &CompileCmdArgs) -> anyhow::Result<()> {
+    let workspace_versions = get_workspace_versions()?;
+    let workspace_dependencies = get_workspace_path_dependencies()?;
+    let mut failures = vec![];
+    for member in get_workspace_members(WorkspaceMemberType::Example) {
+        if args.exclude.contains(&member.name)
+            || (!args.only.is_empty() && !args.only.contains(&member.name))
+        {
+            group_info!("Skip '{}' because it has been excluded!", member.name);
+            continue;
+        }
+        group!("Compile Published: {}", member.name);
+        let result = compile_member_against_published(
+            &member,
+            &workspace_versions,
+            &workspace_dependencies,
+            args.version.as_deref(),
+        );
+        endgroup!();
+        if let Err(e) = result {
+            error!("{e}");
+            failures.push(member.name.clone());
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "Examples do not compile against the published dependencies: {}",
+            failures.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the version of each workspace package.
+fn get_workspace_versions() -> anyhow::Result<HashMap<String, String>> {
+    let metadata = get_workspace_metadata()?;
+    let empty = vec![];
+    Ok(metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|p| {
+            Some((
+                p["name"].as_str()?.to_string(),
+                p["version"].as_str()?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+/// A dependency of `[workspace.dependencies]` defined with a path.
+#[derive(Debug, Clone, PartialEq)]
+struct WorkspacePathDependency {
+    package: String,
+    version: Option<String>,
+}
+
+/// Returns the path dependencies of the `[workspace.dependencies]` of the workspace manifest by
+/// name, the members inheriting them with `workspace = true` depend on a path as well.
+fn get_workspace_path_dependencies() -> anyhow::Result<HashMap<String, WorkspacePathDependency>> {
+    let manifest = std::fs::read_to_string("Cargo.toml")
+        .map_err(|e| anyhow!("Cannot read the workspace manifest ({})", e))?;
+    parse_workspace_path_dependencies(&manifest)
+}
+
+fn parse_workspace_path_dependencies(
+    manifest: &str,
+) -> anyhow::Result<HashMap<String, WorkspacePathDependency>> {
+    let document = ImDocument::parse(manifest)?;
+    let Some(dependencies) = document
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|d| d.as_table_like())
+    else {
+        return Ok(HashMap::new());
+    };
+    Ok(dependencies
+        .iter()
+        .filter_map(|(name, item)| {
+            let dependency = item.as_table_like()?;
+            dependency.get("path")?;
+            let package = dependency
+                .get("package")
+                .and_then(|p| p.as_str())
+                .unwrap_or(name);
+            Some((
+                name.to_string(),
+                WorkspacePathDependency {
+                    package: package.to_string(),
+                    version: dependency
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                },
+            ))
+        })
+        .collect())
+}
+
+fn compile_member_against_published(
+    member: &WorkspaceMember,
+    workspace_versions: &HashMap<String, String>,
+    workspace_dependencies: &HashMap<String, WorkspacePathDependency>,
+    version: Option<&str>,
+) -> anyhow::Result<()> {
+    let manifest_path = Path::new(&member.path).join("Cargo.toml");
+    let manifest = std::fs::read_to_string(&manifest_path)?;
+    let published = replace_path_dependencies(
+        &manifest,
+        workspace_versions,
+        workspace_dependencies,
+        version,
+    )
+    .map_err(|e| anyhow!("Cannot rewrite {}: {e}", manifest_path.display()))?;
+    if published == manifest {
+        info!("'{}' has no path dependencies.", member.name);
+        return Ok(());
+    }
+    // resolving the published dependencies updates the lock file of the workspace
+    let lockfile_path = Path::new("Cargo.lock");
+    let lockfile = std::fs::read(lockfile_path).ok();
+    let restore = {
+        let manifest_path = manifest_path.clone();
+        move || -> std::io::Result<()> {
+            std::fs::write(&manifest_path, &manifest)?;
+            match &lockfile {
+                Some(lockfile) => std::fs::write(lockfile_path, lockfile),
+                None => std::fs::remove_file(lockfile_path),
+            }
+        }
+    };
+    // restore the state on CTRL+c as well
+    let cleanup_name = format!("Restore {}", manifest_path.display());
+    let cleanup_restore = restore.clone();
+    CLEANUP_HANDLER.register(cleanup_name.clone(), move || {
+        if let Err(e) = cleanup_restore() {
+            error!("Cannot restore the manifest and the lock file ({e}).");
+        }
+    });
+    std::fs::write(&manifest_path, published)?;
+    let result = run_process(
+        "cargo",
+        &["check", "-p", &member.name, "--color=always"],
+        None,
+        None,
+        &format!(
+            "Compilation against the published dependencies failed for {}",
+            &member.name
+        ),
+        None,
+    );
+    // restore the state even if the compilation failed
+    CLEANUP_HANDLER.unregister(&cleanup_name);
+    restore()?;
+    result
+}
+
+/// Returns the passed manifest with its path dependencies, including the ones inherited from the
+/// passed workspace path dependencies, replaced by a crates.io dependency on the passed version if
+/// any, otherwise on the version requirement of the dependency, otherwise on the version of the
+/// workspace package. The rest of the manifest is kept as is.
+fn replace_path_dependencies(
+    manifest: &str,
+    workspace_versions: &HashMap<String, String>,
+    workspace_dependencies: &HashMap<String, WorkspacePathDependency>,
+    version: Option<&str>,
+) -> anyhow::Result<String> {
+    let document = ImDocument::parse(manifest)?;
+    let span = |span: Option<Range<usize>>| span.ok_or_else(|| anyhow!("missing span"));
+    let mut replacements: Vec<(Range<usize>, String)> = vec![];
+    for (_, table) in get_dependency_tables(document.as_table()) {
+        for (name, item) in table.iter() {
+            let dependency = match item {
+                Item::Value(Value::InlineTable(dependency)) => dependency.get_values(),
+                Item::Table(dependency) => dependency.get_values(),
+                _ => continue,
+            };
+            let get = |key: &str| {
+                dependency
+                    .iter()
+                    .find(|(keys, _)| keys.len() == 1 && keys[0].get() == key)
+                    .map(|(keys, value)| (keys[0], *value))
+            };
+            let inherited = get("workspace")
+                .filter(|(_, value)| value.as_bool() == Some(true))
+                .and_then(|entry| Some((entry, workspace_dependencies.get(name)?)));
+            let (path_key, path_value, package, inherited_version) = match (get("path"), inherited)
+            {
+                (Some((key, value)), _) => {
+                    let package = get("package").and_then(|(_, v)| v.as_str()).unwrap_or(name);
+                    (key, value, package, None)
+                }
+                (None, Some(((key, value), dependency))) => (
+                    key,
+                    value,
+                    dependency.package.as_str(),
+                    Some(dependency.version.as_deref()),
+                ),
+                (None, None) => continue,
+            };
+            let existing_version = get("version");
+            let published_version = match (version, existing_version, inherited_version.flatten()) {
+                (Some(version), _, _) => format!("={version}"),
+                (None, Some((_, v)), _) => v
+                    .as_str()
+                    .ok_or_else(|| anyhow!("invalid version of dependency '{name}'"))?
+                    .to_string(),
+                (None, None, Some(version)) => version.to_string(),
+                (None, None, None) => {
+                    workspace_versions.get(package).cloned().ok_or_else(|| {
+                        anyhow!("path dependency '{name}' has no version, use --version to set it")
+                    })?
+                }
+            };
+            let version_entry = format!("version = \"{published_version}\"");
+            // the package of a renamed workspace dependency is only in the workspace manifest
+            let package_entry = (inherited_version.is_some() && package != name)
+                .then(|| format!("package = \"{package}\""));
+            let path_entry = span(path_key.span())?.start..span(path_value.span())?.end;
+            match item {
+                Item::Value(Value::InlineTable(_)) => {
+                    // the inline table is rewritten to not leave a dangling comma
+                    let entries = std::iter::once(Ok(version_entry))
+                        .chain(package_entry.map(Ok))
+                        .chain(
+                            dependency
+                                .iter()
+                                .filter(|(keys, _)| {
+                                    !matches!(keys[0].get(), "path" | "version" | "workspace")
+                                })
+                                .map(|(keys, value)| {
+                                    Ok(manifest
+                                        [span(keys[0].span())?.start..span(value.span())?.end]
+                                        .to_string())
+                                }),
+                        )
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    replacements
+                        .push((span(item.span())?, format!("{{ {} }}", entries.join(", "))));
+                }
+                _ => match existing_version {
+                    Some((_, version_value)) => {
+                        replacements.push((path_entry, String::new()));
+                        replacements.push((
+                            span(version_value.span())?,
+                            format!("\"{published_version}\""),
+                        ));
+                    }
+                    None => replacements.push((
+                        path_entry,
+                        std::iter::once(version_entry)
+                            .chain(package_entry)
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    )),
+                },
+            }
+        }
+    }
+    replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut published = manifest.to_string();
+    for (range, replacement) in replacements {
+        published.replace_range(range, &replacement);
+    }
+    Ok(published)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::inline(
+        "[dependencies]\nmy-crate = { path = \"../my-crate\" }\n",
+        None,
+        "[dependencies]\nmy-crate = { version = \"1.2.3\" }\n"
+    )]
+    #[case::inline_with_version_and_features(
+        "[dependencies]\nmy-crate = { path = \"../my-crate\", version = \"1.2\", features = [\"std\"] }\n",
+        None,
+        "[dependencies]\nmy-crate = { version = \"1.2\", features = [\"std\"] }\n"
+    )]
+    #[case::inline_pinned(
+        "[dev-dependencies]\nmy-crate = { version = \"1.2\", path = \"../my-crate\" }\n",
+        Some("1.0.0"),
+        "[dev-dependencies]\nmy-crate = { version = \"=1.0.0\" }\n"
+    )]
+    #[case::renamed(
+        "[dependencies]\nrenamed = { package = \"my-crate\", path = \"../my-crate\" }\n",
+        None,
+        "[dependencies]\nrenamed = { version = \"1.2.3\", package = \"my-crate\" }\n"
+    )]
+    #[case::table(
+        "[dependencies.my-crate]\npath = \"../my-crate\"\nfeatures = [\"std\"]\n",
+        None,
+        "[dependencies.my-crate]\nversion = \"1.2.3\"\nfeatures = [\"std\"]\n"
+    )]
+    #[case::table_with_version(
+        "[dependencies.my-crate]\npath = \"../my-crate\"\nversion = \"1.2\"\n",
+        Some("1.0.0"),
+        "[dependencies.my-crate]\n\nversion = \"=1.0.0\"\n"
+    )]
+    #[case::target_table(
+        "[target.'cfg(unix)'.dependencies]\nmy-crate = { path = \"../my-crate\" }\n",
+        None,
+        "[target.'cfg(unix)'.dependencies]\nmy-crate = { version = \"1.2.3\" }\n"
+    )]
+    #[case::registry_dependencies_untouched(
+        "[dependencies]\nanyhow = \"1.0\"\nlog = { version = \"0.4\" }\n",
+        None,
+        "[dependencies]\nanyhow = \"1.0\"\nlog = { version = \"0.4\" }\n"
+    )]
+    #[case::inherited(
+        "[dependencies]\nmy-crate = { workspace = true, features = [\"std\"] }\n",
+        None,
+        "[dependencies]\nmy-crate = { version = \"1.2\", features = [\"std\"] }\n"
+    )]
+    #[case::inherited_pinned(
+        "[dependencies]\nmy-crate = { workspace = true }\n",
+        Some("1.0.0"),
+        "[dependencies]\nmy-crate = { version = \"=1.0.0\" }\n"
+    )]
+    #[case::inherited_renamed(
+        "[dependencies]\nrenamed = { workspace = true }\n",
+        None,
+        "[dependencies]\nrenamed = { version = \"1.2.3\", package = \"my-crate\" }\n"
+    )]
+    #[case::inherited_table(
+        "[dependencies.renamed]\nworkspace = true\n",
+        None,
+        "[dependencies.renamed]\nversion = \"1.2.3\"\npackage = \"my-crate\"\n"
+    )]
+    #[case::inherited_registry_dependency_untouched(
+        "[dependencies]\nanyhow = { workspace = true }\n",
+        None,
+        "[dependencies]\nanyhow = { workspace = true }\n"
+    )]
+    fn test_replace_path_dependencies(
+        #[case] manifest: &str,
+        #[case] version: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        let versions = HashMap::from([("my-crate".to_string(), "1.2.3".to_string())]);
+        let workspace_dependencies = parse_workspace_path_dependencies(
+            "[workspace.dependencies]\nanyhow = \"1.0\"\nmy-crate = { path = \"crates/my-crate\", version = \"1.2\" }\nrenamed = { package = \"my-crate\", path = \"crates/my-crate\" }\n",
+        )
+        .expect("workspace manifest should be parsed");
+        let published =
+            replace_path_dependencies(manifest, &versions, &workspace_dependencies, version)
+                .expect("manifest should be rewritten");
+        assert_eq!(published, expected);
+    }
+
+    #[test]
+    fn test_replace_path_dependencies_without_version() {
+        let manifest = "[dependencies]\nlocal = { path = \"../local\" }\n";
+        assert!(
+            replace_path_dependencies(manifest, &HashMap::new(), &HashMap::new(), None).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_workspace_path_dependencies() {
+        let manifest = "[workspace.dependencies]\nanyhow = \"1.0\"\nlog = { version = \"0.4\" }\nmy-crate = { path = \"crates/my-crate\", version = \"1.2\" }\n\n[workspace.dependencies.renamed]\npackage = \"my-crate\"\npath = \"crates/my-crate\"\n";
+        let dependencies =
+            parse_workspace_path_dependencies(manifest).expect("manifest should be parsed");
+        assert_eq!(
+            dependencies,
+            HashMap::from([
+                (
+                    "my-crate".to_string(),
+                    WorkspacePathDependency {
+                        package: "my-crate".to_string(),
+                        version: Some("1.2".to_string()),
+                    }
+                ),
+                (
+                    "renamed".to_string(),
+                    WorkspacePathDependency {
+                        package: "my-crate".to_string(),
+                        version: None,
+                    }
+                ),
+            ])
+        );
+    }
+}
@@ -1,11 +1,19 @@
-use anyhow::Ok;
-use clap::Args;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Ok};
+use clap::{Args, ValueEnum};
 
 use crate::{
     endgroup, group,
     prelude::{Context, Environment},
     utils::{
-        cargo::ensure_cargo_crate_is_installed, process::run_process, rustup::rustup_add_component,
+        cargo::ensure_cargo_crate_is_installed,
+        process::{run_process, run_process_for_workspace},
+        rustup::rustup_add_component,
     },
     versions::GRCOV_VERSION,
 };
@@ -37,6 +45,99 @@ pub struct GenerateCmdArgs {
     pub ignore: Vec<String>,
 }
 
+#[derive(Args, Clone, PartialEq)]
+pub struct PatchCmdArgs {
+    /// Path of the lcov file generated by the 'generate' subcommand.
+    #[arg(long, value_name = "PATH", default_value = "lcov.info")]
+    pub lcov: String,
+    /// Git reference to compare with, the patch is made of the lines added since its merge base.
+    #[arg(long, value_name = "REF", default_value = "origin/main")]
+    pub changed_since: String,
+    /// Fail if the percentage of covered added lines is below the passed percentage.
+    #[arg(long, value_name = "PERCENT", required = false)]
+    pub enforce_patch_coverage: Option<f64>,
+}
+
+impl Default for PatchCmdArgs {
+    fn default() -> Self {
+        Self {
+            lcov: "lcov.info".to_string(),
+            changed_since: "origin/main".to_string(),
+            enforce_patch_coverage: None,
+        }
+    }
+}
+
+#[derive(Args, Default, Clone, PartialEq)]
+pub struct LlvmCovCmdArgs {
+    /// Format of the coverage report.
+    #[arg(long, value_enum, default_value_t = LlvmCovFormat::default())]
+    pub format: LlvmCovFormat,
+    /// Path of the report, for the html format the directory in which cargo llvm-cov writes the
+    /// 'html' directory of the report. Defaults to 'lcov.info', 'target/llvm-cov' and
+    /// 'coverage.json' depending on the format.
+    #[arg(long, value_name = "PATH", required = false)]
+    pub output: Option<String>,
+    /// Comma-separated list of excluded crates.
+    #[arg(
+        short = 'x',
+        long,
+        value_name = "CRATE,CRATE,...",
+        value_delimiter = ',',
+        required = false
+    )]
+    pub exclude: Vec<String>,
+}
+
+#[derive(ValueEnum, Default, Copy, Clone, Debug, PartialEq)]
+pub enum LlvmCovFormat {
+    #[default]
+    Lcov,
+    Html,
+    Json,
+}
+
+impl LlvmCovFormat {
+    fn default_output(&self) -> &'static str {
+        match self {
+            LlvmCovFormat::Lcov => "lcov.info",
+            LlvmCovFormat::Html => "target/llvm-cov",
+            LlvmCovFormat::Json => "coverage.json",
+        }
+    }
+
+    /// Returns the path of the report written by cargo llvm-cov for the passed output, the html
+    /// report is written in the 'html' directory of the output directory.
+    fn report_path(&self, output: &str) -> PathBuf {
+        match self {
+            LlvmCovFormat::Html => Path::new(output).join("html").join("index.html"),
+            LlvmCovFormat::Lcov | LlvmCovFormat::Json => PathBuf::from(output),
+        }
+    }
+}
+
+/// Coverage of the lines added by a patch.
+#[derive(Debug, Default, PartialEq)]
+struct PatchCoverage {
+    covered: usize,
+    /// Added lines with no hits indexed by file.
+    uncovered: BTreeMap<String, Vec<u32>>,
+}
+
+impl PatchCoverage {
+    fn instrumented(&self) -> usize {
+        self.covered + self.uncovered.values().map(Vec::len).sum::<usize>()
+    }
+
+    /// Percentage of covered lines, a patch without instrumented lines is fully covered.
+    fn percentage(&self) -> f64 {
+        match self.instrumented() {
+            0 => 100.0,
+            instrumented => self.covered as f64 * 100.0 / instrumented as f64,
+        }
+    }
+}
+
 pub fn handle_command(
     args: CoverageCmdArgs,
     _env: Environment,
@@ -45,6 +146,8 @@ pub fn handle_command(
     match args.get_command() {
         CoverageSubCommand::Install => install_grcov(),
         CoverageSubCommand::Generate(gen_args) => run_grcov(&gen_args),
+        CoverageSubCommand::Patch(patch_args) => run_patch_coverage(&patch_args),
+        CoverageSubCommand::LlvmCov(llvm_cov_args) => run_llvm_cov(&llvm_cov_args),
     }
 }
 
@@ -73,7 +176,331 @@ fn run_grcov(generate_args: &GenerateCmdArgs) -> anyhow::Result<()> {
         .ignore
         .iter()
         .for_each(|i| args.extend(vec!["--ignore", i]));
-    run_process("grcov", &args, None, None, "Error executing grcov")?;
+    run_process("grcov", &args, None, None, "Error executing grcov", None)?;
     endgroup!();
     Ok(())
 }
+
+fn run_llvm_cov(llvm_cov_args: &LlvmCovCmdArgs) -> anyhow::Result<()> {
+    rustup_add_component("llvm-tools-preview")?;
+    ensure_cargo_crate_is_installed("cargo-llvm-cov", None, None, false)?;
+    group!("Cargo: llvm-cov");
+    let args = get_llvm_cov_args(llvm_cov_args);
+    run_process_for_workspace(
+        "cargo",
+        &args.iter().map(String::as_str).collect::<Vec<&str>>(),
+        &llvm_cov_args.exclude,
+        None,
+        None,
+        "Error generating the coverage report with cargo llvm-cov",
+        None,
+        None,
+    )?;
+    endgroup!();
+    let output = llvm_cov_args
+        .output
+        .as_deref()
+        .unwrap_or(llvm_cov_args.format.default_output());
+    info!(
+        "Coverage report written to {}",
+        llvm_cov_args.format.report_path(output).display()
+    );
+    Ok(())
+}
+
+/// Returns the cargo llvm-cov arguments generating the report of the whole workspace, the
+/// excluded crates are appended by `run_process_for_workspace`.
+fn get_llvm_cov_args(llvm_cov_args: &LlvmCovCmdArgs) -> Vec<String> {
+    let output = llvm_cov_args
+        .output
+        .as_deref()
+        .unwrap_or(llvm_cov_args.format.default_output());
+    let (format, output_option) = match llvm_cov_args.format {
+        LlvmCovFormat::Lcov => ("--lcov", "--output-path"),
+        LlvmCovFormat::Html => ("--html", "--output-dir"),
+        LlvmCovFormat::Json => ("--json", "--output-path"),
+    };
+    ["llvm-cov", "--workspace", format, output_option, output]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn run_patch_coverage(patch_args: &PatchCmdArgs) -> anyhow::Result<()> {
+    group!("Patch Coverage");
+    let lcov = std::fs::read_to_string(&patch_args.lcov).map_err(|e| {
+        anyhow!(
+            "Cannot read {} ({e}). Generate it with the 'coverage generate' command.",
+            patch_args.lcov
+        )
+    })?;
+    let output = Command::new("git")
+        .args(["diff", "--unified=0", "--no-color", "--merge-base"])
+        .arg(&patch_args.changed_since)
+        .output()
+        .map_err(|e| anyhow!("Failed to execute git diff: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to compute the changes since '{}': {}",
+            patch_args.changed_since,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let added_lines = parse_diff_added_lines(&String::from_utf8_lossy(&output.stdout));
+    let coverage = compute_patch_coverage(&parse_lcov(&lcov), &added_lines);
+    for (file, lines) in &coverage.uncovered {
+        warn!(
+            "{file}: uncovered added lines {}",
+            format_line_ranges(lines)
+        );
+    }
+    let percentage = coverage.percentage();
+    info!(
+        "Patch coverage: {percentage:.2}% ({}/{} added lines covered)",
+        coverage.covered,
+        coverage.instrumented()
+    );
+    endgroup!();
+    if let Some(threshold) = patch_args.enforce_patch_coverage {
+        if percentage < threshold {
+            return Err(anyhow!(
+                "Patch coverage {percentage:.2}% is below the required {threshold}%."
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the hits of each instrumented line indexed by file from an lcov file.
+/// Absolute paths are made relative to the current directory to match git paths.
+fn parse_lcov(lcov: &str) -> BTreeMap<String, BTreeMap<u32, u64>> {
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let mut files: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+    let mut current_file = None;
+    for line in lcov.lines() {
+        if let Some(path) = line.strip_prefix("SF:") {
+            let path = std::path::Path::new(path);
+            let path = path.strip_prefix(&current_dir).unwrap_or(path);
+            current_file = Some(path.to_string_lossy().replace('\\', "/"));
+        } else if let (Some(file), Some(data)) = (&current_file, line.strip_prefix("DA:")) {
+            let mut fields = data.split(',');
+            let (Some(Result::Ok(line)), Some(Result::Ok(hits))) = (
+                fields.next().map(str::parse::<u32>),
+                fields.next().map(str::parse::<u64>),
+            ) else {
+                continue;
+            };
+            *files
+                .entry(file.clone())
+                .or_default()
+                .entry(line)
+                .or_default() += hits;
+        } else if line == "end_of_record" {
+            current_file = None;
+        }
+    }
+    files
+}
+
+/// Returns the added lines indexed by file from a git diff generated with `--unified=0`.
+fn parse_diff_added_lines(diff: &str) -> BTreeMap<String, BTreeSet<u32>> {
+    let mut files: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+    let mut current_file = None;
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            // deleted files are compared to /dev/null
+            current_file = path.strip_prefix("b/").map(str::to_string);
+        } else if let (Some(file), Some(hunk)) = (&current_file, line.strip_prefix("@@ ")) {
+            // hunk header: @@ -old_start,old_count +new_start,new_count @@
+            let Some(new_range) = hunk
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+            else {
+                continue;
+            };
+            let (start, count) = match new_range.split_once(',') {
+                Some((start, count)) => (start.parse::<u32>(), count.parse::<u32>()),
+                None => (new_range.parse::<u32>(), Result::Ok(1)),
+            };
+            if let (Result::Ok(start), Result::Ok(count)) = (start, count) {
+                files
+                    .entry(file.clone())
+                    .or_default()
+                    .extend(start..start + count);
+            }
+        }
+    }
+    files
+}
+
+/// Compute the coverage of the added lines, lines which are not instrumented are ignored.
+fn compute_patch_coverage(
+    hits: &BTreeMap<String, BTreeMap<u32, u64>>,
+    added_lines: &BTreeMap<String, BTreeSet<u32>>,
+) -> PatchCoverage {
+    let mut coverage = PatchCoverage::default();
+    for (file, lines) in added_lines {
+        let Some(file_hits) = hits.get(file) else {
+            continue;
+        };
+        for line in lines {
+            match file_hits.get(line) {
+                Some(0) => coverage
+                    .uncovered
+                    .entry(file.clone())
+                    .or_default()
+                    .push(*line),
+                Some(_) => coverage.covered += 1,
+                None => {}
+            }
+        }
+    }
+    coverage
+}
+
+/// Format sorted line numbers as ranges, i.e. `3, 5-7`.
+fn format_line_ranges(lines: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = vec![];
+    for line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *line => *end = *line,
+            _ => ranges.push((*line, *line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const LCOV: &str = "TN:
+SF:src/lib.rs
+DA:1,1
+DA:2,0
+DA:3,0
+DA:5,2
+end_of_record
+SF:src/other.rs
+DA:1,0
+end_of_record
+";
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -0,0 +1,3 @@
++fn a() {}
++fn b() {}
++fn c() {}
+@@ -10 +12 @@
+-old
++new
+@@ -20,2 +21,0 @@
+-removed
+-removed
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1 +1 @@
+-a
++b
+diff --git a/src/deleted.rs b/src/deleted.rs
+--- a/src/deleted.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+    #[test]
+    fn test_parse_diff_added_lines() {
+        let added = parse_diff_added_lines(DIFF);
+        assert_eq!(
+            added,
+            BTreeMap::from([
+                ("README.md".to_string(), BTreeSet::from([1])),
+                ("src/lib.rs".to_string(), BTreeSet::from([1, 2, 3, 12])),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_compute_patch_coverage() {
+        let coverage = compute_patch_coverage(&parse_lcov(LCOV), &parse_diff_added_lines(DIFF));
+        assert_eq!(
+            coverage,
+            PatchCoverage {
+                covered: 1,
+                uncovered: BTreeMap::from([("src/lib.rs".to_string(), vec![2, 3])]),
+            }
+        );
+        assert_eq!(coverage.instrumented(), 3);
+    }
+
+    #[rstest]
+    #[case::no_lines(0, 0, 100.0)]
+    #[case::half(1, 1, 50.0)]
+    #[case::uncovered(0, 2, 0.0)]
+    fn test_patch_coverage_percentage(
+        #[case] covered: usize,
+        #[case] uncovered: u32,
+        #[case] expected: f64,
+    ) {
+        let coverage = PatchCoverage {
+            covered,
+            uncovered: BTreeMap::from([("a.rs".to_string(), (1..=uncovered).collect())]),
+        };
+        assert_eq!(coverage.percentage(), expected);
+    }
+
+    #[rstest]
+    #[case::lcov(LlvmCovFormat::Lcov, None, &["--lcov", "--output-path", "lcov.info"])]
+    #[case::html(LlvmCovFormat::Html, None, &["--html", "--output-dir", "target/llvm-cov"])]
+    #[case::json_output(LlvmCovFormat::Json, Some("cov.json"), &["--json", "--output-path", "cov.json"])]
+    fn test_get_llvm_cov_args(
+        #[case] format: LlvmCovFormat,
+        #[case] output: Option<&str>,
+        #[case] expected: &[&str],
+    ) {
+        let args = LlvmCovCmdArgs {
+            format,
+            output: output.map(String::from),
+            exclude: vec![],
+        };
+        let mut expected_args = vec!["llvm-cov", "--workspace"];
+        expected_args.extend(expected);
+        assert_eq!(get_llvm_cov_args(&args), expected_args);
+    }
+
+    #[rstest]
+    #[case::lcov(LlvmCovFormat::Lcov, "lcov.info", "lcov.info")]
+    #[case::html(
+        LlvmCovFormat::Html,
+        "target/llvm-cov",
+        "target/llvm-cov/html/index.html"
+    )]
+    #[case::json(LlvmCovFormat::Json, "cov.json", "cov.json")]
+    fn test_report_path(
+        #[case] format: LlvmCovFormat,
+        #[case] output: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(format.report_path(output), PathBuf::from(expected));
+    }
+
+    #[rstest]
+    #[case::single(&[3], "3")]
+    #[case::ranges(&[1, 2, 3, 5, 7, 8], "1-3, 5, 7-8")]
+    fn test_format_line_ranges(#[case] lines: &[u32], #[case] expected: &str) {
+        assert_eq!(format_line_ranges(lines), expected);
+    }
+}
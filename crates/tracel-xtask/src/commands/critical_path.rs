@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
+use serde_json::Value;
+
+use crate::{
+    commands::CARGO_NIGHTLY_MSG,
+    endgroup, group,
+    prelude::{Context, Environment},
+    utils::{
+        process::{run_process, spawn_child, wait_child_output},
+        rustup::is_current_toolchain_nightly,
+        script::ensure_not_planned,
+        workspace::get_workspace_resolved_metadata,
+    },
+};
+
+/// Dedicated target directory so that every unit is compiled and timed.
+const CRITICAL_PATH_TARGET_DIR: &str = "target/critical-path";
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct CriticalPathCmdArgs {}
+
+pub fn handle_command(
+    args: CriticalPathCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    ensure_not_planned("critical-path")?;
+    // JSON timings are unstable
+    if !is_current_toolchain_nightly() {
+        return Err(anyhow!(CARGO_NIGHTLY_MSG));
+    }
+    group!("Critical Path: build");
+    // a clean build is required as fresh units are not timed
+    run_process(
+        "cargo",
+        &["clean", "--target-dir", CRITICAL_PATH_TARGET_DIR],
+        None,
+        None,
+        "Failed to clean the critical path target directory",
+        None,
+    )?;
+    let mut cargo_args = vec![
+        "build",
+        "--workspace",
+        "--target-dir",
+        CRITICAL_PATH_TARGET_DIR,
+        "-Zunstable-options",
+        "--timings=json",
+        "--color=always",
+    ];
+    if args.release {
+        cargo_args.push("--release");
+    }
+    let output = spawn_child(
+        Command::new("cargo")
+            .args(&cargo_args)
+            .stdout(Stdio::piped()),
+    )
+    .and_then(wait_child_output)
+    .map_err(|e| anyhow!("Failed to execute cargo build: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Critical path build failed"));
+    }
+    endgroup!();
+
+    let durations = parse_timings(&String::from_utf8_lossy(&output.stdout));
+    let metadata = get_workspace_resolved_metadata()?;
+    let path = find_critical_path(&durations, &parse_dependency_graph(&metadata));
+    let names = get_package_names(&metadata);
+    group!("Critical Path");
+    let mut total = 0.0;
+    for (id, duration) in &path {
+        total += duration;
+        let name = names.get(id).map(String::as_str).unwrap_or(id);
+        info!("{duration:>8.2}s  {name}");
+    }
+    info!("{total:>8.2}s  total (lower bound of the build time)");
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the `name vX.Y.Z` display name of each package id.
+fn get_package_names(metadata: &Value) -> HashMap<String, String> {
+    let empty = vec![];
+    metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|p| {
+            let id = p["id"].as_str()?.to_string();
+            Some((
+                id,
+                format!("{} v{}", p["name"].as_str()?, p["version"].as_str()?),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the compile duration of each package id from the output of `cargo build --timings=json`.
+/// The durations of the units of a package (library, build script...) are summed.
+fn parse_timings(output: &str) -> HashMap<String, f64> {
+    let mut durations: HashMap<String, f64> = HashMap::new();
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| message["reason"] == "timing-info")
+        .for_each(|message| {
+            if let (Some(id), Some(duration)) =
+                (message["package_id"].as_str(), message["duration"].as_f64())
+            {
+                *durations.entry(id.to_string()).or_default() += duration;
+            }
+        });
+    durations
+}
+
+/// Returns the normal and build dependencies of each package id from the cargo metadata.
+fn parse_dependency_graph(metadata: &Value) -> HashMap<String, Vec<String>> {
+    let empty = vec![];
+    metadata["resolve"]["nodes"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|node| {
+            let id = node["id"].as_str()?.to_string();
+            let deps = node["deps"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                // dev-dependencies are not built by cargo build
+                .filter(|dep| {
+                    dep["dep_kinds"]
+                        .as_array()
+                        .unwrap_or(&empty)
+                        .iter()
+                        .any(|k| k["kind"].as_str() != Some("dev"))
+                })
+                .filter_map(|dep| dep["pkg"].as_str().map(str::to_string))
+                .collect();
+            Some((id, deps))
+        })
+        .collect()
+}
+
+/// Returns the chain of sequentially dependent compilations with the longest total duration,
+/// starting with the first compiled package.
+fn find_critical_path(
+    durations: &HashMap<String, f64>,
+    graph: &HashMap<String, Vec<String>>,
+) -> Vec<(String, f64)> {
+    // longest path ending with each package, memoized as (total duration, previous package)
+    fn longest<'a>(
+        id: &'a str,
+        durations: &HashMap<String, f64>,
+        graph: &'a HashMap<String, Vec<String>>,
+        memo: &mut HashMap<&'a str, (f64, Option<&'a str>)>,
+    ) -> f64 {
+        if let Some((total, _)) = memo.get(id) {
+            return *total;
+        }
+        let mut best: (f64, Option<&str>) = (0.0, None);
+        for dep in graph.get(id).into_iter().flatten() {
+            let total = longest(dep, durations, graph, memo);
+            if best.1.is_none() || total > best.0 {
+                best = (total, Some(dep));
+            }
+        }
+        let total = best.0 + durations.get(id).copied().unwrap_or_default();
+        memo.insert(id, (total, best.1));
+        total
+    }
+
+    let mut memo = HashMap::new();
+    let mut ids: Vec<&String> = graph.keys().collect();
+    ids.sort();
+    let mut end: Option<(&str, f64)> = None;
+    for id in ids {
+        let total = longest(id, durations, graph, &mut memo);
+        if !matches!(end, Some((_, longest_total)) if longest_total >= total) {
+            end = Some((id, total));
+        }
+    }
+    let mut path = vec![];
+    let mut current = end.map(|(id, _)| id);
+    while let Some(id) = current {
+        path.push((
+            id.to_string(),
+            durations.get(id).copied().unwrap_or_default(),
+        ));
+        current = memo.get(id).and_then(|(_, previous)| *previous);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_timings() {
+        let output = [
+            json!({"reason": "compiler-artifact", "package_id": "a"}).to_string(),
+            json!({"reason": "timing-info", "package_id": "a", "duration": 1.5}).to_string(),
+            json!({"reason": "timing-info", "package_id": "a", "duration": 0.5}).to_string(),
+            json!({"reason": "timing-info", "package_id": "b", "duration": 3.0}).to_string(),
+            "not json".to_string(),
+        ]
+        .join("\n");
+        let durations = parse_timings(&output);
+        assert_eq!(
+            durations,
+            HashMap::from([("a".to_string(), 2.0), ("b".to_string(), 3.0)])
+        );
+    }
+
+    #[test]
+    fn test_parse_dependency_graph_ignores_dev_dependencies() {
+        let metadata = json!({"resolve": {"nodes": [
+            {"id": "a", "deps": [
+                {"pkg": "b", "dep_kinds": [{"kind": null}]},
+                {"pkg": "c", "dep_kinds": [{"kind": "dev"}]},
+                {"pkg": "d", "dep_kinds": [{"kind": "dev"}, {"kind": "build"}]},
+            ]},
+        ]}});
+        let graph = parse_dependency_graph(&metadata);
+        assert_eq!(graph["a"], vec!["b".to_string(), "d".to_string()]);
+    }
+
+    #[test]
+    fn test_find_critical_path() {
+        // app depends on fast and slow which both depend on core
+        let graph = HashMap::from([
+            (
+                "app".to_string(),
+                vec!["fast".to_string(), "slow".to_string()],
+            ),
+            ("fast".to_string(), vec!["core".to_string()]),
+            ("slow".to_string(), vec!["core".to_string()]),
+            ("core".to_string(), vec![]),
+            ("tool".to_string(), vec![]),
+        ]);
+        let durations = HashMap::from([
+            ("app".to_string(), 1.0),
+            ("fast".to_string(), 2.0),
+            ("slow".to_string(), 5.0),
+            ("core".to_string(), 3.0),
+            ("tool".to_string(), 8.0),
+        ]);
+        let path = find_critical_path(&durations, &graph);
+        assert_eq!(
+            path,
+            vec![
+                ("core".to_string(), 3.0),
+                ("slow".to_string(), 5.0),
+                ("app".to_string(), 1.0),
+            ]
+        );
+    }
+}
@@ -1,10 +1,23 @@
-use anyhow::Ok;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
+use serde_json::Value;
 use strum::IntoEnumIterator;
 
 use crate::{
     endgroup, group,
     prelude::{Context, Environment},
-    utils::{cargo::ensure_cargo_crate_is_installed, process::run_process},
+    utils::{
+        cargo::ensure_cargo_crate_is_installed,
+        process::{run_process, spawn_child, wait_child_output},
+        rustup::is_current_toolchain_nightly,
+        script::plan_command,
+        workspace::get_workspace_metadata,
+    },
 };
 
 #[tracel_xtask_macros::declare_command_args(None, DependenciesSubCommand)]
@@ -16,13 +29,27 @@ pub fn handle_command(
     _ctx: Context,
 ) -> anyhow::Result<()> {
     match args.get_command() {
+        DependenciesSubCommand::Cycles => run_cycles_check(),
         DependenciesSubCommand::Deny => run_cargo_deny(),
-        DependenciesSubCommand::Unused => run_cargo_machete(),
+        DependenciesSubCommand::Duplicates => run_duplicates_check(args.deny),
+        DependenciesSubCommand::Outdated => run_cargo_outdated(args.deny),
+        DependenciesSubCommand::Publishable => run_publishable_check(),
+        DependenciesSubCommand::Unused => {
+            if is_current_toolchain_nightly() {
+                run_cargo_udeps(args.deny)
+            } else {
+                warn!("cargo-udeps requires a nightly toolchain, falling back to cargo-machete.");
+                run_cargo_machete(args.deny)
+            }
+        }
         DependenciesSubCommand::All => DependenciesSubCommand::iter()
             .filter(|c| *c != DependenciesSubCommand::All)
             .try_for_each(|c| {
                 handle_command(
-                    DependenciesCmdArgs { command: Some(c) },
+                    DependenciesCmdArgs {
+                        command: Some(c),
+                        ..args.clone()
+                    },
                     _env.clone(),
                     _ctx.clone(),
                 )
@@ -41,24 +68,477 @@ fn run_cargo_deny() -> anyhow::Result<()> {
         None,
         None,
         "Some dependencies don't meet the requirements!",
+        None,
     )?;
     endgroup!();
     Ok(())
 }
 
-/// Run cargo-machete
-fn run_cargo_machete() -> anyhow::Result<()> {
+/// Run cargo-machete, the unused dependencies fail the check only if `deny` is true
+fn run_cargo_machete(deny: bool) -> anyhow::Result<()> {
     ensure_cargo_crate_is_installed("cargo-machete", None, None, false)?;
     // Run cargo machete
     group!("Cargo: run unused dependencies checks");
-    run_process(
+    let result = run_process(
         "cargo",
         &["machete"],
         None,
         None,
         "Unused dependencies found!",
+        None,
+    );
+    endgroup!();
+    deny_findings(result, deny)
+}
+
+/// Run cargo-udeps, the unused dependencies fail the check only if `deny` is true
+fn run_cargo_udeps(deny: bool) -> anyhow::Result<()> {
+    ensure_cargo_crate_is_installed("cargo-udeps", None, None, false)?;
+    group!("Cargo: run unused dependencies checks");
+    let result = run_process(
+        "cargo",
+        &["udeps", "--workspace", "--all-targets"],
+        None,
+        None,
+        "Unused dependencies found!",
+        None,
+    );
+    endgroup!();
+    deny_findings(result, deny)
+}
+
+/// Returns the error of a tool which fails when it finds something only if `deny` is true, the
+/// error is logged as a warning otherwise as the findings have been printed by the tool
+fn deny_findings(result: anyhow::Result<()>, deny: bool) -> anyhow::Result<()> {
+    match result {
+        Err(e) if !deny => {
+            warn!("{e}");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Run cargo-outdated, the outdated dependencies fail the check only if `deny` is true
+fn run_cargo_outdated(deny: bool) -> anyhow::Result<()> {
+    ensure_cargo_crate_is_installed("cargo-outdated", None, None, false)?;
+    group!("Cargo: run outdated dependencies checks");
+    let mut args = vec!["outdated", "--workspace", "--root-deps-only"];
+    if deny {
+        args.extend(["--exit-code", "1"]);
+    }
+    run_process(
+        "cargo",
+        &args,
+        None,
+        None,
+        "Outdated dependencies found!",
+        None,
     )?;
     endgroup!();
+    Ok(())
+}
 
+/// Report the crates built in several versions, they fail the check only if `deny` is true
+fn run_duplicates_check(deny: bool) -> anyhow::Result<()> {
+    group!("Cargo: check duplicated dependencies");
+    let mut command = Command::new("cargo");
+    command.args(["tree", "--workspace", "--duplicates"]);
+    if plan_command(&command) {
+        endgroup!();
+        return Ok(());
+    }
+    let output = spawn_child(command.stdout(Stdio::piped()))
+        .and_then(wait_child_output)
+        .map_err(|e| anyhow!("Failed to execute cargo tree: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("Cannot list the duplicated dependencies."));
+    }
+    let tree = String::from_utf8_lossy(&output.stdout);
+    if !tree.trim().is_empty() {
+        info!("{}", tree.trim_end());
+    }
+    let duplicates = parse_duplicates(&tree);
+    duplicates
+        .iter()
+        .for_each(|(name, versions)| warn!("{name}: {}", versions.join(", ")));
+    endgroup!();
+    if duplicates.is_empty() {
+        info!("No dependency is built in several versions.");
+        return Ok(());
+    }
+    let msg = format!(
+        "Found {} dependencies built in several versions!",
+        duplicates.len()
+    );
+    if deny {
+        return Err(anyhow!(msg));
+    }
+    warn!("{msg}");
     Ok(())
 }
+
+/// Returns the versions of each crate of the passed `cargo tree --duplicates` output, the roots of
+/// the inverted trees are the duplicated crates.
+fn parse_duplicates(tree: &str) -> BTreeMap<String, Vec<String>> {
+    let mut duplicates: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for line in tree.lines() {
+        if line.is_empty() || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let (Some(name), Some(version)) = (words.next(), words.next()) else {
+            continue;
+        };
+        // skip the tree lines and the '[dev-dependencies]' like headers
+        if !version.starts_with('v') || name.starts_with(['├', '└', '│', '[']) {
+            continue;
+        }
+        let versions = duplicates.entry(name.to_string()).or_default();
+        if !versions.iter().any(|v| v == version) {
+            versions.push(version.to_string());
+        }
+    }
+    duplicates.retain(|_, versions| versions.len() > 1);
+    duplicates
+}
+
+/// Check that the dependencies of the publishable crates can be published on crates.io
+fn run_publishable_check() -> anyhow::Result<()> {
+    group!("Cargo: check dependencies of publishable crates");
+    let metadata = get_workspace_metadata()?;
+    let offenders = find_unpublishable_dependencies(&metadata);
+    offenders
+        .iter()
+        .for_each(|(crate_name, reason)| error!("{crate_name}: {reason}"));
+    endgroup!();
+    if !offenders.is_empty() {
+        return Err(anyhow!(
+            "Found {} dependencies that cannot be published on crates.io!",
+            offenders.len()
+        ));
+    }
+    info!("All publishable crates have publishable dependencies.");
+    Ok(())
+}
+
+/// Returns the crate name and the reason of each dependency of a publishable crate that
+/// cannot be published: git dependencies and path dependencies outside of the workspace.
+fn find_unpublishable_dependencies(metadata: &Value) -> Vec<(String, String)> {
+    let workspace_root = Path::new(metadata["workspace_root"].as_str().unwrap_or_default());
+    let empty = vec![];
+    metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        // 'publish = false' is reported as an empty list of registries
+        .filter(|package| !matches!(package["publish"].as_array(), Some(r) if r.is_empty()))
+        .flat_map(|package| {
+            let crate_name = package["name"].as_str().unwrap_or_default();
+            package["dependencies"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                // cargo strips the dev-dependencies without a version when publishing
+                .filter(|dep| dep["kind"].as_str() != Some("dev"))
+                .filter_map(move |dep| {
+                    let dep_name = dep["name"].as_str().unwrap_or_default();
+                    let reason = match (dep["source"].as_str(), dep["path"].as_str()) {
+                        (Some(source), _) if source.starts_with("git+") => {
+                            format!("git dependency '{dep_name}' ({source})")
+                        }
+                        (_, Some(path)) if !Path::new(path).starts_with(workspace_root) => {
+                            format!(
+                                "path dependency '{dep_name}' outside of the workspace ({path})"
+                            )
+                        }
+                        _ => return None,
+                    };
+                    Some((crate_name.to_string(), reason))
+                })
+        })
+        .collect()
+}
+
+/// A cycle in the dependency graph of the workspace crates.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct DependencyCycle {
+    /// True if the cycle goes through a dev-dependency, false if cargo would reject it.
+    dev: bool,
+    /// Crates of the cycle starting and ending with the same crate.
+    path: Vec<String>,
+}
+
+/// Check that the dependency graph of the workspace crates is acyclic
+fn run_cycles_check() -> anyhow::Result<()> {
+    group!("Cargo: check dependency cycles");
+    let metadata = get_workspace_metadata()?;
+    let cycles = find_dependency_cycles(&metadata);
+    cycles.iter().for_each(|cycle| {
+        let kind = if cycle.dev {
+            "dev-dependency cycle"
+        } else {
+            "dependency cycle"
+        };
+        error!("{kind}: {}", cycle.path.join(" -> "));
+    });
+    endgroup!();
+    if !cycles.is_empty() {
+        return Err(anyhow!(
+            "Found {} dependency cycles between the workspace crates!",
+            cycles.len()
+        ));
+    }
+    info!("The dependency graph of the workspace crates is acyclic.");
+    Ok(())
+}
+
+/// Returns the cycles of the graph of the dependencies between the workspace crates.
+/// Cycles made only of normal and build dependencies are reported with `dev` set to false,
+/// cycles that need at least one dev-dependency are reported with `dev` set to true.
+fn find_dependency_cycles(metadata: &Value) -> Vec<DependencyCycle> {
+    let empty = vec![];
+    let packages = metadata["packages"].as_array().unwrap_or(&empty);
+    let members: BTreeSet<&str> = packages.iter().filter_map(|p| p["name"].as_str()).collect();
+    // for each crate, its workspace dependencies and whether they are only dev-dependencies
+    let mut graph: BTreeMap<&str, BTreeMap<&str, bool>> = BTreeMap::new();
+    for package in packages {
+        let name = package["name"].as_str().unwrap_or_default();
+        let edges = graph.entry(name).or_default();
+        for dep in package["dependencies"].as_array().unwrap_or(&empty) {
+            let dep_name = dep["name"].as_str().unwrap_or_default();
+            // only path dependencies can point to a workspace crate
+            if dep["source"].is_null() && members.contains(dep_name) {
+                let dev = dep["kind"].as_str() == Some("dev");
+                let edge_dev = edges.entry(dep_name).or_insert(dev);
+                *edge_dev &= dev;
+            }
+        }
+    }
+    let mut cycles = BTreeSet::new();
+    for dev in [false, true] {
+        for (from, edges) in &graph {
+            // a dev cycle is closed by a dev-dependency and then can go through any dependency
+            for (to, _) in edges.iter().filter(|(_, edge_dev)| **edge_dev == dev) {
+                let Some(mut path) = find_path(&graph, to, from, dev) else {
+                    continue;
+                };
+                path.insert(0, from.to_string());
+                // start each cycle with its smallest crate to report it only once
+                path.pop();
+                let start = (0..path.len()).min_by_key(|i| &path[*i]).unwrap_or(0);
+                path.rotate_left(start);
+                path.push(path[0].clone());
+                cycles.insert(DependencyCycle { dev, path });
+            }
+        }
+    }
+    cycles.into_iter().collect()
+}
+
+/// Returns the shortest path between the two passed crates, dev-dependencies are followed
+/// only if `with_dev` is true.
+fn find_path(
+    graph: &BTreeMap<&str, BTreeMap<&str, bool>>,
+    from: &str,
+    to: &str,
+    with_dev: bool,
+) -> Option<Vec<String>> {
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        if current == to {
+            let mut path = vec![to.to_string()];
+            let mut node = to;
+            while let Some(prev) = previous.get(node) {
+                path.insert(0, prev.to_string());
+                node = prev;
+            }
+            return Some(path);
+        }
+        for (next, dev) in graph.get(current).into_iter().flatten() {
+            if (with_dev || !dev) && *next != from && !previous.contains_key(next) {
+                previous.insert(next, current);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn graph_metadata(dependencies: &[(&str, &str, Option<&str>)]) -> Value {
+        let names: BTreeSet<&str> = dependencies.iter().flat_map(|(f, t, _)| [*f, *t]).collect();
+        let packages: Vec<Value> = names
+            .iter()
+            .map(|name| {
+                let deps: Vec<Value> = dependencies
+                    .iter()
+                    .filter(|(from, _, _)| from == name)
+                    .map(|(_, to, kind)| json!({"name": to, "source": null, "kind": kind}))
+                    .collect();
+                json!({"name": name, "dependencies": deps})
+            })
+            .collect();
+        json!({ "packages": packages })
+    }
+
+    #[rstest]
+    #[case::acyclic(vec![("a", "b", None), ("b", "c", None), ("a", "c", Some("dev"))], vec![])]
+    #[case::normal_cycle(
+        vec![("b", "c", None), ("c", "a", Some("build")), ("a", "b", None)],
+        vec![(false, vec!["a", "b", "c", "a"])]
+    )]
+    #[case::dev_cycle(
+        vec![("a", "b", None), ("b", "a", Some("dev"))],
+        vec![(true, vec!["a", "b", "a"])]
+    )]
+    #[case::normal_edge_wins_over_dev_edge(
+        vec![("a", "b", None), ("b", "a", Some("dev")), ("b", "a", None)],
+        vec![(false, vec!["a", "b", "a"])]
+    )]
+    #[case::both_kinds(
+        vec![("a", "b", None), ("b", "a", None), ("c", "d", Some("dev")), ("d", "c", None)],
+        vec![(false, vec!["a", "b", "a"]), (true, vec!["c", "d", "c"])]
+    )]
+    fn test_find_dependency_cycles(
+        #[case] dependencies: Vec<(&str, &str, Option<&str>)>,
+        #[case] expected: Vec<(bool, Vec<&str>)>,
+    ) {
+        let cycles = find_dependency_cycles(&graph_metadata(&dependencies));
+        let expected: Vec<DependencyCycle> = expected
+            .into_iter()
+            .map(|(dev, path)| DependencyCycle {
+                dev,
+                path: path.into_iter().map(str::to_string).collect(),
+            })
+            .collect();
+        assert_eq!(cycles, expected);
+    }
+
+    #[test]
+    fn test_find_dependency_cycles_ignores_external_crates() {
+        let metadata = json!({
+            "packages": [
+                {"name": "a", "dependencies": [{"name": "b", "source": "registry+https://github.com/rust-lang/crates.io-index", "kind": null}]},
+                {"name": "b", "dependencies": [{"name": "a", "source": null, "kind": null}]},
+            ],
+        });
+        assert!(find_dependency_cycles(&metadata).is_empty());
+    }
+
+    #[rstest]
+    #[case::findings_denied(false, true, false)]
+    #[case::findings_allowed(false, false, true)]
+    #[case::no_findings(true, true, true)]
+    fn test_deny_findings(#[case] found_nothing: bool, #[case] deny: bool, #[case] ok: bool) {
+        let result = if found_nothing {
+            Ok(())
+        } else {
+            Err(anyhow!("Unused dependencies found!"))
+        };
+        assert_eq!(deny_findings(result, deny).is_ok(), ok);
+    }
+
+    #[test]
+    fn test_parse_duplicates() {
+        let tree = "\
+regex-automata v0.1.10
+└── matchers v0.1.0
+    └── tracing-subscriber v0.3.19
+        └── my-crate v1.0.0 (/workspace/crates/my-crate)
+
+regex-automata v0.4.9
+└── regex v1.11.1
+    ├── rstest_macros v0.25.0 (proc-macro)
+    │   └── rstest v0.25.0
+    │       [dev-dependencies]
+    │       └── my-crate v1.0.0 (/workspace/crates/my-crate) (*)
+    └── my-crate v1.0.0 (/workspace/crates/my-crate) (*)
+
+syn v1.0.109
+└── my-macros v1.0.0 (proc-macro) (/workspace/crates/my-macros)
+
+syn v2.0.90 (*)
+";
+        let duplicates = parse_duplicates(tree);
+        assert_eq!(
+            duplicates,
+            BTreeMap::from([
+                (
+                    "regex-automata".to_string(),
+                    vec!["v0.1.10".to_string(), "v0.4.9".to_string()]
+                ),
+                (
+                    "syn".to_string(),
+                    vec!["v1.0.109".to_string(), "v2.0.90".to_string()]
+                ),
+            ])
+        );
+    }
+
+    fn metadata(publish: Value, dependency: Value) -> Value {
+        json!({
+            "workspace_root": "/workspace",
+            "packages": [{
+                "name": "my-crate",
+                "publish": publish,
+                "dependencies": [dependency],
+            }],
+        })
+    }
+
+    #[rstest]
+    #[case::registry(
+        json!(null),
+        json!({"name": "dep", "source": "registry+https://github.com/rust-lang/crates.io-index", "kind": null}),
+        vec![]
+    )]
+    #[case::git(
+        json!(null),
+        json!({"name": "dep", "source": "git+https://github.com/org/dep", "kind": null}),
+        vec!["git dependency 'dep' (git+https://github.com/org/dep)"]
+    )]
+    #[case::git_build(
+        json!(null),
+        json!({"name": "dep", "source": "git+https://github.com/org/dep", "kind": "build"}),
+        vec!["git dependency 'dep' (git+https://github.com/org/dep)"]
+    )]
+    #[case::git_dev(
+        json!(null),
+        json!({"name": "dep", "source": "git+https://github.com/org/dep", "kind": "dev"}),
+        vec![]
+    )]
+    #[case::git_not_publishable(
+        json!([]),
+        json!({"name": "dep", "source": "git+https://github.com/org/dep", "kind": null}),
+        vec![]
+    )]
+    #[case::path_inside_workspace(
+        json!(null),
+        json!({"name": "dep", "source": null, "kind": null, "path": "/workspace/crates/dep"}),
+        vec![]
+    )]
+    #[case::path_outside_workspace(
+        json!(["crates-io"]),
+        json!({"name": "dep", "source": null, "kind": null, "path": "/elsewhere/dep"}),
+        vec!["path dependency 'dep' outside of the workspace (/elsewhere/dep)"]
+    )]
+    fn test_find_unpublishable_dependencies(
+        #[case] publish: Value,
+        #[case] dependency: Value,
+        #[case] expected: Vec<&str>,
+    ) {
+        let offenders = find_unpublishable_dependencies(&metadata(publish, dependency));
+        let expected: Vec<(String, String)> = expected
+            .into_iter()
+            .map(|r| ("my-crate".to_string(), r.to_string()))
+            .collect();
+        assert_eq!(offenders, expected);
+    }
+}
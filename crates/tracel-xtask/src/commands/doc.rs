@@ -48,7 +48,7 @@ fn run_documentation_build(
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
@@ -99,7 +99,7 @@ pub(crate) fn run_documentation(
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
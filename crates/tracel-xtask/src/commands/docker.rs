@@ -47,6 +47,7 @@ pub fn up_docker_compose(
         None,
         None,
         "Failed to execute 'docker compose' to start the container!",
+        None,
     );
     if result.is_err() {
         run_process(
@@ -55,6 +56,7 @@ pub fn up_docker_compose(
             None,
             None,
             "Failed to execute 'docker compose' to start the container!",
+            None,
         )
     } else {
         result
@@ -82,6 +84,7 @@ pub fn down_docker_compose(env: &Environment, project: &str) -> anyhow::Result<(
         None,
         None,
         "Failed to execute 'docker compose' to stop the container!",
+        None,
     );
     if result.is_err() {
         run_process(
@@ -90,6 +93,7 @@ pub fn down_docker_compose(env: &Environment, project: &str) -> anyhow::Result<(
             None,
             None,
             "Failed to execute 'docker compose' to stop the container!",
+            None,
         )
     } else {
         result
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Ok};
+use serde_json::Value;
+use strum::IntoEnumIterator;
+
+use crate::{
+    commands::CARGO_NIGHTLY_MSG,
+    endgroup, group, group_info,
+    prelude::{Context, Environment},
+    utils::{
+        cargo::{get_flags_env, FlagsVar},
+        process::run_process,
+        rustup::{is_current_toolchain_nightly, rustup_get_installed_targets},
+        workspace::{get_workspace_members, get_workspace_metadata, WorkspaceMemberType},
+    },
+};
+
+use super::Target;
+
+/// Configuration flag set by docs.rs for both rustc and rustdoc.
+const DOCSRS_CFG: &str = "--cfg docsrs";
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct DocsrsCheckCmdArgs {}
+
+/// A documentation build as performed by docs.rs for a crate and a target.
+#[derive(Debug, PartialEq)]
+struct DocsrsBuild {
+    /// The target triple, None for the host target.
+    target: Option<String>,
+    cargo_args: Vec<String>,
+    rustflags: String,
+    rustdocflags: String,
+}
+
+pub fn handle_command(
+    args: DocsrsCheckCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    // docs.rs builds the documentation with a nightly toolchain
+    if !is_current_toolchain_nightly() {
+        return Err(anyhow!(CARGO_NIGHTLY_MSG));
+    }
+    run_docsrs_check(&args.target, &args)
+}
+
+fn run_docsrs_check(target: &Target, args: &DocsrsCheckCmdArgs) -> anyhow::Result<()> {
+    match target {
+        // docs.rs builds each crate separately so the workspace target checks all the crates
+        Target::Workspace | Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => get_workspace_members(WorkspaceMemberType::AllCrates),
+            };
+            let docsrs_metadata = get_docsrs_metadata()?;
+            let installed_targets = rustup_get_installed_targets()?;
+            let mut failures = vec![];
+            for member in members {
+                if args.exclude.contains(&member.name)
+                    || (!args.only.is_empty() && !args.only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                    continue;
+                }
+                let docsrs = docsrs_metadata.get(&member.name).unwrap_or(&Value::Null);
+                for build in get_docsrs_builds(&member.name, docsrs) {
+                    let target_name = build.target.as_deref().unwrap_or("host");
+                    if let Some(triple) = &build.target {
+                        if !installed_targets.lines().any(|t| t.trim() == triple) {
+                            warn!(
+                                "Skip target '{triple}' of '{}' because it is not installed, install it with 'rustup target add {triple}'.",
+                                member.name
+                            );
+                            continue;
+                        }
+                    }
+                    group!("docs.rs Build: {} ({})", member.name, target_name);
+                    let cargo_args: Vec<&str> =
+                        build.cargo_args.iter().map(String::as_str).collect();
+                    // the docs.rs flags are appended to the flags of the environment
+                    let flags = |var: FlagsVar, flags: &str| {
+                        let flags: Vec<String> =
+                            flags.split_whitespace().map(String::from).collect();
+                        get_flags_env(var, &flags, true)
+                    };
+                    let (rustflags_var, rustflags) = flags(FlagsVar::Rustflags, &build.rustflags);
+                    let (rustdocflags_var, rustdocflags) =
+                        flags(FlagsVar::Rustdocflags, &build.rustdocflags);
+                    let result = run_process(
+                        "cargo",
+                        &cargo_args,
+                        Some(HashMap::from([
+                            (rustflags_var, rustflags.as_str()),
+                            (rustdocflags_var, rustdocflags.as_str()),
+                        ])),
+                        None,
+                        &format!(
+                            "docs.rs build of '{}' failed for target {}",
+                            member.name, target_name
+                        ),
+                        None,
+                    );
+                    endgroup!();
+                    if let Err(e) = result {
+                        error!("{e}");
+                        failures.push(format!("{} ({})", member.name, target_name));
+                    }
+                }
+            }
+            if !failures.is_empty() {
+                return Err(anyhow!(
+                    "docs.rs builds would fail for: {}",
+                    failures.join(", ")
+                ));
+            }
+        }
+        Target::AllPackages => {
+            Target::iter()
+                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .try_for_each(|t| run_docsrs_check(&t, args))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the `[package.metadata.docs.rs]` table of each workspace package.
+fn get_docsrs_metadata() -> anyhow::Result<HashMap<String, Value>> {
+    let metadata = get_workspace_metadata()?;
+    let empty = vec![];
+    Ok(metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|p| {
+            let name = p["name"].as_str()?.to_string();
+            Some((name, p["metadata"]["docs"]["rs"].clone()))
+        })
+        .collect())
+}
+
+/// Returns the documentation builds performed by docs.rs for the passed crate according to its
+/// `[package.metadata.docs.rs]` table, see `<https://docs.rs/about/metadata>`.
+fn get_docsrs_builds(crate_name: &str, docsrs: &Value) -> Vec<DocsrsBuild> {
+    let strings = |key: &str| -> Vec<String> {
+        docsrs[key]
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut cargo_args: Vec<String> = ["doc", "--no-deps", "-p", crate_name, "--color=always"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let features = strings("features");
+    if !features.is_empty() {
+        cargo_args.extend(["--features".to_string(), features.join(",")]);
+    }
+    if docsrs["all-features"].as_bool().unwrap_or_default() {
+        cargo_args.push("--all-features".to_string());
+    }
+    if docsrs["no-default-features"].as_bool().unwrap_or_default() {
+        cargo_args.push("--no-default-features".to_string());
+    }
+    cargo_args.extend(strings("cargo-args"));
+    let with_cfg = |args: Vec<String>| -> String {
+        std::iter::once(DOCSRS_CFG.to_string())
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let rustflags = with_cfg(strings("rustc-args"));
+    let rustdocflags = with_cfg(strings("rustdoc-args"));
+    // the default target is built first, then the other targets
+    let mut targets: Vec<String> = docsrs["default-target"]
+        .as_str()
+        .map(|t| vec![t.to_string()])
+        .unwrap_or_default();
+    for target in strings("targets") {
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        return vec![DocsrsBuild {
+            target: None,
+            cargo_args,
+            rustflags,
+            rustdocflags,
+        }];
+    }
+    targets
+        .into_iter()
+        .map(|target| {
+            let mut cargo_args = cargo_args.clone();
+            cargo_args.extend(["--target".to_string(), target.clone()]);
+            DocsrsBuild {
+                target: Some(target),
+                cargo_args,
+                rustflags: rustflags.clone(),
+                rustdocflags: rustdocflags.clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_get_docsrs_builds_without_metadata() {
+        let builds = get_docsrs_builds("my-crate", &Value::Null);
+        assert_eq!(
+            builds,
+            vec![DocsrsBuild {
+                target: None,
+                cargo_args: args(&["doc", "--no-deps", "-p", "my-crate", "--color=always"]),
+                rustflags: "--cfg docsrs".to_string(),
+                rustdocflags: "--cfg docsrs".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_get_docsrs_builds_with_metadata() {
+        let docsrs = json!({
+            "features": ["std", "serde"],
+            "no-default-features": true,
+            "default-target": "x86_64-unknown-linux-gnu",
+            "targets": ["wasm32-unknown-unknown", "x86_64-unknown-linux-gnu"],
+            "rustc-args": ["--cfg", "my_cfg"],
+            "rustdoc-args": ["--generate-link-to-definition"],
+        });
+        let builds = get_docsrs_builds("my-crate", &docsrs);
+        let expected_args = |target: &str| {
+            args(&[
+                "doc",
+                "--no-deps",
+                "-p",
+                "my-crate",
+                "--color=always",
+                "--features",
+                "std,serde",
+                "--no-default-features",
+                "--target",
+                target,
+            ])
+        };
+        assert_eq!(
+            builds,
+            vec![
+                DocsrsBuild {
+                    target: Some("x86_64-unknown-linux-gnu".to_string()),
+                    cargo_args: expected_args("x86_64-unknown-linux-gnu"),
+                    rustflags: "--cfg docsrs --cfg my_cfg".to_string(),
+                    rustdocflags: "--cfg docsrs --generate-link-to-definition".to_string(),
+                },
+                DocsrsBuild {
+                    target: Some("wasm32-unknown-unknown".to_string()),
+                    cargo_args: expected_args("wasm32-unknown-unknown"),
+                    rustflags: "--cfg docsrs --cfg my_cfg".to_string(),
+                    rustdocflags: "--cfg docsrs --generate-link-to-definition".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -2,7 +2,10 @@ use anyhow::{Ok, Result};
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS,
+    commands::{
+        check::{run_package_metadata, run_workspace_deps},
+        WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS,
+    },
     endgroup, group,
     prelude::{Context, Environment},
     utils::{
@@ -38,7 +41,13 @@ pub fn handle_command(
             FixSubCommand::Audit => run_audit(),
             FixSubCommand::Format => run_format(&args.target, &args.exclude, &args.only),
             FixSubCommand::Lint => run_lint(&args.target, &args.exclude, &args.only),
+            FixSubCommand::PackageMetadata => {
+                run_package_metadata(&args.target, &args.exclude, &args.only, true, true)
+            }
             FixSubCommand::Typos => run_typos(),
+            FixSubCommand::WorkspaceDeps => {
+                run_workspace_deps(&args.target, &args.exclude, &args.only, true, true)
+            }
             FixSubCommand::All => FixSubCommand::iter()
                 .filter(|c| *c != FixSubCommand::All)
                 .try_for_each(|c| {
@@ -69,6 +78,7 @@ pub(crate) fn run_audit() -> anyhow::Result<()> {
         None,
         None,
         "Audit check execution failed",
+        None,
     )?;
     endgroup!();
     Ok(())
@@ -92,7 +102,7 @@ fn run_format(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> Re
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
@@ -148,7 +158,7 @@ fn run_lint(target: &Target, excluded: &Vec<String>, only: &Vec<String>) -> anyh
         }
         Target::Crates | Target::Examples => {
             let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
+                Target::Crates => get_workspace_members(WorkspaceMemberType::AllCrates),
                 Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
                 _ => unreachable!(),
             };
@@ -197,6 +207,7 @@ pub(crate) fn run_typos() -> anyhow::Result<()> {
         None,
         None,
         "Some typos have been found and cannot be fixed.",
+        None,
     )?;
     endgroup!();
     Ok(())
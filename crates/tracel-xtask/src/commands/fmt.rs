@@ -0,0 +1,118 @@
+use std::{
+    collections::BTreeSet,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Ok};
+use regex::Regex;
+
+use crate::{
+    endgroup, group, group_info,
+    prelude::{Context, Environment},
+    utils::{
+        process::{run_process, spawn_child, wait_child_output},
+        script::plan_command,
+    },
+};
+
+#[tracel_xtask_macros::declare_command_args(None, FmtSubCommand)]
+pub struct FmtCmdArgs {}
+
+pub fn handle_command(args: FmtCmdArgs, _env: Environment, _ctx: Context) -> anyhow::Result<()> {
+    let command = match (args.check, &args.command) {
+        (true, Some(FmtSubCommand::Write)) => {
+            return Err(anyhow!("--check cannot be used with the write subcommand."))
+        }
+        (true, _) => FmtSubCommand::Check,
+        (false, _) => args.get_command(),
+    };
+    match command {
+        FmtSubCommand::Check => run_fmt_check(),
+        FmtSubCommand::Write => run_fmt_write(),
+    }
+}
+
+fn run_fmt_write() -> anyhow::Result<()> {
+    group!("Format Workspace");
+    run_process(
+        "cargo",
+        &["fmt", "--all"],
+        None,
+        None,
+        "Workspace format failed",
+        None,
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+fn run_fmt_check() -> anyhow::Result<()> {
+    group!("Format Check Workspace");
+    let args = ["fmt", "--all", "--", "--check", "--color=always"];
+    group_info!("Command line: cargo {}", args.join(" "));
+    let mut command = Command::new("cargo");
+    command.args(args);
+    if plan_command(&command) {
+        endgroup!();
+        return Ok(());
+    }
+    // stdout is captured to report the files which are not formatted
+    let output = spawn_child(command.stdout(Stdio::piped()))
+        .and_then(wait_child_output)
+        .map_err(|e| anyhow!("Failed to execute cargo fmt: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    print!("{stdout}");
+    endgroup!();
+    if output.status.success() {
+        return Ok(());
+    }
+    let files = parse_unformatted_files(&stdout);
+    if files.is_empty() {
+        return Err(anyhow!("Workspace format check failed"));
+    }
+    Err(anyhow!(
+        "The following files are not formatted, run 'cargo xtask fmt' to format them:\n{}",
+        files.join("\n")
+    ))
+}
+
+/// Returns the sorted files of the `Diff in <FILE>:<LINE>:` and `Diff in <FILE> at line <LINE>:`
+/// lines reported by `rustfmt --check`.
+fn parse_unformatted_files(output: &str) -> Vec<String> {
+    let re =
+        Regex::new(r"^Diff in (.+?)(?::\d+:| at line \d+:)").expect("should compile diff regex");
+    output
+        .lines()
+        .map(crate::utils::process::remove_ansi_codes)
+        .filter_map(|line| re.captures(&line).map(|caps| caps[1].to_string()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unformatted_files() {
+        let output = "\
+Diff in /repo/src/main.rs:12:
+ fn main() {
+-    let x=1;
++    let x = 1;
+ }
+Diff in /repo/src/main.rs:40:
+Diff in /repo/crates/lib/src/lib.rs at line 3:
+\x1b[1mDiff in /repo/src/utils.rs:7:\x1b[0m
+";
+        assert_eq!(
+            parse_unformatted_files(output),
+            vec![
+                "/repo/crates/lib/src/lib.rs",
+                "/repo/src/main.rs",
+                "/repo/src/utils.rs"
+            ]
+        );
+    }
+}
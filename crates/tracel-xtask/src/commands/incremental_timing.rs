@@ -0,0 +1,183 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{anyhow, Ok};
+
+use crate::{
+    config::{get_config, Config},
+    endgroup, group,
+    prelude::{Context, Environment},
+    utils::{
+        process::run_process,
+        time::parse_duration,
+        workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
+    },
+};
+
+#[tracel_xtask_macros::declare_command_args(None, None)]
+pub struct IncrementalTimingCmdArgs {}
+
+pub fn handle_command(
+    args: IncrementalTimingCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    let config = get_config();
+    let threshold = args
+        .threshold
+        .as_deref()
+        .or(config.get_str("incremental-timing.threshold"))
+        .map(parse_duration)
+        .transpose()
+        .map_err(|e| anyhow!("Invalid incremental rebuild threshold: {e}"))?;
+    let members: Vec<WorkspaceMember> = get_workspace_members(WorkspaceMemberType::AllCrates)
+        .into_iter()
+        .filter(|member| args.crates.is_empty() || args.crates.contains(&member.name))
+        .collect();
+    if members.is_empty() {
+        return Err(anyhow!("No crate to touch."));
+    }
+    let mut cargo_args = vec!["build", "--workspace", "--color=always"];
+    if args.release {
+        cargo_args.push("--release");
+    }
+
+    group!("Incremental Timing: full build");
+    run_process(
+        "cargo",
+        &cargo_args,
+        None,
+        None,
+        "Failed to build the workspace",
+        None,
+    )?;
+    endgroup!();
+
+    let mut timings = vec![];
+    for member in &members {
+        let file = get_touched_file(config, member);
+        group!("Incremental Timing: touch {}", file.display());
+        touch(&file)?;
+        let start = Instant::now();
+        run_process(
+            "cargo",
+            &cargo_args,
+            None,
+            None,
+            &format!(
+                "Failed to rebuild the workspace after touching {}",
+                file.display()
+            ),
+            None,
+        )?;
+        timings.push((member.name.clone(), file, start.elapsed()));
+        endgroup!();
+    }
+
+    group!("Incremental Timing");
+    for (name, file, duration) in &timings {
+        info!(
+            "{:>8.2}s  {} ({})",
+            duration.as_secs_f64(),
+            name,
+            file.display()
+        );
+    }
+    endgroup!();
+    if let Some(threshold) = threshold {
+        let regressions = find_regressions(&timings, threshold);
+        if !regressions.is_empty() {
+            return Err(anyhow!(
+                "Incremental rebuilds exceed the threshold of {}s after touching: {}",
+                threshold.as_secs(),
+                regressions.join(", ")
+            ));
+        }
+        info!(
+            "All incremental rebuilds are within the threshold of {}s.",
+            threshold.as_secs()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the file touched to trigger the incremental rebuild of the passed crate, it is read
+/// from the `incremental-timing.touched-files` table of the `xtask.toml` file, relative to the
+/// crate directory, and defaults to the library root or else to the binary root.
+fn get_touched_file(config: &Config, member: &WorkspaceMember) -> PathBuf {
+    let dir = Path::new(&member.path);
+    let configured = config.get_str(&format!("incremental-timing.touched-files.{}", member.name));
+    match configured {
+        Some(file) => dir.join(file),
+        None if dir.join("src/lib.rs").exists() => dir.join("src/lib.rs"),
+        None => dir.join("src/main.rs"),
+    }
+}
+
+/// Update the modification time of the passed file so that cargo rebuilds its crate.
+fn touch(file: &Path) -> anyhow::Result<()> {
+    std::fs::File::options()
+        .write(true)
+        .open(file)
+        .and_then(|f| f.set_modified(SystemTime::now()))
+        .map_err(|e| anyhow!("Cannot touch {} ({e})", file.display()))
+}
+
+/// Returns the names of the crates whose incremental rebuild is longer than the threshold.
+fn find_regressions(timings: &[(String, PathBuf, Duration)], threshold: Duration) -> Vec<String> {
+    timings
+        .iter()
+        .filter(|(_, _, duration)| *duration > threshold)
+        .map(|(name, _, _)| name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::workspace::CrateKind;
+    use rstest::rstest;
+
+    fn member(name: &str, path: &str) -> WorkspaceMember {
+        WorkspaceMember {
+            name: name.to_string(),
+            path: path.to_string(),
+            features: vec![],
+            kind: CrateKind::Library,
+            publishable: true,
+        }
+    }
+
+    #[rstest]
+    #[case::configured("parser", "/repo/crates/parser/src/grammar.rs")]
+    #[case::binary_root("cli", "/repo/crates/cli/src/main.rs")]
+    fn test_get_touched_file(#[case] name: &str, #[case] expected: &str) {
+        let config =
+            Config::parse("[incremental-timing.touched-files]\nparser = \"src/grammar.rs\"\n")
+                .expect("config should be valid");
+        let member = member(name, &format!("/repo/crates/{name}"));
+        assert_eq!(get_touched_file(&config, &member), PathBuf::from(expected));
+    }
+
+    #[test]
+    fn test_find_regressions() {
+        let timings = vec![
+            (
+                "core".to_string(),
+                PathBuf::from("src/lib.rs"),
+                Duration::from_secs(12),
+            ),
+            (
+                "cli".to_string(),
+                PathBuf::from("src/main.rs"),
+                Duration::from_secs(3),
+            ),
+        ];
+        assert_eq!(
+            find_regressions(&timings, Duration::from_secs(10)),
+            vec!["core"]
+        );
+    }
+}
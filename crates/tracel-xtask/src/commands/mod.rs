@@ -1,13 +1,24 @@
+pub mod api_snapshot;
+pub mod bench;
+pub mod bench_ids;
 pub mod build;
 pub mod bump;
+pub mod changelog_lint;
 pub mod check;
+pub mod clippy;
+pub mod compare;
 pub mod compile;
 pub mod coverage;
+pub mod critical_path;
 pub mod dependencies;
 pub mod doc;
 pub mod docker;
+pub mod docsrs_check;
 pub mod fix;
+pub mod fmt;
+pub mod incremental_timing;
 pub mod publish;
+pub mod readme_sync;
 pub mod test;
 pub mod validate;
 pub mod vulnerabilities;
@@ -16,6 +27,8 @@ pub mod vulnerabilities;
 use clap::ValueEnum;
 use strum::{Display, EnumIter, EnumString};
 
+use crate::logging::{end_groups_to, get_open_group_count};
+
 pub const CARGO_NIGHTLY_MSG: &str = "You must use 'cargo +nightly' to run nightly checks.
 Install a nightly toolchain with 'rustup toolchain install nightly'.";
 pub const WARN_IGNORED_EXCLUDE_AND_ONLY_ARGS: &str =
@@ -34,3 +47,63 @@ pub enum Profile {
     Debug,
     Release,
 }
+
+/// Run the passed step of a command. Its error is returned unless `keep_going` is set, the step is
+/// then added to the failed steps and the groups it left open are closed.
+pub(crate) fn run_step(
+    name: &str,
+    keep_going: bool,
+    failures: &mut Vec<String>,
+    step: impl FnOnce() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let open_groups = get_open_group_count();
+    if let Err(e) = step() {
+        if !keep_going {
+            return Err(e);
+        }
+        // close the groups left open by the failure
+        end_groups_to(open_groups);
+        error!("{e}");
+        failures.push(name.to_string());
+    }
+    Ok(())
+}
+
+/// Parse the passed command line arguments, without the program name, into the arguments of a
+/// command. Used by the tests of the commands.
+#[cfg(test)]
+pub(crate) fn try_parse_cmd_args<T: clap::Args>(args: &[&str]) -> Result<T, clap::Error> {
+    #[derive(clap::Parser)]
+    struct Cli<T: clap::Args> {
+        #[command(flatten)]
+        args: T,
+    }
+    <Cli<T> as clap::Parser>::try_parse_from(std::iter::once("xtask").chain(args.iter().copied()))
+        .map(|cli| cli.args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::stop(false, vec![], Some("'format' failed"))]
+    #[case::keep_going(true, vec!["format"], None)]
+    fn test_run_step(
+        #[case] keep_going: bool,
+        #[case] expected_failures: Vec<&str>,
+        #[case] expected_error: Option<&str>,
+    ) {
+        let mut failures = vec![];
+        let result = run_step("format", keep_going, &mut failures, || {
+            Err(anyhow!("'format' failed"))
+        });
+        assert_eq!(failures, expected_failures);
+        assert_eq!(
+            result.err().map(|e| e.to_string()).as_deref(),
+            expected_error
+        );
+    }
+}
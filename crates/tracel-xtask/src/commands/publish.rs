@@ -1,20 +1,43 @@
-use std::{env, process::Command, str};
+use std::{env, process::Command, str, thread, time::Duration};
 
 use anyhow::{anyhow, Ok};
 
 use crate::{
     endgroup, group,
     prelude::{Context, Environment},
-    utils::{cargo::parse_cargo_search_output, process::run_process},
+    utils::{
+        cargo::parse_cargo_search_output,
+        process::run_process,
+        prompt::ask_once,
+        script::ensure_not_planned,
+        workspace::{get_workspace_members_topo, WorkspaceMemberType},
+    },
 };
 
 // Crates.io API token
 const CRATES_IO_API_TOKEN: &str = "CRATES_IO_API_TOKEN";
+// Polling of crates.io until a published version is available
+const AVAILABILITY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const AVAILABILITY_POLL_ATTEMPTS: u32 = 60;
 
 #[tracel_xtask_macros::declare_command_args(None, None)]
 pub struct PublishCmdArgs {
-    /// The name of the crate to publish on crates.io
-    name: String,
+    /// The name of the crate to publish on crates.io, all the publishable crates of the workspace
+    /// are published in dependency order if omitted.
+    name: Option<String>,
+    /// Only run 'cargo publish --dry-run' (true or false), defaults to true when all the crates
+    /// are published and to false when a single crate is published.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    dry_run: Option<bool>,
+    /// The crates.io API token, defaults to the CRATES_IO_API_TOKEN environment variable.
+    #[arg(long, value_name = "TOKEN")]
+    token: Option<String>,
 }
 
 pub fn handle_command(
@@ -22,29 +45,103 @@ pub fn handle_command(
     _env: Environment,
     _ctx: Context,
 ) -> anyhow::Result<()> {
-    let crate_name = args.name;
+    match &args.name {
+        Some(crate_name) => {
+            let dry_run = args.dry_run.unwrap_or(false);
+            if !dry_run {
+                ensure_not_planned("publish")?;
+            }
+            publish_crate(crate_name, dry_run, || get_token(&args))?;
+            Ok(())
+        }
+        None => publish_workspace(&args),
+    }
+}
+
+/// Publish the publishable crates of the workspace in dependency order, waiting for each
+/// published crate to be available on crates.io before publishing its dependents.
+fn publish_workspace(args: &PublishCmdArgs) -> anyhow::Result<()> {
+    let dry_run = args.dry_run.unwrap_or(true);
+    let crates: Vec<String> = get_workspace_members_topo(WorkspaceMemberType::AllCrates)?
+        .into_iter()
+        .filter(|member| member.publishable)
+        .map(|member| member.name)
+        .collect();
+    if crates.is_empty() {
+        info!("No publishable crate in the workspace.");
+        return Ok(());
+    }
+    info!("Publication order: {}", crates.join(", "));
+    if !dry_run {
+        ensure_not_planned("publish")?;
+    }
+    // the publication is confirmed and the token retrieved once a crate is not up to date
+    let mut token: Option<String> = None;
+    for crate_name in &crates {
+        let confirmed_token = || {
+            if let Some(token) = &token {
+                return Ok(token.clone());
+            }
+            if !ask_once(&format!(
+                "This will publish the crates {} to crates.io.",
+                crates.join(", ")
+            )) {
+                return Err(anyhow!("Publication aborted."));
+            }
+            Ok(token.insert(get_token(args)?).clone())
+        };
+        if let Some(version) = publish_crate(crate_name, dry_run, confirmed_token)? {
+            wait_for_version(
+                crate_name,
+                &version,
+                remote_version,
+                AVAILABILITY_POLL_ATTEMPTS,
+                AVAILABILITY_POLL_INTERVAL,
+            )?;
+        }
+    }
+    Ok(())
+}
 
-    group!("Publishing crate '{}'...", &crate_name);
+/// Publish the passed crate if its local version is not on crates.io yet, only a dry run is
+/// performed if `dry_run` is true. The token is only retrieved when the crate is published for
+/// real. Returns the version published for real, if any.
+fn publish_crate(
+    crate_name: &str,
+    dry_run: bool,
+    token: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<Option<String>> {
+    group!("Publishing crate '{}'...", crate_name);
     // Retrieve local version for crate
-    let local_version = local_version(&crate_name)?;
+    let local_version = local_version(crate_name)?;
     info!("Local version: {local_version}");
     // Retrieve remote version for crate if it exists
-    match remote_version(&crate_name)? {
+    match remote_version(crate_name)? {
         Some(remote_version) => {
             info!("Found remote version: {remote_version}");
             // Early return if we don't need to publish the crate
             if local_version == remote_version {
                 info!("Remote version is up to date, skipping publishing!");
-                return Ok(());
+                endgroup!();
+                return Ok(None);
             }
         }
         None => info!("This is the first version to be published on crates.io!"),
     }
     // Publish the crate
-    publish(crate_name)?;
+    let token = if dry_run { None } else { Some(token()?) };
+    publish(crate_name, token.as_deref())?;
     endgroup!();
+    Ok(token.map(|_| local_version))
+}
 
-    Ok(())
+fn get_token(args: &PublishCmdArgs) -> anyhow::Result<String> {
+    match &args.token {
+        Some(token) => Ok(token.clone()),
+        None => env::var(CRATES_IO_API_TOKEN).map_err(|_| {
+            anyhow!("Failed to retrieve the crates.io API token, pass --token or set {CRATES_IO_API_TOKEN}.")
+        }),
+    }
 }
 
 // Obtain local crate version
@@ -87,26 +184,76 @@ fn remote_version(crate_name: &str) -> anyhow::Result<Option<String>> {
     Ok(None)
 }
 
-fn publish(crate_name: String) -> anyhow::Result<()> {
+fn publish(crate_name: &str, token: Option<&str>) -> anyhow::Result<()> {
     // Perform dry-run to ensure everything is good for publishing
     run_process(
         "cargo",
-        &["publish", "-p", &crate_name, "--dry-run"],
+        &["publish", "-p", crate_name, "--dry-run"],
         None,
         None,
-        &format!("Publish dry run failed for crate '{}'.", &crate_name),
+        &format!("Publish dry run failed for crate '{}'.", crate_name),
+        None,
     )?;
 
-    let crates_io_token =
-        env::var(CRATES_IO_API_TOKEN).expect("Failed to retrieve the crates.io API token");
+    let Some(crates_io_token) = token else {
+        return Ok(());
+    };
     // Actually publish the crate
     let status = Command::new("cargo")
-        .env("CRATES_IO_API_TOKEN", crates_io_token.clone())
-        .args(["publish", "-p", &crate_name, "--token", &crates_io_token])
+        .env("CRATES_IO_API_TOKEN", crates_io_token)
+        .args(["publish", "-p", crate_name, "--token", crates_io_token])
         .status()
         .map_err(|e| anyhow!("Failed to execute cargo publish: {}", e))?;
     if !status.success() {
-        return Err(anyhow!("Publish failed for crate '{}'.", &crate_name));
+        return Err(anyhow!("Publish failed for crate '{}'.", crate_name));
     }
     Ok(())
 }
+
+/// Wait until the passed version of the crate is returned by the passed retrieval function,
+/// trying the passed number of attempts separated by the passed interval.
+fn wait_for_version(
+    crate_name: &str,
+    version: &str,
+    mut retrieve_version: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+    attempts: u32,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    for attempt in 1..=attempts {
+        if retrieve_version(crate_name)?.as_deref() == Some(version) {
+            info!("Version {version} of '{crate_name}' is available on crates.io.");
+            return Ok(());
+        }
+        if attempt < attempts {
+            info!(
+                "Waiting for version {version} of '{crate_name}' to be available on crates.io..."
+            );
+            thread::sleep(interval);
+        }
+    }
+    Err(anyhow!(
+        "Version {version} of '{crate_name}' is still not available on crates.io."
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::available_at_once(&[Some("1.0.0")], true)]
+    #[case::available_later(&[None, Some("0.9.0"), Some("1.0.0")], true)]
+    #[case::never_available(&[Some("0.9.0"), Some("0.9.0"), Some("0.9.0")], false)]
+    fn test_wait_for_version(#[case] versions: &[Option<&str>], #[case] available: bool) {
+        let mut attempts = versions.iter();
+        let result = wait_for_version(
+            "my-crate",
+            "1.0.0",
+            |_| Ok(attempts.next().copied().flatten().map(str::to_string)),
+            3,
+            Duration::ZERO,
+        );
+        assert_eq!(result.is_ok(), available);
+    }
+}
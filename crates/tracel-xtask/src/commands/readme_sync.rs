@@ -0,0 +1,237 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, Ok};
+use regex::{Captures, Regex};
+use strum::IntoEnumIterator;
+
+use crate::{
+    endgroup, group, group_info,
+    prelude::{Context, Environment},
+    utils::workspace::{get_workspace_members, get_workspace_metadata, WorkspaceMemberType},
+};
+
+use super::Target;
+
+#[tracel_xtask_macros::declare_command_args(Target, None)]
+pub struct ReadmeSyncCmdArgs {}
+
+/// A version reference in a README which differs from the crate version.
+#[derive(Debug, PartialEq)]
+struct VersionMismatch {
+    line: usize,
+    found: String,
+    expected: String,
+}
+
+pub fn handle_command(
+    args: ReadmeSyncCmdArgs,
+    _env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
+    run_readme_sync(&args.target, &args)
+}
+
+fn run_readme_sync(target: &Target, args: &ReadmeSyncCmdArgs) -> anyhow::Result<()> {
+    match target {
+        // a README is always defined per crate so the workspace target syncs all the crates
+        Target::Workspace | Target::Crates | Target::Examples => {
+            let members = match target {
+                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
+                _ => get_workspace_members(WorkspaceMemberType::AllCrates),
+            };
+            let packages = get_packages_readme_and_version()?;
+            let mut outdated = vec![];
+            for member in members {
+                if args.exclude.contains(&member.name)
+                    || (!args.only.is_empty() && !args.only.contains(&member.name))
+                {
+                    group_info!("Skip '{}' because it has been excluded!", member.name);
+                    continue;
+                }
+                let Some((Some(readme), version)) = packages.get(&member.name) else {
+                    group_info!("Skip '{}' because it has no README.", member.name);
+                    continue;
+                };
+                group!("README Sync: {}", member.name);
+                if !sync_member_readme(&member.name, readme, version, args.fix)? {
+                    outdated.push(member.name.clone());
+                }
+                endgroup!();
+            }
+            if !outdated.is_empty() {
+                return Err(anyhow!(
+                    "README version references are outdated for: {}. Use --fix to update them.",
+                    outdated.join(", ")
+                ));
+            }
+        }
+        Target::AllPackages => {
+            Target::iter()
+                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
+                .try_for_each(|t| run_readme_sync(&t, args))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the README path, if any, and the version of each workspace package.
+fn get_packages_readme_and_version() -> anyhow::Result<HashMap<String, (Option<String>, String)>> {
+    let metadata = get_workspace_metadata()?;
+    let empty = vec![];
+    Ok(metadata["packages"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|p| {
+            let name = p["name"].as_str()?.to_string();
+            let version = p["version"].as_str()?.to_string();
+            // the readme path is relative to the manifest directory
+            let readme = p["readme"].as_str().and_then(|readme| {
+                let manifest_dir = Path::new(p["manifest_path"].as_str()?).parent()?;
+                Some(manifest_dir.join(readme).to_str()?.to_string())
+            });
+            Some((name, (readme, version)))
+        })
+        .collect())
+}
+
+/// Check or fix the version references of the passed README.
+/// Returns false if the README has outdated version references in check mode.
+fn sync_member_readme(
+    crate_name: &str,
+    readme: &str,
+    version: &str,
+    fix: bool,
+) -> anyhow::Result<bool> {
+    let contents = std::fs::read_to_string(readme)
+        .map_err(|e| anyhow!("Cannot read README {readme} ({e})."))?;
+    let (synced, mismatches) = sync_versions(&contents, crate_name, version);
+    if mismatches.is_empty() {
+        info!("README of '{crate_name}' references version {version}.");
+        return Ok(true);
+    }
+    for m in &mismatches {
+        let msg = format!(
+            "{readme}:{}: found version '{}', expected '{}'",
+            m.line, m.found, m.expected
+        );
+        if fix {
+            info!("{msg}, fixed.");
+        } else {
+            error!("{msg}");
+        }
+    }
+    if fix {
+        std::fs::write(readme, synced)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Returns the passed README contents with its version references of the passed crate updated
+/// and the outdated references that were found. Handled references are install snippets like
+/// `crate = "x.y.z"` or `crate = { version = "x.y.z" }` and shields.io badges like
+/// `https://img.shields.io/badge/crate-x.y.z-blue`.
+/// A snippet using a partial version like `crate = "x.y"` is kept partial.
+fn sync_versions(
+    contents: &str,
+    crate_name: &str,
+    version: &str,
+) -> (String, Vec<VersionMismatch>) {
+    let name = regex::escape(crate_name);
+    // dashes are escaped with a second dash in the shields.io badges
+    let badge_name = regex::escape(&crate_name.replace('-', "--"));
+    let patterns = [
+        format!(r#"(?m)(^\s*{name}\s*=\s*(?:\{{[^}}\n]*?version\s*=\s*)?"[\^~=]?)([0-9][^"]*)(")"#),
+        format!(r"(img\.shields\.io/badge/{badge_name}-v?)([0-9][^-/?)\s]*)(-)"),
+    ];
+    let mut synced = contents.to_string();
+    let mut mismatches = vec![];
+    for pattern in patterns {
+        let re = Regex::new(&pattern).expect("should compile README version regex");
+        let current = synced.clone();
+        synced = re
+            .replace_all(&current, |caps: &Captures| {
+                let found = &caps[2];
+                let expected = expected_version(found, version);
+                if found != expected {
+                    let offset = caps.get(2).map(|m| m.start()).unwrap_or_default();
+                    mismatches.push(VersionMismatch {
+                        line: current[..offset].lines().count().max(1),
+                        found: found.to_string(),
+                        expected: expected.clone(),
+                    });
+                }
+                format!("{}{}{}", &caps[1], expected, &caps[3])
+            })
+            .into_owned();
+    }
+    mismatches.sort_by_key(|m| m.line);
+    (synced, mismatches)
+}
+
+/// Returns the crate version truncated to the number of components of the found version.
+fn expected_version(found: &str, version: &str) -> String {
+    let components = found.split('.').count();
+    version
+        .splitn(3, '.')
+        .take(components)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::snippet_up_to_date("my-crate = \"1.2.3\"", "my-crate = \"1.2.3\"", vec![])]
+    #[case::snippet_outdated("my-crate = \"1.2.0\"", "my-crate = \"1.2.3\"", vec![("1.2.0", "1.2.3")])]
+    #[case::snippet_partial_up_to_date("my-crate = \"1.2\"", "my-crate = \"1.2\"", vec![])]
+    #[case::snippet_partial_outdated("my-crate = \"1.1\"", "my-crate = \"1.2\"", vec![("1.1", "1.2")])]
+    #[case::snippet_caret("my-crate = \"^1.0\"", "my-crate = \"^1.2\"", vec![("1.0", "1.2")])]
+    #[case::snippet_table(
+        "my-crate = { version = \"1.0.0\", features = [\"std\"] }",
+        "my-crate = { version = \"1.2.3\", features = [\"std\"] }",
+        vec![("1.0.0", "1.2.3")]
+    )]
+    #[case::snippet_other_crate("my-crate-ext = \"1.0.0\"", "my-crate-ext = \"1.0.0\"", vec![])]
+    #[case::badge_outdated(
+        "![v](https://img.shields.io/badge/my--crate-1.0.0-blue)",
+        "![v](https://img.shields.io/badge/my--crate-1.2.3-blue)",
+        vec![("1.0.0", "1.2.3")]
+    )]
+    #[case::badge_prefixed(
+        "![v](https://img.shields.io/badge/my--crate-v1.2.3-blue)",
+        "![v](https://img.shields.io/badge/my--crate-v1.2.3-blue)",
+        vec![]
+    )]
+    fn test_sync_versions(
+        #[case] contents: &str,
+        #[case] expected_contents: &str,
+        #[case] expected_mismatches: Vec<(&str, &str)>,
+    ) {
+        let (synced, mismatches) = sync_versions(contents, "my-crate", "1.2.3");
+        assert_eq!(synced, expected_contents);
+        let mismatches: Vec<(&str, &str)> = mismatches
+            .iter()
+            .map(|m| (m.found.as_str(), m.expected.as_str()))
+            .collect();
+        assert_eq!(mismatches, expected_mismatches);
+    }
+
+    #[test]
+    fn test_sync_versions_reports_lines() {
+        let contents = "# my-crate\n\n```toml\nmy-crate = \"1.0.0\"\n```\n";
+        let (_, mismatches) = sync_versions(contents, "my-crate", "1.2.3");
+        assert_eq!(
+            mismatches,
+            vec![VersionMismatch {
+                line: 4,
+                found: "1.0.0".to_string(),
+                expected: "1.2.3".to_string(),
+            }]
+        );
+    }
+}
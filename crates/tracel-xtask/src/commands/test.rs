@@ -1,51 +1,345 @@
-use anyhow::Result;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
 use strum::IntoEnumIterator;
 
 use crate::{
-    commands::WARN_IGNORED_ONLY_ARGS,
-    endgroup, group,
+    commands::{run_step, CARGO_NIGHTLY_MSG, WARN_IGNORED_ONLY_ARGS},
+    config::get_config,
+    endgroup, group, group_info,
+    leak_check::{read_reports, LEAK_CHECK_CFG, LEAK_CHECK_DIR_ENV},
     prelude::{Context, Environment},
+    report::{
+        init_reports, parse_report, parse_test_results, write_reports, TestOutcome, TestResult,
+    },
     utils::{
-        process::{run_process_for_package, run_process_for_workspace},
-        workspace::{get_workspace_members, WorkspaceMember, WorkspaceMemberType},
+        cargo::{append_rustflags, expand_features},
+        process::{
+            apply_clean_env, run_process, run_process_for_package, run_process_for_workspace,
+            set_clean_env, set_process_timeout, spawn_child, wait_child_output,
+        },
+        rustup::{is_current_toolchain_nightly, rustc_get_target_list},
+        script::{enable_listing, plan_command},
+        snapshot::{diff_snapshots, find_owner_member, snapshot_tree, TreeSnapshot},
+        workspace::{get_workspace_metadata, Workspace, WorkspaceMember, WorkspaceMemberType},
     },
 };
 
 use super::Target;
 
+/// Number of retries of failed tests in CI when it is not set in the `xtask.toml` file.
+pub const DEFAULT_CI_RETRIES: u32 = 2;
+/// Configuration key of the number of retries of failed tests in CI.
+pub const RETRIES_CONFIG_KEY: &str = "test.retries";
+
+/// Configuration keys of the commands executed once before and after all the integration tests.
+pub const INTEGRATION_SETUP_CONFIG_KEY: &str = "test.integration.setup";
+pub const INTEGRATION_TEARDOWN_CONFIG_KEY: &str = "test.integration.teardown";
+
+/// Directory of the allocation reports written by the test processes with `--leak-check`.
+const LEAK_CHECK_DIR: &str = "target/leak-check";
+
+/// Files written outside of the target directory which are not reported by `--check-writes`:
+/// the lock file updated by cargo and the coverage profiles of `--enable-coverage`.
+const IGNORED_WRITES: [&str; 2] = ["Cargo.lock", "*.profraw"];
+
+/// Files of the workspace root which affect the build of all the members, a change of one of them
+/// with `--changed-since` tests all the members.
+const WORKSPACE_BUILD_FILES: [&str; 6] = [
+    "Cargo.toml",
+    "Cargo.lock",
+    ".cargo/config.toml",
+    ".cargo/config",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// Configuration table mapping a member to the binaries to build before its integration tests.
+pub const BUILD_PREREQUISITES_CONFIG_KEY: &str = "test.prerequisites";
+
 #[tracel_xtask_macros::declare_command_args(Target, TestSubCommand)]
 pub struct TestCmdArgs {}
 
-pub fn handle_command(args: TestCmdArgs, env: Environment, _ctx: Context) -> anyhow::Result<()> {
+pub fn handle_command(
+    mut args: TestCmdArgs,
+    env: Environment,
+    _ctx: Context,
+) -> anyhow::Result<()> {
     if args.target == Target::Workspace && !args.only.is_empty() {
         warn!("{WARN_IGNORED_ONLY_ARGS}");
+        // also ignored when --serial tests the packages of the workspace one at a time
+        args.only.clear();
+    }
+    // the members are loaded once for all the targets and subcommands
+    let workspace = Workspace::load()?;
+    if !args.packages.is_empty() {
+        if args.target == Target::Workspace {
+            warn!("--target workspace ignores the arguments --package. Use --target all-packages instead.");
+        }
+        let members: Vec<String> = workspace
+            .all_members()
+            .into_iter()
+            .map(|member| member.name.clone())
+            .collect();
+        let unknown = find_unknown_packages(&args.packages, &members);
+        if !unknown.is_empty() {
+            return Err(anyhow!(
+                "Package(s) not found in the workspace: {}",
+                unknown.join(", ")
+            ));
+        }
+    }
+    if let Some(gitref) = &args.changed_since {
+        if args.target == Target::Workspace {
+            warn!("--target workspace ignores the argument --changed-since. Use --target all-packages instead.");
+        } else if let Some(changed) = get_changed_members(gitref, &workspace) {
+            let affected = workspace.with_dependents(&changed);
+            args.packages = restrict_packages(&args.packages, &affected);
+            if args.packages.is_empty() {
+                info!("No member is affected by the changes since '{gitref}', there is nothing to test.");
+                return Ok(());
+            }
+            info!(
+                "Members affected by the changes since '{gitref}': {}",
+                args.packages.join(", ")
+            );
+        }
+    }
+    if let Some(triple) = &args.target_triple {
+        check_target_triple(triple, &rustc_get_target_list());
     }
     if !check_environment(&args, &env) {
         std::process::exit(1);
     }
+    args.retries = Some(resolve_retries(args.retries, is_ci(&env)));
+    if args.shuffle && args.shuffle_seed.is_none() {
+        // the seed is drawn once so that all the members share the same order
+        let seed = rand::random::<u64>();
+        info!("Shuffling tests with seed {seed}, use '--shuffle-seed {seed}' to reproduce this order.");
+        args.shuffle_seed = Some(seed);
+    }
+    if !args.cfgs.is_empty() {
+        append_rustflags(&get_cfg_flags(&args.cfgs)?);
+    }
+    if args.list {
+        // the commands are planned instead of executed so that the list follows the real run
+        enable_listing();
+        return run_tests(&args, &workspace);
+    }
+    let leak_check_dir = if args.leak_check {
+        Some(setup_leak_check()?)
+    } else {
+        None
+    };
+    if !args.reports.is_empty() {
+        let reports = args
+            .reports
+            .iter()
+            .map(|report| parse_report(report))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        init_reports(reports);
+    }
+    set_process_timeout(args.timeout.map(Duration::from_secs));
+    set_clean_env(args.clean_env.then(|| args.env_passthrough.clone()));
+    let writes_snapshot = if args.check_writes || args.deny_writes {
+        Some(snapshot_workspace()?)
+    } else {
+        None
+    };
+    let result = run_tests(&args, &workspace);
+    set_process_timeout(None);
+    set_clean_env(None);
+    // reports and writes are written even if the tests failed
+    let result = result.and(write_reports());
+    let writes_result = match writes_snapshot {
+        Some((excluded, before)) => check_writes(&excluded, &before, args.deny_writes, &workspace),
+        None => Ok(()),
+    };
+    let result = result.and(writes_result);
+    match leak_check_dir {
+        Some(dir) => result.and_then(|_| check_leaks(&dir, args.leak_threshold)),
+        None => result,
+    }
+}
+
+fn run_tests(args: &TestCmdArgs, workspace: &Workspace) -> anyhow::Result<()> {
+    match args.shuffle_seed {
+        _ if args.order_check => run_order_check(args, workspace),
+        None => run_test_subcommand(args, workspace),
+        Some(_) if !is_current_toolchain_nightly() => {
+            Err(anyhow!("Test shuffling is unstable. {CARGO_NIGHTLY_MSG}"))
+        }
+        Some(seed) => run_test_subcommand(args, workspace).inspect_err(|_| {
+            error!("Shuffled tests failed with seed {seed}, use '--shuffle-seed {seed}' to reproduce this order.");
+        }),
+    }
+}
+
+/// Returns the directories excluded from the write check and the snapshot of the other files of
+/// the workspace.
+fn snapshot_workspace() -> anyhow::Result<(Vec<PathBuf>, TreeSnapshot)> {
+    let metadata = get_workspace_metadata()?;
+    let mut excluded: Vec<PathBuf> = metadata["target_directory"]
+        .as_str()
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    if let Some(target_dir) = std::env::var_os("CARGO_TARGET_DIR") {
+        excluded.push(std::fs::canonicalize(&target_dir).unwrap_or(target_dir.into()));
+    }
+    let root = std::env::current_dir()?;
+    let snapshot = snapshot_tree(&root, &excluded);
+    Ok((excluded, snapshot))
+}
+
+/// Report the files created or modified in the workspace outside of the target directory since
+/// the passed snapshot, and fail if `deny` is set.
+fn check_writes(
+    excluded: &[PathBuf],
+    before: &TreeSnapshot,
+    deny: bool,
+    workspace: &Workspace,
+) -> anyhow::Result<()> {
+    group!("Write Check");
+    let root = std::env::current_dir()?;
+    let ignored: Vec<glob::Pattern> = IGNORED_WRITES
+        .iter()
+        .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+        .collect();
+    let changes: Vec<_> = diff_snapshots(before, &snapshot_tree(&root, excluded))
+        .into_iter()
+        .filter(|change| {
+            let name = change
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            !ignored.iter().any(|pattern| pattern.matches(&name))
+        })
+        .collect();
+    let members: Vec<WorkspaceMember> = workspace.all_members().into_iter().cloned().collect();
+    for change in &changes {
+        warn!(
+            "{}: {} {}",
+            find_owner_member(&change.path, &members).unwrap_or("workspace"),
+            if change.created {
+                "created"
+            } else {
+                "modified"
+            },
+            change
+                .path
+                .strip_prefix(&root)
+                .unwrap_or(&change.path)
+                .display()
+        );
+    }
+    endgroup!();
+    if changes.is_empty() {
+        info!("The tests did not write outside of the target directory.");
+        return Ok(());
+    }
+    let msg = format!(
+        "The tests wrote {} file(s) outside of the target directory.",
+        changes.len()
+    );
+    if deny {
+        return Err(anyhow!(msg));
+    }
+    warn!("{msg}");
+    Ok(())
+}
+
+/// Returns the rustc flags setting the passed `name` or `name=value` cfgs.
+fn get_cfg_flags(cfgs: &[String]) -> anyhow::Result<Vec<String>> {
+    let mut flags = vec![];
+    for cfg in cfgs {
+        let (name, value) = match cfg.split_once('=') {
+            Some((name, value)) => (name, Some(value.trim_matches('"'))),
+            None => (cfg.as_str(), None),
+        };
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(anyhow!(
+                "Invalid cfg '{cfg}', expected 'name' or 'name=value'."
+            ));
+        }
+        flags.push("--cfg".to_string());
+        flags.push(match value {
+            Some(value) => format!("{name}=\"{value}\""),
+            None => name.to_string(),
+        });
+    }
+    Ok(flags)
+}
+
+/// Enable the counting allocator of the test processes with the leak check cfg and returns the
+/// directory where they write their allocation reports.
+fn setup_leak_check() -> anyhow::Result<PathBuf> {
+    let dir = Path::new(LEAK_CHECK_DIR);
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+    // tests are executed from the directory of their crate so the path must be absolute
+    let dir = std::fs::canonicalize(dir)?;
+    append_rustflags(&["--cfg".to_string(), LEAK_CHECK_CFG.to_string()]);
+    unsafe {
+        std::env::set_var(LEAK_CHECK_DIR_ENV, &dir);
+    }
+    Ok(dir)
+}
+
+/// Report the allocations left by the test processes and fail if one of them left more bytes
+/// than the threshold.
+fn check_leaks(dir: &Path, threshold: u64) -> anyhow::Result<()> {
+    group!("Leak Check");
+    let reports = read_reports(dir)?;
+    if reports.is_empty() {
+        warn!("No allocation report was written, install the counting allocator of 'tracel_xtask::leak_check' in the test binaries.");
+    }
+    let mut leaks = 0;
+    for report in &reports {
+        let msg = format!(
+            "{}: {} bytes in {} allocations not freed at exit",
+            report.executable, report.bytes, report.allocations
+        );
+        if report.bytes > threshold as i64 {
+            error!("{msg}");
+            leaks += 1;
+        } else {
+            info!("{msg}");
+        }
+    }
+    endgroup!();
+    if leaks > 0 {
+        return Err(anyhow!(
+            "{leaks} test process(es) leaked more than {threshold} bytes."
+        ));
+    }
+    Ok(())
+}
+
+fn run_test_subcommand(args: &TestCmdArgs, workspace: &Workspace) -> anyhow::Result<()> {
     match args.get_command() {
-        TestSubCommand::Unit => run_unit(&args.target, &args),
-        TestSubCommand::Integration => run_integration(&args.target, &args),
+        TestSubCommand::Unit => run_unit(&args.target, args, workspace),
+        TestSubCommand::Integration => run_integration(&args.target, args, workspace),
+        TestSubCommand::DocTest => run_doctest(&args.target, args, workspace),
         TestSubCommand::All => TestSubCommand::iter()
             .filter(|c| *c != TestSubCommand::All)
             .try_for_each(|c| {
-                handle_command(
-                    TestCmdArgs {
+                run_test_subcommand(
+                    &TestCmdArgs {
                         command: Some(c),
-                        target: args.target.clone(),
-                        exclude: args.exclude.clone(),
-                        only: args.only.clone(),
-                        threads: args.threads,
-                        test: args.test.clone(),
-                        jobs: args.jobs,
-                        force: args.force,
-                        features: args.features.clone(),
-                        no_default_features: args.no_default_features,
-                        no_capture: args.no_capture,
-                        release: args.release,
+                        ..args.clone()
                     },
-                    env.clone(),
-                    _ctx.clone(),
+                    workspace,
                 )
             }),
     }
@@ -66,15 +360,124 @@ pub fn check_environment(args: &TestCmdArgs, env: &Environment) -> bool {
     true
 }
 
-fn push_optional_args(cmd_args: &mut Vec<String>, args: &TestCmdArgs) {
+/// Return true if the tests run in CI or in production.
+/// The `CI` environment variable is set by most of the CI providers.
+fn is_ci(env: &Environment) -> bool {
+    *env == Environment::Production
+        || std::env::var("CI").is_ok_and(|v| !v.is_empty() && v != "false")
+}
+
+/// Warn if the passed target triple is not a target known by rustc, it is still passed to cargo
+/// as it can be a custom target.
+fn check_target_triple(triple: &str, known: &[String]) -> bool {
+    // the list is empty if rustc cannot be queried
+    let is_known = known.is_empty() || known.iter().any(|t| t == triple);
+    if !is_known {
+        warn!(
+            "'{triple}' is not a target known by rustc, it is passed to cargo as a custom target."
+        );
+    }
+    is_known
+}
+
+/// Returns the number of retries of failed tests. An explicit value always wins, otherwise
+/// tests are retried in CI only so that flaky tests stay visible locally.
+fn resolve_retries(retries: Option<u32>, ci: bool) -> u32 {
+    match retries {
+        Some(retries) => retries,
+        None if ci => get_config()
+            .get_integer(RETRIES_CONFIG_KEY)
+            .and_then(|r| u32::try_from(r).ok())
+            .unwrap_or(DEFAULT_CI_RETRIES),
+        None => 0,
+    }
+}
+
+/// Execute the passed tests runner and retry it as long as it fails and retries are left. Only
+/// the integration tests are retried, the unit tests and doc tests are expected to be
+/// deterministic.
+fn run_with_retries(
+    args: &TestCmdArgs,
+    mut run: impl FnMut() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let retries = args.retries.unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        match run() {
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                warn!("{e}, retrying ({attempt}/{retries})...");
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Resolve the features to enable for the passed member by expanding the glob patterns
+/// (i.e. `gpu-*`) against the features it declares.
+fn resolve_member_features(
+    args: &TestCmdArgs,
+    member: &WorkspaceMember,
+) -> anyhow::Result<Vec<String>> {
+    let Some(requested) = &args.features else {
+        return Ok(vec![]);
+    };
+    let (features, unmatched) = expand_features(requested, &member.features);
+    if !unmatched.is_empty() {
+        // a crate explicitly selected with --only is expected to declare the features
+        if args.only.contains(&member.name) {
+            return Err(anyhow!(
+                "Feature pattern(s) '{}' match no feature of crate '{}'.",
+                unmatched.join(","),
+                member.name
+            ));
+        }
+        group_info!(
+            "Feature pattern(s) '{}' match no feature of crate '{}', skipping them.",
+            unmatched.join(","),
+            member.name
+        );
+    }
+    Ok(features)
+}
+
+/// Resolve the features to enable for the whole workspace by expanding the glob patterns against
+/// the features declared by all the workspace members.
+fn resolve_workspace_features(
+    args: &TestCmdArgs,
+    workspace: &Workspace,
+) -> anyhow::Result<Vec<String>> {
+    let Some(requested) = &args.features else {
+        return Ok(vec![]);
+    };
+    if !requested.iter().any(|f| f.contains(['*', '?', '['])) {
+        return Ok(requested.clone());
+    }
+    let declared: Vec<String> = workspace
+        .all_members()
+        .into_iter()
+        .flat_map(|m| m.features.iter().cloned())
+        .collect();
+    let (features, unmatched) = expand_features(requested, &declared);
+    if !unmatched.is_empty() {
+        return Err(anyhow!(
+            "Feature pattern(s) '{}' match no feature in the workspace.",
+            unmatched.join(",")
+        ));
+    }
+    Ok(features)
+}
+
+fn push_optional_args(cmd_args: &mut Vec<String>, args: &TestCmdArgs, features: &[String]) {
     // cargo options
-    if let Some(jobs) = &args.jobs {
+    if let Some(jobs) = args.serial.then_some(1).or(args.jobs) {
         cmd_args.extend(vec!["--jobs".to_string(), jobs.to_string()]);
     };
-    if let Some(features) = &args.features {
-        if !features.is_empty() {
-            cmd_args.extend(vec!["--features".to_string(), features.join(",")]);
-        }
+    if !features.is_empty() {
+        cmd_args.extend(vec!["--features".to_string(), features.join(",")]);
+    }
+    if args.all_features {
+        cmd_args.push("--all-features".to_string());
     }
     if args.release {
         cmd_args.push("--release".to_string());
@@ -82,18 +485,233 @@ fn push_optional_args(cmd_args: &mut Vec<String>, args: &TestCmdArgs) {
     if args.no_default_features {
         cmd_args.push("--no-default-features".to_string());
     }
+    if let Some(triple) = &args.target_triple {
+        cmd_args.extend(vec!["--target".to_string(), triple.clone()]);
+    }
     // test harness options
     cmd_args.extend(vec!["--".to_string(), "--color=always".to_string()]);
-    if let Some(threads) = &args.threads {
+    if let Some(threads) = args.serial.then_some(1).or(args.threads) {
         cmd_args.extend(vec!["--test-threads".to_string(), threads.to_string()]);
     };
     if args.no_capture {
         cmd_args.push("--nocapture".to_string());
     }
+    if let Some(seed) = &args.shuffle_seed {
+        cmd_args.extend(vec![
+            "-Zunstable-options".to_string(),
+            "--shuffle-seed".to_string(),
+            seed.to_string(),
+        ]);
+    }
+    // tests always run with the libtest harness so the raw arguments are libtest flags
+    cmd_args.extend(args.test_runner_args.iter().cloned());
+}
+
+/// A binary to build before running the integration tests of a member.
+#[derive(Debug, PartialEq)]
+struct BuildPrerequisite {
+    package: String,
+    bin: Option<String>,
+}
+
+impl BuildPrerequisite {
+    /// Parse a prerequisite of the form `<package>:<bin>`, all the binaries of the package
+    /// are built when the binary name is omitted.
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        let (package, bin) = match value.split_once(':') {
+            Some((package, bin)) => (package.trim(), Some(bin.trim())),
+            None => (value.trim(), None),
+        };
+        if package.is_empty() || bin.is_some_and(str::is_empty) {
+            return Err(anyhow!(
+                "Invalid build prerequisite '{value}', expected '<package>:<bin>' or '<package>'."
+            ));
+        }
+        Ok(Self {
+            package: package.to_string(),
+            bin: bin.map(str::to_string),
+        })
+    }
+}
+
+/// Returns the build prerequisites configured for the passed member in the `xtask.toml` file:
+///
+/// ```toml
+/// [test.prerequisites]
+/// my-client = ["my-server:server"]
+/// ```
+fn get_build_prerequisites(member_name: &str) -> anyhow::Result<Vec<BuildPrerequisite>> {
+    get_config()
+        .get_string_array(&format!("{BUILD_PREREQUISITES_CONFIG_KEY}.{member_name}"))
+        .unwrap_or_default()
+        .iter()
+        .map(|value| BuildPrerequisite::parse(value))
+        .collect()
+}
+
+/// Build the prerequisites of the passed member, fails if any of the builds fails.
+fn run_build_prerequisites(member_name: &str, args: &TestCmdArgs) -> anyhow::Result<()> {
+    let prerequisites = get_build_prerequisites(member_name)?;
+    if prerequisites.is_empty() {
+        return Ok(());
+    }
+    group!("Build Prerequisites: {}", member_name);
+    for prerequisite in prerequisites {
+        let mut cmd_args = vec!["build", "-p", &prerequisite.package];
+        match &prerequisite.bin {
+            Some(bin) => cmd_args.extend(["--bin", bin]),
+            None => cmd_args.push("--bins"),
+        }
+        // build with the same profile so that the tests find the binaries
+        if args.release {
+            cmd_args.push("--release");
+        }
+        cmd_args.extend(["--color", "always"]);
+        run_process(
+            "cargo",
+            &cmd_args,
+            None,
+            None,
+            &format!(
+                "Failed to build prerequisite '{}' of '{}'",
+                prerequisite.bin.as_deref().unwrap_or(&prerequisite.package),
+                member_name
+            ),
+            None,
+        )?;
+    }
+    endgroup!();
+    Ok(())
+}
+
+fn is_member_selected(member_name: &String, args: &TestCmdArgs) -> bool {
+    !args.exclude.contains(member_name) && (args.only.is_empty() || args.only.contains(member_name))
+}
+
+/// Returns the members of the passed crates, examples or all packages target, restricted to the
+/// packages passed with `--package` if any.
+fn get_target_members<'a>(
+    target: &Target,
+    args: &TestCmdArgs,
+    workspace: &'a Workspace,
+) -> Vec<&'a WorkspaceMember> {
+    let member_types = match target {
+        Target::Examples => vec![WorkspaceMemberType::Example],
+        Target::AllPackages => vec![WorkspaceMemberType::AllCrates, WorkspaceMemberType::Example],
+        _ => vec![WorkspaceMemberType::AllCrates],
+    };
+    member_types
+        .into_iter()
+        .flat_map(|member_type| workspace.members(member_type))
+        .filter(|member| is_package_selected(&member.name, &args.packages))
+        .collect()
+}
+
+/// Run the passed test function for each member. The run stops at the first failed member unless
+/// `--keep-going` is set, the failed members are then reported at the end.
+fn run_for_members(
+    members: Vec<&WorkspaceMember>,
+    args: &TestCmdArgs,
+    run: impl Fn(&WorkspaceMember, &TestCmdArgs) -> Result<()>,
+) -> Result<()> {
+    let mut failures = vec![];
+    for member in members {
+        run_step(&member.name, args.keep_going, &mut failures, || {
+            run(member, args)
+        })?;
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} member(s) failed: {}",
+            failures.len(),
+            failures.join(", ")
+        ));
+    }
+    Ok(())
 }
 
-pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
+/// Returns the names of the members owning the files changed since the merge base of the passed
+/// git reference, or None with a warning if the changes cannot be computed so that all the members
+/// are tested.
+fn get_changed_members(gitref: &str, workspace: &Workspace) -> Option<BTreeSet<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--relative", "--merge-base", gitref])
+        .output();
+    let diff = match output {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            warn!(
+                "Cannot compute the changes since '{gitref}' ({}), all the members are tested.",
+                String::from_utf8_lossy(&output.stderr)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+            );
+            return None;
+        }
+        Err(e) => {
+            warn!("Cannot execute git ({e}), all the members are tested.");
+            return None;
+        }
+    };
+    let root = std::env::current_dir().ok()?;
+    let members: Vec<WorkspaceMember> = workspace.all_members().into_iter().cloned().collect();
+    Some(find_changed_members(
+        &String::from_utf8_lossy(&diff),
+        &root,
+        &members,
+    ))
+}
+
+/// Returns the names of the members owning the files of the passed `git diff --name-only` output
+/// whose paths are relative to the passed root. All the members are returned when a build file
+/// of the workspace root has changed.
+fn find_changed_members(diff: &str, root: &Path, members: &[WorkspaceMember]) -> BTreeSet<String> {
+    let changes: Vec<&str> = diff.lines().filter(|line| !line.is_empty()).collect();
+    if let Some(file) = changes
+        .iter()
+        .find(|file| WORKSPACE_BUILD_FILES.contains(file))
+    {
+        info!("The workspace file '{file}' has changed, all the members are affected.");
+        return members.iter().map(|member| member.name.clone()).collect();
+    }
+    changes
+        .iter()
+        .filter_map(|line| find_owner_member(&root.join(line), members))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the passed packages restricted to the affected members, or the affected members if no
+/// package is passed.
+fn restrict_packages(packages: &[String], affected: &BTreeSet<String>) -> Vec<String> {
+    if packages.is_empty() {
+        return affected.iter().cloned().collect();
+    }
+    packages
+        .iter()
+        .filter(|package| affected.contains(*package))
+        .cloned()
+        .collect()
+}
+
+fn is_package_selected(member_name: &String, packages: &[String]) -> bool {
+    packages.is_empty() || packages.contains(member_name)
+}
+
+/// Returns the passed packages which are not members of the workspace.
+fn find_unknown_packages<'a>(packages: &'a [String], members: &[String]) -> Vec<&'a str> {
+    packages
+        .iter()
+        .filter(|package| !members.contains(package))
+        .map(String::as_str)
+        .collect()
+}
+
+pub fn run_unit(target: &Target, args: &TestCmdArgs, workspace: &Workspace) -> Result<()> {
     match target {
+        // packages are tested one at a time instead of with a single workspace invocation
+        Target::Workspace if args.serial => run_unit(&Target::AllPackages, args, workspace)?,
         Target::Workspace => {
             info!("Workspace Unit Tests");
             let test = args.test.as_deref().unwrap_or("");
@@ -110,7 +728,11 @@ pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
             .into_iter()
             .map(|s| s.to_string())
             .collect::<Vec<String>>();
-            push_optional_args(&mut cmd_args, args);
+            push_optional_args(
+                &mut cmd_args,
+                args,
+                &resolve_workspace_features(args, workspace)?,
+            );
             run_process_for_workspace(
                 "cargo",
                 &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
@@ -122,21 +744,12 @@ pub fn run_unit(target: &Target, args: &TestCmdArgs) -> Result<()> {
                 Some("No library found to test for in workspace."),
             )?;
         }
-        Target::Crates | Target::Examples => {
-            let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
-            };
-
-            for member in members {
-                run_unit_test(&member, args)?;
-            }
-        }
-        Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_unit(&t, args))?;
+        Target::Crates | Target::Examples | Target::AllPackages => {
+            run_for_members(
+                get_target_members(target, args, workspace),
+                args,
+                run_unit_test,
+            )?;
         }
     }
     anyhow::Ok(())
@@ -158,7 +771,7 @@ pub fn run_unit_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<(),
     .into_iter()
     .map(|s| s.to_string())
     .collect::<Vec<String>>();
-    push_optional_args(&mut cmd_args, args);
+    push_optional_args(&mut cmd_args, args, &resolve_member_features(args, member)?);
     run_process_for_package(
         "cargo",
         &member.name,
@@ -176,75 +789,691 @@ pub fn run_unit_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<(),
     anyhow::Ok(())
 }
 
-pub fn run_integration(target: &Target, args: &TestCmdArgs) -> anyhow::Result<()> {
+/// Execute the integration tests surrounded by the setup and teardown commands of the
+/// `xtask.toml` file which are executed once for the whole run. The teardown command is
+/// always executed, even if the setup or the tests failed.
+pub fn run_integration(
+    target: &Target,
+    args: &TestCmdArgs,
+    workspace: &Workspace,
+) -> anyhow::Result<()> {
+    let result = run_fixture_command(INTEGRATION_SETUP_CONFIG_KEY, "Setup")
+        .and_then(|_| run_integration_for_target(target, args, workspace));
+    let teardown_result = run_fixture_command(INTEGRATION_TEARDOWN_CONFIG_KEY, "Teardown");
+    // a failure of the tests prevails over a failure of the teardown
+    result.and(teardown_result)
+}
+
+/// Execute the fixture command configured with the passed key, if any.
+fn run_fixture_command(config_key: &str, name: &str) -> anyhow::Result<()> {
+    let Some(command) = get_config()
+        .get_string_array(config_key)
+        .filter(|command| !command.is_empty())
+    else {
+        return Ok(());
+    };
+    group!("Integration Tests {}", name);
+    run_process(
+        &command[0],
+        &command[1..]
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
+        None,
+        None,
+        &format!("Integration tests {} command failed", name.to_lowercase()),
+        None,
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+fn run_integration_for_target(
+    target: &Target,
+    args: &TestCmdArgs,
+    workspace: &Workspace,
+) -> anyhow::Result<()> {
     match target {
+        // packages are tested one at a time instead of with a single workspace invocation
+        Target::Workspace if args.serial => {
+            run_integration_for_target(&Target::AllPackages, args, workspace)?
+        }
         Target::Workspace => {
             info!("Workspace Integration Tests");
+            if let Some(members) = get_config().get_table_keys(BUILD_PREREQUISITES_CONFIG_KEY) {
+                members
+                    .iter()
+                    .filter(|m| !args.exclude.contains(m))
+                    .try_for_each(|m| run_build_prerequisites(m, args))?;
+            }
             let test = args.test.as_deref().unwrap_or("*");
             let mut cmd_args = vec!["test", "--workspace", "--test", test, "--color", "always"]
                 .into_iter()
                 .map(|s| s.to_string())
                 .collect::<Vec<String>>();
-            push_optional_args(&mut cmd_args, args);
+            push_optional_args(
+                &mut cmd_args,
+                args,
+                &resolve_workspace_features(args, workspace)?,
+            );
+            run_with_retries(args, || {
+                run_process_for_workspace(
+                    "cargo",
+                    &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+                    &args.exclude,
+                    Some(r".*target/[^/]+/deps/([^-\s]+)"),
+                    Some("Integration Tests"),
+                    "Workspace Integration Tests failed",
+                    Some("no test target matches pattern"),
+                    Some("No tests found matching the pattern `test_*` in workspace."),
+                )
+            })?;
+        }
+        Target::Crates | Target::Examples | Target::AllPackages => {
+            run_for_members(
+                get_target_members(target, args, workspace),
+                args,
+                run_integration_test,
+            )?;
+        }
+    }
+    anyhow::Ok(())
+}
+
+fn run_integration_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<()> {
+    if is_member_selected(&member.name, args) {
+        run_build_prerequisites(&member.name, args)?;
+    }
+    group!("Integration Tests: {}", &member.name);
+    let mut cmd_args = get_integration_test_args(&member.name, args.test.as_deref());
+    push_optional_args(&mut cmd_args, args, &resolve_member_features(args, member)?);
+    run_with_retries(args, || {
+        run_process_for_package(
+            "cargo",
+            &member.name,
+            &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
+            &args.exclude,
+            &args.only,
+            &format!("Failed to execute integration test for '{}'", &member.name),
+            Some("no test target matches pattern"),
+            Some(&format!(
+                "No integration tests found for '{}'.",
+                &member.name
+            )),
+        )
+    })?;
+    endgroup!();
+    anyhow::Ok(())
+}
+
+pub fn run_doctest(
+    target: &Target,
+    args: &TestCmdArgs,
+    workspace: &Workspace,
+) -> anyhow::Result<()> {
+    match target {
+        // packages are tested one at a time instead of with a single workspace invocation
+        Target::Workspace if args.serial => run_doctest(&Target::AllPackages, args, workspace)?,
+        Target::Workspace => {
+            info!("Workspace Doc Tests");
+            let test = args.test.as_deref().unwrap_or("");
+            let mut cmd_args = vec!["test", "--workspace", "--doc", test, "--color", "always"]
+                .into_iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>();
+            push_optional_args(
+                &mut cmd_args,
+                args,
+                &resolve_workspace_features(args, workspace)?,
+            );
             run_process_for_workspace(
                 "cargo",
                 &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
                 &args.exclude,
-                Some(r".*target/[^/]+/deps/([^-\s]+)"),
-                Some("Integration Tests"),
-                "Workspace Integration Tests failed",
-                Some("no test target matches pattern"),
-                Some("No tests found matching the pattern `test_*` in workspace."),
+                Some(r"^\s*Doc-tests ([^\s]+)"),
+                Some("Doc Tests"),
+                "Workspace Doc Tests failed",
+                Some("no library targets found"),
+                Some("No library found to test for in workspace."),
             )?;
         }
-        Target::Crates | Target::Examples => {
-            let members = match target {
-                Target::Crates => get_workspace_members(WorkspaceMemberType::Crate),
-                Target::Examples => get_workspace_members(WorkspaceMemberType::Example),
-                _ => unreachable!(),
-            };
-
-            for member in members {
-                run_integration_test(&member, args)?;
-            }
-        }
-        Target::AllPackages => {
-            Target::iter()
-                .filter(|t| *t != Target::AllPackages && *t != Target::Workspace)
-                .try_for_each(|t| run_integration(&t, args))?;
+        Target::Crates | Target::Examples | Target::AllPackages => {
+            run_for_members(
+                get_target_members(target, args, workspace),
+                args,
+                run_doctest_for_member,
+            )?;
         }
     }
     anyhow::Ok(())
 }
 
-fn run_integration_test(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<()> {
-    group!("Integration Tests: {}", &member.name);
-    let mut cmd_args = vec![
-        "test",
-        "--test",
-        "*",
-        "-p",
-        &member.name,
-        "--color",
-        "always",
-    ]
-    .into_iter()
-    .map(|s| s.to_string())
-    .collect::<Vec<String>>();
-    push_optional_args(&mut cmd_args, args);
+fn run_doctest_for_member(member: &WorkspaceMember, args: &TestCmdArgs) -> Result<()> {
+    group!("Doc Tests: {}", member.name);
+    let test = args.test.as_deref().unwrap_or("");
+    let mut cmd_args = vec!["test", test, "--doc", "-p", &member.name, "--color=always"]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>();
+    push_optional_args(&mut cmd_args, args, &resolve_member_features(args, member)?);
     run_process_for_package(
         "cargo",
         &member.name,
         &cmd_args.iter().map(String::as_str).collect::<Vec<&str>>(),
         &args.exclude,
         &args.only,
-        &format!("Failed to execute integration test for '{}'", &member.name),
-        Some("no test target matches pattern"),
+        &format!("Failed to execute doc tests for '{}'", &member.name),
+        Some("no library targets found"),
         Some(&format!(
-            "No integration tests found for '{}'.",
+            "No library found to test for in the crate '{}'.",
             &member.name
         )),
     )?;
     endgroup!();
     anyhow::Ok(())
 }
+
+/// Returns the cargo arguments running the integration tests of the passed member whose name
+/// matches the passed pattern, all of them when there is no pattern.
+fn get_integration_test_args(member_name: &str, test: Option<&str>) -> Vec<String> {
+    let test = test.unwrap_or("*");
+    vec![
+        "test",
+        "--test",
+        test,
+        "-p",
+        member_name,
+        "--color",
+        "always",
+    ]
+    .into_iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Run the tests of each member twice with different shuffle seeds and fail if the result of a
+/// test depends on the order of execution, or if a test fails in both orders.
+fn run_order_check(args: &TestCmdArgs, workspace: &Workspace) -> anyhow::Result<()> {
+    if !is_current_toolchain_nightly() {
+        return Err(anyhow!("Test order check is unstable. {CARGO_NIGHTLY_MSG}"));
+    }
+    let seeds = [
+        args.shuffle_seed.unwrap_or_else(rand::random),
+        rand::random(),
+    ];
+    info!(
+        "Checking the test order dependence with seeds {} and {}.",
+        seeds[0], seeds[1]
+    );
+    let mut order_dependent = 0;
+    let mut failed = 0;
+    for member in get_order_check_members(args, workspace) {
+        group!("Order Check: {}", member.name);
+        let features = resolve_member_features(args, member)?;
+        let first = run_shuffled_tests(member, args, &features, seeds[0])?;
+        let second = run_shuffled_tests(member, args, &features, seeds[1])?;
+        for (name, passed_first) in find_order_dependent_tests(&first, &second) {
+            let (passing_seed, failing_seed) = if passed_first {
+                (seeds[0], seeds[1])
+            } else {
+                (seeds[1], seeds[0])
+            };
+            error!(
+                "Test '{name}' of '{}' passes with seed {passing_seed} but fails with seed {failing_seed}.",
+                member.name
+            );
+            order_dependent += 1;
+        }
+        for result in first.iter().filter(|r| {
+            r.outcome == TestOutcome::Failed
+                && second.iter().any(|s| {
+                    s.binary == r.binary && s.name == r.name && s.outcome == TestOutcome::Failed
+                })
+        }) {
+            warn!(
+                "Test '{}' of '{}' fails with both seeds.",
+                result.name, member.name
+            );
+            failed += 1;
+        }
+        endgroup!();
+    }
+    if order_dependent > 0 {
+        return Err(anyhow!(
+            "{order_dependent} test(s) depend on the execution order, use '--shuffle-seed <SEED>' with the failing seed to reproduce."
+        ));
+    }
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} test(s) fail regardless of the execution order."
+        ));
+    }
+    info!("No test depends on the execution order.");
+    Ok(())
+}
+
+/// Returns the selected members of the target, the workspace target checks all the members as the
+/// tests are run and compared per member.
+fn get_order_check_members<'a>(
+    args: &TestCmdArgs,
+    workspace: &'a Workspace,
+) -> Vec<&'a WorkspaceMember> {
+    let members = match args.target {
+        Target::Crates => workspace.members(WorkspaceMemberType::AllCrates),
+        Target::Examples => workspace.members(WorkspaceMemberType::Example),
+        _ => workspace.all_members(),
+    };
+    members
+        .into_iter()
+        .filter(|member| is_package_selected(&member.name, &args.packages))
+        .filter(|member| is_member_selected(&member.name, args))
+        .collect()
+}
+
+/// Run the tests of the passed member selected by the subcommand in the order of the passed
+/// shuffle seed and returns their results.
+fn run_shuffled_tests(
+    member: &WorkspaceMember,
+    args: &TestCmdArgs,
+    features: &[String],
+    seed: u64,
+) -> anyhow::Result<Vec<TestResult>> {
+    // a failing test binary must not hide the results of the next ones
+    let mut cmd_args: Vec<String> = ["test", "--no-fail-fast", "-p", &member.name]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    match args.get_command() {
+        TestSubCommand::Unit => {
+            if Path::new(&member.path).join("src/lib.rs").exists() {
+                cmd_args.push("--lib".to_string());
+            }
+            cmd_args.push("--bins".to_string());
+        }
+        TestSubCommand::Integration => cmd_args.extend(["--test".to_string(), "*".to_string()]),
+        TestSubCommand::DocTest => cmd_args.push("--doc".to_string()),
+        TestSubCommand::All => cmd_args.push("--tests".to_string()),
+    }
+    if let Some(test) = &args.test {
+        cmd_args.push(test.clone());
+    }
+    let shuffled_args = TestCmdArgs {
+        shuffle_seed: Some(seed),
+        ..args.clone()
+    };
+    push_optional_args(&mut cmd_args, &shuffled_args, features);
+    info!("Running the tests of '{}' with seed {seed}...", member.name);
+    let mut command = Command::new("cargo");
+    command.args(&cmd_args);
+    apply_clean_env(&mut command);
+    if plan_command(&command) {
+        return Ok(vec![]);
+    }
+    let output = spawn_child(command.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .and_then(wait_child_output)
+        .map_err(|e| anyhow!("Failed to execute cargo test: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let results = parse_test_results(Some(&member.name), &String::from_utf8_lossy(&output.stdout));
+    if !output.status.success() && results.is_empty() {
+        if stderr.contains("no test target matches pattern") {
+            return Ok(results);
+        }
+        error!("{stderr}");
+        return Err(anyhow!("Cannot run the tests of '{}'.", member.name));
+    }
+    Ok(results)
+}
+
+/// Returns the tests which pass in one run and fail in the other with true if they passed in the
+/// first run.
+fn find_order_dependent_tests(first: &[TestResult], second: &[TestResult]) -> Vec<(String, bool)> {
+    first
+        .iter()
+        .filter_map(|result| {
+            let other = second
+                .iter()
+                .find(|r| r.binary == result.binary && r.name == result.name)?;
+            match (&result.outcome, &other.outcome) {
+                (TestOutcome::Passed, TestOutcome::Failed) => Some((result.name.clone(), true)),
+                (TestOutcome::Failed, TestOutcome::Passed) => Some((result.name.clone(), false)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::try_parse_cmd_args;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::none(&[], vec!["--", "--color=always"])]
+    #[case::single(
+        &["--test-runner-arg=--report-time"],
+        vec!["--", "--color=always", "--report-time"]
+    )]
+    #[case::shuffle_seed(
+        &["--shuffle-seed", "42"],
+        vec!["--", "--color=always", "-Zunstable-options", "--shuffle-seed", "42"]
+    )]
+    #[case::serial(
+        &["--serial"],
+        vec!["--jobs", "1", "--", "--color=always", "--test-threads", "1"]
+    )]
+    #[case::all_features(
+        &["--all-features", "--no-default-features"],
+        vec!["--all-features", "--no-default-features", "--", "--color=always"]
+    )]
+    #[case::repeated(
+        &["--test-runner-arg", "-Zunstable-options", "--test-runner-arg", "--shuffle", "--test-threads", "2"],
+        vec!["--", "--color=always", "--test-threads", "2", "-Zunstable-options", "--shuffle"]
+    )]
+    fn test_push_optional_args_test_runner_args(
+        #[case] cli_args: &[&str],
+        #[case] expected: Vec<&str>,
+    ) {
+        let args: TestCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        let mut cmd_args = vec![];
+        push_optional_args(&mut cmd_args, &args, &[]);
+        assert_eq!(cmd_args, expected);
+    }
+
+    #[rstest]
+    #[case::triple(
+        &["--target-triple", "aarch64-unknown-linux-gnu"],
+        &[],
+        vec!["--target", "aarch64-unknown-linux-gnu", "--", "--color=always"]
+    )]
+    #[case::triple_release_features(
+        &["--target-triple", "aarch64-unknown-linux-gnu", "--release", "--no-default-features"],
+        &["std"],
+        vec!["--features", "std", "--release", "--no-default-features", "--target", "aarch64-unknown-linux-gnu", "--", "--color=always"]
+    )]
+    fn test_push_optional_args_target_triple(
+        #[case] cli_args: &[&str],
+        #[case] features: &[&str],
+        #[case] expected: Vec<&str>,
+    ) {
+        let args: TestCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        let features: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+        let mut cmd_args = vec![];
+        push_optional_args(&mut cmd_args, &args, &features);
+        assert_eq!(cmd_args, expected);
+    }
+
+    #[rstest]
+    #[case::known("aarch64-unknown-linux-gnu", true)]
+    #[case::custom("my-board-none-eabi", false)]
+    fn test_check_target_triple(#[case] triple: &str, #[case] expected: bool) {
+        let known = vec![
+            "aarch64-unknown-linux-gnu".to_string(),
+            "x86_64-unknown-linux-gnu".to_string(),
+        ];
+        assert_eq!(check_target_triple(triple, &known), expected);
+        assert!(check_target_triple(triple, &[]));
+    }
+
+    #[rstest]
+    #[case::name(&["loom"], vec!["--cfg", "loom"])]
+    #[case::value(&["backend=cuda"], vec!["--cfg", "backend=\"cuda\""])]
+    #[case::quoted_value(&["backend=\"cuda\""], vec!["--cfg", "backend=\"cuda\""])]
+    #[case::repeated(&["loom", "tokio_unstable"], vec!["--cfg", "loom", "--cfg", "tokio_unstable"])]
+    fn test_get_cfg_flags(#[case] cfgs: &[&str], #[case] expected: Vec<&str>) {
+        let cfgs: Vec<String> = cfgs.iter().map(|c| c.to_string()).collect();
+        assert_eq!(get_cfg_flags(&cfgs).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::leading_digit("1cfg")]
+    #[case::invalid_character("my-cfg=1")]
+    fn test_get_cfg_flags_invalid(#[case] cfg: &str) {
+        assert!(get_cfg_flags(&[cfg.to_string()]).is_err());
+    }
+
+    #[rstest]
+    #[case::jobs(&["--serial", "--compilation-jobs", "4"])]
+    #[case::threads(&["--serial", "--test-threads", "4"])]
+    fn test_serial_conflicts_with_parallelism_args(#[case] cli_args: &[&str]) {
+        assert!(try_parse_cmd_args::<TestCmdArgs>(cli_args).is_err());
+    }
+
+    #[test]
+    fn test_all_features_conflicts_with_features() {
+        assert!(
+            try_parse_cmd_args::<TestCmdArgs>(&["--all-features", "--features", "std"]).is_err()
+        );
+    }
+
+    #[rstest]
+    #[case::explicit_locally(Some(3), false, 3)]
+    #[case::explicit_in_ci(Some(0), true, 0)]
+    #[case::default_locally(None, false, 0)]
+    #[case::default_in_ci(None, true, DEFAULT_CI_RETRIES)]
+    fn test_resolve_retries(#[case] retries: Option<u32>, #[case] ci: bool, #[case] expected: u32) {
+        assert_eq!(resolve_retries(retries, ci), expected);
+    }
+
+    #[rstest]
+    #[case::all_known(&["a", "b"], vec![])]
+    #[case::unknown(&["a", "z", "y"], vec!["z", "y"])]
+    #[case::none(&[], vec![])]
+    fn test_find_unknown_packages(#[case] packages: &[&str], #[case] expected: Vec<&str>) {
+        let packages: Vec<String> = packages.iter().map(|p| p.to_string()).collect();
+        let members = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(find_unknown_packages(&packages, &members), expected);
+    }
+
+    #[rstest]
+    #[case::empty_config("", &[], &[], &[], None)]
+    #[case::defaults(
+        "exclude = [\"a\"]\n[test]\nonly = [\"b\"]\nfeatures = [\"std\"]",
+        &[],
+        &["a"],
+        &["b"],
+        Some(vec!["std"])
+    )]
+    #[case::cli_wins(
+        "exclude = [\"a\"]\nfeatures = [\"std\"]",
+        &["--exclude", "c", "--features", "gpu"],
+        &["c"],
+        &[],
+        Some(vec!["gpu"])
+    )]
+    #[case::all_features("features = [\"std\"]", &["--all-features"], &[], &[], None)]
+    fn test_apply_defaults(
+        #[case] config: &str,
+        #[case] cli_args: &[&str],
+        #[case] exclude: &[&str],
+        #[case] only: &[&str],
+        #[case] features: Option<Vec<&str>>,
+    ) {
+        let config = crate::config::Config::parse(config).unwrap();
+        let mut args: TestCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        args.apply_defaults(|key| config.get_default_args("test", key));
+        assert_eq!(args.exclude, exclude);
+        assert_eq!(args.only, only);
+        assert_eq!(
+            args.features,
+            features.map(|f| f.iter().map(|f| f.to_string()).collect())
+        );
+    }
+
+    #[test]
+    fn test_find_changed_members() {
+        let members: Vec<WorkspaceMember> = ["crates/core", "crates/core/macros", "examples/demo"]
+            .iter()
+            .map(|path| WorkspaceMember {
+                name: path.rsplit('/').next().unwrap().to_string(),
+                path: format!("/repo/{path}"),
+                features: vec![],
+                kind: crate::utils::workspace::CrateKind::Library,
+                publishable: true,
+            })
+            .collect();
+        let diff = "crates/core/src/lib.rs\ncrates/core/macros/src/lib.rs\ncrates/core/Cargo.toml\nREADME.md\n";
+        let changed: Vec<String> = find_changed_members(diff, Path::new("/repo"), &members)
+            .into_iter()
+            .collect();
+        assert_eq!(changed, vec!["core", "macros"]);
+        let diff = "crates/core/src/lib.rs\nCargo.lock\n";
+        let changed: Vec<String> = find_changed_members(diff, Path::new("/repo"), &members)
+            .into_iter()
+            .collect();
+        assert_eq!(changed, vec!["core", "demo", "macros"]);
+    }
+
+    #[rstest]
+    #[case::no_package(&[], &["a", "c"], vec!["a", "c"])]
+    #[case::packages(&["a", "b"], &["a", "c"], vec!["a"])]
+    #[case::nothing_affected(&["a"], &[], vec![])]
+    fn test_restrict_packages(
+        #[case] packages: &[&str],
+        #[case] affected: &[&str],
+        #[case] expected: Vec<&str>,
+    ) {
+        let packages: Vec<String> = packages.iter().map(|p| p.to_string()).collect();
+        let affected: BTreeSet<String> = affected.iter().map(|p| p.to_string()).collect();
+        assert_eq!(restrict_packages(&packages, &affected), expected);
+    }
+
+    #[rstest]
+    #[case::all_tests(None, "*")]
+    #[case::named_test(Some("my_suite"), "my_suite")]
+    #[case::pattern(Some("api_*"), "api_*")]
+    fn test_get_integration_test_args(#[case] test: Option<&str>, #[case] expected: &str) {
+        assert_eq!(
+            get_integration_test_args("my-crate", test),
+            vec!["test", "--test", expected, "-p", "my-crate", "--color", "always"]
+        );
+    }
+
+    #[rstest]
+    #[case::no_retry_success(&["--retries", "0"], 0, 1, true)]
+    #[case::no_retry_failure(&["--retries", "0"], 3, 1, false)]
+    #[case::flaky(&["--retries", "2"], 2, 3, true)]
+    #[case::retries_exhausted(&["--retries", "2"], 5, 3, false)]
+    fn test_run_with_retries(
+        #[case] cli_args: &[&str],
+        #[case] failures: u32,
+        #[case] expected_attempts: u32,
+        #[case] expected_success: bool,
+    ) {
+        let args: TestCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        let mut attempts = 0;
+        let result = run_with_retries(&args, || {
+            attempts += 1;
+            if attempts <= failures {
+                Err(anyhow!("attempt {attempts} failed"))
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(attempts, expected_attempts);
+        assert_eq!(result.is_ok(), expected_success);
+        if !expected_success {
+            // only the failure of the last attempt is returned
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                format!("attempt {expected_attempts} failed")
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::package_and_bin("server:my-server", "server", Some("my-server"))]
+    #[case::package_only("server", "server", None)]
+    #[case::trimmed(" server : my-server ", "server", Some("my-server"))]
+    fn test_build_prerequisite_parse(
+        #[case] value: &str,
+        #[case] expected_package: &str,
+        #[case] expected_bin: Option<&str>,
+    ) {
+        let prerequisite = BuildPrerequisite::parse(value).expect("prerequisite should be valid");
+        assert_eq!(
+            prerequisite,
+            BuildPrerequisite {
+                package: expected_package.to_string(),
+                bin: expected_bin.map(str::to_string),
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::missing_package(":my-server")]
+    #[case::missing_bin("server:")]
+    fn test_build_prerequisite_parse_invalid(#[case] value: &str) {
+        assert!(BuildPrerequisite::parse(value).is_err());
+    }
+
+    #[test]
+    fn test_find_order_dependent_tests() {
+        let results = |outcomes: &[(&str, TestOutcome)]| -> Vec<TestResult> {
+            outcomes
+                .iter()
+                .map(|(name, outcome)| TestResult {
+                    member: "my-crate".to_string(),
+                    binary: "my_crate".to_string(),
+                    name: name.to_string(),
+                    outcome: outcome.clone(),
+                    output: String::new(),
+                })
+                .collect()
+        };
+        let first = results(&[
+            ("stable", TestOutcome::Passed),
+            ("needs_init", TestOutcome::Failed),
+            ("pollutes_state", TestOutcome::Passed),
+            ("broken", TestOutcome::Failed),
+            ("slow", TestOutcome::Ignored),
+        ]);
+        let second = results(&[
+            ("pollutes_state", TestOutcome::Failed),
+            ("broken", TestOutcome::Failed),
+            ("needs_init", TestOutcome::Passed),
+            ("stable", TestOutcome::Passed),
+            ("slow", TestOutcome::Ignored),
+        ]);
+        assert_eq!(
+            find_order_dependent_tests(&first, &second),
+            vec![
+                ("needs_init".to_string(), false),
+                ("pollutes_state".to_string(), true),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::fail_fast(&[], vec!["a", "b"], "'b' failed")]
+    #[case::keep_going(&["--keep-going"], vec!["a", "b", "c", "d"], "2 member(s) failed: b, d")]
+    fn test_run_for_members(
+        #[case] cli_args: &[&str],
+        #[case] expected_runs: Vec<&str>,
+        #[case] expected_error: &str,
+    ) {
+        let args: TestCmdArgs = try_parse_cmd_args(cli_args).unwrap();
+        let members: Vec<WorkspaceMember> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| WorkspaceMember {
+                name: name.to_string(),
+                path: format!("/repo/crates/{name}"),
+                features: vec![],
+                kind: crate::utils::workspace::CrateKind::Library,
+                publishable: true,
+            })
+            .collect();
+        let runs = std::cell::RefCell::new(vec![]);
+        let result = run_for_members(members.iter().collect(), &args, |member, _| {
+            runs.borrow_mut().push(member.name.clone());
+            match member.name.as_str() {
+                "b" | "d" => Err(anyhow!("'{}' failed", member.name)),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(runs.into_inner(), expected_runs);
+        assert_eq!(result.unwrap_err().to_string(), expected_error);
+    }
+}
@@ -1,7 +1,13 @@
-use crate::prelude::{Context, Environment};
+use anyhow::anyhow;
+
+use crate::{
+    config::get_config,
+    prelude::{Context, Environment},
+};
 
 use super::{
     check::{CheckCmdArgs, CheckSubCommand},
+    run_step,
     test::{TestCmdArgs, TestSubCommand},
     Target,
 };
@@ -11,8 +17,14 @@ struct ValidateCmdArgs {}
 
 pub fn handle_command(args: ValidateCmdArgs, env: Environment, ctx: Context) -> anyhow::Result<()> {
     let target = Target::Workspace;
-    let exclude = vec![];
-    let only = vec![];
+    let config = get_config();
+    let exclude = config
+        .get_default_args("validate", "exclude")
+        .unwrap_or_default();
+    let only = config
+        .get_default_args("validate", "only")
+        .unwrap_or_default();
+    let mut failures = vec![];
 
     // checks
     [
@@ -23,38 +35,73 @@ pub fn handle_command(args: ValidateCmdArgs, env: Environment, ctx: Context) ->
     ]
     .iter()
     .try_for_each(|c| {
-        super::check::handle_command(
-            CheckCmdArgs {
+        run_step(&c.to_string(), args.keep_going, &mut failures, || {
+            super::check::handle_command(
+                CheckCmdArgs {
+                    target: target.clone(),
+                    exclude: exclude.clone(),
+                    only: only.clone(),
+                    command: Some(c.clone()),
+                    ignore_audit: args.ignore_audit,
+                    deny: false,
+                    warn_only: vec![],
+                    keep_going: args.keep_going,
+                },
+                env.clone(),
+                ctx.clone(),
+            )
+        })
+    })?;
+
+    // tests
+    run_step("test", args.keep_going, &mut failures, || {
+        super::test::handle_command(
+            TestCmdArgs {
                 target: target.clone(),
                 exclude: exclude.clone(),
                 only: only.clone(),
-                command: Some(c.clone()),
-                ignore_audit: args.ignore_audit,
+                threads: None,
+                test: None,
+                jobs: None,
+                cfgs: vec![],
+                check_writes: false,
+                command: Some(TestSubCommand::All),
+                deny_writes: false,
+                force: false,
+                features: None,
+                all_features: false,
+                no_default_features: false,
+                leak_check: false,
+                leak_threshold: 0,
+                list: false,
+                no_capture: false,
+                order_check: false,
+                release: args.release,
+                reports: vec![],
+                timeout: None,
+                clean_env: false,
+                keep_going: args.keep_going,
+                changed_since: None,
+                env_passthrough: vec![],
+                packages: vec![],
+                retries: None,
+                serial: false,
+                shuffle: false,
+                shuffle_seed: None,
+                target_triple: None,
+                test_runner_args: vec![],
             },
             env.clone(),
             ctx.clone(),
         )
     })?;
 
-    // tests
-    super::test::handle_command(
-        TestCmdArgs {
-            target: target.clone(),
-            exclude: exclude.clone(),
-            only: only.clone(),
-            threads: None,
-            test: None,
-            jobs: None,
-            command: Some(TestSubCommand::All),
-            force: false,
-            features: None,
-            no_default_features: false,
-            no_capture: false,
-            release: args.release,
-        },
-        env.clone(),
-        ctx.clone(),
-    )?;
-
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} validation step(s) failed: {}",
+            failures.len(),
+            failures.join(", ")
+        ));
+    }
     Ok(())
 }
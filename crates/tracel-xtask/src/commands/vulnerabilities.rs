@@ -1,95 +1,517 @@
 use anyhow::{anyhow, Ok};
-use std::process::Command as StdCommand;
+use regex::Regex;
+use std::{
+    cell::OnceCell,
+    path::{Path, PathBuf},
+    process::{Command as StdCommand, Output, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use strum::{EnumIter, IntoEnumIterator};
 
 use crate::{
     commands::CARGO_NIGHTLY_MSG,
+    config::{get_config, Config},
     endgroup, group,
     prelude::{Context, Environment},
     utils::{
-        cargo::ensure_cargo_crate_is_installed,
-        process::run_process,
+        cargo::{ensure_cargo_crate_is_installed, set_flags_env, FlagsVar},
+        process::{
+            remove_ansi_codes, run_command_streamed, run_process, spawn_child, split_args,
+            wait_child_output, CLEAN_ENV_ALLOWLIST,
+        },
         rustup::{
-            is_current_toolchain_nightly, rustup_add_component, rustup_get_installed_targets,
+            get_toolchain_cache_key, is_current_toolchain_nightly, is_toolchain_nightly,
+            rustup_add_component, rustup_add_toolchain_component, rustup_get_installed_targets,
+            rustup_get_toolchain_installed_targets,
         },
+        script::{describe_command, is_planning, plan_command},
+        time::parse_duration,
     },
 };
 
+/// Directory of the builds cached across runs, in a subdirectory per toolchain.
+const VULNERABILITIES_CACHE_DIR: &str = "target/vulnerabilities";
+/// Directory of the sanitizer builds, under the target directory, in a subdirectory per sanitizer.
+const SANITIZERS_TARGET_DIR: &str = "sanitizers";
+/// Stamp file marking that the careful sysroot has been set up for a toolchain.
+const CAREFUL_SETUP_STAMP: &str = "careful-setup.stamp";
+/// Configuration table mapping a target triple to the sanitizers it supports, in addition to the
+/// built-in supported targets.
+pub const SANITIZER_TARGETS_CONFIG_KEY: &str = "vulnerabilities.sanitizer-targets";
+
 #[tracel_xtask_macros::declare_command_args(None, VulnerabilitiesSubCommand)]
 pub struct VulnerabilitiesCmdArgs {}
 
+/// Status of a vulnerability check in the `--report-json` report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check did not run because the toolchain is not nightly or no target supports it.
+    Skipped,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CheckStatus::Passed => write!(f, "passed"),
+            CheckStatus::Failed => write!(f, "failed"),
+            CheckStatus::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Result of a vulnerability check in the `--report-json` report.
+#[derive(Debug, Clone, PartialEq)]
+struct CheckResult {
+    check: String,
+    status: CheckStatus,
+    duration: Duration,
+}
+
 pub fn handle_command(
     args: VulnerabilitiesCmdArgs,
     _env: Environment,
     _ctx: Context,
 ) -> anyhow::Result<()> {
-    args.get_command().run()
+    let mut results = vec![];
+    let result = args.get_command().run(&args, &mut results);
+    // the report is also written when a check fails
+    if let Some(path) = &args.report_json {
+        write_report_json(path, &results)?;
+    }
+    result
 }
 
 impl VulnerabilitiesSubCommand {
-    pub(crate) fn run(&self) -> anyhow::Result<()> {
+    // Run the checks of the subcommand and append their results to the passed ones
+    fn run(
+        &self,
+        args: &VulnerabilitiesCmdArgs,
+        results: &mut Vec<CheckResult>,
+    ) -> anyhow::Result<()> {
+        // the installed targets are retrieved once for all the sanitizers of the run
+        let retriever = CachedTargetRetriever::new(match &args.toolchain {
+            Some(toolchain) => RustupTargetRetriever::for_toolchain(toolchain),
+            None => RustupTargetRetriever::default(),
+        });
         match self {
-            Self::NightlyChecks => run_cargo_careful(),
-            Self::AddressSanitizer => Sanitizer::Address.run_tests(),
-            Self::ControlFlowIntegrity => Sanitizer::CFI.run_tests(),
-            Self::HWAddressSanitizer => Sanitizer::HWAddress.run_tests(),
-            Self::KernelControlFlowIntegrity => Sanitizer::KCFI.run_tests(),
-            Self::LeakSanitizer => Sanitizer::Leak.run_tests(),
-            Self::MemorySanitizer => Sanitizer::Memory.run_tests(),
-            Self::MemTagSanitizer => Sanitizer::MemTag.run_tests(),
-            Self::SafeStack => Sanitizer::SafeStack.run_tests(),
-            Self::ShadowCallStack => Sanitizer::ShadowCallStack.run_tests(),
-            Self::ThreadSanitizer => Sanitizer::Thread.run_tests(),
+            Self::NightlyChecks => run_timed(NIGHTLY_CHECKS, results, || run_cargo_careful(args)),
+            Self::AddressSanitizer => Sanitizer::Address.run_timed(args, &retriever, results),
+            Self::ControlFlowIntegrity => Sanitizer::CFI.run_timed(args, &retriever, results),
+            Self::DataFlowSanitizer => Sanitizer::DataFlow.run_timed(args, &retriever, results),
+            Self::HWAddressSanitizer => Sanitizer::HWAddress.run_timed(args, &retriever, results),
+            Self::KernelControlFlowIntegrity => {
+                Sanitizer::KCFI.run_timed(args, &retriever, results)
+            }
+            Self::LeakSanitizer => Sanitizer::Leak.run_timed(args, &retriever, results),
+            Self::MemorySanitizer => Sanitizer::Memory.run_timed(args, &retriever, results),
+            Self::MemTagSanitizer => Sanitizer::MemTag.run_timed(args, &retriever, results),
+            Self::Miri => run_timed(MIRI, results, || run_cargo_miri(args)),
+            Self::SafeStack => Sanitizer::SafeStack.run_timed(args, &retriever, results),
+            Self::ShadowCallStack => {
+                Sanitizer::ShadowCallStack.run_timed(args, &retriever, results)
+            }
+            Self::ThreadSanitizer => Sanitizer::Thread.run_timed(args, &retriever, results),
             Self::All => {
-                // TODO automatically run all checks supported by the default toolchain of the host
-                // For now run all those supported by X8664UnknownLinuxGnu
-                run_cargo_careful()?;
-                Sanitizer::Address.run_tests()?;
-                Sanitizer::Leak.run_tests()?;
-                Sanitizer::Memory.run_tests()?;
-                Sanitizer::SafeStack.run_tests()?;
-                Sanitizer::Thread.run_tests()
+                run_timed(NIGHTLY_CHECKS, results, || run_cargo_careful(args))?;
+                let host = Target::host();
+                if host.is_miri_supported() {
+                    run_timed(MIRI, results, || run_cargo_miri(args))?;
+                } else {
+                    info!("Miri does not support the host target '{host}', skipping Miri.");
+                }
+                let sanitizers = get_host_sanitizers(&host, get_config());
+                if sanitizers.is_empty() {
+                    info!(
+                        "No sanitizer supports the host target '{host}', skipping the sanitizers."
+                    );
+                    return Ok(());
+                }
+                // a dry run prints the commands one after the other
+                match args.max_parallel.filter(|_| !args.dry_run) {
+                    Some(max_parallel) => run_sanitizers_in_parallel(
+                        &sanitizers,
+                        args,
+                        &retriever,
+                        max_parallel,
+                        results,
+                    ),
+                    None => sanitizers
+                        .iter()
+                        .try_for_each(|s| s.run_timed(args, &retriever, results)),
+                }
             }
         }
     }
 }
 
-/// Run cargo-careful
-fn run_cargo_careful() -> anyhow::Result<()> {
-    if is_current_toolchain_nightly() {
+/// Name of the cargo-careful checks in the `--report-json` report.
+const NIGHTLY_CHECKS: &str = "NightlyChecks";
+/// Name of the Miri checks in the `--report-json` report.
+const MIRI: &str = "Miri";
+
+// Run the passed check and append its status and duration to the passed results
+fn run_timed(
+    check: &str,
+    results: &mut Vec<CheckResult>,
+    run: impl FnOnce() -> anyhow::Result<CheckStatus>,
+) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let result = run();
+    results.push(CheckResult {
+        check: check.to_string(),
+        status: match &result {
+            Result::Ok(status) => *status,
+            Err(_) => CheckStatus::Failed,
+        },
+        duration: start.elapsed(),
+    });
+    result.map(|_| ())
+}
+
+/// Write the results of the checks as a JSON array to the passed path, through a temporary file
+/// renamed once written so that readers never see a partial report.
+fn write_report_json(path: &Path, results: &[CheckResult]) -> anyhow::Result<()> {
+    let contents = serde_json::to_string_pretty(&report_to_json(results))?;
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    std::fs::write(&tmp_path, contents)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|e| anyhow!("Cannot write the report {} ({e})", path.display()))?;
+    info!("Vulnerabilities report written to {}", path.display());
+    Ok(())
+}
+
+fn report_to_json(results: &[CheckResult]) -> serde_json::Value {
+    results
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "check": r.check,
+                "status": r.status.to_string(),
+                "duration_ms": r.duration.as_millis() as u64,
+            })
+        })
+        .collect()
+}
+
+/// Sanitizers run by the `all` subcommand when they support the host target.
+const ALL_SANITIZERS: [Sanitizer; 5] = [
+    Sanitizer::Address,
+    Sanitizer::Leak,
+    Sanitizer::Memory,
+    Sanitizer::SafeStack,
+    Sanitizer::Thread,
+];
+
+/// Returns the number of tests which passed or failed in the 'test result' lines of the passed
+/// libtest output, the doc tests included.
+fn count_executed_tests(output: &str) -> usize {
+    let result_re = Regex::new(r"test result: \S+ (\d+) passed; (\d+) failed")
+        .expect("should compile test result regex");
+    output
+        .lines()
+        .map(remove_ansi_codes)
+        .filter_map(|line| {
+            let caps = result_re.captures(&line)?;
+            Some(caps[1].parse::<usize>().ok()? + caps[2].parse::<usize>().ok()?)
+        })
+        .sum()
+}
+
+/// Returns the sanitizers of the `all` subcommand which support the passed host target.
+fn get_host_sanitizers(host: &Target, config: &Config) -> Vec<Sanitizer> {
+    let host = host.to_string();
+    ALL_SANITIZERS
+        .into_iter()
+        .filter(|s| s.supported_targets(config).contains(&host))
+        .collect()
+}
+
+/// Run the passed sanitizers concurrently, at most `max_parallel` at a time, each one in its own
+/// target directory. All the sanitizers are run before failing if any of them failed.
+fn run_sanitizers_in_parallel(
+    sanitizers: &[Sanitizer],
+    args: &VulnerabilitiesCmdArgs,
+    retriever: &impl TargetRetriever,
+    max_parallel: usize,
+    report: &mut Vec<CheckResult>,
+) -> anyhow::Result<()> {
+    // the targets are retrieved first to report a missing rustup before checking the toolchain
+    let mut supported = vec![];
+    let mut skipped = vec![];
+    for sanitizer in sanitizers {
+        if sanitizer.is_target_supported(retriever, get_config())? {
+            supported.push(*sanitizer);
+        } else {
+            skipped.push(*sanitizer);
+        }
+    }
+    if !check_nightly(args) {
+        report.extend(sanitizers.iter().map(|s| CheckResult {
+            check: s.to_string(),
+            status: CheckStatus::Skipped,
+            duration: Duration::ZERO,
+        }));
+        return Ok(());
+    }
+    info!(
+        "Running {} sanitizer(s), {} at a time.",
+        supported.len(),
+        max_parallel
+    );
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(Sanitizer, anyhow::Result<()>, Duration)> = vec![];
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..max_parallel.clamp(1, supported.len().max(1)) {
+            let sender = sender.clone();
+            let (next, supported) = (&next, &supported);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(sanitizer) = supported.get(index) else {
+                    break;
+                };
+                let start = Instant::now();
+                let output = sanitizer.run_captured(args);
+                let _ = sender.send((*sanitizer, output, start.elapsed()));
+            });
+        }
+        drop(sender);
+        // the groups are only opened by this thread, as the sanitizers complete
+        for (sanitizer, output, duration) in receiver {
+            let result = output.and_then(|output| match output {
+                Some(output) => sanitizer.report_captured(&output, args.require_tests),
+                None => Ok(()),
+            });
+            results.push((sanitizer, result, duration));
+        }
+    });
+    let names = |sanitizers: Vec<&Sanitizer>| -> String {
+        if sanitizers.is_empty() {
+            return "none".to_string();
+        }
+        let names: Vec<String> = sanitizers.iter().map(|s| s.to_string()).collect();
+        names.join(", ")
+    };
+    report.extend(results.iter().map(|(s, result, duration)| CheckResult {
+        check: s.to_string(),
+        status: match result {
+            Result::Ok(()) => CheckStatus::Passed,
+            Err(_) => CheckStatus::Failed,
+        },
+        duration: *duration,
+    }));
+    report.extend(skipped.iter().map(|s| CheckResult {
+        check: s.to_string(),
+        status: CheckStatus::Skipped,
+        duration: Duration::ZERO,
+    }));
+    let failed: Vec<&Sanitizer> = results
+        .iter()
+        .filter(|(_, result, _)| result.is_err())
+        .map(|(s, _, _)| s)
+        .collect();
+    group!("Sanitizers Summary");
+    info!(
+        "Passed: {}",
+        names(
+            results
+                .iter()
+                .filter(|(_, result, _)| result.is_ok())
+                .map(|(s, _, _)| s)
+                .collect()
+        )
+    );
+    info!("Failed: {}", names(failed.clone()));
+    info!(
+        "Skipped (unsupported targets): {}",
+        names(skipped.iter().collect())
+    );
+    endgroup!();
+    if !failed.is_empty() {
+        return Err(anyhow!("Sanitizers found issues: {}", names(failed)));
+    }
+    Ok(())
+}
+
+/// Run cargo-careful, with the passed cargo profile and careful arguments if any
+fn run_cargo_careful(args: &VulnerabilitiesCmdArgs) -> anyhow::Result<CheckStatus> {
+    let extra_args = split_args(args.careful_args.as_deref().unwrap_or_default())
+        .map_err(|e| anyhow!("Invalid --careful-args: {e}"))?;
+    let timeout = args
+        .careful_timeout
+        .as_deref()
+        .map(parse_duration)
+        .transpose()
+        .map_err(|e| anyhow!("Invalid --careful-timeout: {e}"))?;
+    if check_nightly(args) {
+        let toolchain = args.toolchain.as_ref().map(|t| format!("+{t}"));
+        let setup_args: Vec<&str> = toolchain
+            .iter()
+            .map(String::as_str)
+            .chain(["careful", "setup"])
+            .collect();
+        let mut careful_args: Vec<&str> = toolchain
+            .iter()
+            .map(String::as_str)
+            .chain(["careful", "test"])
+            .collect();
+        if let Some(profile) = &args.cargo_profile {
+            careful_args.extend(["--profile", profile]);
+        }
+        careful_args.extend(extra_args.iter().map(String::as_str));
+        if args.dry_run {
+            info!(
+                "Dry run: {}",
+                describe_command(StdCommand::new("cargo").args(&setup_args))
+            );
+            info!(
+                "Dry run: {}",
+                describe_command(StdCommand::new("cargo").args(&careful_args))
+            );
+            return Ok(CheckStatus::Skipped);
+        }
         ensure_cargo_crate_is_installed("cargo-careful", None, None, false)?;
-        rustup_add_component("rust-src")?;
-        // prepare careful sysroot
-        group!("Cargo: careful setup");
-        run_process(
-            "cargo",
-            &["careful", "setup"],
-            None,
-            None,
-            "Error preparing cargo sysroot.",
-        )?;
-        endgroup!();
+        match &args.toolchain {
+            Some(toolchain) => rustup_add_toolchain_component("rust-src", toolchain)?,
+            None => rustup_add_component("rust-src")?,
+        }
+        // prepare careful sysroot, once per toolchain unless it is rebuilt
+        let stamp = get_cache_dir(args.toolchain.as_deref(), args.clean_cache)
+            .map(|dir| dir.join(CAREFUL_SETUP_STAMP));
+        // the sysroot may have been removed from the careful cache since it was prepared
+        let prepared = stamp.as_ref().is_some_and(|stamp| stamp.exists())
+            && get_careful_cache_dir(std::env::consts::OS, |name| std::env::var_os(name))
+                .is_some_and(|dir| dir.is_dir());
+        if prepared && !args.rebuild_sysroot {
+            info!("Skipping cargo careful setup, reusing the sysroot prepared for the toolchain.");
+        } else {
+            if prepared {
+                info!("Rebuilding the careful sysroot as requested by --rebuild-sysroot.");
+            } else {
+                info!("Preparing the careful sysroot for the toolchain.");
+            }
+            group!("Cargo: careful setup");
+            run_process(
+                "cargo",
+                &setup_args,
+                None,
+                None,
+                "Error preparing cargo sysroot.",
+                timeout,
+            )?;
+            endgroup!();
+            // a planned setup has not prepared the sysroot
+            if let Some(stamp) = stamp.filter(|_| !is_planning()) {
+                std::fs::write(stamp, "")?;
+            }
+        }
         // Run cargo careful
         group!("Cargo: run careful checks");
         run_process(
             "cargo",
-            &["careful", "test"],
+            &careful_args,
             None,
             None,
             "Cargo careful test has errors.",
+            timeout,
         )?;
         endgroup!();
+        Ok(CheckStatus::Passed)
     } else {
-        error!("{CARGO_NIGHTLY_MSG}");
+        Ok(CheckStatus::Skipped)
+    }
+}
+
+fn run_cargo_miri(args: &VulnerabilitiesCmdArgs) -> anyhow::Result<CheckStatus> {
+    if !check_nightly(args) {
+        return Ok(CheckStatus::Skipped);
+    }
+    let toolchain = args.toolchain.as_ref().map(|t| format!("+{t}"));
+    let setup_args: Vec<&str> = toolchain
+        .iter()
+        .map(String::as_str)
+        .chain(["miri", "setup"])
+        .collect();
+    let test_args: Vec<&str> = toolchain
+        .iter()
+        .map(String::as_str)
+        .chain(["miri", "test"])
+        .collect();
+    if args.dry_run {
+        info!(
+            "Dry run: {}",
+            describe_command(StdCommand::new("cargo").args(&setup_args))
+        );
+        info!(
+            "Dry run: {}",
+            describe_command(StdCommand::new("cargo").args(&test_args))
+        );
+        return Ok(CheckStatus::Skipped);
+    }
+    for component in ["miri", "rust-src"] {
+        match &args.toolchain {
+            Some(toolchain) => rustup_add_toolchain_component(component, toolchain)?,
+            None => rustup_add_component(component)?,
+        }
+    }
+    // prepare the miri sysroot, miri rebuilds it only when the toolchain changes
+    group!("Cargo: miri setup");
+    run_process(
+        "cargo",
+        &setup_args,
+        None,
+        None,
+        "Error preparing miri sysroot.",
+        None,
+    )?;
+    endgroup!();
+    group!("Cargo: run miri checks");
+    run_process(
+        "cargo",
+        &test_args,
+        None,
+        None,
+        "Cargo miri test has errors.",
+        None,
+    )?;
+    endgroup!();
+    Ok(CheckStatus::Passed)
+}
+
+// Returns true if the toolchain passed with --toolchain, or else the current toolchain, is a
+// nightly toolchain, logs an error otherwise
+fn check_nightly(args: &VulnerabilitiesCmdArgs) -> bool {
+    match &args.toolchain {
+        Some(toolchain) if !is_toolchain_nightly(toolchain) => {
+            error!("The toolchain '{toolchain}' is not an installed nightly toolchain.");
+            false
+        }
+        Some(_) => true,
+        None if !is_current_toolchain_nightly() => {
+            error!("{CARGO_NIGHTLY_MSG}");
+            false
+        }
+        None => true,
     }
-    Ok(())
 }
 
-// Represents the various sanitizer available in nightly compiler
-// source: https://doc.rust-lang.org/beta/unstable-book/compiler-flags/sanitizer.html
+/// The sanitizers available in the nightly compiler.
+/// source: <https://doc.rust-lang.org/beta/unstable-book/compiler-flags/sanitizer.html>
 #[allow(clippy::upper_case_acronyms)]
-enum Sanitizer {
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+pub enum Sanitizer {
     Address,
     CFI,
+    DataFlow,
     HWAddress,
     KCFI,
     Leak,
@@ -105,6 +527,7 @@ impl std::fmt::Display for Sanitizer {
         match self {
             Sanitizer::Address => write!(f, "AddressSanitizer"),
             Sanitizer::CFI => write!(f, "ControlFlowIntegrity"),
+            Sanitizer::DataFlow => write!(f, "DataFlowSanitizer"),
             Sanitizer::HWAddress => write!(f, "HWAddressSanitizer"),
             Sanitizer::KCFI => write!(f, "KernelControlFlowIntegrity"),
             Sanitizer::Leak => write!(f, "LeakSanitizer"),
@@ -119,64 +542,232 @@ impl std::fmt::Display for Sanitizer {
 
 impl Sanitizer {
     const DEFAULT_RUSTFLAGS: &'static str = "-Copt-level=3";
+    const DEBUG_ASSERTIONS_RUSTFLAGS: &'static str = "-Cdebug-assertions=on";
 
-    fn run_tests(&self) -> anyhow::Result<()> {
-        if is_current_toolchain_nightly() {
+    fn run_timed(
+        &self,
+        args: &VulnerabilitiesCmdArgs,
+        retriever: &impl TargetRetriever,
+        results: &mut Vec<CheckResult>,
+    ) -> anyhow::Result<()> {
+        run_timed(&self.to_string(), results, || {
+            self.run_tests(args, retriever)
+        })
+    }
+
+    fn run_tests(
+        &self,
+        args: &VulnerabilitiesCmdArgs,
+        retriever: &impl TargetRetriever,
+    ) -> anyhow::Result<CheckStatus> {
+        // the targets are retrieved first to report a missing rustup before checking the toolchain
+        let supported = self.is_target_supported(retriever, get_config())?;
+        if check_nightly(args) {
             group!("Sanitizer: {}", self.to_string());
-            let retriever = RustupTargetRetriever;
-            if self.is_target_supported(&retriever) {
-                let envs = vec![
-                    (
-                        "RUSTFLAGS",
-                        format!("{} {}", self.flags(), Sanitizer::DEFAULT_RUSTFLAGS),
-                    ),
-                    ("RUSTDOCFLAGS", self.flags().to_string()),
-                ];
-
-                let features = self.cargo_features();
-                let mut args = vec!["test", "--", "--color=always", "--no-capture"];
-                args.extend(features);
-                let status = StdCommand::new("cargo")
-                    .args(&args)
-                    .envs(envs)
-                    .status()
-                    .map_err(|e| anyhow!("Failed to execute instrumentalized test: {}", e))?;
-                if !status.success() {
-                    return Err(anyhow!("Sanitizer found issues!"));
-                }
+            let status = if supported {
+                self.run_instrumented_tests(args)
             } else {
                 info!("No supported target found for this sanitizer.");
-            }
+                Ok(CheckStatus::Skipped)
+            };
+            // the group is closed on failure as well
             endgroup!();
+            status
         } else {
-            error!("{CARGO_NIGHTLY_MSG}");
+            Ok(CheckStatus::Skipped)
+        }
+    }
+
+    // Run the instrumented tests, the output is printed as it comes and kept to count the
+    // executed tests
+    fn run_instrumented_tests(&self, args: &VulnerabilitiesCmdArgs) -> anyhow::Result<CheckStatus> {
+        let mut command = self.test_command(args);
+        if args.dry_run {
+            info!("Dry run: {}", describe_command(&command));
+            return Ok(CheckStatus::Skipped);
+        }
+        if plan_command(&command) {
+            return Ok(CheckStatus::Skipped);
+        }
+        let (status, output) = run_command_streamed(&mut command)
+            .map_err(|e| anyhow!("Failed to execute instrumentalized test: {}", e))?;
+        if !status.success() {
+            return Err(anyhow!("Sanitizer found issues!"));
+        }
+        self.check_executed_tests(&output, args.require_tests)?;
+        Ok(CheckStatus::Passed)
+    }
+
+    // Run the instrumented tests alongside other sanitizers, the output is captured to be printed
+    // at once by `report_captured` so that the outputs of the sanitizers are not interleaved.
+    // Returns None when the command is only planned.
+    fn run_captured(&self, args: &VulnerabilitiesCmdArgs) -> anyhow::Result<Option<Output>> {
+        info!("Starting {}...", self);
+        let mut command = self.test_command(args);
+        if plan_command(&command) {
+            return Ok(None);
         }
+        spawn_child(command.stdout(Stdio::piped()).stderr(Stdio::piped()))
+            .and_then(wait_child_output)
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to execute instrumentalized test: {}", e))
+    }
+
+    // Print the output captured by `run_captured` in the group of the sanitizer and check it
+    fn report_captured(&self, output: &Output, require_tests: bool) -> anyhow::Result<()> {
+        group!("Sanitizer: {}", self.to_string());
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        endgroup!();
+        if !output.status.success() {
+            return Err(anyhow!("{} found issues!", self));
+        }
+        self.check_executed_tests(&String::from_utf8_lossy(&output.stdout), require_tests)
+    }
+
+    // Warn, or fail if `require_tests` is true, when the passed libtest output has no executed
+    // test as the sanitizer then exercised nothing
+    fn check_executed_tests(&self, output: &str, require_tests: bool) -> anyhow::Result<()> {
+        if count_executed_tests(output) > 0 {
+            return Ok(());
+        }
+        let msg = format!("{self} ran no test, the sanitizer did not exercise any code.");
+        if require_tests {
+            return Err(anyhow!(msg));
+        }
+        warn!("{msg}");
         Ok(())
     }
 
+    // Returns the cargo test command of the instrumented tests, built in the target directory of
+    // the sanitizer
+    fn test_command(&self, args: &VulnerabilitiesCmdArgs) -> StdCommand {
+        let mut command = StdCommand::new("cargo");
+        if let Some(toolchain) = &args.toolchain {
+            command.arg(format!("+{toolchain}"));
+        }
+        command.args(self.cargo_test_args(args.cargo_profile.as_deref()));
+        if args.clean_env {
+            command
+                .env_clear()
+                .envs(passthrough_envs(&args.env_passthrough));
+        }
+        command.env(
+            "CARGO_TARGET_DIR",
+            get_sanitizer_target_dir(
+                &self.to_string(),
+                args.target_dir.as_deref(),
+                std::env::var_os("CARGO_TARGET_DIR").map(PathBuf::from),
+            ),
+        );
+        let flags = [
+            (
+                FlagsVar::Rustflags,
+                self.rustflags(
+                    args.debug_assertions,
+                    args.cargo_profile.is_some(),
+                    args.opt_level.as_deref(),
+                    args.extra_rustflags.as_deref(),
+                ),
+            ),
+            (
+                FlagsVar::Rustdocflags,
+                self.rustdocflags(args.debug_assertions),
+            ),
+        ];
+        for (var, flags) in flags {
+            let flags: Vec<String> = flags.split_whitespace().map(String::from).collect();
+            set_flags_env(&mut command, var, &flags, inherit_flags(var, args));
+        }
+        command
+    }
+
+    // Returns the arguments of the cargo test command of the instrumented tests
+    fn cargo_test_args<'a>(&self, cargo_profile: Option<&'a str>) -> Vec<&'a str> {
+        let mut cargo_args = vec!["test"];
+        if let Some(profile) = cargo_profile {
+            cargo_args.extend(["--profile", profile]);
+        }
+        cargo_args.extend(["--", "--color=always", "--nocapture"]);
+        cargo_args.extend(self.cargo_features());
+        cargo_args
+    }
+
+    // Returns the RUSTFLAGS value for the sanitizer build, the optimization level is the passed one
+    // if any, or else it is left to the cargo profile when one is passed. The extra flags come last.
+    fn rustflags(
+        &self,
+        debug_assertions: bool,
+        cargo_profile: bool,
+        opt_level: Option<&str>,
+        extra_rustflags: Option<&str>,
+    ) -> String {
+        let mut flags = match opt_level {
+            Some(level) => format!("{} -Copt-level={level}", self.flags()),
+            None if cargo_profile => self.flags().to_string(),
+            None => format!("{} {}", self.flags(), Sanitizer::DEFAULT_RUSTFLAGS),
+        };
+        if debug_assertions {
+            flags = format!("{flags} {}", Sanitizer::DEBUG_ASSERTIONS_RUSTFLAGS);
+        }
+        if let Some(extra) = extra_rustflags.map(str::trim).filter(|e| !e.is_empty()) {
+            flags = format!("{flags} {extra}");
+        }
+        flags
+    }
+
+    // Returns the RUSTDOCFLAGS value for the sanitizer build
+    fn rustdocflags(&self, debug_assertions: bool) -> String {
+        let mut flags = self.flags().to_string();
+        if debug_assertions {
+            flags = format!("{flags} {}", Sanitizer::DEBUG_ASSERTIONS_RUSTFLAGS);
+        }
+        flags
+    }
+
     fn flags(&self) -> &'static str {
         match self {
             Sanitizer::Address => "-Zsanitizer=address",
             Sanitizer::CFI => "-Zsanitizer=cfi -Clto",
+            Sanitizer::DataFlow => "-Zsanitizer=dataflow",
             Sanitizer::HWAddress => "-Zsanitizer=hwaddress -Ctarget-feature=+tagged-globals",
             Sanitizer::KCFI => "-Zsanitizer=kcfi",
             Sanitizer::Leak => "-Zsanitizer=leak",
             Sanitizer::Memory => "-Zsanitizer=memory -Zsanitizer-memory-track-origins",
-            Sanitizer::MemTag => "--Zsanitizer=memtag -Ctarget-feature=\"+mte\"",
+            Sanitizer::MemTag => "-Zsanitizer=memtag -Ctarget-feature=+mte",
             Sanitizer::SafeStack => "-Zsanitizer=safestack",
             Sanitizer::ShadowCallStack => "-Zsanitizer=shadow-call-stack",
             Sanitizer::Thread => "-Zsanitizer=thread",
         }
     }
 
-    fn cargo_features(&self) -> Vec<&str> {
+    fn cargo_features(&self) -> Vec<&'static str> {
         match self {
             Sanitizer::CFI => vec!["-Zbuild-std", "--target x86_64-unknown-linux-gnu"],
             _ => vec![],
         }
     }
 
-    fn supported_targets(&self) -> Vec<Target> {
+    /// Returns the sanitizer with the passed name, i.e. `AddressSanitizer`, ignoring the case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Sanitizer::iter().find(|s| s.to_string().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the triples of the targets supporting the sanitizer, the built-in ones followed by
+    /// the ones declared in the `vulnerabilities.sanitizer-targets` table of the passed configuration.
+    pub fn supported_targets(&self, config: &Config) -> Vec<String> {
+        let mut targets: Vec<String> = self
+            .builtin_targets()
+            .into_iter()
+            .chain(self.configured_targets(config))
+            .map(|target| target.to_string())
+            .collect();
+        let mut seen = std::collections::HashSet::new();
+        targets.retain(|target| seen.insert(target.clone()));
+        targets
+    }
+
+    fn builtin_targets(&self) -> Vec<Target> {
         match self {
             Sanitizer::Address => vec![
                 Target::Aarch64AppleDarwin,
@@ -188,6 +779,7 @@ impl Sanitizer {
                 Target::X8664UnknownLinuxGnu,
             ],
             Sanitizer::CFI => vec![Target::X8664UnknownLinuxGnu],
+            Sanitizer::DataFlow => vec![Target::X8664UnknownLinuxGnu],
             Sanitizer::HWAddress => {
                 vec![Target::Aarch64LinuxAndroid, Target::Aarch64UnknownLinuxGnu]
             }
@@ -221,17 +813,148 @@ impl Sanitizer {
         }
     }
 
-    // Returns true if the sanitizer is supported by the currently installed targets
-    fn is_target_supported<T: TargetRetriever>(&self, retriever: &T) -> bool {
-        let installed_targets = retriever.get_installed_targets();
-        let supported = self.supported_targets();
-        installed_targets.iter().any(|installed| {
-            let installed_target = Target::from_str(installed.trim()).unwrap_or(Target::Unknown);
-            supported.iter().any(|target| target == &installed_target)
-        })
+    // Returns the targets declared as supporting the sanitizer in the configuration file
+    fn configured_targets(&self, config: &Config) -> Vec<Target> {
+        let name = self.to_string();
+        config
+            .get_table_keys(SANITIZER_TARGETS_CONFIG_KEY)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|triple| {
+                config
+                    .get_string_array(&format!("{SANITIZER_TARGETS_CONFIG_KEY}.{triple}"))
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|sanitizer| sanitizer.eq_ignore_ascii_case(&name))
+            })
+            .map(|triple| Target::from_str(&triple).unwrap_or(Target::Configured(triple)))
+            .collect()
     }
+
+    /// Returns true if the sanitizer is supported by one of the targets returned by the passed
+    /// retriever, usually the installed targets. Fails if the targets cannot be retrieved.
+    pub fn is_target_supported<T: TargetRetriever>(
+        &self,
+        retriever: &T,
+        config: &Config,
+    ) -> anyhow::Result<bool> {
+        let installed_targets = retriever.get_installed_targets()?;
+        let mut supported = self.builtin_targets();
+        supported.extend(self.configured_targets(config));
+        Ok(installed_targets.iter().any(|installed| {
+            let installed = normalize_triple(installed);
+            let installed_target = Target::from_str(&installed).unwrap_or(Target::Unknown);
+            supported.iter().any(|target| match target {
+                Target::Configured(triple) => normalize_triple(triple) == installed,
+                target => target == &installed_target,
+            })
+        }))
+    }
+}
+
+// Returns the cache directory of the passed toolchain, or else of the current one, and removes the
+// ones of the other toolchains if `prune` is true. Returns None if the toolchain version cannot be
+// determined, then nothing is cached.
+fn get_cache_dir(toolchain: Option<&str>, prune: bool) -> Option<PathBuf> {
+    let dir = get_cache_path(toolchain)?;
+    let key = dir.file_name()?;
+    let entries = std::fs::read_dir(VULNERABILITIES_CACHE_DIR)
+        .ok()
+        .filter(|_| prune);
+    if let Some(entries) = entries {
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != key)
+            .for_each(|entry| {
+                info!(
+                    "Removing the cached builds of toolchain {}",
+                    entry.file_name().to_string_lossy()
+                );
+                let _ = std::fs::remove_dir_all(entry.path());
+            });
+    }
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+// Returns the cache directory where cargo-careful prepares its sysroots on the passed OS, the
+// variables of the environment are read with `var`
+fn get_careful_cache_dir(
+    os: &str,
+    var: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> Option<PathBuf> {
+    match os {
+        "windows" => var("LOCALAPPDATA").map(|dir| {
+            PathBuf::from(dir)
+                .join("rust-lang")
+                .join("cargo-careful")
+                .join("cache")
+        }),
+        "macos" => var("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Caches")
+                .join("org.rust-lang.cargo-careful")
+        }),
+        _ => var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join("cargo-careful")),
+    }
+}
+
+// Returns the cache directory of the passed toolchain, or else of the current one, without
+// creating it. Returns None if the toolchain version cannot be determined.
+fn get_cache_path(toolchain: Option<&str>) -> Option<PathBuf> {
+    let key = get_toolchain_cache_key(toolchain)?;
+    Some(Path::new(VULNERABILITIES_CACHE_DIR).join(key))
+}
+
+// Returns the target directory of the sanitizer build, so that the instrumented builds do not
+// invalidate the regular ones and the sanitizers running in parallel do not wait for each other.
+// The directory passed with --target-dir wins, otherwise the sanitizer builds under the
+// `sanitizers` directory of the inherited target directory, or of `target`.
+fn get_sanitizer_target_dir(
+    name: &str,
+    target_dir: Option<&Path>,
+    inherited: Option<PathBuf>,
+) -> PathBuf {
+    match target_dir {
+        Some(dir) => dir.join(name),
+        None => inherited
+            .unwrap_or_else(|| PathBuf::from("target"))
+            .join(SANITIZERS_TARGET_DIR)
+            .join(name),
+    }
+}
+
+// Returns true if the sanitizer flags are appended to the flags of the environment, which are
+// only inherited with --clean-env when they are passed through
+fn inherit_flags(var: FlagsVar, args: &VulnerabilitiesCmdArgs) -> bool {
+    !args.no_inherit_rustflags
+        && (!args.clean_env || args.env_passthrough.iter().any(|name| name == var.name()))
+}
+
+// Returns the allowlisted environment variables and the passed ones which are set
+fn passthrough_envs(env_passthrough: &[String]) -> Vec<(String, String)> {
+    CLEAN_ENV_ALLOWLIST
+        .iter()
+        .map(|name| name.to_string())
+        .chain(env_passthrough.iter().cloned())
+        .filter_map(|name| std::env::var(&name).ok().map(|value| (name, value)))
+        .collect()
 }
 
+// Host targets of the miri component
+const MIRI_HOST_TARGETS: [&str; 5] = [
+    AARCH64_APPLE_DARWIN,
+    AARCH64_UNKNOWN_LINUX_GNU,
+    X8664_APPLE_DARWIN,
+    X8664_UNKNOWN_LINUX_GNU,
+    "x86_64-pc-windows-msvc",
+];
+
 // Constants for target names
 const AARCH64_APPLE_DARWIN: &str = "aarch64-apple-darwin";
 const AARCH64_LINUX_ANDROID: &str = "aarch64-linux-android";
@@ -243,18 +966,66 @@ const X8664_UNKNOWN_FUCHSIA: &str = "x86_64-unknown-fuchsia";
 const X8664_UNKNOWN_FREEBSD: &str = "x86_64-unknown-freebsd";
 const X8664_UNKNOWN_LINUX_GNU: &str = "x86_64-unknown-linux-gnu";
 
-trait TargetRetriever {
-    fn get_installed_targets(&self) -> Vec<String>;
+/// Returns the sanitizer targets, built-in and configured in the `xtask.toml` file, supporting the
+/// sanitizer with the passed name, or None if there is no such sanitizer.
+pub fn sanitizer_supported_targets(name: &str) -> Option<Vec<String>> {
+    Sanitizer::from_name(name).map(|sanitizer| sanitizer.supported_targets(get_config()))
+}
+
+/// Retrieves the target triples checked by `Sanitizer::is_target_supported`.
+pub trait TargetRetriever {
+    fn get_installed_targets(&self) -> anyhow::Result<Vec<String>>;
 }
 
-struct RustupTargetRetriever;
+/// Retrieves the targets installed with rustup for the current toolchain, or for the toolchain
+/// passed to `for_toolchain`, fails if rustup is not installed.
+#[derive(Default)]
+pub struct RustupTargetRetriever {
+    toolchain: Option<String>,
+}
+
+impl RustupTargetRetriever {
+    pub fn for_toolchain(toolchain: &str) -> Self {
+        Self {
+            toolchain: Some(toolchain.to_string()),
+        }
+    }
+}
 
 impl TargetRetriever for RustupTargetRetriever {
-    fn get_installed_targets(&self) -> Vec<String> {
-        rustup_get_installed_targets()
-            .lines()
-            .map(|s| s.to_string())
-            .collect()
+    fn get_installed_targets(&self) -> anyhow::Result<Vec<String>> {
+        let targets = match &self.toolchain {
+            Some(toolchain) => rustup_get_toolchain_installed_targets(toolchain),
+            None => rustup_get_installed_targets(),
+        }
+        .map_err(|e| anyhow!("{e}; sanitizers require a rustup-managed nightly toolchain"))?;
+        Ok(targets.lines().map(|s| s.to_string()).collect())
+    }
+}
+
+/// Retrieves the targets of the wrapped retriever once and returns the same targets afterwards.
+pub struct CachedTargetRetriever<T: TargetRetriever> {
+    inner: T,
+    targets: OnceCell<Vec<String>>,
+}
+
+impl<T: TargetRetriever> CachedTargetRetriever<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            targets: OnceCell::new(),
+        }
+    }
+}
+
+impl<T: TargetRetriever> TargetRetriever for CachedTargetRetriever<T> {
+    fn get_installed_targets(&self) -> anyhow::Result<Vec<String>> {
+        if let Some(targets) = self.targets.get() {
+            return Ok(targets.clone());
+        }
+        // a failure is not cached so that the next call retries
+        let targets = self.inner.get_installed_targets()?;
+        Ok(self.targets.get_or_init(|| targets).clone())
     }
 }
 
@@ -271,12 +1042,57 @@ enum Target {
     X8664UnknownFuchsia,
     X8664UnknownFreebsd,
     X8664UnknownLinuxGnu,
+    // Target declared in the configuration file
+    Configured(String),
     Unknown,
 }
 
 impl Target {
+    /// Returns the host target of the current toolchain as reported by `rustc -vV`, or else the
+    /// target the xtask binary has been compiled for.
+    fn host() -> Self {
+        let output = StdCommand::new("rustc")
+            .arg("-vV")
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        match parse_host_triple(&output) {
+            Some(triple) => {
+                Target::from_str(triple).unwrap_or(Target::Configured(triple.to_string()))
+            }
+            None => {
+                let env = if cfg!(target_env = "gnu") { "gnu" } else { "" };
+                Target::from_os_arch(std::env::consts::OS, std::env::consts::ARCH, env)
+            }
+        }
+    }
+
+    /// Returns the target of the passed `std::env::consts` OS and architecture and of the passed
+    /// `target_env`, `Unknown` if no sanitizer supports it.
+    fn from_os_arch(os: &str, arch: &str, env: &str) -> Self {
+        match (os, arch, env) {
+            ("macos", "aarch64", _) => Self::Aarch64AppleDarwin,
+            ("macos", "x86_64", _) => Self::X8664AppleDarwin,
+            ("android", "aarch64", _) => Self::Aarch64LinuxAndroid,
+            ("android", "x86_64", _) => Self::X8664LinuxAndroid,
+            ("fuchsia", "aarch64", _) => Self::Aarch64UnknownFuchsia,
+            ("fuchsia", "x86_64", _) => Self::X8664UnknownFuchsia,
+            ("freebsd", "x86_64", _) => Self::X8664UnknownFreebsd,
+            ("linux", "aarch64", "gnu") => Self::Aarch64UnknownLinuxGnu,
+            ("linux", "x86_64", "gnu") => Self::X8664UnknownLinuxGnu,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Returns true if the miri component is distributed for this host target.
+    fn is_miri_supported(&self) -> bool {
+        MIRI_HOST_TARGETS.contains(&self.to_string().as_str())
+    }
+
+    /// Returns the target of the passed triple, the case, the surrounding whitespace and the
+    /// `(default)` or `(installed)` annotation of the rustup output are ignored.
     fn from_str(s: &str) -> Option<Self> {
-        match s {
+        match normalize_triple(s).as_str() {
             AARCH64_APPLE_DARWIN => Some(Self::Aarch64AppleDarwin),
             AARCH64_LINUX_ANDROID => Some(Self::Aarch64LinuxAndroid),
             AARCH64_UNKNOWN_FUCHSIA => Some(Self::Aarch64UnknownFuchsia),
@@ -303,16 +1119,39 @@ impl std::fmt::Display for Target {
             Target::X8664UnknownFuchsia => X8664_UNKNOWN_FUCHSIA,
             Target::X8664UnknownFreebsd => X8664_UNKNOWN_FREEBSD,
             Target::X8664UnknownLinuxGnu => X8664_UNKNOWN_LINUX_GNU,
+            Target::Configured(triple) => triple,
             Target::Unknown => "",
         };
         write!(f, "{target_str}")
     }
 }
 
+// Returns the passed target triple lowercased without the surrounding whitespace and without the
+// `(default)` or `(installed)` annotation of the `rustup target list` output
+fn normalize_triple(triple: &str) -> String {
+    let triple = triple.trim().to_lowercase();
+    triple
+        .strip_suffix("(default)")
+        .or_else(|| triple.strip_suffix("(installed)"))
+        .unwrap_or(&triple)
+        .trim_end()
+        .to_string()
+}
+
+// Returns the host triple of the passed `rustc -vV` output
+fn parse_host_triple(output: &str) -> Option<&str> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("host:"))
+        .map(str::trim)
+        .filter(|triple| !triple.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rstest::rstest;
+    use std::cell::Cell;
 
     struct MockTargetRetriever {
         mock_data: Vec<String>,
@@ -325,22 +1164,429 @@ mod tests {
     }
 
     impl TargetRetriever for MockTargetRetriever {
-        fn get_installed_targets(&self) -> Vec<String> {
-            self.mock_data.clone()
+        fn get_installed_targets(&self) -> anyhow::Result<Vec<String>> {
+            Ok(self.mock_data.clone())
         }
     }
 
     #[rstest]
-    #[case(vec!["".to_string()], false)] // empty string
-    #[case(vec!["x86_64-pc-windows-msvc".to_string()], false)] // not supported target
-    #[case(vec!["x86_64-pc-windows-msvc".to_string(), "".to_string()], false)] // not supported target and empty string
-    #[case(vec!["x86_64-unknown-linux-gnu".to_string()], true)] // one supported target
-    #[case(vec!["aarch64-apple-darwin".to_string(), "x86_64-unknown-linux-gnu".to_string()], true)] // one unsupported target and one supported
-    fn test_is_target_supported(#[case] installed_targets: Vec<String>, #[case] expected: bool) {
+    #[case(Sanitizer::Memory, vec!["".to_string()], false)] // empty string
+    #[case(Sanitizer::Memory, vec!["x86_64-pc-windows-msvc".to_string()], false)] // not supported target
+    #[case(Sanitizer::Memory, vec!["x86_64-pc-windows-msvc".to_string(), "".to_string()], false)] // not supported target and empty string
+    #[case(Sanitizer::Memory, vec!["x86_64-unknown-linux-gnu".to_string()], true)] // one supported target
+    #[case(Sanitizer::Memory, vec!["aarch64-apple-darwin".to_string(), "x86_64-unknown-linux-gnu".to_string()], true)] // one unsupported target and one supported
+    #[case(Sanitizer::DataFlow, vec!["x86_64-unknown-linux-gnu".to_string()], true)] // dataflow supported target
+    #[case(Sanitizer::DataFlow, vec!["aarch64-unknown-linux-gnu".to_string()], false)] // dataflow not supported target
+    #[case(Sanitizer::Memory, vec!["x86_64-unknown-linux-gnu (installed)".to_string()], true)] // annotated rustup line
+    fn test_is_target_supported(
+        #[case] sanitizer: Sanitizer,
+        #[case] installed_targets: Vec<String>,
+        #[case] expected: bool,
+    ) {
         let mock_retriever = MockTargetRetriever::new(installed_targets);
-        let sanitizer = Sanitizer::Memory;
         // std::thread::sleep(std::time::Duration::from_secs(1));
-        assert_eq!(sanitizer.is_target_supported(&mock_retriever), expected);
+        assert_eq!(
+            sanitizer
+                .is_target_supported(&mock_retriever, &Config::default())
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::default(None, None, "target/sanitizers/ThreadSanitizer")]
+    #[case::inherited(None, Some("custom"), "custom/sanitizers/ThreadSanitizer")]
+    #[case::target_dir(
+        Some("target/instrumented"),
+        Some("custom"),
+        "target/instrumented/ThreadSanitizer"
+    )]
+    #[case::target_dir_only(
+        Some("target/instrumented"),
+        None,
+        "target/instrumented/ThreadSanitizer"
+    )]
+    fn test_get_sanitizer_target_dir(
+        #[case] target_dir: Option<&str>,
+        #[case] inherited: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            get_sanitizer_target_dir(
+                "ThreadSanitizer",
+                target_dir.map(Path::new),
+                inherited.map(PathBuf::from)
+            ),
+            PathBuf::from(expected)
+        );
+    }
+
+    #[rstest]
+    #[case::linux("linux", &[("HOME", "/home/me")], Some("/home/me/.cache/cargo-careful"))]
+    #[case::linux_xdg(
+        "linux",
+        &[("HOME", "/home/me"), ("XDG_CACHE_HOME", "/cache")],
+        Some("/cache/cargo-careful")
+    )]
+    #[case::linux_relative_xdg(
+        "linux",
+        &[("HOME", "/home/me"), ("XDG_CACHE_HOME", "cache")],
+        Some("/home/me/.cache/cargo-careful")
+    )]
+    #[case::macos(
+        "macos",
+        &[("HOME", "/Users/me")],
+        Some("/Users/me/Library/Caches/org.rust-lang.cargo-careful")
+    )]
+    #[case::no_home("linux", &[], None)]
+    fn test_get_careful_cache_dir(
+        #[case] os: &str,
+        #[case] vars: &[(&str, &str)],
+        #[case] expected: Option<&str>,
+    ) {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.into())
+        };
+        assert_eq!(get_careful_cache_dir(os, var), expected.map(PathBuf::from));
+    }
+
+    #[rstest]
+    #[case::display_name("ThreadSanitizer", Some(Sanitizer::Thread))]
+    #[case::ignore_case("addresssanitizer", Some(Sanitizer::Address))]
+    #[case::unknown("UndefinedSanitizer", None)]
+    fn test_sanitizer_from_name(#[case] name: &str, #[case] expected: Option<Sanitizer>) {
+        assert_eq!(Sanitizer::from_name(name), expected);
+    }
+
+    #[test]
+    fn test_supported_targets() {
+        let config = Config::parse(SANITIZER_TARGETS_CONFIG).expect("config should be valid");
+        assert_eq!(
+            Sanitizer::Leak.supported_targets(&config),
+            vec![
+                "aarch64-apple-darwin",
+                "aarch64-unknown-linux-gnu",
+                "x86_64-apple-darwin",
+                "x86_64-unknown-linux-gnu",
+                "riscv64gc-unknown-linux-gnu",
+            ]
+        );
+        assert_eq!(
+            Sanitizer::SafeStack.supported_targets(&Config::default()),
+            vec!["x86_64-unknown-linux-gnu"]
+        );
+    }
+
+    const SANITIZER_TARGETS_CONFIG: &str = r#"
+[vulnerabilities.sanitizer-targets]
+riscv64gc-unknown-linux-gnu = ["AddressSanitizer", "leaksanitizer"]
+aarch64-apple-darwin = ["MemorySanitizer"]
+"#;
+
+    struct CountingTargetRetriever {
+        calls: Cell<usize>,
+    }
+
+    impl TargetRetriever for CountingTargetRetriever {
+        fn get_installed_targets(&self) -> anyhow::Result<Vec<String>> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(vec!["x86_64-unknown-linux-gnu".to_string()])
+        }
+    }
+
+    #[test]
+    fn test_cached_target_retriever() {
+        let retriever = CachedTargetRetriever::new(CountingTargetRetriever {
+            calls: Cell::new(0),
+        });
+        let config = Config::default();
+        assert!(Sanitizer::Address
+            .is_target_supported(&retriever, &config)
+            .unwrap());
+        assert!(Sanitizer::Memory
+            .is_target_supported(&retriever, &config)
+            .unwrap());
+        assert!(!Sanitizer::ShadowCallStack
+            .is_target_supported(&retriever, &config)
+            .unwrap());
+        assert_eq!(retriever.inner.calls.get(), 1);
+    }
+
+    struct MissingRustupRetriever;
+
+    impl TargetRetriever for MissingRustupRetriever {
+        fn get_installed_targets(&self) -> anyhow::Result<Vec<String>> {
+            Err(anyhow!("rustup not found"))
+        }
+    }
+
+    #[test]
+    fn test_is_target_supported_without_rustup() {
+        let retriever = CachedTargetRetriever::new(MissingRustupRetriever);
+        let result = Sanitizer::Address.is_target_supported(&retriever, &Config::default());
+        assert_eq!(result.unwrap_err().to_string(), "rustup not found");
+        assert!(retriever.targets.get().is_none());
+    }
+
+    #[rstest]
+    #[case::default(None)]
+    #[case::cargo_profile(Some("sanitizer"))]
+    fn test_cargo_test_args_nocapture(#[case] cargo_profile: Option<&str>) {
+        for sanitizer in Sanitizer::iter() {
+            let args = sanitizer.cargo_test_args(cargo_profile);
+            assert!(args.contains(&"--nocapture"), "{sanitizer} args: {args:?}");
+            assert!(
+                !args.contains(&"--no-capture"),
+                "{sanitizer} args: {args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sanitizer_flags_are_well_formed() {
+        for sanitizer in Sanitizer::iter() {
+            let flags = sanitizer.flags();
+            assert!(!flags.starts_with("--Z"), "{sanitizer} flags: {flags}");
+            assert!(!flags.contains('"'), "{sanitizer} flags: {flags}");
+        }
+    }
+
+    #[rstest]
+    #[case::configured_target(Sanitizer::Address, "riscv64gc-unknown-linux-gnu", true)]
+    #[case::case_insensitive_name(Sanitizer::Leak, "riscv64gc-unknown-linux-gnu", true)]
+    #[case::sanitizer_not_configured(Sanitizer::Thread, "riscv64gc-unknown-linux-gnu", false)]
+    #[case::builtin_target_extended(Sanitizer::Memory, "aarch64-apple-darwin", true)]
+    #[case::builtin_target_kept(Sanitizer::Memory, "x86_64-unknown-linux-gnu", true)]
+    fn test_is_target_supported_with_config(
+        #[case] sanitizer: Sanitizer,
+        #[case] installed_target: &str,
+        #[case] expected: bool,
+    ) {
+        let config = Config::parse(SANITIZER_TARGETS_CONFIG).unwrap();
+        let mock_retriever = MockTargetRetriever::new(vec![installed_target.to_string()]);
+        assert_eq!(
+            sanitizer
+                .is_target_supported(&mock_retriever, &config)
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_report_to_json() {
+        let results = vec![
+            CheckResult {
+                check: "NightlyChecks".to_string(),
+                status: CheckStatus::Passed,
+                duration: Duration::from_millis(1234),
+            },
+            CheckResult {
+                check: "MemorySanitizer".to_string(),
+                status: CheckStatus::Skipped,
+                duration: Duration::ZERO,
+            },
+        ];
+        assert_eq!(
+            report_to_json(&results),
+            serde_json::json!([
+                { "check": "NightlyChecks", "status": "passed", "duration_ms": 1234 },
+                { "check": "MemorySanitizer", "status": "skipped", "duration_ms": 0 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_run_timed() {
+        let mut results = vec![];
+        assert!(run_timed("A", &mut results, || Ok(CheckStatus::Passed)).is_ok());
+        assert!(run_timed("B", &mut results, || Err(anyhow!("issues"))).is_err());
+        assert!(run_timed("C", &mut results, || Ok(CheckStatus::Skipped)).is_ok());
+        let statuses: Vec<(&str, CheckStatus)> = results
+            .iter()
+            .map(|r| (r.check.as_str(), r.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("A", CheckStatus::Passed),
+                ("B", CheckStatus::Failed),
+                ("C", CheckStatus::Skipped)
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::linux(
+        Target::X8664UnknownLinuxGnu,
+        vec![Sanitizer::Address, Sanitizer::Leak, Sanitizer::Memory, Sanitizer::SafeStack, Sanitizer::Thread]
+    )]
+    #[case::apple_silicon(
+        Target::Aarch64AppleDarwin,
+        vec![Sanitizer::Address, Sanitizer::Leak, Sanitizer::Thread]
+    )]
+    #[case::freebsd(
+        Target::X8664UnknownFreebsd,
+        vec![Sanitizer::Address, Sanitizer::Memory, Sanitizer::Thread]
+    )]
+    #[case::fuchsia(Target::X8664UnknownFuchsia, vec![Sanitizer::Address])]
+    #[case::windows(Target::Configured("x86_64-pc-windows-msvc".to_string()), vec![])]
+    #[case::unknown(Target::Unknown, vec![])]
+    fn test_get_host_sanitizers(#[case] host: Target, #[case] expected: Vec<Sanitizer>) {
+        assert_eq!(get_host_sanitizers(&host, &Config::default()), expected);
+    }
+
+    #[rstest]
+    #[case::no_test("\nrunning 0 tests\n\ntest result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n", 0)]
+    #[case::only_ignored(
+        "test result: ok. 0 passed; 0 failed; 3 ignored; 0 measured; 0 filtered out\n",
+        0
+    )]
+    #[case::summed(
+        "test result: \x1b[32mok\x1b[0m. 2 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out\n\
+         Doc-tests my_crate\n\
+         test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out\n",
+        4
+    )]
+    fn test_count_executed_tests(#[case] output: &str, #[case] expected: usize) {
+        assert_eq!(count_executed_tests(output), expected);
+    }
+
+    #[rstest]
+    #[case::linux(Target::X8664UnknownLinuxGnu, true)]
+    #[case::apple_silicon(Target::Aarch64AppleDarwin, true)]
+    #[case::windows(Target::Configured("x86_64-pc-windows-msvc".to_string()), true)]
+    #[case::freebsd(Target::X8664UnknownFreebsd, false)]
+    #[case::android(Target::Aarch64LinuxAndroid, false)]
+    #[case::unknown(Target::Unknown, false)]
+    fn test_is_miri_supported(#[case] host: Target, #[case] expected: bool) {
+        assert_eq!(host.is_miri_supported(), expected);
+    }
+
+    #[rstest]
+    #[case::linux("linux", "x86_64", "gnu", Target::X8664UnknownLinuxGnu)]
+    #[case::linux_arm("linux", "aarch64", "gnu", Target::Aarch64UnknownLinuxGnu)]
+    #[case::linux_musl("linux", "x86_64", "musl", Target::Unknown)]
+    #[case::macos("macos", "aarch64", "", Target::Aarch64AppleDarwin)]
+    #[case::android("android", "aarch64", "", Target::Aarch64LinuxAndroid)]
+    #[case::freebsd("freebsd", "x86_64", "", Target::X8664UnknownFreebsd)]
+    #[case::fuchsia("fuchsia", "x86_64", "", Target::X8664UnknownFuchsia)]
+    #[case::fuchsia_arm("fuchsia", "aarch64", "", Target::Aarch64UnknownFuchsia)]
+    #[case::freebsd_arm("freebsd", "aarch64", "", Target::Unknown)]
+    #[case::windows("windows", "x86_64", "msvc", Target::Unknown)]
+    fn test_target_from_os_arch(
+        #[case] os: &str,
+        #[case] arch: &str,
+        #[case] env: &str,
+        #[case] expected: Target,
+    ) {
+        assert_eq!(Target::from_os_arch(os, arch, env), expected);
+    }
+
+    #[rstest]
+    #[case::linux(
+        "rustc 1.80.0 (051478957 2024-07-21)\nbinary: rustc\nhost: x86_64-unknown-linux-gnu\nrelease: 1.80.0\n",
+        Some("x86_64-unknown-linux-gnu")
+    )]
+    #[case::windows(
+        "binary: rustc\nhost: x86_64-pc-windows-msvc\r\n",
+        Some("x86_64-pc-windows-msvc")
+    )]
+    #[case::missing("binary: rustc\n", None)]
+    fn test_parse_host_triple(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(parse_host_triple(output), expected);
+    }
+
+    #[rstest]
+    #[case::default(None, None, "-Zsanitizer=thread -Copt-level=3")]
+    #[case::opt_level(Some("1"), None, "-Zsanitizer=thread -Copt-level=1")]
+    #[case::extra_rustflags(
+        None,
+        Some("-Cdebuginfo=2"),
+        "-Zsanitizer=thread -Copt-level=3 -Cdebuginfo=2"
+    )]
+    #[case::empty_extra_rustflags(None, Some(" "), "-Zsanitizer=thread -Copt-level=3")]
+    #[case::both(
+        Some("s"),
+        Some("-Cdebuginfo=2 -Cforce-frame-pointers=yes"),
+        "-Zsanitizer=thread -Copt-level=s -Cdebuginfo=2 -Cforce-frame-pointers=yes"
+    )]
+    fn test_sanitizer_rustflags_overrides(
+        #[case] opt_level: Option<&str>,
+        #[case] extra_rustflags: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            Sanitizer::Thread.rustflags(false, false, opt_level, extra_rustflags),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::without_debug_assertions(
+        false,
+        false,
+        "-Zsanitizer=address -Copt-level=3",
+        "-Zsanitizer=address"
+    )]
+    #[case::with_debug_assertions(
+        true,
+        false,
+        "-Zsanitizer=address -Copt-level=3 -Cdebug-assertions=on",
+        "-Zsanitizer=address -Cdebug-assertions=on"
+    )]
+    #[case::with_cargo_profile(
+        true,
+        true,
+        "-Zsanitizer=address -Cdebug-assertions=on",
+        "-Zsanitizer=address -Cdebug-assertions=on"
+    )]
+    fn test_sanitizer_flags_composition(
+        #[case] debug_assertions: bool,
+        #[case] cargo_profile: bool,
+        #[case] expected_rustflags: &str,
+        #[case] expected_rustdocflags: &str,
+    ) {
+        let sanitizer = Sanitizer::Address;
+        assert_eq!(
+            sanitizer.rustflags(debug_assertions, cargo_profile, None, None),
+            expected_rustflags
+        );
+        assert_eq!(
+            sanitizer.rustdocflags(debug_assertions),
+            expected_rustdocflags
+        );
+    }
+
+    #[test]
+    fn test_passthrough_envs() {
+        let envs = passthrough_envs(&[
+            "CARGO_PKG_NAME".to_string(),
+            "XTASK_UNSET_PASSTHROUGH_VAR".to_string(),
+        ]);
+        let names: Vec<&str> = envs.iter().map(|(name, _)| name.as_str()).collect();
+        assert!(names.contains(&"PATH"));
+        assert!(names.contains(&"CARGO_PKG_NAME"));
+        assert!(!names.contains(&"XTASK_UNSET_PASSTHROUGH_VAR"));
+    }
+
+    #[rstest]
+    #[case::exact("x86_64-unknown-linux-gnu", Some(Target::X8664UnknownLinuxGnu))]
+    #[case::installed(
+        "x86_64-unknown-linux-gnu (installed)",
+        Some(Target::X8664UnknownLinuxGnu)
+    )]
+    #[case::default(" aarch64-apple-darwin (default)\n", Some(Target::Aarch64AppleDarwin))]
+    #[case::mixed_case("X86_64-Unknown-Linux-GNU", Some(Target::X8664UnknownLinuxGnu))]
+    #[case::mixed_case_annotation(
+        "Aarch64-Linux-Android (Installed)",
+        Some(Target::Aarch64LinuxAndroid)
+    )]
+    #[case::unknown("x86_64-pc-windows-msvc (installed)", None)]
+    #[case::empty("  ", None)]
+    fn test_target_from_str(#[case] s: &str, #[case] expected: Option<Target>) {
+        assert_eq!(Target::from_str(s), expected);
     }
 
     #[test]
@@ -0,0 +1,172 @@
+use std::{path::Path, sync::OnceLock};
+
+use anyhow::anyhow;
+use toml_edit::{DocumentMut, Item};
+
+use crate::group_info;
+
+/// Name of the optional configuration file at the root of the workspace.
+pub const CONFIG_FILENAME: &str = "xtask.toml";
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Configuration read from the optional `xtask.toml` file at the root of the workspace.
+///
+/// Values are retrieved with a dotted path made of the table names followed by the key,
+/// for instance `check.integration-test-pattern` for:
+///
+/// ```toml
+/// [check]
+/// integration-test-pattern = "*_test.rs"
+/// ```
+#[derive(Default)]
+pub struct Config {
+    document: DocumentMut,
+}
+
+impl Config {
+    /// Load the configuration file, an absent file gives an empty configuration.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        group_info!("loading {} file...", path.display());
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents).map_err(|e| anyhow!("Invalid {} file: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let document = contents.parse::<DocumentMut>()?;
+        Ok(Self { document })
+    }
+
+    fn get(&self, path: &str) -> Option<&Item> {
+        path.split('.')
+            .try_fold(self.document.as_item(), |item, key| item.get(key))
+    }
+
+    pub fn get_str(&self, path: &str) -> Option<&str> {
+        self.get(path)?.as_str()
+    }
+
+    pub fn get_integer(&self, path: &str) -> Option<i64> {
+        self.get(path)?.as_integer()
+    }
+
+    pub fn get_bool(&self, path: &str) -> Option<bool> {
+        self.get(path)?.as_bool()
+    }
+
+    /// Returns the keys of the table at the passed path.
+    pub fn get_table_keys(&self, path: &str) -> Option<Vec<String>> {
+        let table = self.get(path)?.as_table_like()?;
+        Some(table.iter().map(|(key, _)| key.to_string()).collect())
+    }
+
+    /// Returns the array of strings at the passed path, non-string values are ignored.
+    pub fn get_string_array(&self, path: &str) -> Option<Vec<String>> {
+        let array = self.get(path)?.as_array()?;
+        Some(
+            array
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect(),
+        )
+    }
+
+    /// Returns the default value of the passed argument of the passed command, the key of the
+    /// command table wins over the root key. For instance the `exclude` argument of the `test`
+    /// command is read from `test.exclude` or else from `exclude`.
+    pub fn get_default_args(&self, command: &str, key: &str) -> Option<Vec<String>> {
+        self.get_string_array(&format!("{command}.{key}"))
+            .or_else(|| self.get_string_array(key))
+    }
+}
+
+/// Returns the configuration loaded by `init_xtask` or an empty configuration.
+pub fn get_config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+pub(crate) fn init_config() -> anyhow::Result<()> {
+    let config = Config::load(Path::new(CONFIG_FILENAME))?;
+    // the configuration can only be initialized once
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const CONFIG: &str = r#"
+root = "value"
+
+[check]
+integration-test-pattern = "*_test.rs"
+deny = true
+jobs = 4
+crates = ["a", "b", 1]
+
+[check.members]
+a = 1
+b = 2
+"#;
+
+    #[rstest]
+    #[case::root_key("root", Some("value"))]
+    #[case::table_key("check.integration-test-pattern", Some("*_test.rs"))]
+    #[case::missing_key("check.missing", None)]
+    #[case::missing_table("missing.integration-test-pattern", None)]
+    #[case::not_a_string("check.deny", None)]
+    fn test_config_get_str(#[case] path: &str, #[case] expected: Option<&str>) {
+        let config = Config::parse(CONFIG).expect("config should be parsed");
+        assert_eq!(config.get_str(path), expected);
+    }
+
+    #[test]
+    fn test_config_typed_values() {
+        let config = Config::parse(CONFIG).expect("config should be parsed");
+        assert_eq!(config.get_bool("check.deny"), Some(true));
+        assert_eq!(config.get_integer("check.jobs"), Some(4));
+        assert_eq!(
+            config.get_string_array("check.crates"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            config.get_table_keys("check.members"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(config.get_table_keys("check.jobs"), None);
+    }
+
+    #[rstest]
+    #[case::command_table("exclude = [\"a\"]\n[test]\nexclude = [\"b\"]", "exclude", Some(vec!["b"]))]
+    #[case::root_key("exclude = [\"a\"]\n[test]\nretries = 2", "exclude", Some(vec!["a"]))]
+    #[case::other_command("[build]\nfeatures = [\"std\"]", "features", None)]
+    #[case::empty_file("", "only", None)]
+    fn test_config_get_default_args(
+        #[case] contents: &str,
+        #[case] key: &str,
+        #[case] expected: Option<Vec<&str>>,
+    ) {
+        let config = Config::parse(contents).expect("config should be parsed");
+        assert_eq!(
+            config.get_default_args("test", key),
+            expected.map(|values| values.iter().map(|v| v.to_string()).collect())
+        );
+    }
+
+    #[test]
+    fn test_config_absent_file_is_empty() {
+        let config = Config::load(Path::new("this-file-does-not-exist.toml"))
+            .expect("absent config should be loaded");
+        assert_eq!(config.get_str("root"), None);
+    }
+
+    #[test]
+    fn test_config_invalid_file() {
+        assert!(Config::parse("[check\nkey = ").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Counting global allocator used by `cargo xtask test --leak-check` to detect memory leaks.
+//!
+//! The allocator counts the bytes and the allocations which are not freed and writes them to a
+//! report file when the process exits. It is installed in the test binaries of a crate with the
+//! `leak_check_allocator` macro, which also registers the report with a constructor run when the
+//! process starts:
+//!
+//! ```ignore
+//! #[cfg(xtask_leak_check)]
+//! tracel_xtask::leak_check_allocator!();
+//! ```
+//!
+//! A binary with its own `main` can install the allocator by hand and call `init` instead.
+//!
+//! The cfg is unknown to rustc, declare it in the `Cargo.toml` file of the crate to avoid the
+//! `unexpected_cfgs` warning:
+//!
+//! ```toml
+//! [lints.rust]
+//! unexpected_cfgs = { level = "warn", check-cfg = ['cfg(xtask_leak_check)'] }
+//! ```
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    path::Path,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Once,
+    },
+};
+
+use crate::utils::cleanup::at_exit;
+
+/// The cfg set by `test --leak-check` to install the counting allocator.
+pub const LEAK_CHECK_CFG: &str = "xtask_leak_check";
+/// Environment variable set by `test --leak-check` to the directory of the report files.
+pub const LEAK_CHECK_DIR_ENV: &str = "XTASK_LEAK_CHECK_DIR";
+
+static NET_BYTES: AtomicI64 = AtomicI64::new(0);
+static NET_ALLOCATIONS: AtomicI64 = AtomicI64::new(0);
+static EXIT_HANDLER: Once = Once::new();
+
+/// Install the counting allocator as the global allocator and register its report when the
+/// process starts. The cfg of the leak check should gate the invocation.
+#[macro_export]
+macro_rules! leak_check_allocator {
+    () => {
+        #[global_allocator]
+        static XTASK_LEAK_CHECK_ALLOCATOR: $crate::leak_check::CountingAllocator =
+            $crate::leak_check::CountingAllocator;
+
+        #[used]
+        #[cfg_attr(
+            any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_os = "dragonfly",
+                target_os = "illumos"
+            ),
+            link_section = ".init_array"
+        )]
+        #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__mod_init_func")]
+        #[cfg_attr(windows, link_section = ".CRT$XCU")]
+        static XTASK_LEAK_CHECK_INIT: extern "C" fn() = $crate::leak_check::init;
+    };
+}
+
+/// Register the report of the counting allocator written when the process exits, only the
+/// first call registers it.
+pub extern "C" fn init() {
+    // the C runtime allocates with its own allocator so this does not recurse
+    EXIT_HANDLER.call_once(|| at_exit(write_report));
+}
+
+/// Global allocator delegating to the system allocator and counting the memory not freed.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn record(bytes: i64, allocations: i64) {
+        NET_BYTES.fetch_add(bytes, Ordering::Relaxed);
+        NET_ALLOCATIONS.fetch_add(allocations, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::record(layout.size() as i64, 1);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::record(layout.size() as i64, 1);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::record(-(layout.size() as i64), -1);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::record(new_size as i64 - layout.size() as i64, 0);
+        }
+        new_ptr
+    }
+}
+
+/// Net allocations of a test process when it exited.
+#[derive(Debug, PartialEq)]
+pub struct LeakReport {
+    pub executable: String,
+    pub bytes: i64,
+    pub allocations: i64,
+}
+
+impl LeakReport {
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let mut counts = lines.next()?.split_whitespace();
+        Some(Self {
+            bytes: counts.next()?.parse().ok()?,
+            allocations: counts.next()?.parse().ok()?,
+            executable: lines.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+/// Returns the reports written in the passed directory.
+pub fn read_reports(dir: &Path) -> anyhow::Result<Vec<LeakReport>> {
+    let mut reports = vec![];
+    if !dir.exists() {
+        return Ok(reports);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(report) = LeakReport::parse(&std::fs::read_to_string(&path)?) {
+            reports.push(report);
+        }
+    }
+    reports.sort_by(|a, b| a.executable.cmp(&b.executable));
+    Ok(reports)
+}
+
+extern "C" fn write_report() {
+    // snapshot the counts before allocating to write the report
+    let bytes = NET_BYTES.load(Ordering::Relaxed);
+    let allocations = NET_ALLOCATIONS.load(Ordering::Relaxed);
+    let Some(dir) = std::env::var_os(LEAK_CHECK_DIR_ENV) else {
+        return;
+    };
+    let executable = std::env::current_exe()
+        .map(|exe| exe.display().to_string())
+        .unwrap_or_default();
+    let report = Path::new(&dir).join(format!("{}.txt", std::process::id()));
+    let _ = std::fs::write(report, format!("{bytes} {allocations}\n{executable}\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::valid(
+        "128 2\n/target/debug/deps/my_crate-1234\n",
+        Some(LeakReport { executable: "/target/debug/deps/my_crate-1234".to_string(), bytes: 128, allocations: 2 })
+    )]
+    #[case::negative("-16 0\n", Some(LeakReport { executable: String::new(), bytes: -16, allocations: 0 }))]
+    #[case::invalid("not a report", None)]
+    fn test_leak_report_parse(#[case] contents: &str, #[case] expected: Option<LeakReport>) {
+        assert_eq!(LeakReport::parse(contents), expected);
+    }
+
+    #[test]
+    fn test_counting_allocator_counts_net_allocations() {
+        let allocator = CountingAllocator;
+        let layout = Layout::from_size_align(64, 8).expect("layout should be valid");
+        let bytes = NET_BYTES.load(Ordering::Relaxed);
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            assert_eq!(NET_BYTES.load(Ordering::Relaxed), bytes + 64);
+            let ptr = allocator.realloc(ptr, layout, 128);
+            assert_eq!(NET_BYTES.load(Ordering::Relaxed), bytes + 128);
+            allocator.dealloc(
+                ptr,
+                Layout::from_size_align(128, 8).expect("layout should be valid"),
+            );
+        }
+        assert_eq!(NET_BYTES.load(Ordering::Relaxed), bytes);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod commands;
+pub mod config;
 pub mod context;
 pub mod environment;
+pub mod leak_check;
 pub mod logging;
+pub mod report;
+pub mod summary;
 pub mod utils;
 mod versions;
 
@@ -24,28 +28,46 @@ pub mod prelude {
     }
 
     pub use crate::commands as base_commands;
+    pub use crate::commands::api_snapshot::ApiSnapshotCmdArgs;
+    pub use crate::commands::bench::BenchCmdArgs;
+    pub use crate::commands::bench_ids::BenchIdsCmdArgs;
     pub use crate::commands::build::BuildCmdArgs;
     pub use crate::commands::bump::BumpCmdArgs;
     pub use crate::commands::bump::BumpSubCommand;
+    pub use crate::commands::changelog_lint::ChangelogLintCmdArgs;
     pub use crate::commands::check::CheckCmdArgs;
     pub use crate::commands::check::CheckSubCommand;
+    pub use crate::commands::clippy::ClippyCmdArgs;
+    pub use crate::commands::compare::CompareCmdArgs;
     pub use crate::commands::compile::CompileCmdArgs;
     pub use crate::commands::coverage::CoverageCmdArgs;
+    pub use crate::commands::critical_path::CriticalPathCmdArgs;
     pub use crate::commands::dependencies::DependenciesCmdArgs;
     pub use crate::commands::dependencies::DependenciesSubCommand;
     pub use crate::commands::doc::DocCmdArgs;
     pub use crate::commands::doc::DocSubCommand;
     pub use crate::commands::docker::DockerCmdArgs;
     pub use crate::commands::docker::DockerSubCommand;
+    pub use crate::commands::docsrs_check::DocsrsCheckCmdArgs;
     pub use crate::commands::fix::FixCmdArgs;
     pub use crate::commands::fix::FixSubCommand;
+    pub use crate::commands::fmt::FmtCmdArgs;
+    pub use crate::commands::fmt::FmtSubCommand;
+    pub use crate::commands::incremental_timing::IncrementalTimingCmdArgs;
     pub use crate::commands::publish::PublishCmdArgs;
+    pub use crate::commands::readme_sync::ReadmeSyncCmdArgs;
     pub use crate::commands::test::TestCmdArgs;
     pub use crate::commands::test::TestSubCommand;
     pub use crate::commands::validate::ValidateCmdArgs;
+    pub use crate::commands::vulnerabilities::sanitizer_supported_targets;
+    pub use crate::commands::vulnerabilities::CachedTargetRetriever;
+    pub use crate::commands::vulnerabilities::RustupTargetRetriever;
+    pub use crate::commands::vulnerabilities::Sanitizer;
+    pub use crate::commands::vulnerabilities::TargetRetriever;
     pub use crate::commands::vulnerabilities::VulnerabilitiesCmdArgs;
     pub use crate::commands::vulnerabilities::VulnerabilitiesSubCommand;
     pub use crate::commands::Target;
+    pub use crate::config::get_config;
     pub use crate::context::Context;
     pub use crate::endgroup;
     pub use crate::environment::Environment;
@@ -53,20 +75,30 @@ pub mod prelude {
     pub use crate::group_info;
     pub use crate::handle_cleanup;
     pub use crate::init_xtask;
+    pub use crate::logging::set_group_style;
+    pub use crate::logging::set_verbosity;
+    pub use crate::logging::GroupStyle;
+    pub use crate::logging::Verbosity;
     pub use crate::parse_args;
     pub use crate::register_cleanup;
+    pub use crate::summary::write_summary;
     pub use crate::utils::cargo::ensure_cargo_crate_is_installed;
     pub use crate::utils::cleanup::CLEANUP_HANDLER;
     pub use crate::utils::helpers;
     pub use crate::utils::process::random_port;
     pub use crate::utils::process::run_process;
     pub use crate::utils::process::run_process_for_package;
+    pub use crate::utils::process::run_process_for_package_captured;
     pub use crate::utils::process::run_process_for_workspace;
+    pub use crate::utils::process::CapturedOutput;
     pub use crate::utils::prompt::ask_once;
+    pub use crate::utils::prompt::ask_once_with_default;
     pub use crate::utils::rustup::is_current_toolchain_nightly;
+    pub use crate::utils::rustup::is_nightly_toolchain_installed;
     pub use crate::utils::rustup::rustup_add_component;
     pub use crate::utils::rustup::rustup_add_target;
     pub use crate::utils::rustup::rustup_get_installed_targets;
+    pub use crate::utils::rustup::rustup_get_toolchain_installed_targets;
     pub use crate::utils::time::format_duration;
     pub use crate::XtaskArgs;
     // does not re-export strum has it is incompatible with strum macros expansions
@@ -74,7 +106,7 @@ pub mod prelude {
 
 use crate::context::Context;
 use crate::environment::Environment;
-use crate::logging::init_logger;
+use crate::logging::{init_logger, set_verbosity, Verbosity};
 
 #[macro_use]
 extern crate log;
@@ -91,22 +123,109 @@ pub struct XtaskArgs<C: clap::Subcommand> {
     /// Set context (for commands that support it).
     #[arg(short = 'c', long, default_value_t = Context::default())]
     pub context: Context,
+    /// Write a JSON summary of the run (per-member results, durations, tool versions and outcome) to the passed path.
+    #[arg(long, value_name = "PATH")]
+    pub print_summary_json: Option<std::path::PathBuf>,
+    /// Terminate the whole invocation with exit code 124 if it runs longer than the passed duration (e.g. 90s, 45m, 1h30m).
+    #[arg(long, value_name = "DURATION", value_parser = utils::time::parse_duration)]
+    pub deadline: Option<std::time::Duration>,
+    /// Run the command in the workspace at the passed path instead of the current directory, relative paths of the other options are then resolved against it.
+    #[arg(long, value_name = "PATH")]
+    pub workspace_root: Option<std::path::PathBuf>,
+    /// Shuffle the order in which the workspace members are processed with the passed seed, they are sorted by name otherwise.
+    #[arg(long, value_name = "SEED")]
+    pub members_seed: Option<u64>,
+    /// Run the command against a temporary copy of the workspace, the changes made to the copy are reported and the workspace is left untouched.
+    #[arg(long)]
+    pub scratch: bool,
+    /// Do not lock the target directory, for concurrent runs which use separate target directories.
+    #[arg(long)]
+    pub no_lock: bool,
+    /// Linker used by the cargo commands, falls back to the default linker with a warning if it is not installed.
+    #[arg(long, value_name = "LINKER", default_value_t = utils::cargo::Linker::default())]
+    pub linker: utils::cargo::Linker,
+    /// Write the commands planned by the run with their environment and directory to the passed path as an executable shell script instead of executing them.
+    // the scratch workspace is removed on exit, the directories of the planned commands would not exist
+    #[arg(long, value_name = "PATH", conflicts_with = "scratch")]
+    pub emit_script: Option<std::path::PathBuf>,
+    /// Answer yes to the confirmation prompts, can also be enabled with the XTASK_ASSUME_YES environment variable.
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+    /// Only log the warnings and the errors.
+    #[arg(short = 'q', long, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Also log the debug messages, including the commands executed with their directory and environment.
+    #[arg(short = 'v', long)]
+    pub verbose: bool,
     #[command(subcommand)]
     pub command: C,
 }
 
 pub fn parse_args<C: clap::Subcommand>() -> anyhow::Result<XtaskArgs<C>> {
-    // init logs early
-    init_logger().init();
     let args = <XtaskArgs<C> as clap::Parser>::parse();
+    // init logs early, once their verbosity is known
+    set_verbosity(if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    });
+    init_logger().init();
     Ok(args)
 }
 
 pub fn init_xtask<C: clap::Subcommand>(args: XtaskArgs<C>) -> anyhow::Result<XtaskArgs<C>> {
+    // workspace root, entered first so that the dotenv and configuration files are its own
+    if let Some(root) = &args.workspace_root {
+        std::env::set_current_dir(root).map_err(|e| {
+            anyhow::anyhow!("Cannot enter the workspace root {} ({e})", root.display())
+        })?;
+        group_info!("Workspace root: {}", root.display());
+    }
+    // planned commands, the variables of the dotenv files are set by the planned commands
+    utils::script::save_initial_env();
     // environment
     group_info!("Environment: {}", args.environment);
     args.environment.load()?;
+    // configuration file
+    config::init_config()?;
+    // order of the workspace members
+    if let Some(seed) = args.members_seed {
+        group_info!("Workspace members shuffled with seed {seed}, use '--members-seed {seed}' to replay this order.");
+    }
+    utils::workspace::set_members_seed(args.members_seed);
     group_info!("Context: {}", args.context);
+    // run summary
+    if let Some(path) = &args.print_summary_json {
+        summary::init_summary(
+            path,
+            &args.environment.to_string(),
+            &args.context.to_string(),
+        );
+    }
+    // scratch workspace
+    if args.scratch {
+        let root = utils::scratch::enter_scratch_workspace()?;
+        group_info!("Scratch workspace: {}", root.display());
+    }
+    if let Some(path) = &args.emit_script {
+        group_info!("Planning the commands to {}", path.display());
+        utils::script::enable_script(path);
+    }
+    // target directory lock, acquired by the first cargo invocation
+    if !args.no_lock {
+        utils::lock::enable_target_lock();
+    }
+    // deadline
+    if let Some(deadline) = args.deadline {
+        group_info!("Deadline: {}", utils::time::format_duration(&deadline));
+        utils::deadline::start_deadline(deadline);
+    }
+    // linker
+    utils::cargo::use_linker(args.linker);
+    // confirmation prompts
+    utils::prompt::set_assume_yes(args.yes);
     // code coverage
     if args.enable_coverage {
         group_info!("Enabling coverage support...");
@@ -116,8 +235,8 @@ pub fn init_xtask<C: clap::Subcommand>(args: XtaskArgs<C>) -> anyhow::Result<Xta
 }
 
 fn setup_coverage() -> anyhow::Result<()> {
+    utils::cargo::append_rustflags(&["-Cinstrument-coverage".to_string()]);
     unsafe {
-        std::env::set_var("RUSTFLAGS", "-Cinstrument-coverage");
         std::env::set_var("LLVM_PROFILE_FILE", "burn-%p-%m.profraw");
     }
     Ok(())
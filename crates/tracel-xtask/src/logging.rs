@@ -1,13 +1,57 @@
-use std::io::Write;
+use std::{
+    cell::RefCell,
+    io::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Style of the collapsible sections emitted by the `group!` and `endgroup!` macros.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupStyle {
+    /// `::group::` workflow commands of GitHub Actions.
+    GitHub,
+    /// `section_start` and `section_end` markers of GitLab CI.
+    GitLab,
+    /// `##[group]` logging commands of Azure Pipelines.
+    Azure,
+    /// `====` banner logged when running locally.
+    Plain,
+}
+
+/// Verbosity of the logs set with the `--quiet` and `--verbose` global options.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Verbosity {
+    /// Only the warnings and the errors are logged.
+    Quiet,
+    /// The debug logs are enabled by the `DEBUG` or `ACTIONS_RUNNER_DEBUG` environment variables.
+    #[default]
+    Normal,
+    /// The debug logs are enabled, they include the commands executed with their directory and
+    /// environment.
+    Verbose,
+}
+
+static VERBOSITY: OnceLock<Verbosity> = OnceLock::new();
+static GROUP_STYLE: Mutex<Option<GroupStyle>> = Mutex::new(None);
+thread_local! {
+    /// Names of the GitLab sections opened by the current thread, the last one is closed by the
+    /// next `endgroup!` of the thread.
+    static OPEN_SECTIONS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+static SECTION_COUNT: AtomicUsize = AtomicUsize::new(0);
 
 /// Initialise and create a `env_logger::Builder` which follows the
 /// GitHub Actions logging syntax when running on CI.
 pub fn init_logger() -> env_logger::Builder {
+    let level = get_log_level(get_verbosity(), |name| std::env::var(name).is_ok());
     let mut builder = env_logger::Builder::from_default_env();
     builder.target(env_logger::Target::Stdout);
 
     // Find and setup the correct log level
-    builder.filter(None, get_log_level());
+    builder.filter(None, level);
     builder.write_style(env_logger::WriteStyle::Always);
 
     // Custom Formatter for Github Actions
@@ -25,20 +69,119 @@ pub fn init_logger() -> env_logger::Builder {
     builder
 }
 
-/// Determine the LogLevel for the logger
-fn get_log_level() -> log::LevelFilter {
-    // DEBUG
-    match std::env::var("DEBUG") {
-        Ok(_value) => return log::LevelFilter::Debug,
-        Err(_err) => (),
+/// Set the verbosity of the logs, it must be called before `init_logger` and can only be set
+/// once.
+pub fn set_verbosity(verbosity: Verbosity) {
+    let _ = VERBOSITY.set(verbosity);
+}
+
+/// Returns the verbosity of the logs, `Normal` if it has not been set.
+pub fn get_verbosity() -> Verbosity {
+    VERBOSITY.get().copied().unwrap_or_default()
+}
+
+/// Determine the LogLevel for the logger, the errors are always logged.
+fn get_log_level(verbosity: Verbosity, is_set: impl Fn(&str) -> bool) -> log::LevelFilter {
+    match verbosity {
+        Verbosity::Quiet => log::LevelFilter::Warn,
+        Verbosity::Verbose => log::LevelFilter::Debug,
+        // DEBUG or ACTIONS_RUNNER_DEBUG
+        Verbosity::Normal if is_set("DEBUG") || is_set("ACTIONS_RUNNER_DEBUG") => {
+            log::LevelFilter::Debug
+        }
+        Verbosity::Normal => log::LevelFilter::Info,
     }
-    // ACTIONS_RUNNER_DEBUG
-    match std::env::var("ACTIONS_RUNNER_DEBUG") {
-        Ok(_value) => return log::LevelFilter::Debug,
-        Err(_err) => (),
-    };
+}
+
+/// Force the style of the groups instead of detecting the CI provider from the environment.
+pub fn set_group_style(style: GroupStyle) {
+    *GROUP_STYLE
+        .lock()
+        .expect("group style lock should not be poisoned") = Some(style);
+}
+
+/// Returns the forced style of the groups or else the style of the detected CI provider.
+pub fn get_group_style() -> GroupStyle {
+    let style = *GROUP_STYLE
+        .lock()
+        .expect("group style lock should not be poisoned");
+    style.unwrap_or_else(|| detect_group_style(|name| std::env::var(name).is_ok()))
+}
+
+fn detect_group_style(is_set: impl Fn(&str) -> bool) -> GroupStyle {
+    if is_set("GITHUB_ACTIONS") {
+        GroupStyle::GitHub
+    } else if is_set("GITLAB_CI") {
+        GroupStyle::GitLab
+    } else if is_set("TF_BUILD") {
+        GroupStyle::Azure
+    } else {
+        GroupStyle::Plain
+    }
+}
+
+/// Open a group with the passed title, called by the `group!` macro.
+#[doc(hidden)]
+pub fn start_group(title: &str) {
+    let style = get_group_style();
+    let section = format!("xtask_{}", SECTION_COUNT.fetch_add(1, Ordering::Relaxed));
+    let line = format_group_start(style, title, &section, unix_timestamp());
+    OPEN_SECTIONS.with_borrow_mut(|sections| sections.push(section));
+    match style {
+        GroupStyle::Plain => info!("{line}"),
+        _ => println!("{line}"),
+    }
+}
+
+/// Close the last open group, called by the `endgroup!` macro.
+#[doc(hidden)]
+pub fn end_group() {
+    let section = OPEN_SECTIONS.with_borrow_mut(|sections| sections.pop());
+    if let Some(line) = format_group_end(get_group_style(), section.as_deref(), unix_timestamp()) {
+        println!("{line}");
+    }
+}
+
+/// Returns the number of groups opened by the current thread.
+pub(crate) fn get_open_group_count() -> usize {
+    OPEN_SECTIONS.with_borrow(|sections| sections.len())
+}
+
+/// Close the open groups until only the passed number of groups is left open.
+pub(crate) fn end_groups_to(count: usize) {
+    while get_open_group_count() > count {
+        end_group();
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
 
-    log::LevelFilter::Info
+fn format_group_start(style: GroupStyle, title: &str, section: &str, timestamp: u64) -> String {
+    match style {
+        GroupStyle::GitHub => format!("::group::{title}"),
+        GroupStyle::GitLab => {
+            format!("\x1b[0Ksection_start:{timestamp}:{section}[collapsed=true]\r\x1b[0K{title}")
+        }
+        GroupStyle::Azure => format!("##[group]{title}"),
+        GroupStyle::Plain => format!("==== {title} ===="),
+    }
+}
+
+/// Returns None if the style has no end marker or if there is no open GitLab section.
+fn format_group_end(style: GroupStyle, section: Option<&str>, timestamp: u64) -> Option<String> {
+    match style {
+        GroupStyle::GitHub => Some("::endgroup::".to_string()),
+        GroupStyle::GitLab => {
+            section.map(|section| format!("\x1b[0Ksection_end:{timestamp}:{section}\r\x1b[0K"))
+        }
+        GroupStyle::Azure => Some("##[endgroup]".to_string()),
+        GroupStyle::Plain => None,
+    }
 }
 
 /// Group Macro
@@ -47,11 +190,8 @@ macro_rules! group {
     // group!()
     ($($arg:tt)*) => {
         let title = format!($($arg)*);
-        if std::env::var("CI").is_ok() {
-            println!("::group::{}", title)
-        } else {
-            log!(log::Level::Info, "{}", title)
-        }
+        $crate::utils::deadline::set_phase(&title);
+        $crate::logging::start_group(&title);
     };
 }
 
@@ -86,8 +226,125 @@ macro_rules! group_error {
 macro_rules! endgroup {
     // endgroup!()
     () => {
-        if std::env::var("CI").is_ok() {
-            println!("::endgroup::")
-        }
+        $crate::logging::end_group();
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    /// Writer of the logger whose output is kept to be checked.
+    #[derive(Clone, Default)]
+    struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case::quiet(Verbosity::Quiet, &[], "warn\nerror\n")]
+    #[case::quiet_with_debug_var(Verbosity::Quiet, &["DEBUG"], "warn\nerror\n")]
+    #[case::normal(Verbosity::Normal, &[], "info\nwarn\nerror\n")]
+    #[case::normal_with_debug_var(Verbosity::Normal, &["ACTIONS_RUNNER_DEBUG"], "debug\ninfo\nwarn\nerror\n")]
+    #[case::verbose(Verbosity::Verbose, &[], "debug\ninfo\nwarn\nerror\n")]
+    fn test_log_level(#[case] verbosity: Verbosity, #[case] vars: &[&str], #[case] expected: &str) {
+        let writer = SharedWriter::default();
+        let logger = env_logger::Builder::new()
+            .filter(None, get_log_level(verbosity, |name| vars.contains(&name)))
+            .target(env_logger::Target::Pipe(Box::new(writer.clone())))
+            .format(|buf, record| writeln!(buf, "{}", record.args()))
+            .build();
+        for level in [
+            log::Level::Trace,
+            log::Level::Debug,
+            log::Level::Info,
+            log::Level::Warn,
+            log::Level::Error,
+        ] {
+            let message = level.as_str().to_lowercase();
+            log::Log::log(
+                &logger,
+                &log::Record::builder()
+                    .level(level)
+                    .args(format_args!("{message}"))
+                    .build(),
+            );
+        }
+        assert_eq!(String::from_utf8_lossy(&writer.0.lock().unwrap()), expected);
+    }
+
+    #[rstest]
+    #[case::github(&["GITHUB_ACTIONS", "CI"], GroupStyle::GitHub)]
+    #[case::gitlab(&["GITLAB_CI", "CI"], GroupStyle::GitLab)]
+    #[case::azure(&["TF_BUILD"], GroupStyle::Azure)]
+    #[case::unknown_ci(&["CI"], GroupStyle::Plain)]
+    #[case::local(&[], GroupStyle::Plain)]
+    fn test_detect_group_style(#[case] vars: &[&str], #[case] expected: GroupStyle) {
+        assert_eq!(detect_group_style(|name| vars.contains(&name)), expected);
+    }
+
+    #[rstest]
+    #[case::github(GroupStyle::GitHub, "::group::Test: my-crate", Some("::endgroup::"))]
+    #[case::gitlab(
+        GroupStyle::GitLab,
+        "\x1b[0Ksection_start:1700000000:xtask_3[collapsed=true]\r\x1b[0KTest: my-crate",
+        Some("\x1b[0Ksection_end:1700000000:xtask_3\r\x1b[0K")
+    )]
+    #[case::azure(GroupStyle::Azure, "##[group]Test: my-crate", Some("##[endgroup]"))]
+    #[case::plain(GroupStyle::Plain, "==== Test: my-crate ====", None)]
+    fn test_format_group(
+        #[case] style: GroupStyle,
+        #[case] start: &str,
+        #[case] end: Option<&str>,
+    ) {
+        assert_eq!(
+            format_group_start(style, "Test: my-crate", "xtask_3", 1_700_000_000),
+            start
+        );
+        assert_eq!(
+            format_group_end(style, Some("xtask_3"), 1_700_000_000).as_deref(),
+            end
+        );
+    }
+
+    #[test]
+    fn test_format_group_end_without_open_section() {
+        assert_eq!(
+            format_group_end(GroupStyle::GitLab, None, 1_700_000_000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_open_groups_are_per_thread() {
+        start_group("main");
+        std::thread::spawn(|| {
+            start_group("worker");
+            assert_eq!(get_open_group_count(), 1);
+            end_group();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(get_open_group_count(), 1);
+        end_group();
+        assert_eq!(get_open_group_count(), 0);
+    }
+
+    #[test]
+    fn test_set_group_style() {
+        let previous = *GROUP_STYLE.lock().unwrap();
+        set_group_style(GroupStyle::Azure);
+        assert_eq!(get_group_style(), GroupStyle::Azure);
+        *GROUP_STYLE.lock().unwrap() = previous;
+    }
+}
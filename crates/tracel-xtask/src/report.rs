@@ -0,0 +1,401 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use regex::Regex;
+
+use crate::{group_info, utils::process::remove_ansi_codes};
+
+static REPORTS: OnceLock<Mutex<Reports>> = OnceLock::new();
+
+/// Format of a test report written with the `--report <FORMAT>:<PATH>` option of the test command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReportFormat {
+    /// Test Anything Protocol version 13, see `<https://testanything.org/tap-version-13-specification.html>`.
+    Tap,
+}
+
+/// Outcome of a test or benchmark parsed from the libtest output.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TestOutcome {
+    Passed,
+    Failed,
+    Ignored,
+    /// Benchmark result in nanoseconds per iteration.
+    Bench(f64),
+}
+
+impl std::fmt::Display for TestOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestOutcome::Passed => write!(f, "ok"),
+            TestOutcome::Failed => write!(f, "FAILED"),
+            TestOutcome::Ignored => write!(f, "ignored"),
+            TestOutcome::Bench(ns) => write!(f, "{ns} ns/iter"),
+        }
+    }
+}
+
+/// Result of a test of a workspace member.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TestResult {
+    pub(crate) member: String,
+    /// Name of the test binary, the crate name for the unit tests and doc tests of a package and
+    /// the target name for its integration tests and benchmarks. A test name is only unique
+    /// within its test binary.
+    pub(crate) binary: String,
+    pub(crate) name: String,
+    pub(crate) outcome: TestOutcome,
+    /// Captured output of a failed test.
+    pub(crate) output: String,
+}
+
+struct Reports {
+    outputs: Vec<(ReportFormat, PathBuf)>,
+    results: Vec<TestResult>,
+}
+
+/// Parse a report option of the form `<FORMAT>:<PATH>`.
+pub(crate) fn parse_report(value: &str) -> anyhow::Result<(ReportFormat, PathBuf)> {
+    let (format, path) = value.split_once(':').unwrap_or((value, ""));
+    if path.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Invalid report '{value}', expected '<FORMAT>:<PATH>' like 'tap:target/tests.tap'."
+        ));
+    }
+    match format {
+        "tap" => Ok((ReportFormat::Tap, PathBuf::from(path))),
+        _ => Err(anyhow::anyhow!(
+            "Unknown report format '{format}', the supported formats are: tap."
+        )),
+    }
+}
+
+/// Enable the recording of the test results which are written to the passed reports by
+/// `write_reports`.
+pub(crate) fn init_reports(outputs: Vec<(ReportFormat, PathBuf)>) {
+    let reports = Reports {
+        // keep writing next to the invocation directory when it changes with '--scratch'
+        outputs: outputs
+            .into_iter()
+            .map(|(format, path)| (format, std::path::absolute(&path).unwrap_or(path)))
+            .collect(),
+        results: vec![],
+    };
+    // the reports can only be initialized once
+    let _ = REPORTS.set(Mutex::new(reports));
+}
+
+/// Return true if test reports are requested.
+pub(crate) fn is_report_enabled() -> bool {
+    REPORTS.get().is_some()
+}
+
+/// Record the test results of the passed libtest output of a cargo process. The member is the
+/// passed package or else the name of the test binary. The results of a retried test replace the
+/// previous ones.
+pub(crate) fn record_test_output(package: Option<&str>, output: &str) {
+    if let Some(reports) = REPORTS.get() {
+        let mut reports = reports.lock().expect("reports lock should not be poisoned");
+        for result in parse_test_results(package, output) {
+            reports.results.retain(|r| {
+                r.member != result.member || r.binary != result.binary || r.name != result.name
+            });
+            reports.results.push(result);
+        }
+    }
+}
+
+/// Write the requested test reports, does nothing if no report is requested.
+pub(crate) fn write_reports() -> anyhow::Result<()> {
+    let Some(reports) = REPORTS.get() else {
+        return Ok(());
+    };
+    let reports = reports.lock().expect("reports lock should not be poisoned");
+    for (format, path) in &reports.outputs {
+        let contents = match format {
+            ReportFormat::Tap => to_tap(&reports.results),
+        };
+        write_report(path, &contents)?;
+        group_info!("Test report written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn write_report(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|e| anyhow::anyhow!("Cannot write the test report {} ({e})", path.display()))
+}
+
+/// Returns the test and benchmark results of the passed cargo output with the captured output of
+/// the failed tests. The test binary of the results is the one announced by cargo before the
+/// libtest output, the member is the passed package or else the name of the test binary.
+pub(crate) fn parse_test_results(package: Option<&str>, output: &str) -> Vec<TestResult> {
+    let binary_re =
+        Regex::new(r"^\s*(?:Running .*[/\\]deps[/\\](?<running>[^-\s]+)-|Doc-tests (?<doc>\S+))")
+            .expect("should compile test binary regex");
+    // doc test names contain spaces, like 'src/lib.rs - add (line 3)'
+    let test_re = Regex::new(
+        r"^test (?<name>.+?)(?: - should panic)? \.\.\. (?:(?<status>ok|FAILED|ignored)|bench:\s+(?<ns>[\d,.]+) ns/iter)",
+    )
+    .expect("should compile test regex");
+    let output_re =
+        Regex::new(r"^---- (?<name>.+) stdout ----$").expect("should compile output regex");
+    let mut results: Vec<TestResult> = vec![];
+    let mut binary = String::new();
+    // index of the failed test whose output is being captured
+    let mut capturing: Option<usize> = None;
+    for line in output.lines().map(remove_ansi_codes) {
+        if let Some(caps) = binary_re.captures(&line) {
+            capturing = None;
+            binary = caps
+                .name("running")
+                .or(caps.name("doc"))
+                .map_or_else(String::new, |name| name.as_str().to_string());
+        } else if let Some(caps) = test_re.captures(&line) {
+            let outcome = match caps.name("status").map(|s| s.as_str()) {
+                Some("ok") => TestOutcome::Passed,
+                Some("FAILED") => TestOutcome::Failed,
+                Some(_) => TestOutcome::Ignored,
+                // the thousands separator is a comma, the decimal separator a dot
+                None => match caps["ns"].replace(',', "").parse() {
+                    Ok(ns) => TestOutcome::Bench(ns),
+                    Err(_) => continue,
+                },
+            };
+            results.push(TestResult {
+                member: package
+                    .or(Some(binary.as_str()).filter(|b| !b.is_empty()))
+                    .unwrap_or("workspace")
+                    .to_string(),
+                binary: binary.clone(),
+                name: caps["name"].to_string(),
+                outcome,
+                output: String::new(),
+            });
+        } else if let Some(caps) = output_re.captures(&line) {
+            capturing = results
+                .iter()
+                .rposition(|r| r.binary == binary && r.name == caps["name"]);
+        } else if line == "failures:" || line.starts_with("test result:") {
+            capturing = None;
+        } else if let Some(index) = capturing {
+            results[index].output.push_str(&line);
+            results[index].output.push('\n');
+        }
+    }
+    for result in &mut results {
+        result.output = result.output.trim_end().to_string();
+    }
+    results
+}
+
+fn to_tap(results: &[TestResult]) -> String {
+    let mut lines = vec![
+        "TAP version 13".to_string(),
+        format!("1..{}", results.len()),
+    ];
+    for (index, result) in results.iter().enumerate() {
+        let number = index + 1;
+        let description = format!("{} {}", result.member, result.name);
+        match result.outcome {
+            TestOutcome::Passed | TestOutcome::Bench(_) => {
+                lines.push(format!("ok {number} - {description}"))
+            }
+            TestOutcome::Ignored => {
+                lines.push(format!("ok {number} - {description} # SKIP ignored"))
+            }
+            TestOutcome::Failed => {
+                lines.push(format!("not ok {number} - {description}"));
+                lines.push("  ---".to_string());
+                lines.push(format!("  member: {}", result.member));
+                if !result.output.is_empty() {
+                    lines.push("  output: |".to_string());
+                    lines.extend(result.output.lines().map(|line| format!("    {line}")));
+                }
+                lines.push("  ...".to_string());
+            }
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const OUTPUT: &str = "
+     Running unittests src/lib.rs (target/debug/deps/my_crate-0123456789abcdef)
+
+running 3 tests
+test tests::test_a ... ok
+test tests::test_b ... FAILED
+test tests::test_c ... ignored
+
+failures:
+
+---- tests::test_b stdout ----
+thread 'tests::test_b' panicked at src/lib.rs:10:5:
+assertion failed
+
+failures:
+    tests::test_b
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+     Running tests/test_api.rs (target/debug/deps/test_api-0123456789abcdef)
+
+running 2 tests
+test test_api ... \x1b[32mok\x1b[0m
+test bench_api ... bench:       1,234.5 ns/iter (+/- 56)
+   Doc-tests my_crate
+
+running 2 tests
+test src/lib.rs - add (line 3) ... ok
+test src/lib.rs - sub (line 9) ... FAILED
+
+failures:
+
+---- src/lib.rs - sub (line 9) stdout ----
+assertion failed
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+    fn result(
+        member: &str,
+        binary: &str,
+        name: &str,
+        outcome: TestOutcome,
+        output: &str,
+    ) -> TestResult {
+        TestResult {
+            member: member.to_string(),
+            binary: binary.to_string(),
+            name: name.to_string(),
+            outcome,
+            output: output.to_string(),
+        }
+    }
+
+    #[rstest]
+    #[case::tap("tap:target/tests.tap", Some((ReportFormat::Tap, "target/tests.tap")))]
+    #[case::windows_path("tap:C:\\reports\\tests.tap", Some((ReportFormat::Tap, "C:\\reports\\tests.tap")))]
+    #[case::missing_path("tap", None)]
+    #[case::unknown_format("xml:report.xml", None)]
+    fn test_parse_report(#[case] value: &str, #[case] expected: Option<(ReportFormat, &str)>) {
+        assert_eq!(
+            parse_report(value).ok(),
+            expected.map(|(format, path)| (format, PathBuf::from(path)))
+        );
+    }
+
+    #[test]
+    fn test_parse_test_results_workspace() {
+        let failure = "thread 'tests::test_b' panicked at src/lib.rs:10:5:\nassertion failed";
+        assert_eq!(
+            parse_test_results(None, OUTPUT),
+            vec![
+                result(
+                    "my_crate",
+                    "my_crate",
+                    "tests::test_a",
+                    TestOutcome::Passed,
+                    ""
+                ),
+                result(
+                    "my_crate",
+                    "my_crate",
+                    "tests::test_b",
+                    TestOutcome::Failed,
+                    failure
+                ),
+                result(
+                    "my_crate",
+                    "my_crate",
+                    "tests::test_c",
+                    TestOutcome::Ignored,
+                    ""
+                ),
+                result("test_api", "test_api", "test_api", TestOutcome::Passed, ""),
+                result(
+                    "test_api",
+                    "test_api",
+                    "bench_api",
+                    TestOutcome::Bench(1234.5),
+                    ""
+                ),
+                result(
+                    "my_crate",
+                    "my_crate",
+                    "src/lib.rs - add (line 3)",
+                    TestOutcome::Passed,
+                    ""
+                ),
+                result(
+                    "my_crate",
+                    "my_crate",
+                    "src/lib.rs - sub (line 9)",
+                    TestOutcome::Failed,
+                    "assertion failed"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_test_results_package() {
+        let members: Vec<String> = parse_test_results(Some("my-crate"), OUTPUT)
+            .into_iter()
+            .map(|r| r.member)
+            .collect();
+        assert_eq!(members, vec!["my-crate"; 7]);
+    }
+
+    #[test]
+    fn test_to_tap() {
+        let results = vec![
+            result(
+                "my-crate",
+                "my_crate",
+                "tests::test_a",
+                TestOutcome::Passed,
+                "",
+            ),
+            result(
+                "my-crate",
+                "my_crate",
+                "tests::test_b",
+                TestOutcome::Failed,
+                "thread panicked\nassertion failed",
+            ),
+            result(
+                "my-crate",
+                "my_crate",
+                "tests::test_c",
+                TestOutcome::Ignored,
+                "",
+            ),
+        ];
+        assert_eq!(
+            to_tap(&results),
+            "TAP version 13
+1..3
+ok 1 - my-crate tests::test_a
+not ok 2 - my-crate tests::test_b
+  ---
+  member: my-crate
+  output: |
+    thread panicked
+    assertion failed
+  ...
+ok 3 - my-crate tests::test_c # SKIP ignored
+"
+        );
+    }
+}
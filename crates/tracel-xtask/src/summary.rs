@@ -0,0 +1,207 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use crate::{group_info, utils::cleanup::at_exit};
+
+static SUMMARY: OnceLock<Mutex<Summary>> = OnceLock::new();
+
+/// Summary of an xtask invocation written as JSON with the `--print-summary-json` global option.
+struct Summary {
+    path: PathBuf,
+    command: String,
+    environment: String,
+    context: String,
+    start: Instant,
+    members: Vec<MemberResult>,
+    /// True once the summary has been written, it is then not written again when exiting.
+    written: bool,
+}
+
+/// Result of a process executed for a workspace member, or for the whole workspace.
+#[derive(Debug, Clone, PartialEq)]
+struct MemberResult {
+    name: String,
+    command: String,
+    success: bool,
+    duration: Duration,
+}
+
+/// Enable the recording of the run summary which is written to the passed path by `write_summary`.
+pub(crate) fn init_summary(path: &Path, environment: &str, context: &str) {
+    let summary = Summary {
+        // keep writing next to the invocation directory when it changes with '--scratch'
+        path: std::path::absolute(path).unwrap_or(path.to_path_buf()),
+        command: std::env::args().collect::<Vec<_>>().join(" "),
+        environment: environment.to_string(),
+        context: context.to_string(),
+        start: Instant::now(),
+        members: vec![],
+        written: false,
+    };
+    // the summary can only be initialized once
+    if SUMMARY.set(Mutex::new(summary)).is_ok() {
+        at_exit(write_summary_at_exit);
+    }
+}
+
+/// Record the result of a process executed for the passed member, does nothing if the summary
+/// is not enabled.
+pub(crate) fn record_member(name: &str, command: &str, success: bool, duration: Duration) {
+    if let Some(summary) = SUMMARY.get() {
+        let mut summary = summary.lock().expect("summary lock should not be poisoned");
+        summary.members.push(MemberResult {
+            name: name.to_string(),
+            command: command.to_string(),
+            success,
+            duration,
+        });
+    }
+}
+
+/// Write the summary of the run with its final result if the `--print-summary-json` option is set.
+pub fn write_summary(result: &anyhow::Result<()>) -> anyhow::Result<()> {
+    let Some(summary) = SUMMARY.get() else {
+        return Ok(());
+    };
+    let mut summary = summary.lock().expect("summary lock should not be poisoned");
+    let json = summary_to_json(&summary, result, get_tool_versions());
+    std::fs::write(&summary.path, serde_json::to_string_pretty(&json)?)?;
+    summary.written = true;
+    group_info!("Run summary written to {}", summary.path.display());
+    Ok(())
+}
+
+/// Write the summary when the process exits if it has not been written yet, for instance when
+/// the `main` function does not call `write_summary`. The result of the run is then the one of
+/// the recorded processes.
+extern "C" fn write_summary_at_exit() {
+    let Some(summary) = SUMMARY.get() else {
+        return;
+    };
+    // the lock may be held by a thread stopped by the exit
+    let result = match summary.try_lock() {
+        Ok(summary) if !summary.written => get_recorded_result(&summary.members),
+        _ => return,
+    };
+    if let Err(e) = write_summary(&result) {
+        error!("Cannot write the run summary ({e})");
+    }
+}
+
+/// Returns an error naming the first failed process of the passed results, if any.
+fn get_recorded_result(members: &[MemberResult]) -> anyhow::Result<()> {
+    match members.iter().find(|m| !m.success) {
+        Some(failed) => Err(anyhow::anyhow!(
+            "'{}' failed for {}.",
+            failed.command,
+            failed.name
+        )),
+        None => Ok(()),
+    }
+}
+
+fn summary_to_json(summary: &Summary, result: &anyhow::Result<()>, tools: Value) -> Value {
+    let members: Vec<Value> = summary
+        .members
+        .iter()
+        .map(|m| {
+            json!({
+                "name": m.name,
+                "command": m.command,
+                "success": m.success,
+                "duration_secs": m.duration.as_secs_f64(),
+            })
+        })
+        .collect();
+    json!({
+        "command": summary.command,
+        "environment": summary.environment,
+        "context": summary.context,
+        "success": result.is_ok(),
+        "error": result.as_ref().err().map(|e| e.to_string()),
+        "duration_secs": summary.start.elapsed().as_secs_f64(),
+        "tools": tools,
+        "members": members,
+    })
+}
+
+fn get_tool_versions() -> Value {
+    let version = |program: &str| -> Option<String> {
+        let output = Command::new(program).arg("--version").output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    json!({
+        "xtask": env!("CARGO_PKG_VERSION"),
+        "rustc": version("rustc"),
+        "cargo": version("cargo"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn summary() -> Summary {
+        Summary {
+            path: PathBuf::from("summary.json"),
+            command: "xtask test unit".to_string(),
+            environment: "dev".to_string(),
+            context: "std".to_string(),
+            start: Instant::now(),
+            members: vec![MemberResult {
+                name: "my-crate".to_string(),
+                command: "cargo test -p my-crate".to_string(),
+                success: false,
+                duration: Duration::from_millis(1500),
+            }],
+            written: false,
+        }
+    }
+
+    #[test]
+    fn test_summary_to_json_success() {
+        let json = summary_to_json(&summary(), &Ok(()), json!({"xtask": "1.0.0"}));
+        assert_eq!(json["command"], "xtask test unit");
+        assert_eq!(json["environment"], "dev");
+        assert_eq!(json["success"], true);
+        assert_eq!(json["error"], Value::Null);
+        assert_eq!(json["tools"]["xtask"], "1.0.0");
+        assert_eq!(
+            json["members"],
+            json!([{
+                "name": "my-crate",
+                "command": "cargo test -p my-crate",
+                "success": false,
+                "duration_secs": 1.5,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_summary_to_json_failure() {
+        let json = summary_to_json(&summary(), &Err(anyhow!("tests failed")), Value::Null);
+        assert_eq!(json["success"], false);
+        assert_eq!(json["error"], "tests failed");
+    }
+
+    #[test]
+    fn test_get_recorded_result() {
+        let mut members = summary().members;
+        assert_eq!(
+            get_recorded_result(&members).unwrap_err().to_string(),
+            "'cargo test -p my-crate' failed for my-crate."
+        );
+        members[0].success = true;
+        assert!(get_recorded_result(&members).is_ok());
+    }
+}
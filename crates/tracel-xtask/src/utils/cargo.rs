@@ -1,20 +1,40 @@
-use std::process::Command;
+use std::{path::Path, process::Command, sync::OnceLock};
 
 use anyhow::Ok;
 use regex::Regex;
+use strum::Display;
+use toml_edit::{Item, Table};
 
-use crate::{endgroup, group, utils::process::run_process};
+use crate::{endgroup, group, group_info, utils::process::run_process};
 
-/// Ensure that a cargo crate is installed
+/// Ensure that a cargo crate is installed, when a version is passed the crate is reinstalled if
+/// the installed version is older.
 pub fn ensure_cargo_crate_is_installed(
     crate_name: &str,
     features: Option<&str>,
     version: Option<&str>,
     locked: bool,
 ) -> anyhow::Result<()> {
-    if !is_cargo_crate_installed(crate_name) {
+    let installed = is_cargo_crate_installed(crate_name);
+    let outdated = match version {
+        Some(version) if installed => {
+            let minimum = semver::Version::parse(version).map_err(|e| {
+                anyhow::anyhow!("Invalid version '{version}' of '{crate_name}': {e}")
+            })?;
+            let current = get_installed_crate_version(crate_name)?;
+            if current < minimum {
+                info!("'{crate_name}' {current} is older than {minimum}, reinstalling it...");
+            }
+            current < minimum
+        }
+        _ => false,
+    };
+    if !installed || outdated {
         group!("Cargo: install crate '{}'", crate_name);
         let mut args = vec!["install", crate_name];
+        if outdated {
+            args.push("--force");
+        }
         if locked {
             args.push("--locked");
         }
@@ -32,6 +52,7 @@ pub fn ensure_cargo_crate_is_installed(
             None,
             None,
             &format!("crate '{crate_name}' should be installed"),
+            None,
         )?;
         endgroup!();
     }
@@ -49,6 +70,55 @@ pub fn is_cargo_crate_installed(crate_name: &str) -> bool {
     output_str.lines().any(|line| line.contains(crate_name))
 }
 
+/// Returns the version reported by the `--version` option of the first binary of the passed
+/// installed crate, the binaries of cargo subcommands are called through cargo.
+fn get_installed_crate_version(crate_name: &str) -> anyhow::Result<semver::Version> {
+    let list = Command::new("cargo")
+        .args(["install", "--list"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to list the installed cargo crates: {e}"))?;
+    let binary = parse_installed_binaries(&String::from_utf8_lossy(&list.stdout), crate_name)
+        .into_iter()
+        .next()
+        .unwrap_or(crate_name.to_string());
+    let mut command = match binary.strip_prefix("cargo-") {
+        Some(subcommand) => {
+            let mut command = Command::new("cargo");
+            command.arg(subcommand);
+            command
+        }
+        None => Command::new(&binary),
+    };
+    let output = command.arg("--version").output().ok();
+    output
+        .and_then(|output| parse_version_output(&String::from_utf8_lossy(&output.stdout)))
+        .ok_or_else(|| {
+            anyhow::anyhow!("Cannot get the version of '{crate_name}' with '{binary} --version'.")
+        })
+}
+
+/// Returns the binaries of the passed crate listed in the `cargo install --list` output.
+fn parse_installed_binaries(output: &str, crate_name: &str) -> Vec<String> {
+    let prefix = format!("{crate_name} v");
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with(&prefix))
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace))
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// Returns the first semantic version of the passed `--version` output, i.e. `cargo-careful 0.4.3`.
+fn parse_version_output(output: &str) -> Option<semver::Version> {
+    let re =
+        Regex::new(r"\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?").expect("should compile version regex");
+    let version = re
+        .find_iter(output)
+        .find_map(|m| semver::Version::parse(m.as_str()).ok());
+    version
+}
+
 pub fn parse_cargo_search_output(output: &str) -> Option<(String, String)> {
     // First strip ANSI color codes
     let ansi_re = Regex::new(r"\x1b\[[0-9;]*m").expect("should compile regex for ANSI codes");
@@ -64,10 +134,316 @@ pub fn parse_cargo_search_output(output: &str) -> Option<(String, String)> {
     None
 }
 
+/// Expand the feature glob patterns (i.e. `gpu-*`) against the passed declared features.
+/// Names without any glob character are kept as is so that cargo can validate them.
+/// Returns the expanded features and the patterns which did not match any declared feature.
+pub fn expand_features(requested: &[String], declared: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut expanded: Vec<String> = vec![];
+    let mut unmatched = vec![];
+    for feature in requested {
+        if !feature.contains(['*', '?', '[']) {
+            if !expanded.contains(feature) {
+                expanded.push(feature.clone());
+            }
+            continue;
+        }
+        let matched: Vec<&String> = match glob::Pattern::new(feature) {
+            Result::Ok(pattern) => declared.iter().filter(|d| pattern.matches(d)).collect(),
+            Err(_) => vec![],
+        };
+        if matched.is_empty() {
+            unmatched.push(feature.clone());
+        }
+        let mut matched: Vec<String> = matched.into_iter().cloned().collect();
+        matched.sort();
+        matched.into_iter().for_each(|m| {
+            if !expanded.contains(&m) {
+                expanded.push(m);
+            }
+        });
+    }
+    (expanded, unmatched)
+}
+
+/// Tables of a manifest which declare dependencies, also looked up in the `target.<cfg>` tables.
+pub const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Returns the dependency tables of the passed manifest root table with their dotted name,
+/// i.e. `dependencies` or `target.cfg(unix).dev-dependencies`.
+pub fn get_dependency_tables(manifest: &Table) -> Vec<(String, &Table)> {
+    let mut tables: Vec<(String, &Table)> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|name| Some((name.to_string(), manifest.get(name)?.as_table()?)))
+        .collect();
+    if let Some(targets) = manifest.get("target").and_then(Item::as_table) {
+        for (target_name, target) in targets.iter() {
+            tables.extend(DEPENDENCY_TABLES.iter().filter_map(|name| {
+                Some((
+                    format!("target.{target_name}.{name}"),
+                    target.get(name)?.as_table()?,
+                ))
+            }));
+        }
+    }
+    tables
+}
+
+/// Environment variables of the flags passed by cargo to rustc or rustdoc.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagsVar {
+    Rustflags,
+    Rustdocflags,
+}
+
+impl FlagsVar {
+    pub fn name(self) -> &'static str {
+        match self {
+            FlagsVar::Rustflags => "RUSTFLAGS",
+            FlagsVar::Rustdocflags => "RUSTDOCFLAGS",
+        }
+    }
+
+    /// Name of the variable which takes precedence over `name()` in cargo.
+    pub fn encoded_name(self) -> &'static str {
+        match self {
+            FlagsVar::Rustflags => "CARGO_ENCODED_RUSTFLAGS",
+            FlagsVar::Rustdocflags => "CARGO_ENCODED_RUSTDOCFLAGS",
+        }
+    }
+}
+
+/// Environment variable of the `build.rustflags` cargo configuration, cargo merges it with the
+/// `build.rustflags` of the configuration files whereas `RUSTFLAGS` replaces them.
+pub const BUILD_RUSTFLAGS_VAR: &str = "CARGO_BUILD_RUSTFLAGS";
+
+/// Append the passed flags to the rustc flags of the cargo commands executed by the process. The
+/// flags extend `RUSTFLAGS` or `CARGO_ENCODED_RUSTFLAGS` when the environment sets them, they are
+/// passed with `CARGO_BUILD_RUSTFLAGS` otherwise so that the `build.rustflags` of the cargo
+/// configuration files still apply.
+pub fn append_rustflags(flags: &[String]) {
+    let plain = std::env::var(FlagsVar::Rustflags.name()).ok();
+    let encoded = std::env::var(FlagsVar::Rustflags.encoded_name()).ok();
+    let config = std::env::var(BUILD_RUSTFLAGS_VAR).ok();
+    let (name, value) = compose_rustflags(
+        plain.as_deref(),
+        encoded.as_deref(),
+        config.as_deref(),
+        flags,
+    );
+    unsafe {
+        std::env::set_var(name, value);
+    }
+}
+
+/// Returns the environment variable and its value to append the passed flags to the rustc flags.
+/// The configuration variable is split on whitespace, so a flag containing whitespace is passed
+/// with the encoded variable.
+fn compose_rustflags(
+    plain: Option<&str>,
+    encoded: Option<&str>,
+    config: Option<&str>,
+    flags: &[String],
+) -> (&'static str, String) {
+    let has_whitespace = flags.iter().any(|f| f.contains(char::is_whitespace));
+    if plain.is_some() || encoded.is_some() || has_whitespace {
+        return compose_flags(FlagsVar::Rustflags, plain.or(config), encoded, flags);
+    }
+    (
+        BUILD_RUSTFLAGS_VAR,
+        format!("{} {}", config.unwrap_or_default(), flags.join(" "))
+            .trim()
+            .to_string(),
+    )
+}
+
+/// Linker selected with the `--linker` global option.
+#[derive(Debug, Default, Display, Clone, Copy, PartialEq, clap::ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum Linker {
+    /// Default linker of the toolchain.
+    #[default]
+    Default,
+    /// mold linker, Linux only.
+    Mold,
+    /// LLVM linker.
+    Lld,
+}
+
+/// Rustc flags of the linker selected with `use_linker`.
+static LINKER_RUSTFLAGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Append the rustc flags selecting the passed linker to the rustc flags of the cargo commands
+/// executed by the process. The default linker is kept with a warning if the passed linker is not
+/// supported on the host or not installed.
+pub fn use_linker(linker: Linker) {
+    if linker == Linker::Default {
+        return;
+    }
+    let env = if cfg!(target_env = "msvc") {
+        "msvc"
+    } else {
+        "gnu"
+    };
+    match get_linker_config(linker, std::env::consts::OS, env) {
+        None => warn!(
+            "The {linker} linker is not supported on {}, using the default linker.",
+            std::env::consts::OS
+        ),
+        Some((_, executable)) if !is_linker_installed(executable) => warn!(
+            "The {linker} linker is not installed ('{executable}' not found), using the default linker."
+        ),
+        Some((flags, _)) => {
+            group_info!("Linker: {linker} ({})", flags.join(" "));
+            append_rustflags(&flags);
+            let _ = LINKER_RUSTFLAGS.set(flags);
+        }
+    }
+}
+
+/// Returns the rustc flags of the linker selected with `use_linker`, they are kept when the
+/// inherited rustc flags are not.
+pub(crate) fn get_linker_rustflags() -> &'static [String] {
+    LINKER_RUSTFLAGS
+        .get()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Returns the rustc flags selecting the passed linker on the passed OS and target environment
+/// with the executable which must be installed, or None if the linker is not supported on the OS.
+fn get_linker_config(linker: Linker, os: &str, env: &str) -> Option<(Vec<String>, &'static str)> {
+    let (flag, executable) = match (linker, os, env) {
+        (Linker::Default, _, _) => return Some((vec![], "")),
+        (Linker::Mold, "linux", _) => ("-Clink-arg=-fuse-ld=mold", "mold"),
+        (Linker::Mold, _, _) => return None,
+        // the MSVC toolchains link with link.exe, rust-lld replaces it
+        (Linker::Lld, "windows", "msvc") => ("-Clinker=rust-lld", "rust-lld"),
+        (Linker::Lld, "macos", _) => ("-Clink-arg=-fuse-ld=lld", "ld64.lld"),
+        (Linker::Lld, _, _) => ("-Clink-arg=-fuse-ld=lld", "ld.lld"),
+    };
+    Some((vec![flag.to_string()], executable))
+}
+
+/// Returns true if the passed linker executable is found in the PATH or, for rust-lld, in the
+/// sysroot of the current toolchain.
+fn is_linker_installed(executable: &str) -> bool {
+    is_executable_in_path(executable)
+        || (executable == "rust-lld" && is_executable_in_sysroot(executable))
+}
+
+/// Returns true if the passed executable is shipped in the `lib/rustlib/<host>/bin` directory of
+/// the sysroot of the current toolchain.
+fn is_executable_in_sysroot(executable: &str) -> bool {
+    let Some(sysroot) = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    else {
+        return false;
+    };
+    let name = format!("{executable}{}", std::env::consts::EXE_SUFFIX);
+    std::fs::read_dir(Path::new(&sysroot).join("lib").join("rustlib"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| entry.path().join("bin").join(&name).is_file())
+}
+
+/// Returns true if the passed executable is found in one of the directories of the PATH, an empty
+/// name is always found.
+fn is_executable_in_path(executable: &str) -> bool {
+    if executable.is_empty() {
+        return true;
+    }
+    let name = format!("{executable}{}", std::env::consts::EXE_SUFFIX);
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(&name).is_file()))
+}
+
+/// Set the passed flags in the environment of the command, appended to the flags inherited from
+/// the environment of the process if `inherit` is true.
+pub fn set_flags_env(command: &mut Command, var: FlagsVar, flags: &[String], inherit: bool) {
+    let (name, value) = get_flags_env(var, flags, inherit);
+    if name == var.name() {
+        // the encoded variable would take precedence
+        command.env_remove(var.encoded_name());
+    }
+    command.env(name, value);
+}
+
+/// Returns the environment variable and its value to pass the flags to cargo, appended to the
+/// flags inherited from the environment of the process if `inherit` is true.
+pub fn get_flags_env(var: FlagsVar, flags: &[String], inherit: bool) -> (&'static str, String) {
+    if !inherit {
+        // the linker flags are appended by xtask, they are not inherited
+        let flags: Vec<String> = match var {
+            FlagsVar::Rustflags => get_linker_rustflags()
+                .iter()
+                .chain(flags)
+                .cloned()
+                .collect(),
+            FlagsVar::Rustdocflags => flags.to_vec(),
+        };
+        return compose_flags(var, None, None, &flags);
+    }
+    // the flags appended by xtask without RUSTFLAGS would be ignored once RUSTFLAGS is set
+    let plain = std::env::var(var.name()).ok().or_else(|| match var {
+        FlagsVar::Rustflags => std::env::var(BUILD_RUSTFLAGS_VAR).ok(),
+        FlagsVar::Rustdocflags => None,
+    });
+    let encoded = std::env::var(var.encoded_name()).ok();
+    compose_flags(var, plain.as_deref(), encoded.as_deref(), flags)
+}
+
+/// Returns the environment variable and its value to pass the existing flags and the new flags to
+/// cargo. The encoded variable takes precedence over the plain one in cargo, even when it is empty,
+/// so it is extended when it is set. It is also used when a flag contains whitespace as the plain
+/// variable is split on whitespace.
+fn compose_flags(
+    var: FlagsVar,
+    plain: Option<&str>,
+    encoded: Option<&str>,
+    flags: &[String],
+) -> (&'static str, String) {
+    const SEPARATOR: &str = "\x1f";
+    let has_whitespace = flags.iter().any(|f| f.contains(char::is_whitespace));
+    match (encoded, plain) {
+        (Some(encoded), _) => (
+            var.encoded_name(),
+            encoded
+                .split(SEPARATOR)
+                .filter(|flag| !flag.is_empty())
+                .map(String::from)
+                .chain(flags.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(SEPARATOR),
+        ),
+        (None, plain) if has_whitespace => (
+            var.encoded_name(),
+            plain
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .chain(flags.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(SEPARATOR),
+        ),
+        (None, plain) => (
+            var.name(),
+            format!("{} {}", plain.unwrap_or_default(), flags.join(" "))
+                .trim()
+                .to_string(),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rstest::rstest;
+    use serial_test::serial;
 
     #[rstest]
     #[case::valid_input("tracel-xtask-macros = \"1.0.1\"", Some(("tracel-xtask-macros", "1.0.1")))]
@@ -83,4 +459,188 @@ mod tests {
         let result = parse_cargo_search_output(input);
         assert_eq!(result, expected);
     }
+
+    const INSTALL_LIST: &str = "cargo-careful v0.4.3:
+    cargo-careful
+typos-cli v1.24.1:
+    typos
+";
+
+    #[rstest]
+    #[case::cargo_subcommand("cargo-careful", vec!["cargo-careful"])]
+    #[case::binary_name_differs("typos-cli", vec!["typos"])]
+    #[case::not_installed("grcov", vec![])]
+    fn test_parse_installed_binaries(#[case] crate_name: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(parse_installed_binaries(INSTALL_LIST, crate_name), expected);
+    }
+
+    #[rstest]
+    #[case::cargo_subcommand("cargo-careful 0.4.3\n", Some("0.4.3"))]
+    #[case::pre_release("typos-cli 1.25.0-rc.1\n", Some("1.25.0-rc.1"))]
+    #[case::git_hash("grcov 0.8.19 (abc1234 2024-05-01)\n", Some("0.8.19"))]
+    #[case::no_version("error: no such command: `careful`\n", None)]
+    fn test_parse_version_output(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(
+            parse_version_output(output),
+            expected.map(|v| semver::Version::parse(v).unwrap())
+        );
+    }
+
+    #[rstest]
+    #[case::default_linker(Linker::Default, "linux", "gnu", Some((vec![], "")))]
+    #[case::mold_linux(Linker::Mold, "linux", "gnu", Some((vec!["-Clink-arg=-fuse-ld=mold"], "mold")))]
+    #[case::mold_macos(Linker::Mold, "macos", "", None)]
+    #[case::lld_linux(Linker::Lld, "linux", "gnu", Some((vec!["-Clink-arg=-fuse-ld=lld"], "ld.lld")))]
+    #[case::lld_macos(Linker::Lld, "macos", "", Some((vec!["-Clink-arg=-fuse-ld=lld"], "ld64.lld")))]
+    #[case::lld_windows_msvc(Linker::Lld, "windows", "msvc", Some((vec!["-Clinker=rust-lld"], "rust-lld")))]
+    #[case::lld_windows_gnu(Linker::Lld, "windows", "gnu", Some((vec!["-Clink-arg=-fuse-ld=lld"], "ld.lld")))]
+    fn test_get_linker_config(
+        #[case] linker: Linker,
+        #[case] os: &str,
+        #[case] env: &str,
+        #[case] expected: Option<(Vec<&str>, &str)>,
+    ) {
+        let expected = expected
+            .map(|(flags, executable)| (flags.into_iter().map(String::from).collect(), executable));
+        assert_eq!(get_linker_config(linker, os, env), expected);
+    }
+
+    #[test]
+    fn test_is_executable_in_path() {
+        assert!(is_executable_in_path(""));
+        assert!(is_executable_in_path("cargo"));
+        assert!(!is_executable_in_path("xtask-missing-executable"));
+    }
+
+    #[test]
+    fn test_is_linker_installed() {
+        // rust-lld is shipped in the sysroot of the toolchains
+        assert!(is_linker_installed("rust-lld"));
+        assert!(!is_linker_installed("xtask-missing-linker"));
+    }
+
+    #[rstest]
+    #[case::no_pattern(vec!["std"], vec!["std", "gpu-cuda"], vec!["std"], vec![])]
+    #[case::prefix_pattern(vec!["gpu-*"], vec!["std", "gpu-rocm", "gpu-cuda"], vec!["gpu-cuda", "gpu-rocm"], vec![])]
+    #[case::pattern_and_name(vec!["std", "gpu-*"], vec!["std", "gpu-cuda"], vec!["std", "gpu-cuda"], vec![])]
+    #[case::duplicates_removed(vec!["gpu-cuda", "gpu-*"], vec!["gpu-cuda"], vec!["gpu-cuda"], vec![])]
+    #[case::unmatched_pattern(vec!["std", "gpu-*"], vec!["std"], vec!["std"], vec!["gpu-*"])]
+    #[case::unknown_name_kept(vec!["unknown"], vec![], vec!["unknown"], vec![])]
+    fn test_expand_features(
+        #[case] requested: Vec<&str>,
+        #[case] declared: Vec<&str>,
+        #[case] expected_features: Vec<&str>,
+        #[case] expected_unmatched: Vec<&str>,
+    ) {
+        let to_strings = |v: Vec<&str>| v.into_iter().map(String::from).collect::<Vec<_>>();
+        let (features, unmatched) = expand_features(&to_strings(requested), &to_strings(declared));
+        assert_eq!(features, expected_features);
+        assert_eq!(unmatched, expected_unmatched);
+    }
+
+    #[rstest]
+    #[case::no_flags(None, None, &["--cfg", "loom"], ("RUSTFLAGS", "--cfg loom"))]
+    #[case::existing_rustflags(Some("-Dwarnings"), None, &["--cfg", "loom"], ("RUSTFLAGS", "-Dwarnings --cfg loom"))]
+    #[case::existing_encoded(
+        Some("-Dwarnings"),
+        Some("-C\x1fopt-level=1"),
+        &["--cfg", "loom"],
+        ("CARGO_ENCODED_RUSTFLAGS", "-C\x1fopt-level=1\x1f--cfg\x1floom")
+    )]
+    #[case::empty_encoded(None, Some(""), &["--cfg", "loom"], ("CARGO_ENCODED_RUSTFLAGS", "--cfg\x1floom"))]
+    #[case::whitespace(
+        Some("-Dwarnings"),
+        None,
+        &["--cfg", "name=\"a b\""],
+        ("CARGO_ENCODED_RUSTFLAGS", "-Dwarnings\x1f--cfg\x1fname=\"a b\"")
+    )]
+    fn test_compose_flags(
+        #[case] plain: Option<&str>,
+        #[case] encoded: Option<&str>,
+        #[case] flags: &[&str],
+        #[case] expected: (&str, &str),
+    ) {
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+        let (name, value) = compose_flags(FlagsVar::Rustflags, plain, encoded, &flags);
+        assert_eq!((name, value.as_str()), expected);
+    }
+
+    #[rstest]
+    #[case::no_flags(None, None, None, &["--cfg", "loom"], ("CARGO_BUILD_RUSTFLAGS", "--cfg loom"))]
+    #[case::existing_config(None, None, Some("-Clink-arg=-fuse-ld=mold"), &["--cfg", "loom"], ("CARGO_BUILD_RUSTFLAGS", "-Clink-arg=-fuse-ld=mold --cfg loom"))]
+    #[case::existing_rustflags(Some("-Dwarnings"), None, None, &["--cfg", "loom"], ("RUSTFLAGS", "-Dwarnings --cfg loom"))]
+    #[case::existing_encoded(None, Some("-Dwarnings"), None, &["--cfg", "loom"], ("CARGO_ENCODED_RUSTFLAGS", "-Dwarnings\x1f--cfg\x1floom"))]
+    #[case::whitespace(
+        None,
+        None,
+        Some("-Clink-arg=-fuse-ld=mold"),
+        &["--cfg", "name=\"a b\""],
+        ("CARGO_ENCODED_RUSTFLAGS", "-Clink-arg=-fuse-ld=mold\x1f--cfg\x1fname=\"a b\"")
+    )]
+    fn test_compose_rustflags(
+        #[case] plain: Option<&str>,
+        #[case] encoded: Option<&str>,
+        #[case] config: Option<&str>,
+        #[case] flags: &[&str],
+        #[case] expected: (&str, &str),
+    ) {
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+        let (name, value) = compose_rustflags(plain, encoded, config, &flags);
+        assert_eq!((name, value.as_str()), expected);
+    }
+
+    #[rstest]
+    #[case::without_inherited_value(None, "-Zsanitizer=address")]
+    #[case::with_inherited_value(
+        Some("-Ctarget-cpu=native"),
+        "-Ctarget-cpu=native -Zsanitizer=address"
+    )]
+    fn test_compose_flags_rustdocflags(#[case] plain: Option<&str>, #[case] expected: &str) {
+        let flags = vec!["-Zsanitizer=address".to_string()];
+        let (name, value) = compose_flags(FlagsVar::Rustdocflags, plain, None, &flags);
+        assert_eq!((name, value.as_str()), ("RUSTDOCFLAGS", expected));
+    }
+
+    #[test]
+    #[serial]
+    fn test_get_flags_env() {
+        let flags = vec!["-Zsanitizer=address".to_string()];
+        unsafe {
+            std::env::remove_var("CARGO_ENCODED_RUSTFLAGS");
+            std::env::remove_var(BUILD_RUSTFLAGS_VAR);
+            std::env::set_var("RUSTFLAGS", "-Ctarget-cpu=native");
+        }
+        let inherited = get_flags_env(FlagsVar::Rustflags, &flags, true);
+        let not_inherited = get_flags_env(FlagsVar::Rustflags, &flags, false);
+        unsafe {
+            std::env::remove_var("RUSTFLAGS");
+        }
+        let without_value = get_flags_env(FlagsVar::Rustflags, &flags, true);
+        unsafe {
+            std::env::set_var(BUILD_RUSTFLAGS_VAR, "--cfg loom");
+        }
+        let appended_by_xtask = get_flags_env(FlagsVar::Rustflags, &flags, true);
+        unsafe {
+            std::env::remove_var(BUILD_RUSTFLAGS_VAR);
+        }
+        assert_eq!(
+            inherited,
+            (
+                "RUSTFLAGS",
+                "-Ctarget-cpu=native -Zsanitizer=address".to_string()
+            )
+        );
+        assert_eq!(
+            not_inherited,
+            ("RUSTFLAGS", "-Zsanitizer=address".to_string())
+        );
+        assert_eq!(
+            without_value,
+            ("RUSTFLAGS", "-Zsanitizer=address".to_string())
+        );
+        assert_eq!(
+            appended_by_xtask,
+            ("RUSTFLAGS", "--cfg loom -Zsanitizer=address".to_string())
+        );
+    }
 }
@@ -1,4 +1,10 @@
-use std::sync::{Arc, LazyLock, Mutex};
+use std::{
+    os::raw::c_int,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use super::process::kill_running_children;
+use crate::summary::write_summary;
 
 pub static CLEANUP_HANDLER: LazyLock<CleanupHandler> = LazyLock::new(CleanupHandler::new);
 
@@ -28,6 +34,10 @@ impl CleanupHandler {
                 warn!("Termination signal received, executing registered functions.");
                 handler_.terminate();
             }
+            kill_running_children();
+            let _ = write_summary(&Err(anyhow::anyhow!(
+                "Interrupted by a termination signal."
+            )));
             std::process::exit(1);
         })
         .expect("Should be able to set termination handler");
@@ -50,6 +60,15 @@ impl CleanupHandler {
             });
     }
 
+    /// Remove the cleanup functions registered with the passed name, for instance when the state
+    /// they restore has already been restored.
+    pub(crate) fn unregister(&self, name: &str) {
+        self.registered
+            .lock()
+            .unwrap()
+            .retain(|registered| registered.name != name);
+    }
+
     fn terminate(&mut self) {
         let mut terminated = self.terminated.lock().unwrap();
         if *terminated {
@@ -74,6 +93,19 @@ impl Drop for CleanupHandler {
     }
 }
 
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> c_int;
+}
+
+/// Register a function called when the process exits, including with `std::process::exit` which
+/// is used by xtask on CTRL+c and when the deadline is exceeded. The leak check allocator also
+/// uses it in the test processes.
+pub(crate) fn at_exit(callback: extern "C" fn()) {
+    unsafe {
+        atexit(callback);
+    }
+}
+
 #[macro_export]
 macro_rules! register_cleanup {
     ($name:expr, $handler:expr) => {
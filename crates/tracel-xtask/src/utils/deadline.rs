@@ -0,0 +1,59 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    summary::write_summary,
+    utils::{cleanup::CLEANUP_HANDLER, process::kill_running_children},
+};
+
+/// Exit code of the xtask process when the `--deadline` global option is exceeded,
+/// it is the same as the one of the `timeout` coreutils command.
+pub const DEADLINE_EXIT_CODE: i32 = 124;
+
+static DEADLINE_ENABLED: AtomicBool = AtomicBool::new(false);
+static CURRENT_PHASE: Mutex<String> = Mutex::new(String::new());
+
+/// Start a timer which terminates the running child processes and exits the xtask process with
+/// `DEADLINE_EXIT_CODE` once the passed duration is elapsed.
+pub(crate) fn start_deadline(deadline: Duration) {
+    DEADLINE_ENABLED.store(true, Ordering::Relaxed);
+    // children get their own process group so make sure that CTRL+c terminates them as well
+    LazyLock::force(&CLEANUP_HANDLER);
+    thread::spawn(move || {
+        thread::sleep(deadline);
+        let phase = CURRENT_PHASE
+            .lock()
+            .map(|phase| phase.clone())
+            .unwrap_or_default();
+        error!(
+            "Deadline of {} exceeded during phase '{}', terminating.",
+            super::time::format_duration(&deadline),
+            if phase.is_empty() { "startup" } else { &phase },
+        );
+        kill_running_children();
+        // run the registered cleanup functions
+        drop(CLEANUP_HANDLER.clone());
+        let _ = write_summary(&Err(anyhow::anyhow!(
+            "Deadline exceeded during phase '{phase}'."
+        )));
+        std::process::exit(DEADLINE_EXIT_CODE);
+    });
+}
+
+/// Return true if the `--deadline` global option is set.
+pub(crate) fn is_deadline_enabled() -> bool {
+    DEADLINE_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record the title of the current log group which is reported when the deadline is exceeded.
+pub fn set_phase(title: &str) {
+    if let Ok(mut phase) = CURRENT_PHASE.lock() {
+        title.clone_into(&mut phase);
+    }
+}
@@ -24,6 +24,7 @@ pub fn custom_crates_build(
             envs.clone(),
             path,
             &format!("Custom build failed for {}", *c),
+            None,
         )?;
         endgroup!();
         Ok(())
@@ -50,6 +51,7 @@ pub fn custom_crates_check(
             envs.clone(),
             path,
             &format!("Custom check failed for {}", *c),
+            None,
         )?;
         endgroup!();
         Ok(())
@@ -76,6 +78,7 @@ pub fn custom_crates_tests(
             envs.clone(),
             path,
             &format!("Custom test failed for {}", *c),
+            None,
         )?;
         endgroup!();
         Ok(())
@@ -102,6 +105,7 @@ pub fn custom_crates_doc_build(
             envs.clone(),
             path,
             &format!("Custom doc build failed for {}", *c),
+            None,
         )?;
         endgroup!();
         Ok(())
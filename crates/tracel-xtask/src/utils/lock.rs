@@ -0,0 +1,169 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+/// Lock file created in the target directory by the xtask run using it.
+pub const TARGET_LOCK_FILENAME: &str = "xtask.lock";
+/// Environment variable set to the process id of the xtask run holding the lock, the nested
+/// xtask runs started by this run inherit it and share the lock.
+const LOCK_OWNER_ENV: &str = "XTASK_TARGET_LOCK_OWNER";
+
+static LOCK_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Open lock file, the operating system releases its lock when the process exits, even if the
+/// process is killed.
+static LOCK_FILE: OnceLock<File> = OnceLock::new();
+
+/// Enable the lock of the target directory acquired before the first cargo invocation.
+pub(crate) fn enable_target_lock() {
+    LOCK_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Acquire the advisory lock of the target directory, waiting for the other xtask run holding it
+/// to finish. It is held until the xtask process exits and does nothing if the lock is disabled,
+/// already held or inherited from the xtask run which started this one.
+pub(crate) fn acquire_target_lock() {
+    if !LOCK_ENABLED.load(Ordering::Relaxed)
+        || LOCK_FILE.get().is_some()
+        || std::env::var_os(LOCK_OWNER_ENV).is_some()
+    {
+        return;
+    }
+    let dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from("target"));
+    let path = std::path::absolute(dir.join(TARGET_LOCK_FILENAME))
+        .unwrap_or(dir.join(TARGET_LOCK_FILENAME));
+    let file = match open_lock_file(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Cannot lock the target directory {} ({e}).", dir.display());
+            return;
+        }
+    };
+    let locked = match lock_file(&file, false) {
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            let owner = read_lock_owner(&path)
+                .map(|owner| format!(" (pid {owner})"))
+                .unwrap_or_default();
+            warn!(
+                "Another xtask run{owner} is using the target directory {}, waiting for it to finish. Use --no-lock for runs with separate target directories.",
+                dir.display()
+            );
+            lock_file(&file, true)
+        }
+        result => result,
+    };
+    if let Err(e) = locked.and_then(|_| write_lock_owner(&file, std::process::id())) {
+        warn!("Cannot lock the target directory {} ({e}).", dir.display());
+        return;
+    }
+    unsafe {
+        std::env::set_var(LOCK_OWNER_ENV, std::process::id().to_string());
+    }
+    let _ = LOCK_FILE.set(file);
+}
+
+fn open_lock_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Record the process id of the lock holder in the lock file, for the message of the waiting runs.
+fn write_lock_owner(mut file: &File, pid: u32) -> std::io::Result<()> {
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{pid}")?;
+    file.flush()
+}
+
+fn read_lock_owner(path: &Path) -> Option<u32> {
+    let mut content = String::new();
+    File::open(path).ok()?.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// Take the exclusive lock of the passed file, a non blocking attempt fails with
+/// `ErrorKind::WouldBlock` if another open file holds it.
+#[cfg(unix)]
+fn lock_file(file: &File, blocking: bool) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let operation = if blocking {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_EX | libc::LOCK_NB
+    };
+    if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Take the exclusive lock of the passed file, a non blocking attempt fails with
+/// `ErrorKind::WouldBlock` if another open file holds it.
+#[cfg(windows)]
+fn lock_file(file: &File, blocking: bool) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::{
+        Foundation::ERROR_LOCK_VIOLATION,
+        Storage::FileSystem::{LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY},
+        System::IO::OVERLAPPED,
+    };
+
+    let flags = if blocking {
+        LOCKFILE_EXCLUSIVE_LOCK
+    } else {
+        LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY
+    };
+    // lock a range past the end of the file so that the waiting runs can still read the owner
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    overlapped.Anonymous.Anonymous.OffsetHigh = u32::MAX;
+    let locked = unsafe { LockFileEx(file.as_raw_handle() as _, flags, 0, 1, 0, &mut overlapped) };
+    if locked != 0 {
+        return Ok(());
+    }
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Err(std::io::ErrorKind::WouldBlock.into())
+    } else {
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_file() {
+        let dir = std::env::temp_dir().join(format!("xtask-test-lock-{}", std::process::id()));
+        let path = dir.join(TARGET_LOCK_FILENAME);
+        let first = open_lock_file(&path).expect("lock file should be opened");
+        lock_file(&first, false).expect("lock should be acquired");
+        write_lock_owner(&first, 42).expect("owner should be written");
+        let second = open_lock_file(&path).expect("lock file should be opened again");
+        let contended = lock_file(&second, false).map_err(|e| e.kind());
+        let owner = read_lock_owner(&path);
+        drop(first);
+        let released = lock_file(&second, false).map_err(|e| e.kind());
+        drop(second);
+        std::fs::remove_dir_all(&dir).expect("dir should be removed");
+        assert_eq!(contended, Err(std::io::ErrorKind::WouldBlock));
+        assert_eq!(owner, Some(42));
+        assert_eq!(released, Ok(()));
+    }
+}
@@ -2,10 +2,15 @@ use std::process::Command;
 
 pub mod cargo;
 pub mod cleanup;
+pub mod deadline;
 pub mod helpers;
+pub mod lock;
 pub mod process;
 pub mod prompt;
 pub mod rustup;
+pub mod scratch;
+pub mod script;
+pub mod snapshot;
 pub mod time;
 pub mod workspace;
 
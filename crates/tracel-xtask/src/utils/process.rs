@@ -1,10 +1,14 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::{BufRead, BufReader},
     path::Path,
-    process::{Command, ExitStatus, Stdio},
-    sync::mpsc,
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        mpsc::{self, Receiver},
+        LazyLock, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
 use anyhow;
@@ -12,8 +16,43 @@ use rand::Rng;
 use regex::Regex;
 
 use crate::group_info;
+use crate::report::{is_report_enabled, record_test_output};
+use crate::summary::record_member;
+use crate::utils::cargo::{FlagsVar, BUILD_RUSTFLAGS_VAR};
+use crate::utils::deadline::is_deadline_enabled;
+use crate::utils::lock::acquire_target_lock;
+use crate::utils::script::{describe_command, get_initial_env, plan_command};
 use crate::{endgroup, group};
 
+/// Child processes currently running, with true if they have their own process group.
+static RUNNING_CHILDREN: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+/// Maximum duration of the processes run for the workspace or for a package.
+static PROCESS_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+/// Variables passed through in addition to the allowlist when the environment of the processes run
+/// for the workspace or for a package is cleared.
+static CLEAN_ENV: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Source location of a panic or of a compilation error in the output of a cargo process.
+static FAILURE_LOCATION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?<kind>panicked at|-->) (?:'.*', )?(?<file>[^\s:]+):(?<line>\d+):(?<col>\d+)")
+        .expect("should compile failure location regex")
+});
+/// Package named by cargo when it fails to compile or to test a package of the workspace.
+static FAILED_PACKAGE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:could not compile `|to rerun pass `-p )(?<package>[^`\s]+)")
+        .expect("should compile failed package regex")
+});
+
+/// Environment variables passed through to the processes run in a clean environment.
+pub(crate) const CLEAN_ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "TMPDIR",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+];
+
 /// A custom error for failed subprocesses.
 ///
 /// To get the `ExitStatus`, downcast the error at call sites.
@@ -77,13 +116,87 @@ fn extract_exit_signal(line: &str) -> Option<ExitSignal> {
     })
 }
 
+/// Failure details parsed from the output of a cargo process to annotate the failures on GitHub
+/// Actions.
+#[derive(Debug, Default, PartialEq)]
+struct FailureScan {
+    /// First package named by cargo as failed.
+    package: Option<String>,
+    /// First location of a panic or of a compilation error as `(file, line, column)`.
+    location: Option<(String, u32, u32)>,
+    /// True while the lines of a compilation error are scanned, the locations of the warnings
+    /// are ignored.
+    in_error: bool,
+}
+
+impl FailureScan {
+    fn scan(&mut self, line: &str) {
+        let line = remove_ansi_codes(line);
+        if let Some(caps) = FAILED_PACKAGE_RE.captures(&line) {
+            self.package
+                .get_or_insert_with(|| caps["package"].to_string());
+            return;
+        }
+        if line.starts_with("error") {
+            self.in_error = true;
+        } else if line.starts_with("warning") {
+            self.in_error = false;
+        }
+        if self.location.is_some() {
+            return;
+        }
+        let Some(caps) = FAILURE_LOCATION_RE.captures(&line) else {
+            return;
+        };
+        if &caps["kind"] == "-->" && !self.in_error {
+            return;
+        }
+        if let (Ok(line), Ok(col)) = (caps["line"].parse(), caps["col"].parse()) {
+            self.location = Some((standardize_slashes(&caps["file"]), line, col));
+        }
+    }
+}
+
+fn is_github_actions() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok()
+}
+
+/// Returns the `::error` workflow command of GitHub Actions annotating the failure of the passed
+/// package at the passed location, if any.
+fn format_error_annotation(
+    package: &str,
+    location: Option<&(String, u32, u32)>,
+    message: &str,
+) -> String {
+    let escape_data = |s: &str| {
+        s.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let escape_property = |s: &str| escape_data(s).replace(':', "%3A").replace(',', "%2C");
+    let mut properties = vec![];
+    if let Some((file, line, col)) = location {
+        properties.push(format!("file={}", escape_property(file)));
+        properties.push(format!("line={line}"));
+        properties.push(format!("col={col}"));
+    }
+    properties.push(format!(
+        "title={}",
+        escape_property(&format!("{package} failed"))
+    ));
+    format!("::error {}::{}", properties.join(","), escape_data(message))
+}
+
 /// Run a process
+/// When a timeout is passed the process tree is terminated once it is exceeded and an error is
+/// returned.
 pub fn run_process(
     name: &str,
     args: &[&str],
     envs: Option<HashMap<&str, &str>>,
     path: Option<&Path>,
     error_msg: &str,
+    timeout: Option<Duration>,
 ) -> anyhow::Result<()> {
     let joined_args = args.join(" ");
     group_info!("Command line: {} {}", name, &joined_args);
@@ -94,7 +207,12 @@ pub fn run_process(
     if let Some(envs) = envs {
         command.envs(&envs);
     }
-    let status = command.args(args).status().map_err(|e| {
+    command.args(args);
+    if plan_command(&command) {
+        return anyhow::Ok(());
+    }
+    let start = Instant::now();
+    let child = spawn_isolated_child(&mut command, timeout.is_some()).map_err(|e| {
         anyhow::anyhow!(
             "Failed to execute {} {}: {}",
             name,
@@ -102,6 +220,12 @@ pub fn run_process(
             e
         )
     })?;
+    let Some(status) = wait_child_until(child, timeout.map(|timeout| start + timeout)) else {
+        return Err(timeout_error(
+            &format!("{name} {joined_args}"),
+            start.elapsed(),
+        ));
+    };
     if !status.success() {
         return return_process_error(error_msg, status, None);
     }
@@ -131,20 +255,25 @@ pub fn run_process_for_workspace<'a>(
         .for_each(|ex| cmd_args.extend(["--exclude", ex]));
     cmd_args.extend(binary_args);
     group_info!("Command line: cargo {}", cmd_args.join(" "));
+    let start = Instant::now();
     // process
-    let mut child = Command::new(name)
+    let mut command = Command::new(name);
+    command
         .args(&cmd_args)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| {
-            anyhow::anyhow!(format!(
-                "Failed to start {} {}: {}",
-                name,
-                cmd_args.first().unwrap(),
-                e
-            ))
-        })?;
+        .stderr(Stdio::piped());
+    apply_clean_env(&mut command);
+    if plan_command(&command) {
+        return anyhow::Ok(());
+    }
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(format!(
+            "Failed to start {} {}: {}",
+            name,
+            cmd_args.first().unwrap(),
+            e
+        ))
+    })?;
 
     // handle stdout and stderr in dedicated threads using a MPSC channel for synchronization
     let (tx, rx) = mpsc::channel();
@@ -175,8 +304,19 @@ pub fn run_process_for_workspace<'a>(
     let mut ignore_error = false;
     let mut close_group = false;
     let mut signal = None;
-    for (line, _is_stderr) in rx.iter() {
+    let mut test_output = String::new();
+    let github = is_github_actions();
+    let mut failure = FailureScan::default();
+    let deadline = get_process_timeout().map(|timeout| start + timeout);
+    while let Some((line, _is_stderr)) = receive_line(&rx, deadline) {
         let mut skip_line = false;
+        if is_report_enabled() {
+            test_output.push_str(&line);
+            test_output.push('\n');
+        }
+        if github {
+            failure.scan(&line);
+        }
 
         if let Some(rx) = &group_rx {
             let cleaned_line = standardize_slashes(&remove_ansi_codes(&line));
@@ -209,20 +349,63 @@ pub fn run_process_for_workspace<'a>(
         }
     }
 
-    let status = child
-        .wait()
-        .expect("Should be able to wait for the process to finish.");
+    let Some(status) = wait_child_until(child, deadline) else {
+        record_member(
+            "workspace",
+            &format!("{name} {}", cmd_args.join(" ")),
+            false,
+            start.elapsed(),
+        );
+        return Err(timeout_error("workspace", start.elapsed()));
+    };
+    if is_report_enabled() {
+        record_test_output(None, &test_output);
+    }
 
+    record_member(
+        "workspace",
+        &format!("{name} {}", cmd_args.join(" ")),
+        status.success() || ignore_error,
+        start.elapsed(),
+    );
     if status.success() || ignore_error {
         if close_group {
             endgroup!();
         }
         anyhow::Ok(())
     } else {
+        if github {
+            println!(
+                "{}",
+                format_error_annotation(
+                    failure.package.as_deref().unwrap_or("workspace"),
+                    failure.location.as_ref(),
+                    error_msg
+                )
+            );
+        }
         return_process_error(error_msg, status, signal)
     }
 }
 
+/// Output of a process run for a package with `run_process_for_package_captured`.
+#[derive(Debug)]
+pub struct CapturedOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+    /// True if the ignored log has been found in the output, the failure of the process is then
+    /// ignored.
+    pub ignored: bool,
+}
+
+impl CapturedOutput {
+    /// Returns true if the process succeeded or if its failure is ignored.
+    pub fn success(&self) -> bool {
+        self.status.success() || self.ignored
+    }
+}
+
 /// Run a process command for a package
 #[allow(clippy::too_many_arguments)]
 pub fn run_process_for_package(
@@ -239,22 +422,111 @@ pub fn run_process_for_package(
         group_info!("Skip '{}' because it has been excluded!", package);
         return anyhow::Ok(());
     }
+    let github = is_github_actions();
+    let mut failure = FailureScan::default();
+    let Some((status, ignore_error, signal)) =
+        execute_for_package(name, package, args, ignore_log, ignore_msg, |line, _| {
+            if github {
+                failure.scan(line);
+            }
+            println!("{line}")
+        })?
+    else {
+        return anyhow::Ok(());
+    };
+
+    if status.success() || ignore_error {
+        anyhow::Ok(())
+    } else {
+        let xtask_args: Vec<String> = std::env::args().collect();
+        info!(
+            "Rerun only '{}' with: {}",
+            package,
+            get_reproduction_command(&xtask_args, package)
+        );
+        if github {
+            println!(
+                "{}",
+                format_error_annotation(package, failure.location.as_ref(), error_msg)
+            );
+        }
+        return_process_error(error_msg, status, signal)
+    }
+}
+
+/// Run a process command for a package and capture its stdout and stderr instead of streaming
+/// them. The lines of the ignored log are left out of the captured output like they are from the
+/// streamed one.
+/// Returns None if the package is excluded or if the command is only planned.
+#[allow(clippy::too_many_arguments)]
+pub fn run_process_for_package_captured(
+    name: &str,
+    package: &String,
+    args: &[&str],
+    excluded: &[String],
+    only: &[String],
+    ignore_log: Option<&str>,
+    ignore_msg: Option<&str>,
+) -> anyhow::Result<Option<CapturedOutput>> {
+    if excluded.contains(package) || (!only.is_empty() && !only.contains(package)) {
+        group_info!("Skip '{}' because it has been excluded!", package);
+        return anyhow::Ok(None);
+    }
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let result = execute_for_package(
+        name,
+        package,
+        args,
+        ignore_log,
+        ignore_msg,
+        |line, is_stderr| {
+            let output = if is_stderr { &mut stderr } else { &mut stdout };
+            output.push_str(line);
+            output.push('\n');
+        },
+    )?;
+    anyhow::Ok(result.map(|(status, ignored, _)| CapturedOutput {
+        status,
+        stdout,
+        stderr,
+        ignored,
+    }))
+}
+
+/// Execute the process command for a package and pass the lines of its output which are not
+/// skipped by the ignored log to `on_line` with true for the stderr lines.
+/// Returns the exit status, whether the failure is ignored and the exit signal, or None if the
+/// command is only planned.
+fn execute_for_package(
+    name: &str,
+    package: &str,
+    args: &[&str],
+    ignore_log: Option<&str>,
+    ignore_msg: Option<&str>,
+    mut on_line: impl FnMut(&str, bool),
+) -> anyhow::Result<Option<(ExitStatus, bool, Option<ExitSignal>)>> {
     let joined_args = args.join(" ");
     group_info!("Command line: cargo {}", &joined_args);
+    let start = Instant::now();
 
-    let mut child = Command::new(name)
+    let mut command = Command::new(name);
+    command
         .args(args)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| {
-            anyhow::anyhow!(format!(
-                "Failed to start {} {}: {}",
-                name,
-                args.first().unwrap(),
-                e
-            ))
-        })?;
+        .stderr(Stdio::piped());
+    apply_clean_env(&mut command);
+    if plan_command(&command) {
+        return anyhow::Ok(None);
+    }
+    let mut child = spawn_child(&mut command).map_err(|e| {
+        anyhow::anyhow!(format!(
+            "Failed to start {} {}: {}",
+            name,
+            args.first().unwrap(),
+            e
+        ))
+    })?;
 
     // handle stdout and stderr in dedicated threads using a MPSC channel for synchronization
     let (tx, rx) = mpsc::channel();
@@ -285,7 +557,13 @@ pub fn run_process_for_package(
     let mut ignore_error = false;
     let mut skip_line = false;
     let mut signal = None;
-    for (line, is_stderr) in rx.iter() {
+    let mut test_output = String::new();
+    let deadline = get_process_timeout().map(|timeout| start + timeout);
+    while let Some((line, is_stderr)) = receive_line(&rx, deadline) {
+        if is_report_enabled() {
+            test_output.push_str(&line);
+            test_output.push('\n');
+        }
         if let Some(log) = ignore_log {
             if !is_stderr {
                 // skip the lines until a non stderr line is encountered
@@ -304,19 +582,373 @@ pub fn run_process_for_package(
         }
 
         if !skip_line {
-            println!("{line}");
+            on_line(&line, is_stderr);
+        }
+    }
+
+    let Some(status) = wait_child_until(child, deadline) else {
+        record_member(
+            package,
+            &format!("{name} {joined_args}"),
+            false,
+            start.elapsed(),
+        );
+        return Err(timeout_error(package, start.elapsed()));
+    };
+    if is_report_enabled() {
+        record_test_output(Some(package), &test_output);
+    }
+    record_member(
+        package,
+        &format!("{name} {joined_args}"),
+        status.success() || ignore_error,
+        start.elapsed(),
+    );
+    anyhow::Ok(Some((status, ignore_error, signal)))
+}
+
+/// Returns the xtask command line which runs the passed xtask arguments for the passed package
+/// only, the target, exclude and only arguments are replaced.
+fn get_reproduction_command(xtask_args: &[String], package: &str) -> String {
+    let global_options = get_global_value_options();
+    const TARGET_OPTIONS: [&str; 6] = ["-t", "--target", "-x", "--exclude", "-n", "--only"];
+    let mut command = vec!["cargo".to_string(), "xtask".to_string()];
+    let mut args = xtask_args.iter().skip(1);
+    let mut command_found = false;
+    while let Some(arg) = args.next() {
+        if TARGET_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+            continue;
+        }
+        let is_target_option = TARGET_OPTIONS.iter().any(|option| {
+            if option.starts_with("--") {
+                arg.starts_with(&format!("{option}="))
+            } else {
+                !arg.starts_with("--") && arg.starts_with(option)
+            }
+        });
+        if command_found && is_target_option {
+            continue;
+        }
+        command.push(quote_arg(arg));
+        if command_found {
+            continue;
+        }
+        if global_options.iter().any(|option| option == arg) {
+            if let Some(value) = args.next() {
+                command.push(quote_arg(value));
+            }
+        } else if !arg.starts_with('-') {
+            command_found = true;
+            command.extend([
+                "--target".to_string(),
+                "all-packages".to_string(),
+                "--only".to_string(),
+                quote_arg(package),
+            ]);
+        }
+    }
+    command.join(" ")
+}
+
+/// Returns the short and long names of the global options taking a value, they precede the command.
+fn get_global_value_options() -> Vec<String> {
+    #[derive(clap::Subcommand)]
+    enum NoCommand {}
+    <crate::XtaskArgs<NoCommand> as clap::CommandFactory>::command()
+        .get_arguments()
+        .filter(|arg| arg.get_action().takes_values())
+        .flat_map(|arg| {
+            let short = arg.get_short().map(|short| format!("-{short}"));
+            let long = arg.get_long().map(|long| format!("--{long}"));
+            short.into_iter().chain(long)
+        })
+        .collect()
+}
+
+/// Quote the passed argument for a POSIX shell when it contains special characters.
+pub(crate) fn quote_arg(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=+,./:@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Split the passed string into arguments like a POSIX shell does, without any expansion. Words
+/// are separated by whitespace, single quotes preserve their content, double quotes preserve their
+/// content except for escaped double quotes and backslashes, and a backslash outside of quotes
+/// escapes the next character.
+pub fn split_args(s: &str) -> anyhow::Result<Vec<String>> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(anyhow::anyhow!("Unterminated single quote in: {s}")),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => {
+                                return Err(anyhow::anyhow!("Unterminated double quote in: {s}"))
+                            }
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(anyhow::anyhow!("Unterminated double quote in: {s}")),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    current.get_or_insert_with(String::new).push(c);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
         }
     }
+    args.extend(current);
+    Ok(args)
+}
+
+/// Set the maximum duration of the processes run for the workspace or for a package, their
+/// process tree is terminated once it is exceeded. None disables the timeout.
+pub(crate) fn set_process_timeout(timeout: Option<Duration>) {
+    if let Ok(mut process_timeout) = PROCESS_TIMEOUT.lock() {
+        *process_timeout = timeout;
+    }
+}
+
+fn get_process_timeout() -> Option<Duration> {
+    PROCESS_TIMEOUT.lock().ok().and_then(|timeout| *timeout)
+}
+
+/// Clear the environment of the processes run for the workspace or for a package, only the
+/// allowlisted variables, the passed ones and the variables set by xtask are passed through.
+/// None inherits the whole environment.
+pub(crate) fn set_clean_env(passthrough: Option<Vec<String>>) {
+    if let Ok(mut clean_env) = CLEAN_ENV.lock() {
+        *clean_env = passthrough;
+    }
+}
+
+/// Clear the environment of the passed command if a clean environment is set with `set_clean_env`.
+pub(crate) fn apply_clean_env(command: &mut Command) {
+    let Some(passthrough) = CLEAN_ENV
+        .lock()
+        .ok()
+        .and_then(|clean_env| clean_env.clone())
+    else {
+        return;
+    };
+    let current: BTreeMap<String, String> = std::env::vars().collect();
+    command
+        .env_clear()
+        .envs(get_clean_envs(&passthrough, &get_initial_env(), &current));
+}
+
+/// Returns the variables of a clean environment: the allowlisted and passed through variables of
+/// the current environment, and the ones set or changed by xtask compared to the initial
+/// environment. The flags appended by xtask to inherited flags, like the `--cfg` flags, are passed
+/// without the inherited ones.
+pub(crate) fn get_clean_envs(
+    passthrough: &[String],
+    initial: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let is_passed =
+        |name: &str| CLEAN_ENV_ALLOWLIST.contains(&name) || passthrough.iter().any(|p| p == name);
+    let flags_vars: Vec<&str> = [FlagsVar::Rustflags, FlagsVar::Rustdocflags]
+        .into_iter()
+        .flat_map(|var| [var.name(), var.encoded_name()])
+        .chain([BUILD_RUSTFLAGS_VAR])
+        .collect();
+    current
+        .iter()
+        .filter_map(|(name, value)| {
+            let inherited = initial.get(name);
+            if is_passed(name) {
+                return Some((name.clone(), value.clone()));
+            }
+            match inherited {
+                None => Some((name.clone(), value.clone())),
+                Some(inherited) if inherited == value => None,
+                Some(inherited) if flags_vars.contains(&name.as_str()) => {
+                    let appended = value
+                        .strip_prefix(inherited.as_str())
+                        .unwrap_or(value)
+                        .trim_start_matches([' ', '\x1f']);
+                    (!appended.is_empty()).then(|| (name.clone(), appended.to_string()))
+                }
+                Some(_) => Some((name.clone(), value.clone())),
+            }
+        })
+        .collect()
+}
+
+fn timeout_error(name: &str, elapsed: Duration) -> anyhow::Error {
+    // a sub-second timeout would be reported as 0s with whole seconds
+    anyhow::anyhow!("'{name}' timed out after {elapsed:.3?} and has been terminated.")
+}
 
+/// Returns the next output line of a child, or None once the child closed its output or the
+/// deadline is exceeded.
+fn receive_line(
+    rx: &Receiver<(String, bool)>,
+    deadline: Option<Instant>,
+) -> Option<(String, bool)> {
+    match deadline {
+        None => rx.recv().ok(),
+        Some(deadline) => rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok(),
+    }
+}
+
+/// Spawn the passed command and register it as a running child so that it can be terminated with
+/// `kill_running_children`. When a deadline or a process timeout is set the child gets its own
+/// process group so that its whole process tree can be terminated.
+pub(crate) fn spawn_child(command: &mut Command) -> std::io::Result<Child> {
+    spawn_isolated_child(command, false)
+}
+
+/// Spawn the passed command like `spawn_child`, the child also gets its own process group if
+/// `isolated` is true.
+fn spawn_isolated_child(command: &mut Command, isolated: bool) -> std::io::Result<Child> {
+    if command.get_program() == "cargo" {
+        acquire_target_lock();
+    }
+    debug!("Executing: {}", describe_command(command));
+    let isolated = isolated || is_deadline_enabled() || get_process_timeout().is_some();
+    #[cfg(unix)]
+    if isolated {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let child = command.spawn()?;
+    if let Ok(mut children) = RUNNING_CHILDREN.lock() {
+        children.push((child.id(), isolated));
+    }
+    Ok(child)
+}
+
+/// Wait for the passed child spawned with `spawn_child` and unregister it.
+pub(crate) fn wait_child(mut child: Child) -> ExitStatus {
     let status = child
         .wait()
         .expect("Should be able to wait for the process to finish.");
+    unregister_child(child.id());
+    status
+}
 
-    if status.success() || ignore_error {
-        anyhow::Ok(())
-    } else {
-        return_process_error(error_msg, status, signal)
+/// Wait for the passed child spawned with `spawn_child` and unregister it, the streams of the
+/// child configured with `Stdio::piped` are captured in the returned output.
+pub(crate) fn wait_child_output(child: Child) -> std::io::Result<Output> {
+    let id = child.id();
+    let output = child.wait_with_output();
+    unregister_child(id);
+    output
+}
+
+/// Run the passed command and print its stdout line by line as it comes, the stderr is inherited.
+/// Returns the exit status of the command and the printed stdout.
+pub(crate) fn run_command_streamed(command: &mut Command) -> std::io::Result<(ExitStatus, String)> {
+    let mut child = spawn_child(command.stdout(Stdio::piped()))?;
+    let mut output = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            println!("{line}");
+            output.push_str(&line);
+            output.push('\n');
+        }
     }
+    Ok((wait_child(child), output))
+}
+
+fn unregister_child(id: u32) {
+    if let Ok(mut children) = RUNNING_CHILDREN.lock() {
+        children.retain(|(child, _)| *child != id);
+    }
+}
+
+/// Wait for the passed child spawned with `spawn_child` until the passed deadline, if any, and
+/// unregister it. Returns None if the deadline is exceeded, the process tree of the child is
+/// then terminated.
+fn wait_child_until(mut child: Child, deadline: Option<Instant>) -> Option<ExitStatus> {
+    let Some(deadline) = deadline else {
+        return Some(wait_child(child));
+    };
+    loop {
+        match child.try_wait() {
+            Ok(None) if Instant::now() >= deadline => break,
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            _ => return Some(wait_child(child)),
+        }
+    }
+    let isolated = RUNNING_CHILDREN.lock().is_ok_and(|children| {
+        children
+            .iter()
+            .any(|(id, isolated)| *id == child.id() && *isolated)
+    });
+    kill_process_tree(child.id(), isolated);
+    wait_child(child);
+    None
+}
+
+/// Terminate the process trees of the running children.
+pub(crate) fn kill_running_children() {
+    let children = match RUNNING_CHILDREN.lock() {
+        Ok(mut children) => std::mem::take(&mut *children),
+        Err(_) => return,
+    };
+    for (id, isolated) in children {
+        kill_process_tree(id, isolated);
+    }
+}
+
+fn kill_process_tree(id: u32, isolated: bool) {
+    warn!("Terminating child process {id}...");
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("taskkill");
+        command.args(["/T", "/F", "/PID", &id.to_string()]);
+        command
+    } else {
+        // a negative pid targets the whole process group
+        let target = if isolated {
+            format!("-{id}")
+        } else {
+            id.to_string()
+        };
+        let mut command = Command::new("kill");
+        command.args(["-KILL", "--", &target]);
+        command
+    };
+    let _ = command.stdout(Stdio::null()).stderr(Stdio::null()).status();
 }
 
 /// Return a random port between 3000 and 9999
@@ -325,7 +957,7 @@ pub fn random_port() -> u16 {
     rng.random_range(3000..=9999)
 }
 
-fn remove_ansi_codes(s: &str) -> String {
+pub(crate) fn remove_ansi_codes(s: &str) -> String {
     let re = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
     re.replace_all(s, "").to_string()
 }
@@ -351,6 +983,272 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    #[rstest]
+    #[case::panic(
+        &["test tests::test_b ... FAILED", "thread 'tests::test_b' panicked at crates/core/src/lib.rs:10:5:", "error: test failed, to rerun pass `-p core --lib`"],
+        Some("core"),
+        Some(("crates/core/src/lib.rs", 10, 5))
+    )]
+    #[case::legacy_panic(&["thread 'main' panicked at 'boom', src\\main.rs:3:9"], None, Some(("src/main.rs", 3, 9)))]
+    #[case::compilation_error(
+        &["warning: unused variable: `x`", "  --> crates/core/src/lib.rs:2:9", "\x1b[31merror[E0308]\x1b[0m: mismatched types", "  --> crates/core/src/lib.rs:4:5", "error: could not compile `core` (lib) due to 1 previous error"],
+        Some("core"),
+        Some(("crates/core/src/lib.rs", 4, 5))
+    )]
+    #[case::warning_only(&["warning: unused variable: `x`", "  --> src/lib.rs:2:9"], None, None)]
+    fn test_failure_scan(
+        #[case] lines: &[&str],
+        #[case] package: Option<&str>,
+        #[case] location: Option<(&str, u32, u32)>,
+    ) {
+        let mut failure = FailureScan::default();
+        lines.iter().for_each(|line| failure.scan(line));
+        assert_eq!(failure.package.as_deref(), package);
+        assert_eq!(
+            failure.location,
+            location.map(|(file, line, col)| (file.to_string(), line, col))
+        );
+    }
+
+    #[rstest]
+    #[case::location(
+        Some(("crates/core/src/lib.rs", 10, 5)),
+        "Failed to execute unit test for 'core'",
+        "::error file=crates/core/src/lib.rs,line=10,col=5,title=core failed::Failed to execute unit test for 'core'"
+    )]
+    #[case::escaped(
+        None,
+        "100% failed\nsee: logs",
+        "::error title=core failed::100%25 failed%0Asee: logs"
+    )]
+    fn test_format_error_annotation(
+        #[case] location: Option<(&str, u32, u32)>,
+        #[case] message: &str,
+        #[case] expected: &str,
+    ) {
+        let location = location.map(|(file, line, col)| (file.to_string(), line, col));
+        assert_eq!(
+            format_error_annotation("core", location.as_ref(), message),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::empty("", vec![])]
+    #[case::whitespace(" --release \t -- my_test ", vec!["--release", "--", "my_test"])]
+    #[case::single_quotes("-- 'my test' --exact", vec!["--", "my test", "--exact"])]
+    #[case::double_quotes(r#"--features "a b" "say \"hi\"""#, vec!["--features", "a b", "say \"hi\""])]
+    #[case::empty_quotes("'' x", vec!["", "x"])]
+    #[case::escaped_space(r"my\ test", vec!["my test"])]
+    #[case::adjacent_quotes(r#"--cfg='a'"b""#, vec!["--cfg=ab"])]
+    fn test_split_args(#[case] s: &str, #[case] expected: Vec<&str>) {
+        assert_eq!(split_args(s).expect("args should be valid"), expected);
+    }
+
+    #[rstest]
+    #[case::single_quote("'unterminated")]
+    #[case::double_quote("\"unterminated")]
+    fn test_split_args_unterminated(#[case] s: &str) {
+        assert!(split_args(s).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_child_until() {
+        let child = spawn_child(&mut Command::new("true")).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        assert!(wait_child_until(child, Some(deadline)).is_some_and(|s| s.success()));
+
+        let start = Instant::now();
+        let child = spawn_child(Command::new("sleep").arg("30")).unwrap();
+        let deadline = start + Duration::from_millis(200);
+        assert!(wait_child_until(child, Some(deadline)).is_none());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_child_output() {
+        let child =
+            spawn_child(Command::new("echo").arg("captured").stdout(Stdio::piped())).unwrap();
+        let id = child.id();
+        let output = wait_child_output(child).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "captured\n");
+        assert!(!RUNNING_CHILDREN
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(child, _)| *child == id));
+    }
+
+    #[test]
+    fn test_get_clean_envs() {
+        let env = |vars: &[(&str, &str)]| -> BTreeMap<String, String> {
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        let initial = env(&[
+            ("PATH", "/usr/bin"),
+            ("RUSTFLAGS", "-Ctarget-cpu=native"),
+            ("RUSTDOCFLAGS", "-Dwarnings"),
+            ("DATABASE_URL", "postgres://localhost"),
+            ("EDITOR", "vim"),
+        ]);
+        let current = env(&[
+            ("PATH", "/usr/bin"),
+            ("RUSTFLAGS", "-Ctarget-cpu=native --cfg xtask_leak_check"),
+            ("RUSTDOCFLAGS", "-Dwarnings"),
+            ("DATABASE_URL", "postgres://localhost"),
+            ("EDITOR", "vim"),
+            ("XTASK_LEAK_CHECK_DIR", "/tmp/leaks"),
+        ]);
+        assert_eq!(
+            get_clean_envs(&["DATABASE_URL".to_string()], &initial, &current),
+            env(&[
+                ("DATABASE_URL", "postgres://localhost"),
+                ("PATH", "/usr/bin"),
+                ("RUSTFLAGS", "--cfg xtask_leak_check"),
+                ("XTASK_LEAK_CHECK_DIR", "/tmp/leaks"),
+            ])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_process_timeout() {
+        let marker = std::env::temp_dir().join(format!("xtask-timeout-{}", std::process::id()));
+        let script = format!("(sleep 1; touch '{}') & wait", marker.display());
+        let start = Instant::now();
+        let error = run_process(
+            "sh",
+            &["-c", &script],
+            None,
+            None,
+            "Failed to run the script",
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("'sh -c (sleep 1;"), "{error}");
+        assert!(error.contains("ms and has been terminated"), "{error}");
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // the grandchild is terminated with the shell
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists());
+
+        let timeout = Some(Duration::from_secs(30));
+        assert!(run_process("true", &[], None, None, "Failed", timeout).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_process_for_package_captured() {
+        let package = "my-crate".to_string();
+        // the stdout and stderr lines are received in an undefined order so the skipped stderr
+        // lines are not followed by stdout lines
+        let script = "echo 'warning: unused' >&2; echo 'error: no library targets found' >&2; echo '  in package' >&2; exit 101";
+        let output = run_process_for_package_captured(
+            "sh",
+            &package,
+            &["-c", script],
+            &[],
+            &[],
+            Some("no library targets found"),
+            Some("No library in the crate."),
+        )
+        .unwrap()
+        .expect("the command should be executed");
+        assert_eq!(output.status.code(), Some(101));
+        assert_eq!(output.stdout, "");
+        assert_eq!(output.stderr, "warning: unused\n");
+        assert!(output.ignored);
+        assert!(output.success());
+
+        let script =
+            "echo compiling; echo done; echo 'error: no library targets found' >&2; exit 101";
+        let output =
+            run_process_for_package_captured("sh", &package, &["-c", script], &[], &[], None, None)
+                .unwrap()
+                .expect("the command should be executed");
+        assert_eq!(output.stdout, "compiling\ndone\n");
+        assert_eq!(output.stderr, "error: no library targets found\n");
+        assert!(!output.ignored);
+        assert!(!output.success());
+
+        let excluded = vec![package.clone()];
+        assert!(run_process_for_package_captured(
+            "sh",
+            &package,
+            &["-c", script],
+            &excluded,
+            &[],
+            None,
+            None
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn test_get_global_value_options() {
+        let options = get_global_value_options();
+        for option in [
+            "-e",
+            "--environment",
+            "--deadline",
+            "--linker",
+            "--emit-script",
+        ] {
+            assert!(options.contains(&option.to_string()), "{option}");
+        }
+        for flag in ["--scratch", "-q", "--verbose", "--help"] {
+            assert!(!options.contains(&flag.to_string()), "{flag}");
+        }
+    }
+
+    #[rstest]
+    #[case::target_workspace(
+        "xtask test --features x unit",
+        "cargo xtask test --target all-packages --only my-crate --features x unit"
+    )]
+    #[case::target_options(
+        "xtask -e test --deadline 1h test -t crates -x other,more --only=a,b --release unit",
+        "cargo xtask -e test --deadline 1h test --target all-packages --only my-crate --release unit"
+    )]
+    #[case::workspace_root(
+        "xtask --workspace-root ../other test unit",
+        "cargo xtask --workspace-root ../other test --target all-packages --only my-crate unit"
+    )]
+    #[case::members_seed(
+        "xtask --members-seed 42 test --target crates unit",
+        "cargo xtask --members-seed 42 test --target all-packages --only my-crate unit"
+    )]
+    #[case::short_options_without_space(
+        "xtask check -tcrates -nother lint",
+        "cargo xtask check --target all-packages --only my-crate lint"
+    )]
+    #[case::quoted(
+        "xtask test --test \"my test\" unit",
+        "cargo xtask test --target all-packages --only my-crate --test 'my test' unit"
+    )]
+    fn test_get_reproduction_command(#[case] command_line: &str, #[case] expected: &str) {
+        // split on spaces outside of double quotes
+        let args: Vec<String> = command_line
+            .split('"')
+            .enumerate()
+            .flat_map(|(i, part)| {
+                if i % 2 == 1 {
+                    vec![part.to_string()]
+                } else {
+                    part.split_whitespace().map(str::to_string).collect()
+                }
+            })
+            .collect();
+        assert_eq!(get_reproduction_command(&args, "my-crate"), expected);
+    }
+
     #[rstest]
     fn test_random_port_in_range() {
         for _ in 0..10000 {
@@ -1,12 +1,106 @@
-use std::io::{self, Write};
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
+/// Environment variable answering yes to the prompts, like the `--yes` global option.
+pub const ASSUME_YES_ENV_VAR: &str = "XTASK_ASSUME_YES";
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Answer yes to all the prompts without asking, set by the `--yes` global option.
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+fn is_assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+        || std::env::var(ASSUME_YES_ENV_VAR)
+            .is_ok_and(|v| !v.is_empty() && v != "0" && v != "false")
+}
+
+/// Ask for a confirmation once, an empty answer is a no. See `ask_once_with_default`.
 pub fn ask_once(prompt: &str) -> bool {
-    print!("{prompt}\nDo you want to proceed? (yes/no): ");
+    ask_once_with_default(prompt, false)
+}
+
+/// Ask for a confirmation once and return the passed default on an empty answer, an
+/// unrecognized answer is a no. Returns true without asking when yes is assumed and false when
+/// stdin is not a terminal as nobody can answer.
+pub fn ask_once_with_default(prompt: &str, default: bool) -> bool {
+    if let Some(answer) = get_unattended_answer(is_assume_yes(), io::stdin().is_terminal()) {
+        if answer {
+            info!("{prompt}\nProceeding as yes is assumed.");
+        } else {
+            warn!(
+                "{prompt}\nAborting because stdin is not a terminal, pass --yes or set {ASSUME_YES_ENV_VAR} to proceed."
+            );
+        }
+        return answer;
+    }
+    let choices = if default { "Y/n" } else { "y/N" };
+    print!("{prompt}\nDo you want to proceed? ({choices}): ");
     io::stdout().flush().expect("stdout should be flushed");
 
     let mut input = String::new();
     io::stdin()
         .read_line(&mut input)
         .expect("should be able to read stdin line");
-    input.trim().to_lowercase() == "yes" || input.trim().to_lowercase() == "y"
+    if input.trim().is_empty() {
+        default
+    } else {
+        parse_answer(&input).unwrap_or(false)
+    }
+}
+
+/// Returns true for an affirmative answer, false for a negative one and None if the answer is
+/// not recognized.
+fn parse_answer(input: &str) -> Option<bool> {
+    match input.trim().to_lowercase().as_str() {
+        "yes" | "y" => Some(true),
+        "no" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the answer given without prompting or None if the user must be asked.
+fn get_unattended_answer(assume_yes: bool, is_terminal: bool) -> Option<bool> {
+    if assume_yes {
+        Some(true)
+    } else if !is_terminal {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::yes("yes\n", Some(true))]
+    #[case::y("y", Some(true))]
+    #[case::uppercase("YES", Some(true))]
+    #[case::no("no\n", Some(false))]
+    #[case::n(" N ", Some(false))]
+    #[case::unknown("maybe", None)]
+    #[case::empty("\n", None)]
+    fn test_parse_answer(#[case] input: &str, #[case] expected: Option<bool>) {
+        assert_eq!(parse_answer(input), expected);
+    }
+
+    #[rstest]
+    #[case::assume_yes_terminal(true, true, Some(true))]
+    #[case::assume_yes_no_terminal(true, false, Some(true))]
+    #[case::no_terminal(false, false, Some(false))]
+    #[case::interactive(false, true, None)]
+    fn test_get_unattended_answer(
+        #[case] assume_yes: bool,
+        #[case] is_terminal: bool,
+        #[case] expected: Option<bool>,
+    ) {
+        assert_eq!(get_unattended_answer(assume_yes, is_terminal), expected);
+    }
 }
@@ -11,6 +11,7 @@ pub fn rustup_add_target(target: &str) -> anyhow::Result<()> {
         None,
         None,
         &format!("Failed to add target {target}"),
+        None,
     )?;
     endgroup!();
     Ok(())
@@ -25,19 +26,77 @@ pub fn rustup_add_component(component: &str) -> anyhow::Result<()> {
         None,
         None,
         &format!("Failed to add component {component}"),
+        None,
     )?;
     endgroup!();
     Ok(())
 }
 
-// Returns the output of the rustup command to get the installed targets
-pub fn rustup_get_installed_targets() -> String {
-    let output = Command::new("rustup")
-        .args(["target", "list", "--installed"])
+/// Add a Rust component to the passed toolchain
+pub fn rustup_add_toolchain_component(component: &str, toolchain: &str) -> anyhow::Result<()> {
+    group!("Rustup: add component {} to {}", component, toolchain);
+    run_process(
+        "rustup",
+        &["component", "add", component, "--toolchain", toolchain],
+        None,
+        None,
+        &format!("Failed to add component {component} to {toolchain}"),
+        None,
+    )?;
+    endgroup!();
+    Ok(())
+}
+
+/// Returns the targets known by rustc, the list is empty if rustc cannot be executed.
+pub fn rustc_get_target_list() -> Vec<String> {
+    Command::new("rustc")
+        .args(["--print", "target-list"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the output of the rustup command listing the installed targets, fails if rustup is not
+/// installed.
+pub fn rustup_get_installed_targets() -> anyhow::Result<String> {
+    get_installed_targets(None)
+}
+
+/// Returns the output of the rustup command listing the targets installed for the passed
+/// toolchain, fails if rustup is not installed.
+pub fn rustup_get_toolchain_installed_targets(toolchain: &str) -> anyhow::Result<String> {
+    get_installed_targets(Some(toolchain))
+}
+
+fn get_installed_targets(toolchain: Option<&str>) -> anyhow::Result<String> {
+    let mut command = Command::new("rustup");
+    command.args(["target", "list", "--installed"]);
+    if let Some(toolchain) = toolchain {
+        command.args(["--toolchain", toolchain]);
+    }
+    let output = command
         .stdout(Stdio::piped())
         .output()
-        .expect("Rustup command should execute successfully");
-    String::from_utf8(output.stdout).expect("Output should be valid UTF-8")
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!("rustup not found"),
+            _ => anyhow::anyhow!("Failed to execute rustup: {e}"),
+        })?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Cannot list the installed targets: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
 
 /// Returns true if the current toolchain is the nightly
@@ -56,3 +115,86 @@ pub fn is_current_toolchain_nightly() -> bool {
     // assume we are using a stable toolchain if we did not find the nightly compiler
     false
 }
+
+/// Returns true if the passed toolchain, i.e. `nightly-2024-01-01`, is a nightly toolchain
+pub fn is_toolchain_nightly(toolchain: &str) -> bool {
+    Command::new("rustc")
+        .args([&format!("+{toolchain}"), "-V"])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success() && is_nightly_version(&String::from_utf8_lossy(&output.stdout))
+        })
+}
+
+// Returns true if the passed `rustc -V` output is the one of a nightly compiler
+fn is_nightly_version(version: &str) -> bool {
+    version.contains("-nightly")
+}
+
+/// Returns true if a nightly toolchain is installed, whether it is the current toolchain or not
+pub fn is_nightly_toolchain_installed() -> bool {
+    let output = Command::new("rustup")
+        .args(["toolchain", "list"])
+        .output()
+        .expect("Should get the list of installed Rust toolchains");
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    output_str.lines().any(|line| line.starts_with("nightly"))
+}
+
+/// Returns a key identifying the passed toolchain, or else the current one, made of its release
+/// and commit hash, i.e. `1.90.0-nightly-29483883e`, to key the caches of toolchain dependent
+/// builds.
+pub fn get_toolchain_cache_key(toolchain: Option<&str>) -> Option<String> {
+    let mut command = Command::new("rustc");
+    if let Some(toolchain) = toolchain {
+        command.arg(format!("+{toolchain}"));
+    }
+    let output = command.arg("-vV").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_toolchain_cache_key(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_toolchain_cache_key(rustc_version: &str) -> Option<String> {
+    let field = |name: &str| {
+        rustc_version
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .map(str::trim)
+    };
+    let release = field("release:")?;
+    let key = match field("commit-hash:") {
+        Some(hash) if hash != "unknown" => format!("{release}-{}", &hash[..hash.len().min(9)]),
+        _ => release.to_string(),
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::nightly(
+        "rustc 1.90.0-nightly (29483883e 2025-06-01)\nbinary: rustc\ncommit-hash: 29483883eed69d5fb4db01964cdf2af4d86e9cb2\nrelease: 1.90.0-nightly\n",
+        Some("1.90.0-nightly-29483883e")
+    )]
+    #[case::unknown_commit(
+        "rustc 1.80.0\ncommit-hash: unknown\nrelease: 1.80.0\n",
+        Some("1.80.0")
+    )]
+    #[case::invalid("not a rustc output", None)]
+    fn test_parse_toolchain_cache_key(#[case] output: &str, #[case] expected: Option<&str>) {
+        assert_eq!(parse_toolchain_cache_key(output).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case::nightly("rustc 1.77.0-nightly (bf3c6c5be 2024-01-01)\n", true)]
+    #[case::stable("rustc 1.80.0 (051478957 2024-07-21)\n", false)]
+    #[case::beta("rustc 1.81.0-beta.1 (0f5193c8b 2024-07-26)\n", false)]
+    fn test_is_nightly_version(#[case] version: &str, #[case] expected: bool) {
+        assert_eq!(is_nightly_version(version), expected);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use crate::utils::{cleanup::at_exit, snapshot::snapshot_tree};
+
+/// Scratch copy of the workspace used by the `--scratch` global option.
+struct Scratch {
+    original_root: PathBuf,
+    root: PathBuf,
+    /// Copied files relative to the workspace root.
+    files: Vec<PathBuf>,
+}
+
+/// Change made by the command to a file of the scratch workspace.
+#[derive(Debug, PartialEq)]
+enum ScratchChange {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl std::fmt::Display for ScratchChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScratchChange::Created => write!(f, "created"),
+            ScratchChange::Modified => write!(f, "modified"),
+            ScratchChange::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+static SCRATCH: OnceLock<Scratch> = OnceLock::new();
+
+/// Copy the workspace to a temporary directory and make it the current directory. When the
+/// xtask process exits the changes made to the copy are reported and the copy is removed.
+pub(crate) fn enter_scratch_workspace() -> anyhow::Result<PathBuf> {
+    let original_root = std::env::current_dir()?;
+    let root = std::env::temp_dir().join(format!("xtask-scratch-{}", std::process::id()));
+    if root.exists() {
+        std::fs::remove_dir_all(&root)?;
+    }
+    let files = list_workspace_files(&original_root);
+    copy_files(&files, &original_root, &root)?;
+    // relative paths, including the target directory, now resolve against the scratch root
+    std::env::set_current_dir(&root)?;
+    let _ = SCRATCH.set(Scratch {
+        original_root,
+        root: root.clone(),
+        files,
+    });
+    at_exit(exit_scratch_workspace);
+    Ok(root)
+}
+
+extern "C" fn exit_scratch_workspace() {
+    let Some(scratch) = SCRATCH.get() else {
+        return;
+    };
+    let changes = find_scratch_changes(&scratch.original_root, &scratch.root, &scratch.files);
+    if changes.is_empty() {
+        info!("No file of the scratch workspace has been changed.");
+    } else {
+        info!("Files changed in the scratch workspace, the workspace is left untouched:");
+        for (path, change) in changes {
+            info!("  {change}: {}", path.display());
+        }
+    }
+    let _ = std::env::set_current_dir(&scratch.original_root);
+    if let Err(e) = std::fs::remove_dir_all(&scratch.root) {
+        warn!(
+            "Cannot remove the scratch workspace {} ({e}).",
+            scratch.root.display()
+        );
+    }
+}
+
+/// Returns the files of the workspace relative to its root without the files ignored by git.
+/// Outside of a git repository all the files are returned. The target directory is never
+/// returned, the lock file always is.
+fn list_workspace_files(root: &Path) -> Vec<PathBuf> {
+    let output = Command::new("git")
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .current_dir(root)
+        .output();
+    let mut files: Vec<PathBuf> = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|file| !file.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        _ => snapshot_tree(root, &[root.join("target")])
+            .into_keys()
+            .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .collect(),
+    };
+    // tracked files can be deleted from the working tree
+    files.retain(|file| !file.starts_with("target") && root.join(file).is_file());
+    // keep the resolved dependencies even when the lock file is ignored
+    let lock_file = PathBuf::from("Cargo.lock");
+    if root.join(&lock_file).is_file() && !files.contains(&lock_file) {
+        files.push(lock_file);
+    }
+    files
+}
+
+fn copy_files(files: &[PathBuf], from: &Path, to: &Path) -> anyhow::Result<()> {
+    for file in files {
+        let destination = to.join(file);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(from.join(file), &destination).map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot copy {} to the scratch workspace ({e})",
+                file.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the files created, modified or deleted in the scratch copy compared to the original
+/// files, the target directory is ignored.
+fn find_scratch_changes(
+    original_root: &Path,
+    root: &Path,
+    files: &[PathBuf],
+) -> Vec<(PathBuf, ScratchChange)> {
+    let mut changes: Vec<(PathBuf, ScratchChange)> = snapshot_tree(root, &[root.join("target")])
+        .into_keys()
+        .filter_map(|path| {
+            let file = path.strip_prefix(root).ok()?.to_path_buf();
+            if !files.contains(&file) {
+                return Some((file, ScratchChange::Created));
+            }
+            let original = std::fs::read(original_root.join(&file)).ok();
+            (original != std::fs::read(&path).ok()).then_some((file, ScratchChange::Modified))
+        })
+        .collect();
+    changes.extend(
+        files
+            .iter()
+            .filter(|file| !root.join(file).exists())
+            .map(|file| (file.clone(), ScratchChange::Deleted)),
+    );
+    changes.sort_by(|a, b| a.0.cmp(&b.0));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_files_and_find_scratch_changes() {
+        let dir = std::env::temp_dir().join(format!("xtask-test-scratch-{}", std::process::id()));
+        let (original_root, root) = (dir.join("original"), dir.join("scratch"));
+        for file in ["Cargo.toml", "src/lib.rs", "src/main.rs"] {
+            let path = original_root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).expect("dir should be created");
+            std::fs::write(path, file).expect("file should be written");
+        }
+        let files: Vec<PathBuf> = ["Cargo.toml", "src/lib.rs", "src/main.rs"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        copy_files(&files, &original_root, &root).expect("files should be copied");
+        assert_eq!(find_scratch_changes(&original_root, &root, &files), vec![]);
+
+        std::fs::write(root.join("src/lib.rs"), "fixed").expect("file should be written");
+        std::fs::remove_file(root.join("src/main.rs")).expect("file should be removed");
+        std::fs::write(root.join("generated.rs"), "").expect("file should be written");
+        std::fs::create_dir_all(root.join("target")).expect("dir should be created");
+        std::fs::write(root.join("target/artifact"), "").expect("file should be written");
+        let changes = find_scratch_changes(&original_root, &root, &files);
+        std::fs::remove_dir_all(&dir).expect("dir should be removed");
+        assert_eq!(
+            changes,
+            vec![
+                (PathBuf::from("generated.rs"), ScratchChange::Created),
+                (PathBuf::from("src/lib.rs"), ScratchChange::Modified),
+                (PathBuf::from("src/main.rs"), ScratchChange::Deleted),
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::utils::{cleanup::at_exit, process::quote_arg};
+
+/// Script of the commands planned by the `--emit-script` global option, or by the `--list` option
+/// of the test command.
+struct Script {
+    /// None if the planned commands are only listed.
+    path: Option<PathBuf>,
+    /// Environment of the xtask process before it loads the dotenv files.
+    initial_env: BTreeMap<String, String>,
+    commands: Vec<PlannedCommand>,
+}
+
+/// A command planned instead of being executed.
+#[derive(Debug, PartialEq)]
+struct PlannedCommand {
+    dir: PathBuf,
+    /// Variables to set, or to unset when the value is None.
+    envs: BTreeMap<String, Option<String>>,
+    program: String,
+    args: Vec<String>,
+}
+
+static SCRIPT: OnceLock<Mutex<Script>> = OnceLock::new();
+static INITIAL_ENV: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+
+/// Save the environment of the process before xtask changes it so that the script sets the
+/// variables set by xtask, it must be called before loading the dotenv files.
+pub(crate) fn save_initial_env() {
+    let _ = INITIAL_ENV.set(std::env::vars().collect());
+}
+
+/// Returns the environment saved by `save_initial_env`, or the current one if it is not saved.
+pub(crate) fn get_initial_env() -> BTreeMap<String, String> {
+    INITIAL_ENV
+        .get()
+        .cloned()
+        .unwrap_or_else(|| std::env::vars().collect())
+}
+
+/// Plan the commands executed by the process helpers instead of executing them and write them to
+/// the passed path as an executable shell script when the xtask process exits.
+pub(crate) fn enable_script(path: &Path) {
+    let script = Script {
+        // keep writing next to the invocation directory if the current directory changes
+        path: Some(std::path::absolute(path).unwrap_or(path.to_path_buf())),
+        initial_env: INITIAL_ENV.get().cloned().unwrap_or_default(),
+        commands: vec![],
+    };
+    if SCRIPT.set(Mutex::new(script)).is_ok() {
+        at_exit(write_script);
+    }
+}
+
+/// Plan the commands executed by the process helpers instead of executing them, they are only
+/// logged. Does nothing if the script is already enabled.
+pub(crate) fn enable_listing() {
+    let _ = SCRIPT.set(Mutex::new(Script {
+        path: None,
+        initial_env: INITIAL_ENV.get().cloned().unwrap_or_default(),
+        commands: vec![],
+    }));
+}
+
+/// Record the passed command in the script if it is enabled.
+/// Returns true if the command is planned and must not be executed.
+pub(crate) fn plan_command(command: &Command) -> bool {
+    let Some(script) = SCRIPT.get() else {
+        return false;
+    };
+    let mut script = script.lock().expect("script lock should not be poisoned");
+    let current_env: BTreeMap<String, String> = std::env::vars().collect();
+    let planned = to_planned_command(command, diff_env(&script.initial_env, &current_env));
+    info!("Planned: {}", format_command(&planned));
+    script.commands.push(planned);
+    true
+}
+
+/// Returns true if the commands are planned instead of being executed.
+pub(crate) fn is_planning() -> bool {
+    SCRIPT.get().is_some()
+}
+
+/// Returns an error if the commands are planned instead of being executed, for the commands which
+/// need the output of the processes they run to go on.
+pub(crate) fn ensure_not_planned(command: &str) -> anyhow::Result<()> {
+    if is_planning() {
+        return Err(anyhow::anyhow!(
+            "'{command}' needs the output of the commands it runs and cannot be planned."
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the shell line executing the passed command in its directory with the variables it
+/// sets, the inherited environment is omitted.
+pub(crate) fn describe_command(command: &Command) -> String {
+    format_command(&to_planned_command(command, BTreeMap::new()))
+}
+
+// Returns the planned command of the passed command with the passed variables in addition to the
+// ones set by the command
+fn to_planned_command(
+    command: &Command,
+    mut envs: BTreeMap<String, Option<String>>,
+) -> PlannedCommand {
+    for (name, value) in command.get_envs() {
+        envs.insert(
+            name.to_string_lossy().into_owned(),
+            value.map(|v| v.to_string_lossy().into_owned()),
+        );
+    }
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let dir = match command.get_current_dir() {
+        Some(dir) => current_dir.join(dir),
+        None => current_dir,
+    };
+    let to_string = |s: &OsStr| s.to_string_lossy().into_owned();
+    PlannedCommand {
+        dir,
+        envs,
+        program: to_string(command.get_program()),
+        args: command.get_args().map(to_string).collect(),
+    }
+}
+
+extern "C" fn write_script() {
+    let Some(script) = SCRIPT.get() else {
+        return;
+    };
+    let Ok(script) = script.lock() else {
+        return;
+    };
+    let Some(path) = &script.path else {
+        return;
+    };
+    let xtask_args: Vec<String> = std::env::args().collect();
+    let contents = to_script(&xtask_args, &script.commands);
+    match std::fs::write(path, contents) {
+        Ok(()) => {
+            make_executable(path);
+            info!(
+                "{} planned command(s) written to {}",
+                script.commands.len(),
+                path.display()
+            );
+        }
+        Err(e) => error!("Cannot write the script {} ({e}).", path.display()),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755));
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) {}
+
+/// Returns the variables set, changed or removed in the current environment.
+fn diff_env(
+    initial: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> BTreeMap<String, Option<String>> {
+    let mut envs: BTreeMap<String, Option<String>> = current
+        .iter()
+        .filter(|(name, value)| initial.get(*name) != Some(value))
+        .map(|(name, value)| (name.clone(), Some(value.clone())))
+        .collect();
+    envs.extend(
+        initial
+            .keys()
+            .filter(|name| !current.contains_key(*name))
+            .map(|name| (name.clone(), None)),
+    );
+    envs
+}
+
+/// Returns the shell line executing the passed command in its directory with its environment.
+fn format_command(command: &PlannedCommand) -> String {
+    let mut words = vec![
+        "cd".to_string(),
+        quote_arg(&command.dir.to_string_lossy()),
+        "&&".to_string(),
+    ];
+    if !command.envs.is_empty() {
+        words.push("env".to_string());
+        for (name, value) in &command.envs {
+            match value {
+                Some(value) => words.push(quote_arg(&format!("{name}={value}"))),
+                None => words.extend(["-u".to_string(), quote_arg(name)]),
+            }
+        }
+    }
+    words.push(quote_arg(&command.program));
+    words.extend(command.args.iter().map(|arg| quote_arg(arg)));
+    words.join(" ")
+}
+
+fn to_script(xtask_args: &[String], commands: &[PlannedCommand]) -> String {
+    let invocation: Vec<String> = xtask_args.iter().map(|arg| quote_arg(arg)).collect();
+    let mut lines = vec![
+        "#!/bin/sh".to_string(),
+        format!("# Commands planned by: {}", invocation.join(" ")),
+        "set -e".to_string(),
+    ];
+    lines.extend(commands.iter().map(format_command));
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(clap::Subcommand, Clone, Debug)]
+    enum XtaskCommand {
+        Run,
+    }
+
+    #[test]
+    fn test_emit_script_conflicts_with_scratch() {
+        let result = <crate::XtaskArgs<XtaskCommand> as clap::Parser>::try_parse_from([
+            "xtask",
+            "--scratch",
+            "--emit-script",
+            "plan.sh",
+            "run",
+        ]);
+        assert_eq!(
+            result.map(|_| ()).map_err(|e| e.kind()),
+            Err(clap::error::ErrorKind::ArgumentConflict)
+        );
+    }
+
+    fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_env() {
+        let initial = env(&[
+            ("HOME", "/root"),
+            ("RUSTFLAGS", "-Dwarnings"),
+            ("TERM", "xterm"),
+        ]);
+        let current = env(&[
+            ("HOME", "/root"),
+            ("RUSTFLAGS", "-Dwarnings -Cinstrument-coverage"),
+            ("DATABASE_URL", "postgres://localhost"),
+        ]);
+        assert_eq!(
+            diff_env(&initial, &current),
+            BTreeMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    Some("postgres://localhost".to_string())
+                ),
+                (
+                    "RUSTFLAGS".to_string(),
+                    Some("-Dwarnings -Cinstrument-coverage".to_string())
+                ),
+                ("TERM".to_string(), None),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_describe_command() {
+        let mut command = Command::new("cargo");
+        command
+            .args(["test", "--", "--no-capture"])
+            .current_dir("/repo")
+            .env("RUSTFLAGS", "-Zsanitizer=address -Copt-level=3")
+            .env_remove("CARGO_ENCODED_RUSTFLAGS");
+        assert_eq!(
+            describe_command(&command),
+            "cd /repo && env -u CARGO_ENCODED_RUSTFLAGS 'RUSTFLAGS=-Zsanitizer=address -Copt-level=3' cargo test -- --no-capture"
+        );
+    }
+
+    #[test]
+    fn test_to_script() {
+        let commands = vec![
+            PlannedCommand {
+                dir: PathBuf::from("/repo"),
+                envs: BTreeMap::new(),
+                program: "cargo".to_string(),
+                args: vec!["build".to_string(), "--workspace".to_string()],
+            },
+            PlannedCommand {
+                dir: PathBuf::from("/repo/my app"),
+                envs: BTreeMap::from([
+                    ("CARGO_ENCODED_RUSTFLAGS".to_string(), None),
+                    (
+                        "RUSTFLAGS".to_string(),
+                        Some("-Cforce-frame-pointers=yes".to_string()),
+                    ),
+                ]),
+                program: "cargo".to_string(),
+                args: vec!["test".to_string(), "--test".to_string(), "*".to_string()],
+            },
+        ];
+        let xtask_args = vec!["target/debug/xtask".to_string(), "test".to_string()];
+        assert_eq!(
+            to_script(&xtask_args, &commands),
+            "#!/bin/sh
+# Commands planned by: target/debug/xtask test
+set -e
+cd /repo && cargo build --workspace
+cd '/repo/my app' && env -u CARGO_ENCODED_RUSTFLAGS RUSTFLAGS=-Cforce-frame-pointers=yes cargo test --test '*'
+"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::utils::workspace::WorkspaceMember;
+
+/// Modification time and size of each file of a directory tree.
+pub type TreeSnapshot = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// A file created or modified between two snapshots.
+#[derive(Debug, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub created: bool,
+}
+
+/// Returns the snapshot of the files under the passed root, the excluded directories and the
+/// `.git` directories are not traversed.
+pub fn snapshot_tree(root: &Path, excluded: &[PathBuf]) -> TreeSnapshot {
+    let mut snapshot = TreeSnapshot::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if entry.file_name() != ".git" && !excluded.contains(&path) {
+                    pending.push(path);
+                }
+            } else {
+                snapshot.insert(path, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    snapshot
+}
+
+/// Returns the files created or modified in the after snapshot, deleted files are ignored.
+pub fn diff_snapshots(before: &TreeSnapshot, after: &TreeSnapshot) -> Vec<FileChange> {
+    after
+        .iter()
+        .filter_map(|(path, state)| match before.get(path) {
+            None => Some(FileChange {
+                path: path.clone(),
+                created: true,
+            }),
+            Some(previous) if previous != state => Some(FileChange {
+                path: path.clone(),
+                created: false,
+            }),
+            Some(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the name of the member whose directory contains the passed path, the most nested
+/// member wins.
+pub fn find_owner_member<'a>(path: &Path, members: &'a [WorkspaceMember]) -> Option<&'a str> {
+    members
+        .iter()
+        .filter(|member| path.starts_with(&member.path))
+        .max_by_key(|member| member.path.len())
+        .map(|member| member.name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::workspace::CrateKind;
+    use std::time::Duration;
+
+    #[test]
+    fn test_diff_snapshots() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(10);
+        let later = time + Duration::from_secs(5);
+        let before = TreeSnapshot::from([
+            (PathBuf::from("README.md"), (Some(time), 10)),
+            (PathBuf::from("src/lib.rs"), (Some(time), 20)),
+            (PathBuf::from("deleted.txt"), (Some(time), 1)),
+        ]);
+        let after = TreeSnapshot::from([
+            (PathBuf::from("README.md"), (Some(time), 10)),
+            (PathBuf::from("src/lib.rs"), (Some(later), 20)),
+            (PathBuf::from("fixture.json"), (Some(later), 5)),
+        ]);
+        assert_eq!(
+            diff_snapshots(&before, &after),
+            vec![
+                FileChange {
+                    path: PathBuf::from("fixture.json"),
+                    created: true
+                },
+                FileChange {
+                    path: PathBuf::from("src/lib.rs"),
+                    created: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_owner_member() {
+        let member = |name: &str, path: &str| WorkspaceMember {
+            name: name.to_string(),
+            path: path.to_string(),
+            features: vec![],
+            kind: CrateKind::Library,
+            publishable: true,
+        };
+        let members = vec![
+            member("app", "/repo/crates/app"),
+            member("app-example", "/repo/crates/app/examples/basic"),
+            member("core", "/repo/crates/core"),
+        ];
+        let owner = |path: &str| find_owner_member(Path::new(path), &members);
+        assert_eq!(owner("/repo/crates/app/out.txt"), Some("app"));
+        assert_eq!(
+            owner("/repo/crates/app/examples/basic/out.txt"),
+            Some("app-example")
+        );
+        assert_eq!(owner("/repo/crates/core2/out.txt"), None);
+        assert_eq!(owner("/repo/out.txt"), None);
+    }
+}
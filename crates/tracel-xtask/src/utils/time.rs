@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{num::IntErrorKind, time::Duration};
 
 /// Print duration as HH:MM:SS format
 #[allow(dead_code)]
@@ -11,3 +11,67 @@ pub fn format_duration(duration: &Duration) -> String {
 
     format!("{hours:02}:{remaining_minutes:02}:{remaining_seconds:02}")
 }
+
+/// Parse a duration like `90`, `90s`, `15m`, `2h` or `1h30m`, a number without unit is in seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration '{value}', expected for instance 90s, 15m or 1h30m");
+    let overflow = || format!("duration '{value}' is too long");
+    // add the passed number of units to the seconds, an overflow is an error
+    let add = |seconds: u64, number: &str, factor: u64| match number.parse::<u64>() {
+        Ok(number) => number
+            .checked_mul(factor)
+            .and_then(|number| seconds.checked_add(number))
+            .ok_or_else(overflow),
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => Err(overflow()),
+        Err(_) => Err(invalid()),
+    };
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let factor = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        seconds = add(seconds, &number, factor)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        seconds = add(seconds, &number, 1)?;
+    }
+    if seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::seconds_without_unit("90", Some(90))]
+    #[case::seconds("45s", Some(45))]
+    #[case::minutes("15m", Some(900))]
+    #[case::hours("2h", Some(7200))]
+    #[case::combined("1h30m10s", Some(5410))]
+    #[case::zero("0s", None)]
+    #[case::empty("", None)]
+    #[case::unknown_unit("3d", None)]
+    #[case::missing_number("h", None)]
+    #[case::multiplication_overflow("18446744073709551615h", None)]
+    #[case::addition_overflow("18446744073709551615s1s", None)]
+    #[case::number_overflow("99999999999999999999", None)]
+    fn test_parse_duration(#[case] value: &str, #[case] expected: Option<u64>) {
+        assert_eq!(
+            parse_duration(value).ok(),
+            expected.map(Duration::from_secs)
+        );
+    }
+}
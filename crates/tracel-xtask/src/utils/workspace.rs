@@ -1,5 +1,12 @@
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde_json::Value;
-use std::{path::Path, process::Command};
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    process::Command,
+    sync::Mutex,
+};
 
 const MEMBER_PATH_PREFIX: &str = if cfg!(target_os = "windows") {
     "path+file:///"
@@ -7,59 +14,351 @@ const MEMBER_PATH_PREFIX: &str = if cfg!(target_os = "windows") {
     "path+file://"
 };
 
+/// Seed of the shuffled order of the workspace members, they are sorted by name if it is not set.
+static MEMBERS_SEED: Mutex<Option<u64>> = Mutex::new(None);
+
 pub enum WorkspaceMemberType {
+    /// The members which are not examples nor proc-macro crates.
     Crate,
     Example,
+    /// The members which are proc-macro crates.
+    ProcMacro,
+    /// All the members which are not examples, the `Crate` and the `ProcMacro` members.
+    AllCrates,
 }
 
-#[derive(Debug)]
+/// Kind of the crate of a workspace member.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrateKind {
+    /// Crate with a library target.
+    Library,
+    /// Crate with binary targets only.
+    Binary,
+    /// Crate whose library is a procedural macro (`proc-macro = true`), it cannot be instrumented.
+    ProcMacro,
+}
+
+#[derive(Clone, Debug)]
 #[allow(dead_code)]
 pub struct WorkspaceMember {
     pub name: String,
     pub path: String,
+    /// Features declared in the `[features]` table of the member manifest.
+    pub features: Vec<String>,
+    pub kind: CrateKind,
+    /// False if the member manifest sets `publish = false`.
+    pub publishable: bool,
 }
 
 impl WorkspaceMember {
-    fn new(name: String, path: String) -> Self {
-        Self { name, path }
+    /// Returns the member of the passed `cargo metadata` package.
+    fn new(name: String, path: String, package: &Value) -> Self {
+        let features = package["features"]
+            .as_object()
+            .map(|f| f.keys().cloned().collect())
+            .unwrap_or_default();
+        Self {
+            name,
+            path,
+            features,
+            kind: get_crate_kind(package),
+            // 'publish = false' is reported as an empty list of registries
+            publishable: !matches!(package["publish"].as_array(), Some(r) if r.is_empty()),
+        }
     }
 }
 
-/// Get workspace crates
-pub fn get_workspace_members(w_type: WorkspaceMemberType) -> Vec<WorkspaceMember> {
-    // Run `cargo metadata` command to get project metadata
+/// Get the metadata of the workspace members without their dependencies resolution
+pub fn get_workspace_metadata() -> anyhow::Result<Value> {
+    run_cargo_metadata(&["--no-deps"])
+}
+
+/// Get the metadata of the workspace with the resolution of its dependencies
+pub fn get_workspace_resolved_metadata() -> anyhow::Result<Value> {
+    run_cargo_metadata(&[])
+}
+
+fn run_cargo_metadata(args: &[&str]) -> anyhow::Result<Value> {
     let output = Command::new("cargo")
         .arg("metadata")
+        .args(args)
+        .args(["--format-version", "1"])
         .output()
-        .expect("Failed to execute command");
-    // Parse the JSON output
-    let metadata: Value = serde_json::from_slice(&output.stdout).expect("Failed to parse JSON");
-    // Extract workspace members from the metadata
-    let workspaces = metadata["workspace_members"]
-        .as_array()
-        .expect("Expected an array of workspace members")
-        .iter()
-        .filter_map(|member| {
-            let member_str = member.as_str()?;
+        .map_err(|e| anyhow::anyhow!("Failed to execute cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "Failed to retrieve the workspace metadata: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Members of the workspace parsed once from the `cargo metadata` output, to be built once and
+/// passed around instead of calling `get_workspace_members` for each target.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    crates: Vec<WorkspaceMember>,
+    examples: Vec<WorkspaceMember>,
+    /// Names of the members each member depends on, dev-dependencies excluded.
+    dependencies: HashMap<String, BTreeSet<String>>,
+    /// Names of the members each member depends on, dev-dependencies included.
+    test_dependencies: HashMap<String, BTreeSet<String>>,
+}
+
+impl Workspace {
+    /// Run `cargo metadata` and parse the members of the current workspace.
+    pub fn load() -> anyhow::Result<Self> {
+        let metadata = get_workspace_resolved_metadata()?;
+        let mut workspace = Self::from_metadata(&metadata)?;
+        workspace.order_members(get_members_seed());
+        Ok(workspace)
+    }
+
+    /// Parse the members of the passed `cargo metadata` output.
+    pub fn from_metadata(metadata: &Value) -> anyhow::Result<Self> {
+        // packages indexed by package id
+        let packages: HashMap<&str, &Value> = metadata["packages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|p| Some((p["id"].as_str()?, p)))
+            .collect();
+        let mut workspace = Self::default();
+        let members = metadata["workspace_members"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Expected an array of workspace members"))?;
+        // member names indexed by package id
+        let mut names: HashMap<&str, String> = HashMap::new();
+        for member_str in members.iter().filter_map(Value::as_str) {
             let has_whitespace = member_str.chars().any(|c| c.is_whitespace());
-            let (name, path) = if has_whitespace {
-                parse_workspace_member0(member_str)?
+            let parsed = if has_whitespace {
+                parse_workspace_member0(member_str)
             } else {
-                parse_workspace_member1(member_str)?
+                parse_workspace_member1(member_str)
             };
-            match w_type {
-                WorkspaceMemberType::Crate if !path.contains("examples/") => {
-                    Some(WorkspaceMember::new(name.to_string(), path.to_string()))
-                }
-                WorkspaceMemberType::Example if path.contains("examples/") => {
-                    Some(WorkspaceMember::new(name.to_string(), path.to_string()))
+            let Some((name, path)) = parsed else {
+                continue;
+            };
+            names.insert(member_str, name.clone());
+            let package = packages.get(member_str).copied().unwrap_or(&Value::Null);
+            let member = WorkspaceMember::new(name, path, package);
+            if member.path.contains("examples/") {
+                workspace.examples.push(member);
+            } else {
+                workspace.crates.push(member);
+            }
+        }
+        // the dependency graph is only resolved when the metadata is not retrieved with --no-deps
+        let nodes = metadata["resolve"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        for node in nodes {
+            let Some(name) = node["id"].as_str().and_then(|id| names.get(id)) else {
+                continue;
+            };
+            let mut dependencies = BTreeSet::new();
+            let mut test_dependencies = BTreeSet::new();
+            for dep in node["deps"].as_array().into_iter().flatten() {
+                let Some(dep_name) = dep["pkg"].as_str().and_then(|id| names.get(id)) else {
+                    continue;
+                };
+                let is_normal = dep["dep_kinds"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|k| k["kind"].as_str() != Some("dev"));
+                if is_normal {
+                    dependencies.insert(dep_name.clone());
                 }
-                _ => None,
+                test_dependencies.insert(dep_name.clone());
             }
-        })
-        .collect();
+            workspace.dependencies.insert(name.clone(), dependencies);
+            workspace
+                .test_dependencies
+                .insert(name.clone(), test_dependencies);
+        }
+        Ok(workspace)
+    }
+
+    /// Sort the crates and the examples by name so that the order does not depend on the machine,
+    /// or shuffle them with the passed seed. The generator is fixed as the algorithm of `StdRng` may
+    /// change between `rand` versions, which would change the order of a seed.
+    fn order_members(&mut self, seed: Option<u64>) {
+        for members in [&mut self.crates, &mut self.examples] {
+            members.sort_by(|a, b| a.name.cmp(&b.name));
+            if let Some(seed) = seed {
+                members.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+            }
+        }
+    }
 
-    workspaces
+    /// Returns the members of the passed type.
+    pub fn members(&self, w_type: WorkspaceMemberType) -> Vec<&WorkspaceMember> {
+        match w_type {
+            WorkspaceMemberType::Crate => self
+                .crates
+                .iter()
+                .filter(|m| m.kind != CrateKind::ProcMacro)
+                .collect(),
+            WorkspaceMemberType::Example => self.examples.iter().collect(),
+            WorkspaceMemberType::ProcMacro => self
+                .crates
+                .iter()
+                .filter(|m| m.kind == CrateKind::ProcMacro)
+                .collect(),
+            WorkspaceMemberType::AllCrates => self.crates.iter().collect(),
+        }
+    }
+
+    /// Returns all the members, the crates followed by the examples.
+    pub fn all_members(&self) -> Vec<&WorkspaceMember> {
+        self.crates.iter().chain(&self.examples).collect()
+    }
+
+    /// Returns the members of the passed type ordered so that the members come after the members
+    /// they depend on, the order of the members is kept otherwise. Dev-dependencies are ignored and an
+    /// error is returned if the members depend on each other in a cycle.
+    pub fn members_topo(
+        &self,
+        w_type: WorkspaceMemberType,
+    ) -> anyhow::Result<Vec<&WorkspaceMember>> {
+        let mut remaining = self.members(w_type);
+        let selected: BTreeSet<&str> = remaining.iter().map(|m| m.name.as_str()).collect();
+        let mut ordered: Vec<&WorkspaceMember> = Vec::with_capacity(remaining.len());
+        let mut done: BTreeSet<&str> = BTreeSet::new();
+        while !remaining.is_empty() {
+            let Some(index) = remaining.iter().position(|m| {
+                self.dependencies
+                    .get(&m.name)
+                    .into_iter()
+                    .flatten()
+                    .all(|dep| !selected.contains(dep.as_str()) || done.contains(dep.as_str()))
+            }) else {
+                let remaining: BTreeSet<&str> = remaining.iter().map(|m| m.name.as_str()).collect();
+                return Err(anyhow::anyhow!(
+                    "A dependency cycle prevents ordering the workspace members: {}",
+                    self.find_cycle(&remaining).join(" -> ")
+                ));
+            };
+            let member = remaining.remove(index);
+            done.insert(&member.name);
+            ordered.push(member);
+        }
+        Ok(ordered)
+    }
+
+    /// Returns the members of a dependency cycle among the passed members, the first member is
+    /// repeated at the end. Each of the passed members must depend on another one of them, which
+    /// is the case of the members left unordered by `members_topo`.
+    fn find_cycle<'a>(&'a self, members: &BTreeSet<&'a str>) -> Vec<&'a str> {
+        let mut path: Vec<&str> = members.first().into_iter().copied().collect();
+        loop {
+            let last = path[path.len() - 1];
+            let Some(next) = self
+                .dependencies
+                .get(last)
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .find(|dep| members.contains(dep))
+            else {
+                return path;
+            };
+            if let Some(start) = path.iter().position(|name| *name == next) {
+                path.drain(..start);
+                path.push(next);
+                return path;
+            }
+            path.push(next);
+        }
+    }
+
+    /// Returns the passed member names with the names of the members which depend on them, directly
+    /// or transitively. Dev-dependencies are included since the tests of a member build them.
+    pub fn with_dependents(&self, names: &BTreeSet<String>) -> BTreeSet<String> {
+        let mut affected = names.clone();
+        loop {
+            let dependents: Vec<&String> = self
+                .test_dependencies
+                .iter()
+                .filter(|(name, deps)| {
+                    !affected.contains(*name) && deps.iter().any(|dep| affected.contains(dep))
+                })
+                .map(|(name, _)| name)
+                .collect();
+            if dependents.is_empty() {
+                return affected;
+            }
+            affected.extend(dependents.into_iter().cloned());
+        }
+    }
+
+    /// Returns the member with the passed name.
+    pub fn member(&self, name: &str) -> Option<&WorkspaceMember> {
+        self.crates
+            .iter()
+            .chain(&self.examples)
+            .find(|m| m.name == name)
+    }
+}
+
+/// Shuffle the order of the members loaded by `Workspace::load` with the passed seed, or sort them
+/// by name if there is no seed.
+pub(crate) fn set_members_seed(seed: Option<u64>) {
+    *MEMBERS_SEED
+        .lock()
+        .expect("members seed lock should not be poisoned") = seed;
+}
+
+fn get_members_seed() -> Option<u64> {
+    *MEMBERS_SEED
+        .lock()
+        .expect("members seed lock should not be poisoned")
+}
+
+/// Get workspace crates
+pub fn get_workspace_members(w_type: WorkspaceMemberType) -> Vec<WorkspaceMember> {
+    Workspace::load()
+        .expect("Failed to load the workspace members")
+        .members(w_type)
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+/// Get workspace crates in dependency order, see `Workspace::members_topo`.
+pub fn get_workspace_members_topo(
+    w_type: WorkspaceMemberType,
+) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let workspace = Workspace::load()?;
+    let members = workspace.members_topo(w_type)?;
+    Ok(members.into_iter().cloned().collect())
+}
+
+/// Returns the kind of the crate of the passed `cargo metadata` package from the kinds of its
+/// targets, cargo reports the `proc-macro` kind for the libraries with `proc-macro = true`.
+fn get_crate_kind(package: &Value) -> CrateKind {
+    let kinds: Vec<&str> = package["targets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|t| t["kind"].as_array().into_iter().flatten())
+        .filter_map(Value::as_str)
+        .collect();
+    if kinds.contains(&"proc-macro") {
+        CrateKind::ProcMacro
+    } else if kinds
+        .iter()
+        .any(|k| matches!(*k, "lib" | "rlib" | "dylib" | "cdylib" | "staticlib"))
+    {
+        CrateKind::Library
+    } else if kinds.contains(&"bin") {
+        CrateKind::Binary
+    } else {
+        CrateKind::Library
+    }
 }
 
 /// Legacy cargo metadata format for member specs (rust < 1.77)
@@ -88,3 +387,197 @@ fn parse_workspace_member1(specs: &str) -> Option<(String, String)> {
     let path = path.to_str()?;
     Some((name.to_string(), path.to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    // each target is described by its whitespace-separated kinds
+    #[rstest]
+    #[case::library(&["lib", "test"], CrateKind::Library)]
+    #[case::library_and_binary(&["lib", "bin"], CrateKind::Library)]
+    #[case::cdylib(&["cdylib rlib"], CrateKind::Library)]
+    #[case::binary(&["bin", "custom-build"], CrateKind::Binary)]
+    #[case::proc_macro(&["proc-macro", "test"], CrateKind::ProcMacro)]
+    #[case::no_target(&[], CrateKind::Library)]
+    fn test_get_crate_kind(#[case] targets: &[&str], #[case] expected: CrateKind) {
+        let targets: Vec<Value> = targets
+            .iter()
+            .map(|kinds| json!({ "kind": kinds.split_whitespace().collect::<Vec<_>>() }))
+            .collect();
+        assert_eq!(get_crate_kind(&json!({ "targets": targets })), expected);
+    }
+
+    #[test]
+    fn test_workspace_from_metadata() {
+        let metadata = json!({
+            "packages": [
+                {
+                    "id": "path+file:///repo/crates/core#0.1.0",
+                    "features": { "std": [] },
+                    "targets": [{ "kind": ["lib"] }]
+                },
+                {
+                    "id": "path+file:///repo/crates/core-macros#0.1.0",
+                    "features": {},
+                    "targets": [{ "kind": ["proc-macro"] }]
+                },
+                {
+                    "id": "path+file:///repo/examples/demo#0.1.0",
+                    "features": {},
+                    "publish": [],
+                    "targets": [{ "kind": ["bin"] }]
+                }
+            ],
+            "workspace_members": [
+                "path+file:///repo/crates/core#0.1.0",
+                "path+file:///repo/crates/core-macros#0.1.0",
+                "path+file:///repo/examples/demo#0.1.0"
+            ]
+        });
+        let workspace = Workspace::from_metadata(&metadata).unwrap();
+        let names = |members: Vec<&WorkspaceMember>| -> Vec<String> {
+            members.iter().map(|m| m.name.clone()).collect()
+        };
+        assert_eq!(
+            names(workspace.members(WorkspaceMemberType::Crate)),
+            vec!["core"]
+        );
+        assert_eq!(
+            names(workspace.members(WorkspaceMemberType::AllCrates)),
+            vec!["core", "core-macros"]
+        );
+        assert_eq!(
+            names(workspace.members(WorkspaceMemberType::Example)),
+            vec!["demo"]
+        );
+        assert_eq!(
+            names(workspace.members(WorkspaceMemberType::ProcMacro)),
+            vec!["core-macros"]
+        );
+        let core = workspace.member("core").unwrap();
+        assert_eq!(core.features, vec!["std"]);
+        assert_eq!(core.kind, CrateKind::Library);
+        assert!(core.publishable);
+        let demo = workspace.member("demo").unwrap();
+        assert_eq!(demo.kind, CrateKind::Binary);
+        assert!(!demo.publishable);
+        assert!(workspace.member("unknown").is_none());
+    }
+
+    /// Returns the metadata of crates in `/repo/crates` with their resolved dependencies given as
+    /// `(from, to, kind)` where the kind is null for normal dependencies.
+    fn resolved_metadata(crates: &[&str], dependencies: &[(&str, &str, Option<&str>)]) -> Value {
+        let id = |name: &str| format!("path+file:///repo/crates/{name}#0.1.0");
+        let packages: Vec<Value> = crates
+            .iter()
+            .map(|name| json!({ "id": id(name), "features": {}, "targets": [{ "kind": ["lib"] }] }))
+            .collect();
+        let nodes: Vec<Value> = crates
+            .iter()
+            .map(|name| {
+                let deps: Vec<Value> = dependencies
+                    .iter()
+                    .filter(|(from, _, _)| from == name)
+                    .map(|(_, to, kind)| json!({ "pkg": id(to), "dep_kinds": [{ "kind": kind }] }))
+                    .collect();
+                json!({ "id": id(name), "deps": deps })
+            })
+            .collect();
+        json!({
+            "packages": packages,
+            "workspace_members": crates.iter().map(|name| id(name)).collect::<Vec<_>>(),
+            "resolve": { "nodes": nodes }
+        })
+    }
+
+    #[rstest]
+    #[case::no_dependencies(&[], vec!["app", "core", "macros"])]
+    #[case::chain(&[("app", "core", None), ("core", "macros", None)], vec!["macros", "core", "app"])]
+    #[case::build_dependency(&[("app", "macros", Some("build"))], vec!["core", "macros", "app"])]
+    #[case::dev_dependency_ignored(&[("core", "app", Some("dev"))], vec!["app", "core", "macros"])]
+    #[case::dev_cycle_ignored(
+        &[("app", "core", None), ("core", "app", Some("dev"))],
+        vec!["core", "app", "macros"]
+    )]
+    fn test_members_topo(
+        #[case] dependencies: &[(&str, &str, Option<&str>)],
+        #[case] expected: Vec<&str>,
+    ) {
+        let metadata = resolved_metadata(&["app", "core", "macros"], dependencies);
+        let workspace = Workspace::from_metadata(&metadata).unwrap();
+        let names: Vec<&str> = workspace
+            .members_topo(WorkspaceMemberType::Crate)
+            .unwrap()
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, expected);
+    }
+
+    #[rstest]
+    #[case::no_dependencies(&[], &["core"], &["core"])]
+    #[case::transitive(&[("app", "core", None), ("core", "macros", None)], &["macros"], &["app", "core", "macros"])]
+    #[case::dependency_unchanged(&[("app", "core", None)], &["app"], &["app"])]
+    #[case::dev_dependency(&[("app", "core", Some("dev"))], &["core"], &["app", "core"])]
+    #[case::nothing_changed(&[("app", "core", None)], &[], &[])]
+    fn test_with_dependents(
+        #[case] dependencies: &[(&str, &str, Option<&str>)],
+        #[case] changed: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let metadata = resolved_metadata(&["app", "core", "macros"], dependencies);
+        let workspace = Workspace::from_metadata(&metadata).unwrap();
+        let changed: BTreeSet<String> = changed.iter().map(|name| name.to_string()).collect();
+        let affected: Vec<String> = workspace.with_dependents(&changed).into_iter().collect();
+        assert_eq!(affected, expected);
+    }
+
+    #[test]
+    fn test_order_members() {
+        let names = |workspace: &Workspace| -> Vec<String> {
+            workspace
+                .members(WorkspaceMemberType::Crate)
+                .iter()
+                .map(|m| m.name.clone())
+                .collect()
+        };
+        let metadata = resolved_metadata(&["macros", "core", "app", "backend", "cli"], &[]);
+        let mut workspace = Workspace::from_metadata(&metadata).unwrap();
+        workspace.order_members(None);
+        assert_eq!(
+            names(&workspace),
+            vec!["app", "backend", "cli", "core", "macros"]
+        );
+        workspace.order_members(Some(42));
+        let shuffled = names(&workspace);
+        // the order of a seed is stable across versions
+        assert_eq!(shuffled, vec!["cli", "backend", "app", "macros", "core"]);
+        let mut replayed = Workspace::from_metadata(&metadata).unwrap();
+        replayed.order_members(Some(42));
+        assert_eq!(names(&replayed), shuffled);
+        let mut sorted = shuffled.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec!["app", "backend", "cli", "core", "macros"]);
+    }
+
+    #[test]
+    fn test_members_topo_cycle() {
+        let metadata = resolved_metadata(
+            &["app", "core", "macros"],
+            &[
+                ("app", "core", None),
+                ("core", "macros", None),
+                ("macros", "core", None),
+            ],
+        );
+        let workspace = Workspace::from_metadata(&metadata).unwrap();
+        let error = workspace
+            .members_topo(WorkspaceMemberType::Crate)
+            .unwrap_err();
+        // app depends on the cycle without being part of it
+        assert!(error.to_string().ends_with(": core -> macros -> core"));
+    }
+}
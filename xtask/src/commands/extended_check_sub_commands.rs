@@ -26,6 +26,9 @@ pub fn handle_command(args: ExtendedCheckArgsCmdArgs) -> anyhow::Result<()> {
                         exclude: args.exclude.clone(),
                         only: args.only.clone(),
                         ignore_audit: args.ignore_audit,
+                        deny: args.deny,
+                        warn_only: args.warn_only.clone(),
+                        keep_going: args.keep_going,
                     })
                 })
         }
@@ -2,20 +2,28 @@ mod commands;
 
 use tracel_xtask::{init_xtask, prelude::*};
 
-#[macro_use]
-extern crate log;
-
 #[macros::base_commands(
+    ApiSnapshot,
+    Bench,
+    BenchIds,
     Bump,
     Build,
+    ChangelogLint,
     Check,
+    Clippy,
+    Compare,
     Compile,
     Coverage,
+    CriticalPath,
     Doc,
     Docker,
+    DocsrsCheck,
     Dependencies,
     Fix,
+    Fmt,
+    IncrementalTiming,
     Publish,
+    ReadmeSync,
     Test,
     Validate,
     Vulnerabilities
@@ -45,7 +53,7 @@ pub enum Command {
 
 fn main() -> anyhow::Result<()> {
     let args = init_xtask::<Command>(parse_args::<Command>()?)?;
-    match args.command {
+    let result = match args.command {
         Command::ExtendedBuildArgs(cmd_args) => {
             commands::extended_build_args::handle_command(cmd_args, args.environment, args.context)
         }
@@ -70,6 +78,7 @@ fn main() -> anyhow::Result<()> {
         }
         // dispatch_base_commands function is generated by the base_commands macro
         _ => dispatch_base_commands(args),
-    }?;
-    Ok(())
+    };
+    write_summary(&result)?;
+    result
 }
@@ -30,6 +30,11 @@ use rstest::rstest;
 #[case::extend_base_command_advanced_example(&["extended-fix", "--target", "ci", "new-sub-command"], "Executing new subcommand on CI.", true)]
 #[case::extend_base_command_advanced_example_default_target(&["extended-fix", "new-sub-command"], "Executing new subcommand on workspace.", true)]
 #[case::cannot_execute_tests_in_production(&["-e", "prod", "test"], "Abort tests to avoid running them in production!", false)]
+#[case::list_tests_without_executing_them(&["test", "--target", "crates", "--only", "tracel-xtask-macros", "--list", "unit"], "Planned: cd ", true)]
+#[case::serial_workspace_ignores_only(&["test", "--target", "workspace", "--serial", "--only", "tracel-xtask-macros", "--list", "unit"], "-p tracel-xtask --color=always --jobs 1", true)]
+#[case::run_in_another_workspace_root(&["--workspace-root", "../crates/tracel-xtask-macros", "test", "--list", "unit"], "/crates/tracel-xtask-macros && env FROM_DOTENV=.env", true)]
+#[case::missing_workspace_root(&["--workspace-root", "does-not-exist", "build"], "", false)]
+#[case::refuse_planning_commands_needing_their_output(&["--emit-script", "target/xtask-refused-plan.sh", "bench-ids"], "", false)]
 #[case::force_tests_execution_in_production(&["-e", "prod", "extended-test-args", "-f"], "Force running tests in production (--force argument is set)", true)]
 fn test_xtask_example_status_success_and_returns_expected_output(
     #[case] cargo_args: &[&str],